@@ -0,0 +1,133 @@
+//! Numeric arrays padded to their natural alignment, for zero-copy views into
+//! memory-mapped files.
+//!
+//! A slice marshalled with `marshal_slice` places its elements wherever the previous
+//! field ended, so reading them as a `&[u64]` would be an unaligned access, which
+//! faults on some platforms and is undefined behavior in Rust everywhere. The aligned
+//! layout inserts padding before the elements so they start at a multiple of their
+//! alignment, counted from the start of the message:
+//!
+//! - the element count as a varint,
+//! - the number of padding bytes as one byte, followed by that many zero bytes,
+//! - the elements in little-endian order,
+//! - the terminator sequence.
+//!
+//! The padding length is on the wire, so decoding needs no knowledge of the offset. A
+//! message whose start is aligned in memory, such as a file mapped at a page boundary,
+//! can then be viewed without copying with `unmarshal_aligned_slice`, which is enabled
+//! by the `bytemuck` feature and casts the bytes through `bytemuck`.
+
+use crate::{
+    Error, Result, TERMINATOR, advance, marshal_u8, marshal_usize, read_terminator, size_usize, unmarshal_u8,
+    unmarshal_usize, write_to_slice,
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A numeric type that can be marshalled with the aligned layout. Every bit pattern of
+/// its size is a valid value.
+pub trait AlignedElement: sealed::Sealed + Copy + 'static {
+    #[doc(hidden)]
+    fn write_le(self, out: &mut [u8]);
+    #[doc(hidden)]
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+macro_rules! aligned_element_impl {
+    ($($type:ty),*) => {
+        $(
+            impl sealed::Sealed for $type {}
+
+            impl AlignedElement for $type {
+                fn write_le(self, out: &mut [u8]) {
+                    out.copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_le(bytes: &[u8]) -> Self {
+                    <$type>::from_le_bytes(bytes.try_into().unwrap())
+                }
+            }
+        )*
+    };
+}
+
+aligned_element_impl!(u16, u32, u64, u128, i16, i32, i64, i128, f32, f64);
+
+/// Returns the number of padding bytes that align the elements of a slice of `len`
+/// elements marshalled at `offset`.
+fn padding<T>(len: usize, offset: usize) -> usize {
+    let start = offset + size_usize(len) + 1;
+    start.next_multiple_of(align_of::<T>()) - start
+}
+
+/// Returns the number of bytes needed to marshal a slice with the aligned layout at
+/// `offset` bytes from the start of the message.
+pub fn size_aligned_slice<T: AlignedElement>(slice: &[T], offset: usize) -> usize {
+    let len = slice.len();
+    size_usize(len) + 1 + padding::<T>(len, offset) + size_of_val(slice) + TERMINATOR.len()
+}
+
+/// Marshals a slice with the aligned layout into the writer. `offset` is the number of
+/// bytes of the message before the slice, which a caller marshalling into `buf` gets
+/// as `buf.len() - writer.len()`.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_aligned_slice<T: AlignedElement>(slice: &[T], offset: usize, writer: &mut &mut [u8]) -> Result<()> {
+    let pad = padding::<T>(slice.len(), offset);
+    marshal_usize(slice.len(), writer)?;
+    // The padding is smaller than the alignment, which is at most 16.
+    marshal_u8(pad as u8, writer)?;
+    write_to_slice(writer, &[0; 16][..pad])?;
+    let size = size_of_val(slice);
+    if writer.len() < size {
+        return Err(Error::BufferTooSmall { needed: size, available: writer.len() });
+    }
+    let (out, rest) = std::mem::take(writer).split_at_mut(size);
+    for (v, chunk) in slice.iter().zip(out.chunks_exact_mut(size_of::<T>())) {
+        v.write_le(chunk);
+    }
+    *writer = rest;
+    write_to_slice(writer, &TERMINATOR)
+}
+
+/// Reads the header and padding of an aligned slice and returns the bytes of its
+/// elements.
+fn aligned_payload<'a, T>(reader: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = unmarshal_usize(reader)?;
+    let pad = unmarshal_u8(reader)? as usize;
+    if pad >= align_of::<T>() {
+        return Err(Error::InvalidValue);
+    }
+    advance(reader, pad)?;
+    let size = len.checked_mul(size_of::<T>()).ok_or(Error::OutOfRange)?;
+    let payload = advance(reader, size)?;
+    read_terminator(reader)?;
+    Ok(payload)
+}
+
+/// Unmarshals a slice with the aligned layout from the reader without copying, by
+/// viewing its elements in place.
+///
+/// Returns an `InvalidValue` error if the elements are not aligned in memory, which
+/// happens when the message does not start at an aligned address. Only available on
+/// little-endian targets, where the wire layout matches the memory layout.
+#[cfg(all(feature = "bytemuck", target_endian = "little"))]
+pub fn unmarshal_aligned_slice<'a, T: AlignedElement + bytemuck::Pod>(reader: &mut &'a [u8]) -> Result<&'a [T]> {
+    let payload = aligned_payload::<T>(reader)?;
+    bytemuck::try_cast_slice(payload).map_err(|_| Error::InvalidValue)
+}
+
+/// Unmarshals a slice with the aligned layout from the reader into a vector, which
+/// works regardless of the alignment of the buffer and the endianness of the target.
+pub fn unmarshal_aligned_slice_copied<T: AlignedElement>(reader: &mut &[u8]) -> Result<Vec<T>> {
+    let payload = aligned_payload::<T>(reader)?;
+    Ok(payload.chunks_exact(size_of::<T>()).map(T::read_le).collect())
+}
+
+/// Skips over a slice marshalled with the aligned layout in the reader.
+pub fn skip_aligned_slice<T: AlignedElement>(reader: &mut &[u8]) -> Result<()> {
+    aligned_payload::<T>(reader)?;
+    Ok(())
+}
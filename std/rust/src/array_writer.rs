@@ -0,0 +1,94 @@
+//! A writer over a stack array, for building small messages without allocating.
+
+use std::fmt;
+use std::ops::Deref;
+
+use crate::{BencEncode, Result};
+
+/// Marshals values into an inline array of `N` bytes.
+///
+/// Values are appended one after another with the usual marshalers, and the written
+/// prefix is available through `as_bytes`. Nothing is allocated, which suits embedded
+/// and latency-critical code paths.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ArrayWriter<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Default for ArrayWriter<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ArrayWriter<N> {
+    /// Creates an empty writer.
+    pub const fn new() -> Self {
+        ArrayWriter { buf: [0; N], len: 0 }
+    }
+
+    /// Appends a value using its marshaler.
+    ///
+    /// Returns an error if the marshaler fails, typically because the value does not
+    /// fit into the remaining space; the writer is left unchanged.
+    pub fn write(&mut self, marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>) -> Result<()> {
+        let mut writer = &mut self.buf[self.len..];
+        let before = writer.len();
+        marshaler(&mut writer)?;
+        self.len += before - writer.len();
+        Ok(())
+    }
+
+    /// Appends a value using its [`BencEncode`] impl.
+    ///
+    /// Returns an error if the value does not fit; the writer is left unchanged.
+    pub fn push<T: BencEncode + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.write(|w| value.marshal(w))
+    }
+
+    /// Returns the bytes written so far.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if nothing has been written.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes that can still be written.
+    pub fn remaining(&self) -> usize {
+        N - self.len
+    }
+
+    /// Discards everything written so far.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Deref for ArrayWriter<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for ArrayWriter<N> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<const N: usize> fmt::Debug for ArrayWriter<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArrayWriter").field("capacity", &N).field("written", &self.as_bytes()).finish()
+    }
+}
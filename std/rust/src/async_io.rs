@@ -0,0 +1,367 @@
+//! Framed messaging over async byte streams, enabled by the `futures` feature.
+//!
+//! Every message is sent as a frame: the varint byte length of the marshalled value
+//! followed by the value itself, which is the same layout as `marshal_bytes`.
+//! [`BencSink`] implements the futures `Sink` trait over any `AsyncWrite`, and
+//! [`BencStream`] implements `Stream` over any `AsyncRead`, so benc messaging works
+//! with `select!`, `forward` and the rest of the async ecosystem. [`FrameReader`]
+//! reads the raw frames instead, and [`AsyncRpc`] is the async counterpart of
+//! `BlockingRpc`.
+//!
+//! Reading is cancellation-safe: received bytes are kept in the reader or stream, not
+//! in the future awaiting them, so a future of `FrameReader::read_frame` or
+//! `StreamExt::next` dropped by `select!` before a frame is complete loses nothing,
+//! and the next call continues where it stopped.
+//!
+//! Errors are reported as `io::Error`; encoding and decoding failures have the kind
+//! `InvalidData` and wrap the benc [`Error`](crate::Error).
+
+use std::collections::VecDeque;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures::{Sink, Stream};
+
+use crate::{BencDecode, BencEncode, FrameDecoder, FrameEncoder, RpcEndpoint, RpcEvent, from_slice};
+
+/// The number of buffered bytes at which `BencSink` stops accepting messages until
+/// the buffer has been written out.
+const BACKPRESSURE_BOUNDARY: usize = 8 * 1024;
+
+/// The number of bytes `BencStream`, `FrameReader` and `AsyncRpc` ask the reader for
+/// at a time.
+const READ_SIZE: usize = 8 * 1024;
+
+// ===================================================================================
+// Sink
+// ===================================================================================
+
+/// Marshals messages of type `T` into frames written to an `AsyncWrite`.
+///
+/// Frames are buffered and written out once the buffer grows past a few kilobytes or
+/// the sink is flushed, and `poll_ready` waits for the buffer to drain before accepting
+/// more messages, so a slow writer applies backpressure to the producer.
+pub struct BencSink<W, T> {
+    writer: W,
+    encoder: FrameEncoder,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<W, T> BencSink<W, T> {
+    /// Creates a sink writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        BencSink { writer, encoder: FrameEncoder::new(), _marker: PhantomData }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer. Writing to it directly
+    /// while frames are buffered corrupts the stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the underlying writer, discarding frames that have not been written.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: AsyncWrite + Unpin, T> BencSink<W, T> {
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.encoder.is_empty() {
+            let n = ready!(Pin::new(&mut self.writer).poll_write(cx, self.encoder.pending()))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.encoder.consume(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin, T: BencEncode> Sink<T> for BencSink<W, T> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.encoder.pending().len() >= BACKPRESSURE_BOUNDARY {
+            ready!(this.poll_write_buf(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> io::Result<()> {
+        Ok(self.get_mut().encoder.encode(&item)?)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.writer).poll_close(cx)
+    }
+}
+
+// ===================================================================================
+// Stream
+// ===================================================================================
+
+/// Reads frames from an `AsyncRead` and unmarshals them into messages of type `T`.
+///
+/// The stream ends when the reader reaches end of file between frames. A frame cut
+/// short by end of file yields an `UnexpectedEof` error, and a frame longer than the
+/// limit an `InvalidData` error.
+pub struct BencStream<R, T> {
+    reader: R,
+    decoder: FrameDecoder,
+    eof: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> BencStream<R, T> {
+    /// Creates a stream reading from `reader`, accepting frames of up to
+    /// [`DEFAULT_MAX_FRAME_LEN`](crate::DEFAULT_MAX_FRAME_LEN) bytes.
+    pub fn new(reader: R) -> Self {
+        BencStream { reader, decoder: FrameDecoder::new(), eof: false, _marker: PhantomData }
+    }
+
+    /// Sets the largest frame length the stream accepts, which bounds the memory a
+    /// peer can make it allocate.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.decoder = self.decoder.with_max_frame_len(max_frame_len);
+        self
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying reader. Reading from it directly
+    /// corrupts the stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader, discarding bytes that have been read but not
+    /// decoded.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin, T: for<'a> BencDecode<'a>> Stream for BencStream<R, T> {
+    type Item = io::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<io::Result<T>>> {
+        let this = self.get_mut();
+        loop {
+            match this.decoder.next_frame() {
+                Ok(Some(frame)) => return Poll::Ready(Some(from_slice(frame).map_err(io::Error::from))),
+                Ok(None) => {}
+                Err(err) => {
+                    // The stream cannot be resynchronized after a bad frame header.
+                    this.decoder.clear();
+                    this.eof = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+
+            if this.eof {
+                let result = this.decoder.finish();
+                this.decoder.clear();
+                return Poll::Ready(result.err().map(Err));
+            }
+
+            let result = Pin::new(&mut this.reader).poll_read(cx, this.decoder.read_buf(READ_SIZE));
+            let n = match result {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                Poll::Pending => return Poll::Pending,
+            };
+            this.decoder.commit(n);
+            this.eof = n == 0;
+        }
+    }
+}
+
+// ===================================================================================
+// Frame reader
+// ===================================================================================
+
+/// Reads the frames of an `AsyncRead` without decoding them.
+///
+/// [`read_frame`](Self::read_frame) is cancellation-safe, so it can be raced against
+/// timeouts or shutdown signals in a `select!` loop.
+pub struct FrameReader<R> {
+    reader: R,
+    decoder: FrameDecoder,
+}
+
+impl<R> FrameReader<R> {
+    /// Creates a reader of frames from `reader`, accepting frames of up to
+    /// [`DEFAULT_MAX_FRAME_LEN`](crate::DEFAULT_MAX_FRAME_LEN) bytes.
+    pub fn new(reader: R) -> Self {
+        FrameReader { reader, decoder: FrameDecoder::new() }
+    }
+
+    /// Sets the largest frame length the reader accepts, which bounds the memory a
+    /// peer can make it allocate.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.decoder = self.decoder.with_max_frame_len(max_frame_len);
+        self
+    }
+
+    /// Returns the number of bytes read from the reader that are not part of a
+    /// returned frame.
+    pub fn buffered(&self) -> usize {
+        self.decoder.buffered()
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the underlying reader, discarding bytes that have been read but not
+    /// returned as part of a frame.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    /// Reads the next frame, or `None` when the reader reaches end of file between
+    /// frames.
+    ///
+    /// The future is cancellation-safe: if it is dropped before it completes, the
+    /// bytes it has read stay buffered and no frame is lost.
+    ///
+    /// Returns an `UnexpectedEof` error if a frame is cut short by end of file, and an
+    /// `InvalidData` error if the frame header is malformed or the frame is longer than
+    /// the limit. The stream cannot be resynchronized after the latter.
+    pub async fn read_frame(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(frame) = self.decoder.next_frame()? {
+                return Ok(Some(frame.to_vec()));
+            }
+            // A pending read has not taken any bytes from the reader, and a completed
+            // one is committed before the next await point, so dropping the future
+            // at that point loses nothing.
+            let n = self.reader.read(self.decoder.read_buf(READ_SIZE)).await?;
+            self.decoder.commit(n);
+            if n == 0 {
+                return self.decoder.finish().map(|()| None);
+            }
+        }
+    }
+}
+
+// ===================================================================================
+// RPC
+// ===================================================================================
+
+/// An RPC connection over an async stream, driving an [`RpcEndpoint`].
+///
+/// Events that arrive while [`call`](Self::call) waits for its response are kept
+/// and returned by [`next_event`](Self::next_event).
+pub struct AsyncRpc<S> {
+    stream: S,
+    endpoint: RpcEndpoint,
+    events: VecDeque<RpcEvent>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRpc<S> {
+    /// Creates a connection over `stream`.
+    pub fn new(stream: S) -> Self {
+        AsyncRpc { stream, endpoint: RpcEndpoint::new(), events: VecDeque::new() }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        let pending = self.endpoint.transmit();
+        self.stream.write_all(pending).await?;
+        let n = pending.len();
+        self.endpoint.consume(n);
+        self.stream.flush().await
+    }
+
+    /// Reads the next event from the stream, or `None` at end of file.
+    async fn read_event(&mut self) -> io::Result<Option<RpcEvent>> {
+        loop {
+            if let Some(event) = self.endpoint.poll_event()? {
+                return Ok(Some(event));
+            }
+            let decoder = self.endpoint.decoder_mut();
+            let n = self.stream.read(decoder.read_buf(READ_SIZE)).await?;
+            decoder.commit(n);
+            if n == 0 {
+                return self.endpoint.finish().map(|()| None);
+            }
+        }
+    }
+
+    /// Calls `method` and waits for the response, which is the payload or the error
+    /// message of the peer.
+    ///
+    /// Returns an `UnexpectedEof` error if the peer closes the connection first.
+    pub async fn call(&mut self, method: &str, payload: &[u8]) -> io::Result<Result<Vec<u8>, String>> {
+        let id = self.endpoint.call(method, payload);
+        self.flush().await?;
+        loop {
+            match self.read_event().await? {
+                Some(RpcEvent::Response { id: got, result }) if got == id => return Ok(result),
+                Some(event) => self.events.push_back(event),
+                None => return Err(io::ErrorKind::UnexpectedEof.into()),
+            }
+        }
+    }
+
+    /// Returns the next request, notification or response, or `None` when the peer
+    /// has closed the connection.
+    pub async fn next_event(&mut self) -> io::Result<Option<RpcEvent>> {
+        match self.events.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None => self.read_event().await,
+        }
+    }
+
+    /// Sends a notification.
+    pub async fn notify(&mut self, method: &str, payload: &[u8]) -> io::Result<()> {
+        self.endpoint.notify(method, payload);
+        self.flush().await
+    }
+
+    /// Sends the response to the request with the given id.
+    pub async fn respond(&mut self, id: u64, payload: &[u8]) -> io::Result<()> {
+        self.endpoint.respond(id, payload);
+        self.flush().await
+    }
+
+    /// Sends an error response to the request with the given id.
+    pub async fn respond_error(&mut self, id: u64, message: &str) -> io::Result<()> {
+        self.endpoint.respond_error(id, message);
+        self.flush().await
+    }
+}
@@ -0,0 +1,149 @@
+//! Support for arbitrary-precision integers from `num-bigint`, enabled by the
+//! `num-bigint` feature.
+//!
+//! A `BigUint` is marshalled as its little-endian magnitude bytes, using the byte
+//! slice format. A `BigInt` is additionally prefixed by a sign byte: 0 for zero,
+//! 1 for positive and 2 for negative values. Both types also implement the encoding
+//! traits, so structs defined with [`benc_struct!`](crate::benc_struct) can hold them.
+
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::{
+    BencDecode, BencEncode, Error, Result, Schema, Type, WireType, marshal_bytes, marshal_u8, size_u8, size_usize,
+    skip_bytes, skip_u8, unmarshal_bytes_cropped, unmarshal_u8,
+};
+
+const SIGN_ZERO: u8 = 0;
+const SIGN_PLUS: u8 = 1;
+const SIGN_MINUS: u8 = 2;
+
+/// Returns the number of bytes a magnitude occupies, zero being represented by no bytes.
+fn magnitude_len(v: &BigUint) -> usize {
+    v.bits().div_ceil(8) as usize
+}
+
+// ===================================================================================
+// BigUint
+// ===================================================================================
+
+/// Returns the number of bytes required to marshal a `BigUint`.
+pub fn size_biguint(v: &BigUint) -> usize {
+    let len = magnitude_len(v);
+    size_usize(len) + len
+}
+
+/// Marshals a `BigUint` as its little-endian magnitude bytes.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_biguint(v: &BigUint, writer: &mut &mut [u8]) -> Result<()> {
+    if v.bits() == 0 {
+        return marshal_bytes(&[], writer);
+    }
+    marshal_bytes(&v.to_bytes_le(), writer)
+}
+
+/// Unmarshals a `BigUint` from the reader.
+pub fn unmarshal_biguint(reader: &mut &[u8]) -> Result<BigUint> {
+    let bytes = unmarshal_bytes_cropped(reader)?;
+    Ok(BigUint::from_bytes_le(bytes))
+}
+
+/// Skips over a marshalled `BigUint` in the reader.
+pub fn skip_biguint(reader: &mut &[u8]) -> Result<()> {
+    skip_bytes(reader)
+}
+
+// ===================================================================================
+// BigInt
+// ===================================================================================
+
+/// Returns the number of bytes required to marshal a `BigInt`.
+pub fn size_bigint(v: &BigInt) -> usize {
+    size_u8() + size_biguint(v.magnitude())
+}
+
+/// Marshals a `BigInt` as a sign byte followed by its little-endian magnitude bytes.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_bigint(v: &BigInt, writer: &mut &mut [u8]) -> Result<()> {
+    let sign = match v.sign() {
+        Sign::NoSign => SIGN_ZERO,
+        Sign::Plus => SIGN_PLUS,
+        Sign::Minus => SIGN_MINUS,
+    };
+    marshal_u8(sign, writer)?;
+    marshal_biguint(v.magnitude(), writer)
+}
+
+/// Unmarshals a `BigInt` from the reader.
+/// Returns an `OutOfRange` error if the sign byte is not valid.
+pub fn unmarshal_bigint(reader: &mut &[u8]) -> Result<BigInt> {
+    let sign = match unmarshal_u8(reader)? {
+        SIGN_ZERO => Sign::NoSign,
+        SIGN_PLUS => Sign::Plus,
+        SIGN_MINUS => Sign::Minus,
+        _ => return Err(Error::OutOfRange),
+    };
+    let bytes = unmarshal_bytes_cropped(reader)?;
+    Ok(BigInt::from_bytes_le(sign, bytes))
+}
+
+/// Skips over a marshalled `BigInt` in the reader.
+pub fn skip_bigint(reader: &mut &[u8]) -> Result<()> {
+    skip_u8(reader)?;
+    skip_bytes(reader)
+}
+
+// ===================================================================================
+// Traits
+// ===================================================================================
+
+impl BencEncode for BigUint {
+    const WIRE_TYPE: Option<WireType> = Some(WireType::Bytes);
+
+    fn benc_type() -> Option<Type> {
+        Some(Type::Bytes)
+    }
+
+    fn size(&self) -> usize {
+        size_biguint(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_biguint(self, writer)
+    }
+}
+
+impl BencDecode<'_> for BigUint {
+    fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+        unmarshal_biguint(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_biguint(reader)
+    }
+}
+
+impl BencEncode for BigInt {
+    fn benc_type() -> Option<Type> {
+        Some(Type::Struct(Schema::new().field("sign", Type::U8).field("magnitude", Type::Bytes)))
+    }
+
+    fn size(&self) -> usize {
+        size_bigint(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_bigint(self, writer)
+    }
+}
+
+impl BencDecode<'_> for BigInt {
+    fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+        unmarshal_bigint(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_bigint(reader)
+    }
+}
@@ -0,0 +1,85 @@
+//! Support for `bitvec::BitVec` and `bitvec::BitSlice`, enabled by the `bitvec`
+//! feature.
+//!
+//! A bit sequence is marshalled in the packed-bit slice format: the varint number of
+//! bits, the bits packed eight to a byte, with bit `i` in bit `i % 8` of byte `i / 8`,
+//! and the terminator. Unused bits of the last byte are zero, and a decoder rejects
+//! them otherwise so every sequence has a single encoding. The layout does not depend
+//! on the storage type or bit order of the `BitVec`, so bitmaps written by one
+//! program can be read into any other `BitVec`.
+
+use bitvec::prelude::{BitOrder, BitSlice, BitStore, BitVec};
+
+use crate::{
+    BencDecode, BencEncode, Error, Result, TERMINATOR, advance, marshal_usize, read_terminator, size_usize,
+    unmarshal_usize, write_to_slice,
+};
+
+/// Returns the number of bytes required to marshal a bit sequence.
+pub fn size_bit_slice<T: BitStore, O: BitOrder>(bits: &BitSlice<T, O>) -> usize {
+    size_usize(bits.len()) + bits.len().div_ceil(8) + TERMINATOR.len()
+}
+
+/// Marshals a bit sequence into the writer in the packed-bit slice format.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_bit_slice<T: BitStore, O: BitOrder>(bits: &BitSlice<T, O>, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_usize(bits.len(), writer)?;
+    for chunk in bits.chunks(8) {
+        let byte = chunk.iter().by_vals().enumerate().fold(0u8, |byte, (i, bit)| byte | (u8::from(bit) << i));
+        write_to_slice(writer, &[byte])?;
+    }
+    write_to_slice(writer, &TERMINATOR)
+}
+
+/// Unmarshals a bit sequence in the packed-bit slice format from the reader.
+///
+/// Returns an `InvalidValue` error if an unused bit of the last byte is set.
+pub fn unmarshal_bit_vec<T: BitStore, O: BitOrder>(reader: &mut &[u8]) -> Result<BitVec<T, O>> {
+    let len = unmarshal_usize(reader)?;
+    let bytes = advance(reader, len.div_ceil(8))?;
+    if !len.is_multiple_of(8) && bytes[bytes.len() - 1] >> (len % 8) != 0 {
+        return Err(Error::InvalidValue);
+    }
+    let mut bits = BitVec::with_capacity(len);
+    bits.extend((0..len).map(|i| (bytes[i / 8] >> (i % 8)) & 1 == 1));
+    read_terminator(reader)?;
+    Ok(bits)
+}
+
+/// Skips over a marshalled bit sequence in the reader.
+pub fn skip_bit_slice(reader: &mut &[u8]) -> Result<()> {
+    let len = unmarshal_usize(reader)?;
+    advance(reader, len.div_ceil(8))?;
+    read_terminator(reader)
+}
+
+impl<T: BitStore, O: BitOrder> BencEncode for BitSlice<T, O> {
+    fn size(&self) -> usize {
+        size_bit_slice(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_bit_slice(self, writer)
+    }
+}
+
+impl<T: BitStore, O: BitOrder> BencEncode for BitVec<T, O> {
+    fn size(&self) -> usize {
+        size_bit_slice(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_bit_slice(self, writer)
+    }
+}
+
+impl<T: BitStore, O: BitOrder> BencDecode<'_> for BitVec<T, O> {
+    fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+        unmarshal_bit_vec(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_bit_slice(reader)
+    }
+}
@@ -0,0 +1,170 @@
+//! Support for inline, bounded vectors: `arrayvec::ArrayVec` (feature `arrayvec`) and
+//! `tinyvec::ArrayVec` and `tinyvec::TinyVec` (feature `tinyvec`).
+//!
+//! All of them use the slice format, so a field can switch between `Vec<T>` and an
+//! inline vector without changing its encoding. Decoding into an array-backed vector
+//! checks the marshalled length against its capacity before reading any element, and
+//! returns a `CapacityExceeded` error if it does not fit, which makes these types a
+//! simple way to bound protocol fields. A `TinyVec` moves to the heap when it outgrows
+//! its array, so it accepts any length.
+
+// ===================================================================================
+// arrayvec::ArrayVec
+// ===================================================================================
+
+#[cfg(feature = "arrayvec")]
+mod array_vec {
+    use arrayvec::ArrayVec;
+
+    use crate::{
+        BencDecode, BencEncode, Error, Result, Type, marshal_slice, read_terminator, size_slice, skip_slice,
+        unmarshal_usize,
+    };
+
+    /// Unmarshals a slice from the reader into an `ArrayVec<T, N>`.
+    ///
+    /// Returns a `CapacityExceeded` error if the slice holds more than `N` elements.
+    pub fn unmarshal_array_vec<'a, T, const N: usize, E: From<Error>>(
+        reader: &mut &'a [u8],
+        unmarshaler: impl Fn(&mut &'a [u8]) -> Result<T, E>,
+    ) -> Result<ArrayVec<T, N>, E> {
+        let len = unmarshal_usize(reader)?;
+        if len > N {
+            return Err(Error::CapacityExceeded { len, capacity: N }.into());
+        }
+        let mut vec = ArrayVec::new();
+        for _ in 0..len {
+            vec.push(unmarshaler(reader)?);
+        }
+        read_terminator(reader)?;
+        Ok(vec)
+    }
+
+    impl<T: BencEncode, const N: usize> BencEncode for ArrayVec<T, N> {
+        fn benc_type() -> Option<Type> {
+            T::benc_type().map(|ty| Type::Slice(Box::new(ty)))
+        }
+
+        fn size(&self) -> usize {
+            size_slice(self, T::size)
+        }
+
+        fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_slice(self, writer, T::marshal)
+        }
+    }
+
+    impl<'a, T: BencDecode<'a>, const N: usize> BencDecode<'a> for ArrayVec<T, N> {
+        fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+            unmarshal_array_vec(reader, T::unmarshal)
+        }
+
+        fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_slice(reader, T::skip)
+        }
+    }
+}
+
+#[cfg(feature = "arrayvec")]
+pub use array_vec::*;
+
+// ===================================================================================
+// tinyvec::ArrayVec and tinyvec::TinyVec
+// ===================================================================================
+
+#[cfg(feature = "tinyvec")]
+mod tiny_vec {
+    use tinyvec::{Array, ArrayVec, TinyVec};
+
+    use crate::{
+        BencDecode, BencEncode, Error, Result, Type, marshal_slice, read_terminator, size_slice, skip_slice,
+        unmarshal_usize,
+    };
+
+    /// Unmarshals a slice from the reader into a `tinyvec::ArrayVec`.
+    ///
+    /// Returns a `CapacityExceeded` error if the slice holds more elements than the
+    /// array.
+    pub fn unmarshal_tiny_array_vec<'a, A: Array, E: From<Error>>(
+        reader: &mut &'a [u8],
+        unmarshaler: impl Fn(&mut &'a [u8]) -> Result<A::Item, E>,
+    ) -> Result<ArrayVec<A>, E> {
+        let len = unmarshal_usize(reader)?;
+        if len > A::CAPACITY {
+            return Err(Error::CapacityExceeded { len, capacity: A::CAPACITY }.into());
+        }
+        let mut vec = ArrayVec::new();
+        for _ in 0..len {
+            vec.push(unmarshaler(reader)?);
+        }
+        read_terminator(reader)?;
+        Ok(vec)
+    }
+
+    /// Unmarshals a slice from the reader into a `TinyVec`, which stays inline if the
+    /// slice fits into the array.
+    pub fn unmarshal_tiny_vec<'a, A: Array, E: From<Error>>(
+        reader: &mut &'a [u8],
+        unmarshaler: impl Fn(&mut &'a [u8]) -> Result<A::Item, E>,
+    ) -> Result<TinyVec<A>, E> {
+        let len = unmarshal_usize(reader)?;
+        // The length is untrusted, so the preallocation is bounded by the remaining input.
+        let mut vec = TinyVec::with_capacity(len.min(reader.len()));
+        for _ in 0..len {
+            vec.push(unmarshaler(reader)?);
+        }
+        read_terminator(reader)?;
+        Ok(vec)
+    }
+
+    impl<A: Array<Item: BencEncode>> BencEncode for ArrayVec<A> {
+        fn benc_type() -> Option<Type> {
+            A::Item::benc_type().map(|ty| Type::Slice(Box::new(ty)))
+        }
+
+        fn size(&self) -> usize {
+            size_slice(self, A::Item::size)
+        }
+
+        fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_slice(self, writer, A::Item::marshal)
+        }
+    }
+
+    impl<'a, A: Array<Item: BencDecode<'a>>> BencDecode<'a> for ArrayVec<A> {
+        fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+            unmarshal_tiny_array_vec(reader, A::Item::unmarshal)
+        }
+
+        fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_slice(reader, A::Item::skip)
+        }
+    }
+
+    impl<A: Array<Item: BencEncode>> BencEncode for TinyVec<A> {
+        fn benc_type() -> Option<Type> {
+            A::Item::benc_type().map(|ty| Type::Slice(Box::new(ty)))
+        }
+
+        fn size(&self) -> usize {
+            size_slice(self, A::Item::size)
+        }
+
+        fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_slice(self, writer, A::Item::marshal)
+        }
+    }
+
+    impl<'a, A: Array<Item: BencDecode<'a>>> BencDecode<'a> for TinyVec<A> {
+        fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+            unmarshal_tiny_vec(reader, A::Item::unmarshal)
+        }
+
+        fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_slice(reader, A::Item::skip)
+        }
+    }
+}
+
+#[cfg(feature = "tinyvec")]
+pub use tiny_vec::*;
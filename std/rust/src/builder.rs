@@ -0,0 +1,451 @@
+//! Builders that modify already-marshalled data without re-encoding it, or that
+//! marshal values that are not known up front, such as the length of a map or a
+//! checksum of the bytes that follow it.
+
+use crate::{BencEncode, Error, Result, TERMINATOR, marshal_uint, read_terminator, size_uint, unmarshal_uint};
+
+// ===================================================================================
+// SliceBuilder
+// ===================================================================================
+
+/// Appends elements to a marshalled slice without re-encoding the existing elements.
+///
+/// The builder owns the marshalled bytes. New elements are written after the existing
+/// ones and `finish` patches the length prefix and re-appends the terminator. The
+/// prefix is overwritten in place when its varint width does not change, otherwise
+/// the buffer is spliced once.
+///
+/// With the `zeroize` feature, the buffer is scrubbed whenever it is moved to a
+/// larger allocation, so no copies of the elements are left in freed memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SliceBuilder {
+    buf: Vec<u8>,
+    len: u64,
+    header_len: usize,
+}
+
+impl Default for SliceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SliceBuilder {
+    /// Creates a builder for a new, empty slice.
+    pub fn new() -> Self {
+        SliceBuilder { buf: vec![0], len: 0, header_len: 1 }
+    }
+
+    /// Opens a buffer that holds exactly one marshalled slice for appending.
+    ///
+    /// Returns a `MissingTerminator` error if the buffer does not end with the
+    /// terminator sequence.
+    pub fn open(mut buf: Vec<u8>) -> Result<Self> {
+        let mut reader = buf.as_slice();
+        let len = unmarshal_uint(&mut reader)?;
+        let header_len = buf.len() - reader.len();
+        if reader.len() < TERMINATOR.len() {
+            return Err(Error::BufferTooSmall { needed: TERMINATOR.len(), available: reader.len() });
+        }
+        if !buf.ends_with(&TERMINATOR) {
+            return Err(Error::MissingTerminator);
+        }
+        buf.truncate(buf.len() - TERMINATOR.len());
+        Ok(SliceBuilder { buf, len, header_len })
+    }
+
+    /// Makes room for at least `additional` more bytes.
+    #[cfg(feature = "zeroize")]
+    fn reserve(&mut self, additional: usize) {
+        use zeroize::Zeroize;
+
+        if self.buf.capacity() - self.buf.len() >= additional {
+            return;
+        }
+        let capacity = (self.buf.len() + additional).max(self.buf.capacity() * 2);
+        let mut grown = Vec::with_capacity(capacity);
+        grown.extend_from_slice(&self.buf);
+        let mut old = std::mem::replace(&mut self.buf, grown);
+        old.zeroize();
+    }
+
+    /// Makes room for at least `additional` more bytes.
+    #[cfg(not(feature = "zeroize"))]
+    fn reserve(&mut self, additional: usize) {
+        self.buf.reserve(additional);
+    }
+
+    /// Returns the number of elements in the slice, including appended ones.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the slice has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends an element using its marshaler. `size` must be at least the number of
+    /// bytes the marshaler writes, as returned by the matching sizer.
+    ///
+    /// Returns an error if the marshaler fails; the builder is left unchanged.
+    pub fn push(
+        &mut self,
+        size: usize,
+        marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>,
+    ) -> Result<()> {
+        let start = self.buf.len();
+        self.reserve(size);
+        self.buf.resize(start + size, 0);
+        let mut writer = &mut self.buf[start..];
+        if let Err(err) = marshaler(&mut writer) {
+            self.buf.truncate(start);
+            return Err(err);
+        }
+        let unused = writer.len();
+        self.buf.truncate(start + size - unused);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends an element that is already marshalled.
+    pub fn push_encoded(&mut self, element: &[u8]) {
+        self.reserve(element.len());
+        self.buf.extend_from_slice(element);
+        self.len += 1;
+    }
+
+    /// Patches the length prefix, appends the terminator and returns the marshalled slice.
+    pub fn finish(mut self) -> Vec<u8> {
+        let new_header_len = size_uint(self.len);
+        self.reserve(new_header_len.saturating_sub(self.header_len) + TERMINATOR.len());
+        let mut header = [0u8; 10];
+        marshal_uint(self.len, &mut &mut header[..]).unwrap();
+        if new_header_len == self.header_len {
+            self.buf[..new_header_len].copy_from_slice(&header[..new_header_len]);
+        } else {
+            self.buf.splice(..self.header_len, header[..new_header_len].iter().copied());
+        }
+        self.buf.extend_from_slice(&TERMINATOR);
+        self.buf
+    }
+
+    /// Like `finish`, but returns the marshalled slice wrapped so that it is scrubbed
+    /// when dropped.
+    #[cfg(feature = "zeroize")]
+    pub fn finish_zeroizing(self) -> zeroize::Zeroizing<Vec<u8>> {
+        zeroize::Zeroizing::new(self.finish())
+    }
+}
+
+// ===================================================================================
+// MapEncoder
+// ===================================================================================
+
+/// The number of bytes `MapEncoder` reserves for the length prefix of a map.
+const RESERVED_LEN_WIDTH: usize = 10;
+
+/// How a `MapEncoder` records the number of entries of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MapLength {
+    /// A length prefix of the widest varint is reserved before the entries and patched
+    /// by `finish`. Every map decoder reads it, but the padded varint is not the
+    /// canonical encoding, so strict and canonical decoders reject it and `lint` warns
+    /// about it.
+    #[default]
+    Reserved,
+    /// The entries are written without a length prefix and end with the terminator
+    /// alone. Such a map must be read with [`unmarshal_map_terminator_only`].
+    TerminatorOnly,
+}
+
+/// Marshals a map entry by entry into a buffer, for producers that do not know the
+/// number of entries before they are done, such as ones iterating a database cursor.
+///
+/// The map is appended to the buffer, so it can follow the fields marshalled before
+/// it, and the entries are written as they are pushed. `finish` records the length
+/// the way the [`MapLength`] of the encoder asks for and appends the terminator; a
+/// map whose encoder is dropped without it is incomplete.
+#[derive(Debug)]
+pub struct MapEncoder<'b> {
+    buf: &'b mut Vec<u8>,
+    header: usize,
+    len: u64,
+    mode: MapLength,
+}
+
+impl<'b> MapEncoder<'b> {
+    /// Starts a map at the end of `buf`.
+    pub fn new(buf: &'b mut Vec<u8>, mode: MapLength) -> Self {
+        let header = buf.len();
+        if mode == MapLength::Reserved {
+            buf.resize(header + RESERVED_LEN_WIDTH, 0);
+        }
+        MapEncoder { buf, header, len: 0, mode }
+    }
+
+    /// Returns the number of entries pushed so far.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if no entry has been pushed.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends an entry using a marshaler that writes its key followed by its value.
+    /// `size` must be at least the number of bytes the marshaler writes.
+    ///
+    /// Returns an error if the marshaler fails; the map is left unchanged.
+    pub fn push(&mut self, size: usize, marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>) -> Result<()> {
+        let start = self.buf.len();
+        self.buf.resize(start + size, 0);
+        let mut writer = &mut self.buf[start..];
+        if let Err(err) = marshaler(&mut writer) {
+            self.buf.truncate(start);
+            return Err(err);
+        }
+        let unused = writer.len();
+        self.buf.truncate(start + size - unused);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends an entry whose key and value are already marshalled.
+    pub fn push_encoded(&mut self, key: &[u8], value: &[u8]) {
+        self.buf.extend_from_slice(key);
+        self.buf.extend_from_slice(value);
+        self.len += 1;
+    }
+
+    /// Appends an entry of types with a canonical encoding.
+    ///
+    /// Returns an error if marshalling the key or the value fails; the map is left
+    /// unchanged.
+    pub fn insert<K: BencEncode + ?Sized, V: BencEncode + ?Sized>(&mut self, key: &K, value: &V) -> Result<()> {
+        self.push(key.size() + value.size(), |w| {
+            key.marshal(w)?;
+            value.marshal(w)
+        })
+    }
+
+    /// Records the length of the map and appends the terminator.
+    pub fn finish(self) {
+        if self.mode == MapLength::Reserved {
+            let header = &mut self.buf[self.header..self.header + RESERVED_LEN_WIDTH];
+            let mut v = self.len;
+            for b in &mut header[..RESERVED_LEN_WIDTH - 1] {
+                *b = (v & 0x7f) as u8 | 0x80;
+                v >>= 7;
+            }
+            header[RESERVED_LEN_WIDTH - 1] = v as u8;
+        }
+        self.buf.extend_from_slice(&TERMINATOR);
+    }
+}
+
+/// Unmarshals a map written by a `MapEncoder` in [`MapLength::TerminatorOnly`] mode
+/// from the reader, into any collection that can be extended with key-value pairs.
+///
+/// The entries end where the reader holds the terminator, so a key whose encoding
+/// starts with the terminator sequence ends the map early. Use the mode only for keys
+/// whose encoding cannot start with it, such as strings that are never exactly one
+/// byte long.
+pub fn unmarshal_map_terminator_only<'a, K, V, M, E: From<Error>>(
+    reader: &mut &'a [u8],
+    k_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<K, E>,
+    v_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<V, E>,
+) -> Result<M, E>
+where
+    M: Default + Extend<(K, V)>,
+{
+    let mut map = M::default();
+    while !reader.starts_with(&TERMINATOR) {
+        let k = k_unmarshaler(reader)?;
+        let v = v_unmarshaler(reader)?;
+        map.extend(Some((k, v)));
+    }
+    read_terminator(reader)?;
+    Ok(map)
+}
+
+/// Skips over a map written in [`MapLength::TerminatorOnly`] mode in the reader.
+pub fn skip_map_terminator_only<E: From<Error>>(
+    reader: &mut &[u8],
+    skip_key: impl Fn(&mut &[u8]) -> Result<(), E>,
+    skip_value: impl Fn(&mut &[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    while !reader.starts_with(&TERMINATOR) {
+        skip_key(reader)?;
+        skip_value(reader)?;
+    }
+    Ok(read_terminator(reader)?)
+}
+
+// ===================================================================================
+// MessageBuilder
+// ===================================================================================
+
+/// A fixed-size range of a `MessageBuilder` reserved for a value written later.
+///
+/// A slot is filled by the builder that reserved it; filling it again overwrites the
+/// previous value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use = "a reserved slot must be filled before the message is finished"]
+pub struct Slot {
+    index: usize,
+    offset: usize,
+    len: usize,
+}
+
+impl Slot {
+    /// Returns the offset of the slot in the message.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the number of bytes of the slot.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the slot holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Marshals a message front to back, with slots reserved for values known only once
+/// later parts are written, such as a count or a checksum.
+///
+/// Values are appended as with a writer, and [`reserve`](Self::reserve) leaves a
+/// zeroed slot of a fixed size in their place, to be filled with
+/// [`fill`](Self::fill) at any later point. `finish` checks that every slot has been
+/// filled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageBuilder {
+    buf: Vec<u8>,
+    /// The offset, length and state of every reserved slot, by index.
+    slots: Vec<(usize, usize, bool)>,
+    /// Holds the value of a slot while it is filled, so a failed fill leaves the slot
+    /// as it was. Empty between calls.
+    scratch: Vec<u8>,
+}
+
+impl MessageBuilder {
+    /// Creates a builder for an empty message.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a builder for an empty message with room for `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        MessageBuilder { buf: Vec::with_capacity(capacity), slots: Vec::new(), scratch: Vec::new() }
+    }
+
+    /// Returns the number of bytes of the message so far, reserved slots included.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns `true` if nothing has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the message so far, with unfilled slots zeroed, to compute a checksum
+    /// over a part of it for instance.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Appends a value using its marshaler. `size` must be at least the number of
+    /// bytes the marshaler writes, as returned by the matching sizer.
+    ///
+    /// Returns an error if the marshaler fails; the message is left unchanged.
+    pub fn push(&mut self, size: usize, marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>) -> Result<()> {
+        let start = self.buf.len();
+        self.buf.resize(start + size, 0);
+        let mut writer = &mut self.buf[start..];
+        if let Err(err) = marshaler(&mut writer) {
+            self.buf.truncate(start);
+            return Err(err);
+        }
+        let unused = writer.len();
+        self.buf.truncate(start + size - unused);
+        Ok(())
+    }
+
+    /// Appends bytes that are already marshalled.
+    pub fn push_encoded(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Appends a value of a type with a canonical encoding.
+    ///
+    /// Returns an error if marshalling fails; the message is left unchanged.
+    pub fn put<T: BencEncode + ?Sized>(&mut self, v: &T) -> Result<()> {
+        self.push(v.size(), |w| v.marshal(w))
+    }
+
+    /// Reserves a zeroed slot of `len` bytes at the end of the message.
+    pub fn reserve(&mut self, len: usize) -> Slot {
+        let offset = self.buf.len();
+        self.buf.resize(offset + len, 0);
+        self.slots.push((offset, len, false));
+        Slot { index: self.slots.len() - 1, offset, len }
+    }
+
+    /// Reserves a slot for a value of a type with a constant encoded size, such as
+    /// `u32` or `u64`.
+    ///
+    /// Returns an `Unsupported` error if the size of `T` is not constant.
+    pub fn reserve_for<T: BencEncode + ?Sized>(&mut self) -> Result<Slot> {
+        let len = T::ENCODED_SIZE
+            .ok_or_else(|| Error::Unsupported(format!("{} has no constant size", std::any::type_name::<T>())))?;
+        Ok(self.reserve(len))
+    }
+
+    /// Fills a slot using a marshaler, which must write exactly as many bytes as the
+    /// slot holds.
+    ///
+    /// Returns an `InvalidValue` error if it writes fewer, an error if it fails or
+    /// writes more, or an `OutOfRange` error if the slot does not match a slot
+    /// reserved by this builder. The slot is left as it was on error.
+    pub fn fill(&mut self, slot: Slot, marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>) -> Result<()> {
+        match self.slots.get(slot.index) {
+            Some(&(offset, len, _)) if (offset, len) == (slot.offset, slot.len) => {}
+            _ => return Err(Error::OutOfRange),
+        }
+        self.scratch.resize(slot.len, 0);
+        let mut writer = self.scratch.as_mut_slice();
+        let result = match marshaler(&mut writer) {
+            Ok(()) if !writer.is_empty() => Err(Error::InvalidValue),
+            result => result,
+        };
+        if result.is_ok() {
+            self.buf[slot.offset..slot.offset + slot.len].copy_from_slice(&self.scratch);
+            self.slots[slot.index].2 = true;
+        }
+        self.scratch.clear();
+        result
+    }
+
+    /// Fills a slot with a value of a type with a canonical encoding.
+    ///
+    /// Returns the errors of [`fill`](Self::fill).
+    pub fn fill_with<T: BencEncode + ?Sized>(&mut self, slot: Slot, v: &T) -> Result<()> {
+        self.fill(slot, |w| v.marshal(w))
+    }
+
+    /// Returns the marshalled message.
+    ///
+    /// Returns an `InvalidValue` error if a reserved slot has not been filled.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        if self.slots.iter().any(|&(_, _, filled)| !filled) {
+            return Err(Error::InvalidValue);
+        }
+        Ok(self.buf)
+    }
+}
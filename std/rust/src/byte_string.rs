@@ -0,0 +1,94 @@
+//! Support for byte strings from `bstr`, enabled by the `bstr` feature.
+//!
+//! Byte strings are conventionally UTF-8 but not guaranteed to be, which suits data
+//! such as file names and legacy logs. They are marshalled exactly like byte slices,
+//! and no UTF-8 validation is performed when decoding. `BString`, `BStr` and `&BStr`
+//! also implement the encoding traits, so structs defined with
+//! [`benc_struct!`](crate::benc_struct) can hold them.
+
+use bstr::{BStr, BString};
+
+use crate::{
+    BencDecode, BencEncode, Result, Type, WireType, marshal_bytes, size_bytes, skip_bytes, unmarshal_bytes_cropped,
+};
+
+/// Returns the number of bytes required to marshal a byte string.
+pub fn size_bstr(s: &BStr) -> usize {
+    size_bytes(s)
+}
+
+/// Marshals a byte string into the writer.
+/// The format is a varint-encoded length followed by the bytes.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_bstr(s: &BStr, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_bytes(s, writer)
+}
+
+/// Unmarshals a byte string from the reader without allocating.
+/// The returned `&BStr` is a slice of the input buffer.
+pub fn unmarshal_bstr<'a>(reader: &mut &'a [u8]) -> Result<&'a BStr> {
+    unmarshal_bytes_cropped(reader).map(BStr::new)
+}
+
+/// Unmarshals a byte string from the reader by copying into a new `BString`.
+pub fn unmarshal_bstring(reader: &mut &[u8]) -> Result<BString> {
+    unmarshal_bytes_cropped(reader).map(BString::from)
+}
+
+/// Skips over a marshalled byte string in the reader.
+pub fn skip_bstr(reader: &mut &[u8]) -> Result<()> {
+    skip_bytes(reader)
+}
+
+impl BencEncode for BStr {
+    const WIRE_TYPE: Option<WireType> = Some(WireType::Bytes);
+
+    fn benc_type() -> Option<Type> {
+        Some(Type::Bytes)
+    }
+
+    fn size(&self) -> usize {
+        size_bstr(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_bstr(self, writer)
+    }
+}
+
+impl BencEncode for BString {
+    const WIRE_TYPE: Option<WireType> = Some(WireType::Bytes);
+
+    fn benc_type() -> Option<Type> {
+        Some(Type::Bytes)
+    }
+
+    fn size(&self) -> usize {
+        size_bstr(self.as_ref())
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_bstr(self.as_ref(), writer)
+    }
+}
+
+impl BencDecode<'_> for BString {
+    fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+        unmarshal_bstring(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_bstr(reader)
+    }
+}
+
+impl<'a> BencDecode<'a> for &'a BStr {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        unmarshal_bstr(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_bstr(reader)
+    }
+}
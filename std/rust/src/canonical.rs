@@ -0,0 +1,149 @@
+//! The canonical encoding profile, for signing messages.
+//!
+//! A signature covers bytes, so signer and verifier must agree on the exact encoding
+//! of a value. The regular encoding leaves a few choices open; the canonical profile
+//! fixes each of them, so any implementation that follows it, in any language,
+//! produces the same bytes for the same value:
+//!
+//! - varints, including lengths and counts, use the fewest bytes possible,
+//! - booleans and option flags are `0` or `1`,
+//! - NaN is the quiet NaN with no payload (`0x7fc00000` and `0x7ff8000000000000`)
+//!   and negative zero is positive zero,
+//! - map entries are ordered by the bytes of their marshalled keys, compared
+//!   lexicographically, and no key appears twice,
+//! - layouts with optional padding, such as the aligned slices, are not used.
+//!
+//! Ordering maps by key bytes rather than by key value keeps the rule independent of
+//! the language, but differs from the order `marshal_pairs_sorted` uses for fixed-size
+//! integer keys, which are little-endian.
+
+use crate::{
+    BencEncode, Error, Result, TERMINATOR, Type, advance, marshal_uint, read_terminator, unmarshal_bytes_cropped,
+    unmarshal_string, unmarshal_u8, unmarshal_u32, unmarshal_u64, unmarshal_uint, unmarshal_usize,
+};
+
+/// Appends a minimal varint.
+fn push_uint(out: &mut Vec<u8>, v: u64) {
+    let mut buf = [0u8; 10];
+    let mut writer = &mut buf[..];
+    // Ten bytes hold any varint.
+    marshal_uint(v, &mut writer).unwrap();
+    let len = 10 - writer.len();
+    out.extend_from_slice(&buf[..len]);
+}
+
+fn canonicalize_type(reader: &mut &[u8], ty: &Type, out: &mut Vec<u8>) -> Result<()> {
+    match ty {
+        Type::Bool => out.push((unmarshal_u8(reader)? == 1) as u8),
+        Type::U8 | Type::I8 | Type::U16 | Type::I16 | Type::U32 | Type::I32 | Type::U64 | Type::I64 | Type::Time => {
+            // Checked by the match arm: these types have a fixed size.
+            out.extend_from_slice(advance(reader, ty.fixed_size().unwrap())?);
+        }
+        Type::F32 => {
+            let v = f32::from_bits(unmarshal_u32(reader)?);
+            let bits = if v.is_nan() { 0x7fc0_0000 } else if v == 0.0 { 0 } else { v.to_bits() };
+            out.extend_from_slice(&bits.to_le_bytes());
+        }
+        Type::F64 => {
+            let v = f64::from_bits(unmarshal_u64(reader)?);
+            let bits = if v.is_nan() { 0x7ff8_0000_0000_0000 } else if v == 0.0 { 0 } else { v.to_bits() };
+            out.extend_from_slice(&bits.to_le_bytes());
+        }
+        // ZigZag leaves the varint itself unchanged, so both are re-encoded alike.
+        Type::Uint | Type::Int => push_uint(out, unmarshal_uint(reader)?),
+        Type::String => {
+            let s = unmarshal_string(reader)?;
+            push_uint(out, s.len() as u64);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Type::Bytes => {
+            let b = unmarshal_bytes_cropped(reader)?;
+            push_uint(out, b.len() as u64);
+            out.extend_from_slice(b);
+        }
+        Type::Slice(elem) => {
+            let len = unmarshal_usize(reader)?;
+            push_uint(out, len as u64);
+            for _ in 0..len {
+                canonicalize_type(reader, elem, out)?;
+            }
+            read_terminator(reader)?;
+            out.extend_from_slice(&TERMINATOR);
+        }
+        Type::Map(k, v) => {
+            let len = unmarshal_usize(reader)?;
+            // The count is untrusted, so the preallocation is bounded by the input.
+            let mut entries = Vec::with_capacity(len.min(reader.len()));
+            for _ in 0..len {
+                let mut key = Vec::new();
+                canonicalize_type(reader, k, &mut key)?;
+                let mut value = Vec::new();
+                canonicalize_type(reader, v, &mut value)?;
+                entries.push((key, value));
+            }
+            read_terminator(reader)?;
+            entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            if entries.windows(2).any(|w| w[0].0 == w[1].0) {
+                return Err(Error::NonCanonical);
+            }
+            push_uint(out, len as u64);
+            for (key, value) in &entries {
+                out.extend_from_slice(key);
+                out.extend_from_slice(value);
+            }
+            out.extend_from_slice(&TERMINATOR);
+        }
+        Type::Option(inner) => {
+            let some = unmarshal_u8(reader)? == 1;
+            out.push(some as u8);
+            if some {
+                canonicalize_type(reader, inner, out)?;
+            }
+        }
+        Type::Struct(schema) => {
+            for field in schema.fields() {
+                canonicalize_type(reader, &field.ty, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites a marshalled value of the given type in the canonical profile.
+///
+/// Returns a `NonCanonical` error if a map holds the same key twice, and a
+/// `TrailingBytes` error if `buf` holds more than one value.
+pub fn canonicalize(buf: &[u8], ty: &Type) -> Result<Vec<u8>> {
+    let mut reader = buf;
+    let mut out = Vec::with_capacity(buf.len());
+    canonicalize_type(&mut reader, ty, &mut out)?;
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(out)
+}
+
+/// Returns the [`Type`] of `T`, or an `Unsupported` error if it has none.
+fn canonical_type<T: BencEncode + ?Sized>() -> Result<Type> {
+    T::benc_type().ok_or_else(|| Error::Unsupported(format!("{} has no benc type", std::any::type_name::<T>())))
+}
+
+/// Marshals a value in the canonical profile.
+///
+/// Returns an `Unsupported` error if the type does not describe its encoding through
+/// [`BencEncode::benc_type`], which the profile needs to find its maps and floats.
+pub fn marshal_canonical<T: BencEncode + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    canonicalize(&value.to_vec(), &canonical_type::<T>()?)
+}
+
+/// Checks that `buf` holds a single `T` marshalled in the canonical profile, as a
+/// verifier should before checking a signature over it.
+///
+/// Returns a `NonCanonical` error if the bytes decode but are not canonical, and the
+/// decoding error if they do not decode.
+pub fn verify_canonical<T: BencEncode + ?Sized>(buf: &[u8]) -> Result<()> {
+    if canonicalize(buf, &canonical_type::<T>()?)? != buf {
+        return Err(Error::NonCanonical);
+    }
+    Ok(())
+}
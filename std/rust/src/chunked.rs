@@ -0,0 +1,204 @@
+//! Chunked slices, which split a large slice into independently decodable chunks.
+//!
+//! A chunked slice is marshalled as the varint total element count, the varint chunk
+//! count, an index holding the varint element count and byte length of every chunk,
+//! the chunk data back to back, and the terminator. Each chunk holds its elements
+//! marshalled one after another.
+//!
+//! Since the index gives the location of every chunk up front, a decoder can hand the
+//! chunks to worker threads, decode them concurrently and concatenate the results.
+//! With the `rayon` feature, `unmarshal_chunked_parallel` does exactly that.
+
+use crate::{
+    Error, Result, TERMINATOR, advance, marshal_usize, read_terminator, size_usize,
+    unmarshal_usize, write_to_slice,
+};
+
+/// Returns the byte length of every chunk of `chunk_len` elements.
+fn chunk_sizes<T>(slice: &[T], chunk_len: usize, sizer: impl Fn(&T) -> usize) -> Vec<usize> {
+    slice.chunks(chunk_len.max(1)).map(|chunk| chunk.iter().map(&sizer).sum()).collect()
+}
+
+fn size_index(slice_len: usize, chunk_len: usize, sizes: &[usize]) -> usize {
+    let chunk_len = chunk_len.max(1);
+    let counts = (0..sizes.len()).map(|i| size_usize(chunk_len.min(slice_len - i * chunk_len)));
+    size_usize(slice_len)
+        + size_usize(sizes.len())
+        + counts.sum::<usize>()
+        + sizes.iter().map(|&s| size_usize(s) + s).sum::<usize>()
+}
+
+// ===================================================================================
+// Encoding
+// ===================================================================================
+
+/// Returns the number of bytes needed to marshal a slice split into chunks of
+/// `chunk_len` elements. A `chunk_len` of zero is treated as one.
+pub fn size_chunked<T>(slice: &[T], chunk_len: usize, sizer: impl Fn(&T) -> usize) -> usize {
+    let sizes = chunk_sizes(slice, chunk_len, sizer);
+    size_index(slice.len(), chunk_len, &sizes) + TERMINATOR.len()
+}
+
+/// Marshals a slice split into chunks of `chunk_len` elements (the last chunk may be
+/// shorter). A `chunk_len` of zero is treated as one.
+///
+/// `sizer` must return exactly the number of bytes `marshaler` writes for an element,
+/// since the index records the byte length of every chunk.
+///
+/// Returns an error if the writer is too small or a marshaler fails.
+pub fn marshal_chunked<T, E: From<Error>>(
+    slice: &[T],
+    chunk_len: usize,
+    writer: &mut &mut [u8],
+    sizer: impl Fn(&T) -> usize,
+    mut marshaler: impl FnMut(&T, &mut &mut [u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    let sizes = chunk_sizes(slice, chunk_len, sizer);
+    marshal_usize(slice.len(), writer)?;
+    marshal_usize(sizes.len(), writer)?;
+    for (chunk, &size) in slice.chunks(chunk_len.max(1)).zip(&sizes) {
+        marshal_usize(chunk.len(), writer)?;
+        marshal_usize(size, writer)?;
+    }
+    for element in slice {
+        marshaler(element, writer)?;
+    }
+    write_to_slice(writer, &TERMINATOR)?;
+    Ok(())
+}
+
+// ===================================================================================
+// Decoding
+// ===================================================================================
+
+/// A chunk of a chunked slice, holding a number of still-marshalled elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunk<'a> {
+    len: usize,
+    data: &'a [u8],
+}
+
+impl<'a> Chunk<'a> {
+    /// Returns the number of elements in the chunk.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the chunk holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the marshalled elements.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Unmarshals the elements of the chunk, appending them to `out`.
+    ///
+    /// Returns a `TrailingBytes` error if the elements do not occupy the whole chunk.
+    pub fn decode_into<T, E: From<Error>>(
+        &self,
+        out: &mut Vec<T>,
+        mut unmarshaler: impl FnMut(&mut &'a [u8]) -> Result<T, E>,
+    ) -> Result<(), E> {
+        let mut reader = self.data;
+        out.reserve(self.len.min(reader.len()));
+        for _ in 0..self.len {
+            out.push(unmarshaler(&mut reader)?);
+        }
+        if !reader.is_empty() {
+            return Err(Error::TrailingBytes.into());
+        }
+        Ok(())
+    }
+
+    /// Unmarshals the elements of the chunk.
+    ///
+    /// Returns a `TrailingBytes` error if the elements do not occupy the whole chunk.
+    pub fn decode<T, E: From<Error>>(
+        &self,
+        unmarshaler: impl FnMut(&mut &'a [u8]) -> Result<T, E>,
+    ) -> Result<Vec<T>, E> {
+        let mut out = Vec::new();
+        self.decode_into(&mut out, unmarshaler)?;
+        Ok(out)
+    }
+}
+
+/// A parsed chunked slice, giving access to its chunks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedReader<'a> {
+    len: usize,
+    chunks: Vec<Chunk<'a>>,
+}
+
+impl<'a> ChunkedReader<'a> {
+    /// Returns the total number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the slice has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the chunks in order.
+    pub fn chunks(&self) -> &[Chunk<'a>] {
+        &self.chunks
+    }
+
+    /// Unmarshals all elements, one chunk after another.
+    pub fn decode<T, E: From<Error>>(
+        &self,
+        mut unmarshaler: impl FnMut(&mut &'a [u8]) -> Result<T, E>,
+    ) -> Result<Vec<T>, E> {
+        let mut out = Vec::new();
+        for chunk in &self.chunks {
+            chunk.decode_into(&mut out, &mut unmarshaler)?;
+        }
+        Ok(out)
+    }
+}
+
+/// Parses a chunked slice from the reader, advancing it past the whole slice. Only the
+/// index is read; the elements are not touched until a chunk is decoded.
+///
+/// Returns an `InvalidValue` error if the element counts in the index do not add up to
+/// the total.
+pub fn unmarshal_chunked_index<'a>(reader: &mut &'a [u8]) -> Result<ChunkedReader<'a>> {
+    let len = unmarshal_usize(reader)?;
+    let count = unmarshal_usize(reader)?;
+    let mut index = Vec::with_capacity(count.min(reader.len()));
+    let mut total = 0usize;
+    for _ in 0..count {
+        let chunk_len = unmarshal_usize(reader)?;
+        let size = unmarshal_usize(reader)?;
+        total = total.checked_add(chunk_len).ok_or(Error::InvalidValue)?;
+        index.push((chunk_len, size));
+    }
+    if total != len {
+        return Err(Error::InvalidValue);
+    }
+    let mut chunks = Vec::with_capacity(index.len());
+    for (chunk_len, size) in index {
+        chunks.push(Chunk { len: chunk_len, data: advance(reader, size)? });
+    }
+    read_terminator(reader)?;
+    Ok(ChunkedReader { len, chunks })
+}
+
+/// Unmarshals a chunked slice, decoding the chunks one after another.
+pub fn unmarshal_chunked<'a, T, E: From<Error>>(
+    reader: &mut &'a [u8],
+    unmarshaler: impl FnMut(&mut &'a [u8]) -> Result<T, E>,
+) -> Result<Vec<T>, E> {
+    unmarshal_chunked_index(reader)?.decode(unmarshaler)
+}
+
+/// Skips over a marshalled chunked slice in the reader without looking at its
+/// elements.
+pub fn skip_chunked(reader: &mut &[u8]) -> Result<()> {
+    unmarshal_chunked_index(reader).map(|_| ())
+}
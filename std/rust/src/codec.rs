@@ -0,0 +1,271 @@
+//! Codecs bundle the four functions of a type into a single value.
+//!
+//! Encoding a type by hand takes a sizer, a marshaler, an unmarshaler and a skip
+//! function that must all agree on the format. A [`Codec`] defines them together,
+//! and the combinators [`slice_of`], [`map_of`] and [`option_of`] build codecs for
+//! collections from the codecs of their elements, so nested types are described once:
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use benc::codec::{self, Codec};
+//!
+//! let codec = codec::map_of(codec::Str, codec::slice_of(codec::U32));
+//! let value = HashMap::from([("primes".to_string(), vec![2, 3, 5])]);
+//!
+//! let mut buf = vec![0; codec.size(&value)];
+//! codec.marshal(&value, &mut buf.as_mut_slice()).unwrap();
+//! assert_eq!(codec.unmarshal(&mut buf.as_slice()).unwrap(), value);
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    Result, marshal_bool, marshal_bytes, marshal_f32, marshal_f64, marshal_i8, marshal_i16,
+    marshal_i32, marshal_i64, marshal_int, marshal_isize, marshal_map, marshal_option,
+    marshal_slice, marshal_string, marshal_time, marshal_u8, marshal_u16, marshal_u32, marshal_u64,
+    marshal_uint, marshal_usize, size_bool, size_bytes, size_f32, size_f64, size_i8, size_i16,
+    size_i32, size_i64, size_int, size_isize, size_map, size_option, size_slice, size_string,
+    size_time, size_u8, size_u16, size_u32, size_u64, size_uint, size_usize, skip_bool, skip_bytes,
+    skip_f32, skip_f64, skip_fixed_map, skip_fixed_slice, skip_i8, skip_i16, skip_i32, skip_i64,
+    skip_int, skip_isize, skip_map, skip_option, skip_slice, skip_string, skip_time, skip_u8,
+    skip_u16, skip_u32, skip_u64, skip_uint, skip_usize, unmarshal_bool, unmarshal_bytes_copied,
+    unmarshal_f32, unmarshal_f64, unmarshal_i8, unmarshal_i16, unmarshal_i32, unmarshal_i64,
+    unmarshal_int, unmarshal_isize, unmarshal_map, unmarshal_option, unmarshal_slice,
+    unmarshal_string, unmarshal_time, unmarshal_u8, unmarshal_u16, unmarshal_u32, unmarshal_u64,
+    unmarshal_uint, unmarshal_usize,
+};
+
+/// The encoding of values of type `T`.
+pub trait Codec<T> {
+    /// Returns the number of bytes required to marshal a value.
+    fn size(&self, v: &T) -> usize;
+
+    /// Marshals a value into the writer.
+    ///
+    /// Returns an error if the writer is too small.
+    fn marshal(&self, v: &T, writer: &mut &mut [u8]) -> Result<()>;
+
+    /// Unmarshals a value from the reader.
+    fn unmarshal(&self, reader: &mut &[u8]) -> Result<T>;
+
+    /// Skips over a marshalled value in the reader.
+    fn skip(&self, reader: &mut &[u8]) -> Result<()>;
+
+    /// Returns the size of every marshalled value if it is constant, which lets
+    /// collections of them be skipped in one step.
+    fn fixed_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<T, C: Codec<T> + ?Sized> Codec<T> for &C {
+    fn size(&self, v: &T) -> usize {
+        (**self).size(v)
+    }
+
+    fn marshal(&self, v: &T, writer: &mut &mut [u8]) -> Result<()> {
+        (**self).marshal(v, writer)
+    }
+
+    fn unmarshal(&self, reader: &mut &[u8]) -> Result<T> {
+        (**self).unmarshal(reader)
+    }
+
+    fn skip(&self, reader: &mut &[u8]) -> Result<()> {
+        (**self).skip(reader)
+    }
+
+    fn fixed_size(&self) -> Option<usize> {
+        (**self).fixed_size()
+    }
+}
+
+// ===================================================================================
+// Primitive Codecs
+// ===================================================================================
+
+// Use a macro to generate the codecs for types passed by value to avoid boilerplate.
+macro_rules! codec_impl {
+    ($codec:ident, $type:ty, $fixed:expr, $size:expr, $marshal_fn:ident, $unmarshal_fn:ident, $skip_fn:ident) => {
+        #[doc = concat!("The codec for `", stringify!($type), "` (`", stringify!($marshal_fn), "`).")]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        pub struct $codec;
+
+        impl Codec<$type> for $codec {
+            fn size(&self, v: &$type) -> usize {
+                ($size)(*v)
+            }
+
+            fn marshal(&self, v: &$type, writer: &mut &mut [u8]) -> Result<()> {
+                $marshal_fn(*v, writer)
+            }
+
+            fn unmarshal(&self, reader: &mut &[u8]) -> Result<$type> {
+                $unmarshal_fn(reader)
+            }
+
+            fn skip(&self, reader: &mut &[u8]) -> Result<()> {
+                $skip_fn(reader)
+            }
+
+            fn fixed_size(&self) -> Option<usize> {
+                $fixed
+            }
+        }
+    };
+}
+
+codec_impl!(Bool, bool, Some(size_bool()), |_| size_bool(), marshal_bool, unmarshal_bool, skip_bool);
+codec_impl!(U8, u8, Some(size_u8()), |_| size_u8(), marshal_u8, unmarshal_u8, skip_u8);
+codec_impl!(U16, u16, Some(size_u16()), |_| size_u16(), marshal_u16, unmarshal_u16, skip_u16);
+codec_impl!(U32, u32, Some(size_u32()), |_| size_u32(), marshal_u32, unmarshal_u32, skip_u32);
+codec_impl!(U64, u64, Some(size_u64()), |_| size_u64(), marshal_u64, unmarshal_u64, skip_u64);
+codec_impl!(I8, i8, Some(size_i8()), |_| size_i8(), marshal_i8, unmarshal_i8, skip_i8);
+codec_impl!(I16, i16, Some(size_i16()), |_| size_i16(), marshal_i16, unmarshal_i16, skip_i16);
+codec_impl!(I32, i32, Some(size_i32()), |_| size_i32(), marshal_i32, unmarshal_i32, skip_i32);
+codec_impl!(I64, i64, Some(size_i64()), |_| size_i64(), marshal_i64, unmarshal_i64, skip_i64);
+codec_impl!(F32, f32, Some(size_f32()), |_| size_f32(), marshal_f32, unmarshal_f32, skip_f32);
+codec_impl!(F64, f64, Some(size_f64()), |_| size_f64(), marshal_f64, unmarshal_f64, skip_f64);
+codec_impl!(Uint, u64, None, size_uint, marshal_uint, unmarshal_uint, skip_uint);
+codec_impl!(Int, i64, None, size_int, marshal_int, unmarshal_int, skip_int);
+codec_impl!(Usize, usize, None, size_usize, marshal_usize, unmarshal_usize, skip_usize);
+codec_impl!(Isize, isize, None, size_isize, marshal_isize, unmarshal_isize, skip_isize);
+codec_impl!(Time, DateTime<Utc>, Some(size_time()), |_| size_time(), marshal_time, unmarshal_time, skip_time);
+
+/// The codec for `String` (`marshal_string`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Str;
+
+impl Codec<String> for Str {
+    fn size(&self, v: &String) -> usize {
+        size_string(v)
+    }
+
+    fn marshal(&self, v: &String, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_string(v, writer)
+    }
+
+    fn unmarshal(&self, reader: &mut &[u8]) -> Result<String> {
+        unmarshal_string(reader).map(String::from)
+    }
+
+    fn skip(&self, reader: &mut &[u8]) -> Result<()> {
+        skip_string(reader)
+    }
+}
+
+/// The codec for `Vec<u8>` (`marshal_bytes`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bytes;
+
+impl Codec<Vec<u8>> for Bytes {
+    fn size(&self, v: &Vec<u8>) -> usize {
+        size_bytes(v)
+    }
+
+    fn marshal(&self, v: &Vec<u8>, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_bytes(v, writer)
+    }
+
+    fn unmarshal(&self, reader: &mut &[u8]) -> Result<Vec<u8>> {
+        unmarshal_bytes_copied(reader)
+    }
+
+    fn skip(&self, reader: &mut &[u8]) -> Result<()> {
+        skip_bytes(reader)
+    }
+}
+
+// ===================================================================================
+// Combinators
+// ===================================================================================
+
+/// The codec for `Vec<T>`, built by [`slice_of`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SliceOf<C>(pub C);
+
+/// Returns the codec for a slice whose elements use the given codec.
+pub fn slice_of<C>(element: C) -> SliceOf<C> {
+    SliceOf(element)
+}
+
+impl<T, C: Codec<T>> Codec<Vec<T>> for SliceOf<C> {
+    fn size(&self, v: &Vec<T>) -> usize {
+        size_slice(v, |e| self.0.size(e))
+    }
+
+    fn marshal(&self, v: &Vec<T>, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_slice(v, writer, |e, w| self.0.marshal(e, w))
+    }
+
+    fn unmarshal(&self, reader: &mut &[u8]) -> Result<Vec<T>> {
+        unmarshal_slice(reader, |r| self.0.unmarshal(r))
+    }
+
+    fn skip(&self, reader: &mut &[u8]) -> Result<()> {
+        match self.0.fixed_size() {
+            Some(element_size) => skip_fixed_slice(reader, element_size),
+            None => skip_slice(reader, |r| self.0.skip(r)),
+        }
+    }
+}
+
+/// The codec for `HashMap<K, V>`, built by [`map_of`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MapOf<KC, VC>(pub KC, pub VC);
+
+/// Returns the codec for a map whose keys and values use the given codecs.
+pub fn map_of<KC, VC>(key: KC, value: VC) -> MapOf<KC, VC> {
+    MapOf(key, value)
+}
+
+impl<K: Eq + Hash, V, KC: Codec<K>, VC: Codec<V>> Codec<HashMap<K, V>> for MapOf<KC, VC> {
+    fn size(&self, v: &HashMap<K, V>) -> usize {
+        size_map(v, |k| self.0.size(k), |v| self.1.size(v))
+    }
+
+    fn marshal(&self, v: &HashMap<K, V>, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_map(v, writer, |k, w| self.0.marshal(k, w), |v, w| self.1.marshal(v, w))
+    }
+
+    fn unmarshal(&self, reader: &mut &[u8]) -> Result<HashMap<K, V>> {
+        unmarshal_map(reader, |r| self.0.unmarshal(r), |r| self.1.unmarshal(r))
+    }
+
+    fn skip(&self, reader: &mut &[u8]) -> Result<()> {
+        match (self.0.fixed_size(), self.1.fixed_size()) {
+            (Some(key_size), Some(value_size)) => skip_fixed_map(reader, key_size, value_size),
+            _ => skip_map(reader, |r| self.0.skip(r), |r| self.1.skip(r)),
+        }
+    }
+}
+
+/// The codec for `Option<T>`, built by [`option_of`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptionOf<C>(pub C);
+
+/// Returns the codec for an option whose value uses the given codec.
+pub fn option_of<C>(inner: C) -> OptionOf<C> {
+    OptionOf(inner)
+}
+
+impl<T, C: Codec<T>> Codec<Option<T>> for OptionOf<C> {
+    fn size(&self, v: &Option<T>) -> usize {
+        size_option(v, |e| self.0.size(e))
+    }
+
+    fn marshal(&self, v: &Option<T>, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_option(v, writer, |e, w| self.0.marshal(e, w))
+    }
+
+    fn unmarshal(&self, reader: &mut &[u8]) -> Result<Option<T>> {
+        unmarshal_option(reader, |r| self.0.unmarshal(r))
+    }
+
+    fn skip(&self, reader: &mut &[u8]) -> Result<()> {
+        skip_option(reader, |r| self.0.skip(r))
+    }
+}
@@ -0,0 +1,470 @@
+//! Source code generation for the other benc ports, enabled by the `codegen` feature.
+//!
+//! The generators turn schemas, typically taken from [`benc_struct!`](crate::benc_struct)
+//! types with [`schema_of`](crate::schema_of), into type definitions and codecs that
+//! marshal exactly like the Rust types. Running them from a build script or a test
+//! keeps the ports from drifting apart when a shared message type changes.
+//!
+//! Nested structs have no name in a schema. One equal to a schema passed to the
+//! generator takes its name, and is generated once; others are named after the struct
+//! and field containing them, so field `origin` of `Doc` becomes `DocOrigin`, with a
+//! `Key` or `Value` suffix for the keys and values of maps.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::{Schema, Type};
+
+/// The structs to generate, in an order where every struct comes after the structs
+/// nested in it, along with their names.
+struct Structs<'s> {
+    roots: &'s [(&'s str, &'s Schema)],
+    list: Vec<(String, &'s Schema)>,
+    names: HashMap<*const Schema, usize>,
+}
+
+impl<'s> Structs<'s> {
+    fn collect(roots: &'s [(&'s str, &'s Schema)]) -> Self {
+        let mut structs = Structs { roots, list: Vec::new(), names: HashMap::new() };
+        for (name, schema) in roots {
+            structs.visit_schema(name.to_string(), schema);
+        }
+        structs
+    }
+
+    fn visit_schema(&mut self, mut name: String, schema: &'s Schema) {
+        if let Some(i) = self.list.iter().position(|(_, s)| *s == schema) {
+            self.names.insert(schema, i);
+            return;
+        }
+        if let Some((root, _)) = self.roots.iter().find(|(_, s)| *s == schema) {
+            name = root.to_string();
+        }
+        for field in schema.fields() {
+            self.visit_type(&format!("{name}{}", pascal_case(&field.name)), &field.ty);
+        }
+        self.names.insert(schema, self.list.len());
+        self.list.push((name, schema));
+    }
+
+    fn visit_type(&mut self, name: &str, ty: &'s Type) {
+        match ty {
+            Type::Slice(elem) | Type::Option(elem) => self.visit_type(name, elem),
+            Type::Map(k, v) => {
+                self.visit_type(&format!("{name}Key"), k);
+                self.visit_type(&format!("{name}Value"), v);
+            }
+            Type::Struct(schema) => self.visit_schema(name.to_string(), schema),
+            _ => {}
+        }
+    }
+
+    fn name(&self, schema: &Schema) -> &str {
+        &self.list[self.names[&(schema as *const Schema)]].0
+    }
+}
+
+/// Converts a `snake_case` name to `PascalCase`.
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .flat_map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|c| c.to_ascii_uppercase()).into_iter().chain(chars)
+        })
+        .collect()
+}
+
+// ===================================================================================
+// TypeScript
+// ===================================================================================
+
+/// Generates a TypeScript module with an interface and a codec for every struct.
+///
+/// For a struct `Name`, the module exports the interface `Name`, the offset-based
+/// functions `sizeName`, `marshalName`, `unmarshalName` and `skipName`, and the
+/// conveniences `encodeName`, returning a new `Uint8Array`, and `decodeName`, which
+/// throws if the buffer holds more than one message. The functions are built on the
+/// TypeScript port of benc, imported from `runtime`.
+///
+/// `u64` and `i64` fields are `bigint`s, varint fields `number | bigint`, maps `Map`s
+/// and options `T | null`. Times are `Date`s, which only keep milliseconds.
+pub fn typescript(runtime: &str, types: &[(&str, &Schema)]) -> String {
+    let structs = Structs::collect(types);
+    let mut out = String::new();
+    // Writing into a `String` cannot fail.
+    writeln!(out, "// Code generated by benc. DO NOT EDIT.\n").unwrap();
+    writeln!(out, "import * as bstd from {runtime:?};").unwrap();
+    for (name, schema) in &structs.list {
+        ts_struct(&mut out, &structs, name, schema);
+    }
+    out
+}
+
+/// The TypeScript runtime functions of a primitive type, as the suffixes of the size,
+/// marshal, unmarshal and skip functions, and whether the size function takes the value.
+fn ts_primitive(ty: &Type) -> Option<([&'static str; 4], bool)> {
+    Some(match ty {
+        Type::Bool => (["Bool"; 4], false),
+        Type::U8 => (["Byte"; 4], false),
+        Type::U16 => (["Uint16"; 4], false),
+        Type::U32 => (["Uint32"; 4], false),
+        Type::U64 => (["Uint64"; 4], false),
+        Type::I8 => (["Int8"; 4], false),
+        Type::I16 => (["Int16"; 4], false),
+        Type::I32 => (["Int32"; 4], false),
+        Type::I64 => (["Int64"; 4], false),
+        Type::F32 => (["Float32"; 4], false),
+        Type::F64 => (["Float64"; 4], false),
+        Type::Uint => (["Uint", "Uint", "Uint", "Varint"], true),
+        Type::Int => (["Int", "Int", "Int", "Varint"], true),
+        Type::String => (["String"; 4], true),
+        Type::Bytes => (["Bytes", "Bytes", "BytesCopied", "Bytes"], true),
+        Type::Time => (["Time"; 4], false),
+        _ => return None,
+    })
+}
+
+fn ts_type(structs: &Structs, ty: &Type) -> String {
+    match ty {
+        Type::Bool => "boolean".into(),
+        Type::U64 | Type::I64 => "bigint".into(),
+        Type::Uint | Type::Int => "number | bigint".into(),
+        Type::String => "string".into(),
+        Type::Bytes => "Uint8Array".into(),
+        Type::Time => "Date".into(),
+        Type::Slice(elem) => format!("Array<{}>", ts_type(structs, elem)),
+        Type::Map(k, v) => format!("Map<{}, {}>", ts_type(structs, k), ts_type(structs, v)),
+        Type::Option(inner) => format!("{} | null", ts_type(structs, inner)),
+        Type::Struct(schema) => structs.name(schema).into(),
+        _ => "number".into(),
+    }
+}
+
+/// Returns an expression sizing the value `v`. Nested callbacks name their parameters
+/// after `depth` to keep them apart.
+fn ts_size(structs: &Structs, ty: &Type, v: &str, depth: usize) -> String {
+    if let Some(([size, ..], by_value)) = ts_primitive(ty) {
+        return if by_value { format!("bstd.size{size}({v})") } else { format!("bstd.size{size}()") };
+    }
+    let (x, y) = (format!("v{depth}"), format!("w{depth}"));
+    match ty {
+        Type::Slice(elem) => format!("bstd.sizeSlice({v}, ({x}) => {})", ts_size(structs, elem, &x, depth + 1)),
+        Type::Map(k, val) => format!(
+            "bstd.sizeMap({v}, ({x}) => {}, ({y}) => {})",
+            ts_size(structs, k, &x, depth + 1),
+            ts_size(structs, val, &y, depth + 1)
+        ),
+        Type::Option(inner) => format!("bstd.sizePointer({v}, ({x}) => {})", ts_size(structs, inner, &x, depth + 1)),
+        Type::Struct(schema) => format!("size{}({v})", structs.name(schema)),
+        _ => unreachable!(),
+    }
+}
+
+/// Returns a function marshalling values of the type.
+fn ts_marshal(structs: &Structs, ty: &Type, depth: usize) -> String {
+    if let Some(([_, marshal, ..], _)) = ts_primitive(ty) {
+        return format!("bstd.marshal{marshal}");
+    }
+    let x = format!("v{depth}");
+    match ty {
+        Type::Struct(schema) => format!("marshal{}", structs.name(schema)),
+        _ => format!("(n: number, b: Uint8Array, {x}: {}) => {}", ts_type(structs, ty), ts_marshal_call(structs, ty, &x, depth + 1)),
+    }
+}
+
+/// Returns an expression marshalling the value `v` at offset `n` of `b`.
+fn ts_marshal_call(structs: &Structs, ty: &Type, v: &str, depth: usize) -> String {
+    match ty {
+        Type::Slice(elem) => format!("bstd.marshalSlice(n, b, {v}, {})", ts_marshal(structs, elem, depth)),
+        Type::Map(k, val) => {
+            format!("bstd.marshalMap(n, b, {v}, {}, {})", ts_marshal(structs, k, depth), ts_marshal(structs, val, depth))
+        }
+        Type::Option(inner) => format!("bstd.marshalPointer(n, b, {v}, {})", ts_marshal(structs, inner, depth)),
+        _ => format!("{}(n, b, {v})", ts_marshal(structs, ty, depth)),
+    }
+}
+
+/// Returns a function unmarshalling values of the type.
+fn ts_unmarshal(structs: &Structs, ty: &Type) -> String {
+    if let Some(([_, _, unmarshal, _], _)) = ts_primitive(ty) {
+        return format!("bstd.unmarshal{unmarshal}");
+    }
+    match ty {
+        Type::Struct(schema) => format!("unmarshal{}", structs.name(schema)),
+        _ => format!("(n: number, b: Uint8Array) => {}", ts_unmarshal_call(structs, ty)),
+    }
+}
+
+/// Returns an expression unmarshalling a value at offset `n` of `b`.
+fn ts_unmarshal_call(structs: &Structs, ty: &Type) -> String {
+    match ty {
+        Type::Slice(elem) => format!("bstd.unmarshalSlice(n, b, {})", ts_unmarshal(structs, elem)),
+        Type::Map(k, v) => format!("bstd.unmarshalMap(n, b, {}, {})", ts_unmarshal(structs, k), ts_unmarshal(structs, v)),
+        Type::Option(inner) => format!("bstd.unmarshalPointer(n, b, {})", ts_unmarshal(structs, inner)),
+        _ => format!("{}(n, b)", ts_unmarshal(structs, ty)),
+    }
+}
+
+/// Returns a function skipping values of the type.
+fn ts_skip(structs: &Structs, ty: &Type) -> String {
+    if let Some(([.., skip], _)) = ts_primitive(ty) {
+        return format!("bstd.skip{skip}");
+    }
+    match ty {
+        Type::Struct(schema) => format!("skip{}", structs.name(schema)),
+        _ => format!("(n: number, b: Uint8Array) => {}", ts_skip_call(structs, ty)),
+    }
+}
+
+/// Returns an expression skipping a value at offset `n` of `b`.
+fn ts_skip_call(structs: &Structs, ty: &Type) -> String {
+    match ty {
+        Type::Slice(elem) => format!("bstd.skipSlice(n, b, {})", ts_skip(structs, elem)),
+        Type::Map(k, v) => format!("bstd.skipMap(n, b, {}, {})", ts_skip(structs, k), ts_skip(structs, v)),
+        Type::Option(inner) => format!("bstd.skipPointer(n, b, {})", ts_skip(structs, inner)),
+        _ => format!("{}(n, b)", ts_skip(structs, ty)),
+    }
+}
+
+fn ts_struct(out: &mut String, structs: &Structs, name: &str, schema: &Schema) {
+    let fields = schema.fields();
+
+    writeln!(out, "\nexport interface {name} {{").unwrap();
+    for field in fields {
+        writeln!(out, "  {}: {};", field.name, ts_type(structs, &field.ty)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    writeln!(out, "\nexport function size{name}(v: {name}): number {{").unwrap();
+    let sizes: Vec<_> = fields.iter().map(|f| ts_size(structs, &f.ty, &format!("v.{}", f.name), 0)).collect();
+    let sum = if sizes.is_empty() { "0".to_string() } else { sizes.join("\n    + ") };
+    writeln!(out, "  return {sum};\n}}").unwrap();
+
+    writeln!(out, "\nexport function marshal{name}(n: number, b: Uint8Array, v: {name}): number {{").unwrap();
+    for field in fields {
+        writeln!(out, "  n = {};", ts_marshal_call(structs, &field.ty, &format!("v.{}", field.name), 0)).unwrap();
+    }
+    writeln!(out, "  return n;\n}}").unwrap();
+
+    writeln!(out, "\nexport function unmarshal{name}(n: number, b: Uint8Array): [number, {name}] {{").unwrap();
+    writeln!(out, "  const v = {{}} as {name};").unwrap();
+    for field in fields {
+        writeln!(out, "  [n, v.{}] = {};", field.name, ts_unmarshal_call(structs, &field.ty)).unwrap();
+    }
+    writeln!(out, "  return [n, v];\n}}").unwrap();
+
+    writeln!(out, "\nexport function skip{name}(n: number, b: Uint8Array): number {{").unwrap();
+    for field in fields {
+        writeln!(out, "  n = {};", ts_skip_call(structs, &field.ty)).unwrap();
+    }
+    writeln!(out, "  return n;\n}}").unwrap();
+
+    writeln!(out, "\nexport function encode{name}(v: {name}): Uint8Array {{").unwrap();
+    writeln!(out, "  const b = new Uint8Array(size{name}(v));").unwrap();
+    writeln!(out, "  marshal{name}(0, b, v);").unwrap();
+    writeln!(out, "  return b;\n}}").unwrap();
+
+    writeln!(out, "\nexport function decode{name}(b: Uint8Array): {name} {{").unwrap();
+    writeln!(out, "  const [n, v] = unmarshal{name}(0, b);").unwrap();
+    writeln!(out, "  if (n !== b.length) {{").unwrap();
+    writeln!(out, "    throw new Error(\"unexpected trailing bytes after the encoded data\");").unwrap();
+    writeln!(out, "  }}").unwrap();
+    writeln!(out, "  return v;\n}}").unwrap();
+}
+
+// ===================================================================================
+// Go
+// ===================================================================================
+
+/// Generates a Go file declaring a struct with a codec for every struct.
+///
+/// For a struct `Name`, the file declares the struct `Name` with exported fields and
+/// the methods `Size`, `Marshal` and `Unmarshal`, shaped like the output of the Go
+/// generator in `cmd/generator`. The methods are built on the Go port of benc.
+///
+/// Varint fields are `uint` and `int`, which must be 64 bits wide, and options are
+/// pointers. Maps need keys Go can compare, so slices and structs holding them cannot
+/// be map keys.
+pub fn go(package: &str, types: &[(&str, &Schema)]) -> String {
+    let structs = Structs::collect(types);
+    let mut out = String::new();
+    writeln!(out, "// Code generated by benc. DO NOT EDIT.\n").unwrap();
+    writeln!(out, "package {package}\n\nimport (").unwrap();
+    if structs.list.iter().any(|(_, schema)| schema.fields().iter().any(|f| go_uses_time(&f.ty))) {
+        writeln!(out, "\t\"time\"\n").unwrap();
+    }
+    writeln!(out, "\tbstd \"github.com/banditmoscow1337/benc/std/golang\"\n)").unwrap();
+    for (name, schema) in &structs.list {
+        go_struct(&mut out, &structs, name, schema);
+    }
+    out
+}
+
+fn go_uses_time(ty: &Type) -> bool {
+    match ty {
+        Type::Time => true,
+        Type::Slice(elem) | Type::Option(elem) => go_uses_time(elem),
+        Type::Map(k, v) => go_uses_time(k) || go_uses_time(v),
+        _ => false,
+    }
+}
+
+/// The Go runtime functions of a primitive type, as the suffixes of the size, marshal
+/// and unmarshal functions, and whether the size function takes the value.
+fn go_primitive(ty: &Type) -> Option<([&'static str; 3], bool)> {
+    Some(match ty {
+        Type::Bool => (["Bool"; 3], false),
+        Type::U8 => (["Byte"; 3], false),
+        Type::U16 => (["Uint16"; 3], false),
+        Type::U32 => (["Uint32"; 3], false),
+        Type::U64 => (["Uint64"; 3], false),
+        Type::I8 => (["Int8"; 3], false),
+        Type::I16 => (["Int16"; 3], false),
+        Type::I32 => (["Int32"; 3], false),
+        Type::I64 => (["Int64"; 3], false),
+        Type::F32 => (["Float32"; 3], false),
+        Type::F64 => (["Float64"; 3], false),
+        Type::Uint => (["Uint"; 3], true),
+        Type::Int => (["Int"; 3], true),
+        Type::String => (["String"; 3], true),
+        Type::Bytes => (["Bytes", "Bytes", "BytesCopied"], true),
+        Type::Time => (["Time"; 3], false),
+        _ => return None,
+    })
+}
+
+fn go_type(structs: &Structs, ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".into(),
+        Type::U8 => "byte".into(),
+        Type::U16 => "uint16".into(),
+        Type::U32 => "uint32".into(),
+        Type::U64 => "uint64".into(),
+        Type::I8 => "int8".into(),
+        Type::I16 => "int16".into(),
+        Type::I32 => "int32".into(),
+        Type::I64 => "int64".into(),
+        Type::F32 => "float32".into(),
+        Type::F64 => "float64".into(),
+        Type::Uint => "uint".into(),
+        Type::Int => "int".into(),
+        Type::String => "string".into(),
+        Type::Bytes => "[]byte".into(),
+        Type::Time => "time.Time".into(),
+        Type::Slice(elem) => format!("[]{}", go_type(structs, elem)),
+        Type::Map(k, v) => format!("map[{}]{}", go_type(structs, k), go_type(structs, v)),
+        Type::Option(inner) => format!("*{}", go_type(structs, inner)),
+        Type::Struct(schema) => structs.name(schema).into(),
+    }
+}
+
+/// Returns an expression sizing the value `v`. Nested callbacks name their parameters
+/// after `depth` to keep them apart.
+fn go_size(structs: &Structs, ty: &Type, v: &str, depth: usize) -> String {
+    if let Some(([size, ..], by_value)) = go_primitive(ty) {
+        return if by_value { format!("bstd.Size{size}({v})") } else { format!("bstd.Size{size}()") };
+    }
+    let sizer = |ty: &Type, x: &str| {
+        format!("func({x} {}) int {{ return {} }}", go_type(structs, ty), go_size(structs, ty, x, depth + 1))
+    };
+    let (x, y) = (format!("v{depth}"), format!("w{depth}"));
+    match ty {
+        Type::Slice(elem) => format!("bstd.SizeSlice({v}, {})", sizer(elem, &x)),
+        Type::Map(k, val) => format!("bstd.SizeMap({v}, {}, {})", sizer(k, &x), sizer(val, &y)),
+        Type::Option(inner) => format!("bstd.SizePointer({v}, {})", sizer(inner, &x)),
+        Type::Struct(_) => format!("{v}.Size()"),
+        _ => unreachable!(),
+    }
+}
+
+/// Returns a function marshalling values of the type.
+fn go_marshal(structs: &Structs, ty: &Type, depth: usize) -> String {
+    if let Some(([_, marshal, _], _)) = go_primitive(ty) {
+        return format!("bstd.Marshal{marshal}");
+    }
+    let x = format!("v{depth}");
+    format!("func(n int, b []byte, {x} {}) int {{ return {} }}", go_type(structs, ty), go_marshal_call(structs, ty, &x, depth + 1))
+}
+
+/// Returns an expression marshalling the value `v` at offset `n` of `b`.
+fn go_marshal_call(structs: &Structs, ty: &Type, v: &str, depth: usize) -> String {
+    match ty {
+        Type::Slice(elem) => format!("bstd.MarshalSlice(n, b, {v}, {})", go_marshal(structs, elem, depth)),
+        Type::Map(k, val) => {
+            format!("bstd.MarshalMap(n, b, {v}, {}, {})", go_marshal(structs, k, depth), go_marshal(structs, val, depth))
+        }
+        Type::Option(inner) => format!("bstd.MarshalPointer(n, b, {v}, {})", go_marshal(structs, inner, depth)),
+        Type::Struct(_) => format!("{v}.Marshal(n, b)"),
+        _ => format!("{}(n, b, {v})", go_marshal(structs, ty, depth)),
+    }
+}
+
+/// Returns a function unmarshalling values of the type, in one of the shapes the
+/// generic unmarshal functions of the Go port accept.
+fn go_unmarshal(structs: &Structs, ty: &Type) -> String {
+    if let Some(([.., unmarshal], _)) = go_primitive(ty) {
+        return format!("bstd.Unmarshal{unmarshal}");
+    }
+    let t = go_type(structs, ty);
+    match ty {
+        Type::Struct(_) => format!("func(n int, b []byte, v *{t}) (int, error) {{ return v.Unmarshal(n, b) }}"),
+        _ => format!("func(n int, b []byte) (int, {t}, error) {{ return {} }}", go_unmarshal_call(structs, ty)),
+    }
+}
+
+/// Returns an expression unmarshalling a value at offset `n` of `b`, evaluating to the
+/// new offset, the value and an error.
+fn go_unmarshal_call(structs: &Structs, ty: &Type) -> String {
+    match ty {
+        Type::Slice(elem) => {
+            format!("bstd.UnmarshalSlice[{}](n, b, {})", go_type(structs, elem), go_unmarshal(structs, elem))
+        }
+        Type::Map(k, v) => format!(
+            "bstd.UnmarshalMap[{}, {}](n, b, {}, {})",
+            go_type(structs, k),
+            go_type(structs, v),
+            go_unmarshal(structs, k),
+            go_unmarshal(structs, v)
+        ),
+        Type::Option(inner) => {
+            format!("bstd.UnmarshalPointer[{}](n, b, {})", go_type(structs, inner), go_unmarshal(structs, inner))
+        }
+        _ => format!("{}(n, b)", go_unmarshal(structs, ty)),
+    }
+}
+
+fn go_struct(out: &mut String, structs: &Structs, name: &str, schema: &Schema) {
+    let fields = schema.fields();
+    let receiver = format!("{}{}", name[..1].to_ascii_lowercase(), &name[1..]);
+
+    writeln!(out, "\ntype {name} struct {{").unwrap();
+    for field in fields {
+        writeln!(out, "\t{} {}", pascal_case(&field.name), go_type(structs, &field.ty)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    writeln!(out, "\nfunc ({receiver} *{name}) Size() (s int) {{").unwrap();
+    for field in fields {
+        let v = format!("{receiver}.{}", pascal_case(&field.name));
+        writeln!(out, "\ts += {}", go_size(structs, &field.ty, &v, 0)).unwrap();
+    }
+    writeln!(out, "\treturn\n}}").unwrap();
+
+    writeln!(out, "\nfunc ({receiver} *{name}) Marshal(tn int, b []byte) (n int) {{\n\tn = tn").unwrap();
+    for field in fields {
+        let v = format!("{receiver}.{}", pascal_case(&field.name));
+        writeln!(out, "\tn = {}", go_marshal_call(structs, &field.ty, &v, 0)).unwrap();
+    }
+    writeln!(out, "\treturn n\n}}").unwrap();
+
+    writeln!(out, "\nfunc ({receiver} *{name}) Unmarshal(tn int, b []byte) (n int, err error) {{\n\tn = tn").unwrap();
+    for field in fields {
+        let v = format!("{receiver}.{}", pascal_case(&field.name));
+        let stmt = match field.ty {
+            Type::Struct(_) => format!("n, err = {v}.Unmarshal(n, b)"),
+            _ => format!("n, {v}, err = {}", go_unmarshal_call(structs, &field.ty)),
+        };
+        writeln!(out, "\tif {stmt}; err != nil {{\n\t\treturn\n\t}}").unwrap();
+    }
+    writeln!(out, "\treturn\n}}").unwrap();
+}
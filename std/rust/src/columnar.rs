@@ -0,0 +1,227 @@
+//! Column-major (struct-of-arrays) encoding for batches of homogeneous records.
+//!
+//! Instead of marshalling N records one after another, every field ("column") of
+//! the batch is marshalled contiguously. Values of the same kind end up next to each
+//! other, which compresses better and lets a reader decode only the columns it needs.
+//!
+//! The format is a varint record count, a varint column count, one length-prefixed
+//! byte block per column holding the values of that column back to back, and the
+//! terminator sequence.
+//!
+//! Structs defined with [`benc_struct!`](crate::benc_struct) in the positional mode
+//! implement [`BencColumns`], with one column per field that is not skipped, so a
+//! batch of them is encoded with [`marshal_records`] and decoded with
+//! [`Columns::decode`]. Other record types describe their columns by hand.
+
+use crate::{
+    BencDecode, Error, Result, TERMINATOR, marshal_usize, read_terminator, size_uint, size_usize,
+    skip_bytes, unmarshal_bytes_cropped, unmarshal_usize, write_to_slice,
+};
+
+type Sizer<'c, T> = Box<dyn Fn(&T) -> usize + 'c>;
+type Marshaler<'c, T> = Box<dyn Fn(&T, &mut &mut [u8]) -> Result<()> + 'c>;
+
+/// Describes how a single field of a record type `T` is sized and marshalled.
+pub struct Column<'c, T> {
+    sizer: Sizer<'c, T>,
+    marshaler: Marshaler<'c, T>,
+}
+
+impl<'c, T> Column<'c, T> {
+    /// Creates a column from a sizer and a marshaler for the field it represents.
+    pub fn new(
+        sizer: impl Fn(&T) -> usize + 'c,
+        marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<()> + 'c,
+    ) -> Self {
+        Column {
+            sizer: Box::new(sizer),
+            marshaler: Box::new(marshaler),
+        }
+    }
+
+    /// Returns the number of bytes the values of this column occupy, excluding the
+    /// length prefix.
+    fn payload_size(&self, records: &[T]) -> usize {
+        records.iter().map(|r| (self.sizer)(r)).sum()
+    }
+}
+
+/// A record type with one column per field, implemented by
+/// [`benc_struct!`](crate::benc_struct) for positional structs.
+pub trait BencColumns<'a>: BencDecode<'a> {
+    /// Returns the columns of the record, in field order.
+    fn columns<'c>() -> Vec<Column<'c, Self>>;
+
+    /// Unmarshals one record, reading one value from the reader of each column.
+    ///
+    /// Returns an `InvalidValue` error if there are fewer readers than columns.
+    fn unmarshal_row(readers: &mut [&'a [u8]]) -> Result<Self>;
+}
+
+// ===================================================================================
+// Encoding
+// ===================================================================================
+
+/// Returns the number of bytes needed to marshal a batch of records column by column.
+pub fn size_columns<T>(records: &[T], columns: &[Column<'_, T>]) -> usize {
+    let mut total = size_usize(records.len()) + size_usize(columns.len()) + TERMINATOR.len();
+    for column in columns {
+        let payload = column.payload_size(records);
+        total += size_uint(payload as u64) + payload;
+    }
+    total
+}
+
+/// Marshals a batch of records column by column into the writer.
+///
+/// Returns an error if the writer is too small, or an `InvalidValue` error if the
+/// marshaler of a column writes a different number of bytes than its sizer returns.
+pub fn marshal_columns<T>(
+    records: &[T],
+    writer: &mut &mut [u8],
+    columns: &[Column<'_, T>],
+) -> Result<()> {
+    marshal_usize(records.len(), writer)?;
+    marshal_usize(columns.len(), writer)?;
+    for column in columns {
+        let payload = column.payload_size(records);
+        marshal_usize(payload, writer)?;
+        let available = writer.len();
+        for record in records {
+            (column.marshaler)(record, writer)?;
+        }
+        if available - writer.len() != payload {
+            return Err(Error::InvalidValue);
+        }
+    }
+    write_to_slice(writer, &TERMINATOR)
+}
+
+/// Returns the number of bytes needed to marshal a batch of records with one column
+/// per field.
+pub fn size_records<'a, T: BencColumns<'a>>(records: &[T]) -> usize {
+    size_columns(records, &T::columns())
+}
+
+/// Marshals a batch of records into the writer with one column per field.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_records<'a, T: BencColumns<'a>>(records: &[T], writer: &mut &mut [u8]) -> Result<()> {
+    marshal_columns(records, writer, &T::columns())
+}
+
+// ===================================================================================
+// Decoding
+// ===================================================================================
+
+/// A decoded columnar batch. Column data is borrowed from the input buffer and only
+/// decoded on demand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Columns<'a> {
+    len: usize,
+    columns: Vec<&'a [u8]>,
+}
+
+impl<'a> Columns<'a> {
+    /// Returns the number of records in the batch.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the batch holds no records.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of columns in the batch.
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns the raw, marshalled values of a column.
+    /// Returns an `OutOfRange` error if the column does not exist.
+    pub fn column(&self, index: usize) -> Result<&'a [u8]> {
+        self.columns.get(index).copied().ok_or(Error::OutOfRange)
+    }
+
+    /// Unmarshals every value of a single column.
+    /// Returns a `TrailingBytes` error if the column holds more data than `len()` values,
+    /// and an `InvalidValue` error if it holds fewer bytes than `len()`, so values that
+    /// occupy no bytes cannot be decoded.
+    pub fn decode_column<V>(
+        &self,
+        index: usize,
+        unmarshaler: impl Fn(&mut &'a [u8]) -> Result<V>,
+    ) -> Result<Vec<V>> {
+        let mut reader = self.column(index)?;
+        if self.len > reader.len() {
+            return Err(Error::InvalidValue);
+        }
+        let mut values = Vec::with_capacity(self.len.min(reader.len()));
+        for _ in 0..self.len {
+            values.push(unmarshaler(&mut reader)?);
+        }
+        if !reader.is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(values)
+    }
+
+    /// Reassembles the records of the batch.
+    ///
+    /// The unmarshaler receives one reader per column (in column order) and must read
+    /// exactly one value from each column it uses.
+    ///
+    /// Returns an `InvalidValue` error if the columns hold fewer bytes in total than
+    /// `len()`, so records that occupy no bytes cannot be decoded.
+    pub fn decode_records<T>(
+        &self,
+        unmarshaler: impl Fn(&mut [&'a [u8]]) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        // The record count comes from the input; every record must account for some of
+        // the column data for it to be checked.
+        let data: usize = self.columns.iter().map(|c| c.len()).sum();
+        if self.len > data {
+            return Err(Error::InvalidValue);
+        }
+        let mut readers = self.columns.clone();
+        let mut records = Vec::with_capacity(self.len.min(data));
+        for _ in 0..self.len {
+            records.push(unmarshaler(&mut readers)?);
+        }
+        if readers.iter().any(|r| !r.is_empty()) {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(records)
+    }
+
+    /// Reassembles the records of a batch marshalled with [`marshal_records`].
+    ///
+    /// Returns the errors of [`decode_records`](Self::decode_records).
+    pub fn decode<T: BencColumns<'a>>(&self) -> Result<Vec<T>> {
+        self.decode_records(T::unmarshal_row)
+    }
+}
+
+/// Unmarshals a columnar batch from the reader without decoding its values.
+pub fn unmarshal_columns<'a>(reader: &mut &'a [u8]) -> Result<Columns<'a>> {
+    let len = unmarshal_usize(reader)?;
+    let count = unmarshal_usize(reader)?;
+    let mut columns = Vec::with_capacity(count.min(reader.len()));
+    for _ in 0..count {
+        columns.push(unmarshal_bytes_cropped(reader)?);
+    }
+    read_terminator(reader)?;
+    Ok(Columns { len, columns })
+}
+
+/// Skips over a marshalled columnar batch in the reader.
+pub fn skip_columns(reader: &mut &[u8]) -> Result<()> {
+    unmarshal_usize(reader)?;
+    let count = unmarshal_usize(reader)?;
+    for _ in 0..count {
+        skip_bytes(reader)?;
+    }
+    read_terminator(reader)?;
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! An offset-based API mirroring the Go `bstd` package.
+//!
+//! Where the crate root works on cursors, the functions in this module take the whole
+//! buffer and an offset into it, and return the offset just past the value, like their
+//! Go counterparts:
+//!
+//! ```
+//! use benc::compat;
+//!
+//! let mut buf = vec![0; compat::size_string("hi") + compat::size_u32()];
+//! let n = compat::marshal_string(0, &mut buf, "hi").unwrap();
+//! let n = compat::marshal_u32(n, &mut buf, 7).unwrap();
+//! assert_eq!(n, buf.len());
+//!
+//! let (n, s) = compat::unmarshal_string(0, &buf).unwrap();
+//! let (n, v) = compat::unmarshal_u32(n, &buf).unwrap();
+//! assert_eq!((n, s, v), (buf.len(), "hi", 7));
+//! ```
+//!
+//! This eases porting Go code and serving FFI callers that think in offsets. Unlike
+//! Go, marshalers report a buffer that is too small as an error instead of panicking.
+//! The element callbacks of slices and maps receive the remaining buffer at offset 0.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use chrono::{DateTime, Utc};
+
+pub use crate::{
+    size_bool, size_bytes, size_f32, size_f64, size_fixed_slice, size_i8, size_i16, size_i32,
+    size_i64, size_int, size_map, size_slice, size_string, size_time, size_u8, size_u16, size_u32,
+    size_u64, size_uint,
+};
+use crate::{Error, Result};
+
+/// Runs a cursor unmarshaler at offset `n`, returning the offset after the value.
+fn read<'a, T>(n: usize, b: &'a [u8], f: impl FnOnce(&mut &'a [u8]) -> Result<T>) -> Result<(usize, T)> {
+    let mut reader = b.get(n..).ok_or(Error::BufferTooSmall { needed: n, available: b.len() })?;
+    let v = f(&mut reader)?;
+    Ok((b.len() - reader.len(), v))
+}
+
+/// Runs a cursor marshaler at offset `n`, returning the offset after the value.
+fn write(n: usize, b: &mut [u8], f: impl FnOnce(&mut &mut [u8]) -> Result<()>) -> Result<usize> {
+    let len = b.len();
+    let mut writer = b.get_mut(n..).ok_or(Error::BufferTooSmall { needed: n, available: len })?;
+    f(&mut writer)?;
+    Ok(len - writer.len())
+}
+
+/// Runs a cursor skipper at offset `n`, returning the offset after the value.
+fn skip(n: usize, b: &[u8], f: impl FnOnce(&mut &[u8]) -> Result<()>) -> Result<usize> {
+    read(n, b, f).map(|(n, ())| n)
+}
+
+/// Adapts an offset-based element unmarshaler to a cursor.
+fn read_element<'a, T>(
+    reader: &mut &'a [u8],
+    f: &impl Fn(usize, &'a [u8]) -> Result<(usize, T)>,
+) -> Result<T> {
+    let (n, v) = f(0, reader)?;
+    *reader = reader.get(n..).ok_or(Error::OutOfRange)?;
+    Ok(v)
+}
+
+/// Adapts an offset-based element marshaler to a cursor.
+fn write_element(
+    writer: &mut &mut [u8],
+    f: impl FnOnce(usize, &mut [u8]) -> Result<usize>,
+) -> Result<()> {
+    let n = f(0, writer)?;
+    let buf = std::mem::take(writer);
+    let len = buf.len();
+    *writer = buf.get_mut(n..).ok_or(Error::BufferTooSmall { needed: n, available: len })?;
+    Ok(())
+}
+
+/// Adapts an offset-based element skipper to a cursor.
+fn skip_element(reader: &mut &[u8], f: &impl Fn(usize, &[u8]) -> Result<usize>) -> Result<()> {
+    let n = f(0, reader)?;
+    *reader = reader.get(n..).ok_or(Error::OutOfRange)?;
+    Ok(())
+}
+
+// Use a macro to generate the offset-based functions for types passed by value.
+macro_rules! offset_impl {
+    ($type:ty, $marshal_fn:ident, $unmarshal_fn:ident, $skip_fn:ident) => {
+        #[doc = concat!("Marshals a `", stringify!($type), "` at offset `n`, returning the offset after it.")]
+        pub fn $marshal_fn(n: usize, b: &mut [u8], v: $type) -> Result<usize> {
+            write(n, b, |w| crate::$marshal_fn(v, w))
+        }
+
+        #[doc = concat!("Unmarshals a `", stringify!($type), "` at offset `n`, returning the offset after it and the value.")]
+        pub fn $unmarshal_fn(n: usize, b: &[u8]) -> Result<(usize, $type)> {
+            read(n, b, crate::$unmarshal_fn)
+        }
+
+        #[doc = concat!("Skips a `", stringify!($type), "` at offset `n`, returning the offset after it.")]
+        pub fn $skip_fn(n: usize, b: &[u8]) -> Result<usize> {
+            skip(n, b, crate::$skip_fn)
+        }
+    };
+}
+
+offset_impl!(bool, marshal_bool, unmarshal_bool, skip_bool);
+offset_impl!(u8, marshal_u8, unmarshal_u8, skip_u8);
+offset_impl!(u16, marshal_u16, unmarshal_u16, skip_u16);
+offset_impl!(u32, marshal_u32, unmarshal_u32, skip_u32);
+offset_impl!(u64, marshal_u64, unmarshal_u64, skip_u64);
+offset_impl!(i8, marshal_i8, unmarshal_i8, skip_i8);
+offset_impl!(i16, marshal_i16, unmarshal_i16, skip_i16);
+offset_impl!(i32, marshal_i32, unmarshal_i32, skip_i32);
+offset_impl!(i64, marshal_i64, unmarshal_i64, skip_i64);
+offset_impl!(f32, marshal_f32, unmarshal_f32, skip_f32);
+offset_impl!(f64, marshal_f64, unmarshal_f64, skip_f64);
+offset_impl!(i64, marshal_int, unmarshal_int, skip_int);
+offset_impl!(u64, marshal_uint, unmarshal_uint, skip_uint);
+offset_impl!(DateTime<Utc>, marshal_time, unmarshal_time, skip_time);
+
+// ===================================================================================
+// Strings and Byte Slices
+// ===================================================================================
+
+/// Marshals a string at offset `n`, returning the offset after it.
+pub fn marshal_string(n: usize, b: &mut [u8], s: &str) -> Result<usize> {
+    write(n, b, |w| crate::marshal_string(s, w))
+}
+
+/// Unmarshals a string at offset `n`, returning the offset after it and the string,
+/// which borrows from the buffer.
+pub fn unmarshal_string(n: usize, b: &[u8]) -> Result<(usize, &str)> {
+    read(n, b, crate::unmarshal_string)
+}
+
+/// Skips a string at offset `n`, returning the offset after it.
+pub fn skip_string(n: usize, b: &[u8]) -> Result<usize> {
+    skip(n, b, crate::skip_string)
+}
+
+/// Marshals a byte slice at offset `n`, returning the offset after it.
+pub fn marshal_bytes(n: usize, b: &mut [u8], bs: &[u8]) -> Result<usize> {
+    write(n, b, |w| crate::marshal_bytes(bs, w))
+}
+
+/// Unmarshals a byte slice at offset `n`, returning the offset after it and the bytes,
+/// which borrow from the buffer.
+pub fn unmarshal_bytes_cropped(n: usize, b: &[u8]) -> Result<(usize, &[u8])> {
+    read(n, b, crate::unmarshal_bytes_cropped)
+}
+
+/// Unmarshals a byte slice at offset `n`, returning the offset after it and a copy of
+/// the bytes.
+pub fn unmarshal_bytes_copied(n: usize, b: &[u8]) -> Result<(usize, Vec<u8>)> {
+    read(n, b, crate::unmarshal_bytes_copied)
+}
+
+/// Skips a byte slice at offset `n`, returning the offset after it.
+pub fn skip_bytes(n: usize, b: &[u8]) -> Result<usize> {
+    skip(n, b, crate::skip_bytes)
+}
+
+// ===================================================================================
+// Slices and Maps
+// ===================================================================================
+
+/// Marshals a slice at offset `n` with an offset-based element marshaler, returning
+/// the offset after it.
+pub fn marshal_slice<T>(
+    n: usize,
+    b: &mut [u8],
+    slice: &[T],
+    marshaler: impl Fn(usize, &mut [u8], &T) -> Result<usize>,
+) -> Result<usize> {
+    write(n, b, |w| crate::marshal_slice(slice, w, |v, w| write_element(w, |n, b| marshaler(n, b, v))))
+}
+
+/// Unmarshals a slice at offset `n` with an offset-based element unmarshaler,
+/// returning the offset after it and the elements.
+pub fn unmarshal_slice<T>(
+    n: usize,
+    b: &[u8],
+    unmarshaler: impl Fn(usize, &[u8]) -> Result<(usize, T)>,
+) -> Result<(usize, Vec<T>)> {
+    read(n, b, |r| crate::unmarshal_slice(r, |r| read_element(r, &unmarshaler)))
+}
+
+/// Skips a slice at offset `n` with an offset-based element skipper, returning the
+/// offset after it.
+pub fn skip_slice(
+    n: usize,
+    b: &[u8],
+    skipper: impl Fn(usize, &[u8]) -> Result<usize>,
+) -> Result<usize> {
+    skip(n, b, |r| crate::skip_slice(r, |r| skip_element(r, &skipper)))
+}
+
+/// Marshals a map at offset `n` with offset-based key and value marshalers, returning
+/// the offset after it.
+pub fn marshal_map<K, V, S>(
+    n: usize,
+    b: &mut [u8],
+    map: &HashMap<K, V, S>,
+    key_marshaler: impl Fn(usize, &mut [u8], &K) -> Result<usize>,
+    value_marshaler: impl Fn(usize, &mut [u8], &V) -> Result<usize>,
+) -> Result<usize> {
+    write(n, b, |w| {
+        crate::marshal_map(
+            map,
+            w,
+            |k, w| write_element(w, |n, b| key_marshaler(n, b, k)),
+            |v, w| write_element(w, |n, b| value_marshaler(n, b, v)),
+        )
+    })
+}
+
+/// Unmarshals a map at offset `n` with offset-based key and value unmarshalers,
+/// returning the offset after it and the map.
+pub fn unmarshal_map<'a, K: Eq + Hash, V>(
+    n: usize,
+    b: &'a [u8],
+    key_unmarshaler: impl Fn(usize, &'a [u8]) -> Result<(usize, K)>,
+    value_unmarshaler: impl Fn(usize, &'a [u8]) -> Result<(usize, V)>,
+) -> Result<(usize, HashMap<K, V>)> {
+    read(n, b, |r| {
+        crate::unmarshal_map(
+            r,
+            |r| read_element(r, &key_unmarshaler),
+            |r| read_element(r, &value_unmarshaler),
+        )
+    })
+}
+
+/// Skips a map at offset `n` with offset-based key and value skippers, returning the
+/// offset after it.
+pub fn skip_map(
+    n: usize,
+    b: &[u8],
+    key_skipper: impl Fn(usize, &[u8]) -> Result<usize>,
+    value_skipper: impl Fn(usize, &[u8]) -> Result<usize>,
+) -> Result<usize> {
+    skip(n, b, |r| {
+        crate::skip_map(r, |r| skip_element(r, &key_skipper), |r| skip_element(r, &value_skipper))
+    })
+}
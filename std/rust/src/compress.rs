@@ -0,0 +1,94 @@
+//! Compression envelopes using zstd, enabled by the `zstd` feature.
+//!
+//! An envelope is the id of the dictionary used as a `u32` (`0` for none), the
+//! varint-encoded uncompressed length and the compressed bytes as a byte slice.
+//!
+//! Small messages barely compress on their own. Training a [`Dictionary`] on
+//! representative samples and sharing it between both ends recovers most of the
+//! gain; the dictionary id stored in every envelope lets a reader pick the right one
+//! when dictionaries are rotated.
+
+use std::io;
+
+use zstd::bulk::{Compressor, Decompressor};
+
+use crate::{
+    Error, marshal_bytes, marshal_u32, marshal_usize, size_bytes, size_u32, size_usize,
+    unmarshal_bytes_cropped, unmarshal_u32, unmarshal_usize,
+};
+
+/// A zstd dictionary shared by the compressing and decompressing ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dictionary {
+    id: u32,
+    data: Vec<u8>,
+}
+
+impl Dictionary {
+    /// Loads a dictionary produced by `train_dict` (or the `zstd --train` tool).
+    ///
+    /// Returns an `InvalidValue` error if the data has no dictionary id.
+    pub fn new(data: Vec<u8>) -> io::Result<Self> {
+        let id = zstd::zstd_safe::get_dict_id_from_dict(&data).ok_or(Error::InvalidValue)?;
+        Ok(Dictionary { id: id.get(), data })
+    }
+
+    /// Returns the id embedded in the dictionary.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the raw dictionary, e.g. for distributing it to readers.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Trains a dictionary of at most `max_size` bytes on sample messages.
+pub fn train_dict<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> io::Result<Dictionary> {
+    Dictionary::new(zstd::dict::from_samples(samples, max_size)?)
+}
+
+/// Compresses data into an envelope at the given zstd level, optionally with a
+/// dictionary.
+pub fn compress(data: &[u8], dict: Option<&Dictionary>, level: i32) -> io::Result<Vec<u8>> {
+    let (id, compressed) = match dict {
+        Some(dict) => (dict.id, Compressor::with_dictionary(level, &dict.data)?.compress(data)?),
+        None => (0, zstd::bulk::compress(data, level)?),
+    };
+    let size = size_u32() + size_usize(data.len()) + size_bytes(&compressed);
+    let mut envelope = vec![0u8; size];
+    let mut writer = envelope.as_mut_slice();
+    marshal_u32(id, &mut writer)?;
+    marshal_usize(data.len(), &mut writer)?;
+    marshal_bytes(&compressed, &mut writer)?;
+    Ok(envelope)
+}
+
+/// Decompresses an envelope, looking up the dictionary it was compressed with among
+/// `dicts`.
+///
+/// Returns an `OutOfRange` error if the uncompressed data would exceed `max_size`
+/// bytes, and an `InvalidValue` error if the required dictionary is not in `dicts`.
+pub fn decompress(envelope: &[u8], dicts: &[Dictionary], max_size: usize) -> io::Result<Vec<u8>> {
+    let mut reader = envelope;
+    let id = unmarshal_u32(&mut reader)?;
+    let len = unmarshal_usize(&mut reader)?;
+    let compressed = unmarshal_bytes_cropped(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes.into());
+    }
+    if len > max_size {
+        return Err(Error::OutOfRange.into());
+    }
+    let data = if id == 0 {
+        zstd::bulk::decompress(compressed, len)?
+    } else {
+        let dict = dicts.iter().find(|d| d.id == id).ok_or(Error::InvalidValue)?;
+        Decompressor::with_dictionary(&dict.data)?.decompress(compressed, len)?
+    };
+    if data.len() != len {
+        return Err(Error::InvalidValue.into());
+    }
+    Ok(data)
+}
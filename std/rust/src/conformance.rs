@@ -0,0 +1,373 @@
+//! Wire format conformance vectors, enabled by the `testing` feature.
+//!
+//! [`vectors`] returns a fixed set of encoded edge cases: varints at every 7-bit
+//! boundary, extreme fixed-size integers and floats, empty, multi-byte and long
+//! strings, nested empty collections, collections whose data contains the terminator
+//! sequence, and malformed inputs together with the error they must produce.
+//! [`manifest`] renders them as JSON, so a port to another language can check its
+//! decoder and encoder against this crate without linking to it.
+//!
+//! The manifest is an object with a `format` and a `version`, and a `vectors` array.
+//! Each vector has a `name`, a `type` (such as `u16`, `uint`, `slice<string>` or
+//! `map<uint,bytes>`), the encoding as lowercase `hex`, and either the decoded `value`
+//! or the name of the `error` variant a decoder reports. Values are written as
+//! follows, so that no JSON parser loses precision:
+//!
+//! - `u8` to `u32` and `i8` to `i32` as numbers, and 64-bit integers, `uint`, `int`
+//!   and times (nanoseconds since the Unix epoch) as decimal strings,
+//! - floats as the hex string of their bits, such as `"0x3fc00000"`,
+//! - bytes as hex strings, slices as arrays and maps as arrays of `[key, value]`,
+//! - options as `null` or the value, and structs as objects with ordered fields.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use chrono::DateTime;
+
+use crate::{Error, Result, Schema, Type, Value, marshal_value, size_value, unmarshal_value};
+
+/// The version of the manifest layout written by [`manifest`].
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// An encoded edge case and the outcome of decoding it.
+#[derive(Debug)]
+pub struct Vector {
+    /// A unique, descriptive name.
+    pub name: String,
+    /// The type the bytes are decoded as.
+    pub ty: Type,
+    /// The encoded bytes.
+    pub encoded: Vec<u8>,
+    /// The decoded value, or the error decoding must report.
+    pub expected: Result<Value>,
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut buf = vec![0; size_value(value)];
+    // The buffer has exactly the size of the value.
+    marshal_value(value, &mut buf.as_mut_slice()).unwrap();
+    buf
+}
+
+fn boxed(ty: Type) -> Box<Type> {
+    Box::new(ty)
+}
+
+/// Returns the conformance vectors, in a stable order.
+pub fn vectors() -> Vec<Vector> {
+    let mut out = Vec::new();
+    let mut valid = |name: String, ty: Type, value: Value| {
+        out.push(Vector { name, ty, encoded: encode(&value), expected: Ok(value) });
+    };
+
+    // Varints around every 7-bit boundary, and ZigZag around the sign flips.
+    valid("uint 0".into(), Type::Uint, Value::Uint(0));
+    for bits in (7..64).step_by(7) {
+        valid(format!("uint 2^{bits}-1"), Type::Uint, Value::Uint((1 << bits) - 1));
+        valid(format!("uint 2^{bits}"), Type::Uint, Value::Uint(1 << bits));
+    }
+    valid("uint max".into(), Type::Uint, Value::Uint(u64::MAX));
+    for v in [0, -1, 1, -64, 63, -65, 64, -8192, 8191, i64::MIN, i64::MAX] {
+        valid(format!("int {v}"), Type::Int, Value::Int(v));
+    }
+
+    // Fixed-size values at their extremes.
+    valid("bool false".into(), Type::Bool, Value::Bool(false));
+    valid("bool true".into(), Type::Bool, Value::Bool(true));
+    valid("u8 max".into(), Type::U8, Value::U8(u8::MAX));
+    valid("u16 max".into(), Type::U16, Value::U16(u16::MAX));
+    valid("u32 max".into(), Type::U32, Value::U32(u32::MAX));
+    valid("u64 max".into(), Type::U64, Value::U64(u64::MAX));
+    valid("i8 min".into(), Type::I8, Value::I8(i8::MIN));
+    valid("i16 min".into(), Type::I16, Value::I16(i16::MIN));
+    valid("i32 min".into(), Type::I32, Value::I32(i32::MIN));
+    valid("i64 min".into(), Type::I64, Value::I64(i64::MIN));
+    valid("i64 max".into(), Type::I64, Value::I64(i64::MAX));
+    for (name, v) in [
+        ("zero", 0.0),
+        ("negative zero", -0.0),
+        ("1.5", 1.5),
+        ("infinity", f32::INFINITY),
+        ("negative infinity", f32::NEG_INFINITY),
+        ("nan", f32::NAN),
+        ("min positive", f32::MIN_POSITIVE),
+        ("subnormal", f32::from_bits(1)),
+    ] {
+        valid(format!("f32 {name}"), Type::F32, Value::F32(v));
+    }
+    for (name, v) in [("negative zero", -0.0), ("max", f64::MAX), ("nan", f64::NAN), ("subnormal", f64::from_bits(1))] {
+        valid(format!("f64 {name}"), Type::F64, Value::F64(v));
+    }
+    valid("time epoch".into(), Type::Time, Value::Time(DateTime::from_timestamp_nanos(0)));
+    valid("time before epoch".into(), Type::Time, Value::Time(DateTime::from_timestamp_nanos(-1)));
+    valid("time max".into(), Type::Time, Value::Time(DateTime::from_timestamp_nanos(i64::MAX)));
+
+    // Strings and bytes around the length varint boundaries.
+    valid("string empty".into(), Type::String, Value::String(String::new()));
+    valid("string multi-byte".into(), Type::String, Value::String("é日本🦀".into()));
+    valid("string nul".into(), Type::String, Value::String("\0".into()));
+    for len in [127, 128, 16384] {
+        valid(format!("string {len} bytes"), Type::String, Value::String("x".repeat(len)));
+    }
+    valid("bytes empty".into(), Type::Bytes, Value::Bytes(Vec::new()));
+    valid("bytes 128".into(), Type::Bytes, Value::Bytes((0..128).collect()));
+
+    // Collections, empty, nested and holding the terminator sequence.
+    let u8_slice = || Type::Slice(boxed(Type::U8));
+    let bytes_of = |b: &[u8]| Value::Slice(b.iter().map(|&b| Value::U8(b)).collect());
+    valid("slice empty".into(), u8_slice(), bytes_of(&[]));
+    valid("slice of terminator bytes".into(), u8_slice(), bytes_of(&[1, 1, 1, 1]));
+    valid("slice ending like a terminator".into(), u8_slice(), bytes_of(&[1, 1, 1, 1, 1, 1, 1]));
+    valid("slice of empty slice".into(), Type::Slice(boxed(u8_slice())), Value::Slice(vec![bytes_of(&[])]));
+    valid(
+        "slice of empty slices".into(),
+        Type::Slice(boxed(Type::Slice(boxed(u8_slice())))),
+        Value::Slice(vec![Value::Slice(vec![]), Value::Slice(vec![bytes_of(&[])])]),
+    );
+    valid(
+        "slice of empty strings".into(),
+        Type::Slice(boxed(Type::String)),
+        Value::Slice(vec![Value::String(String::new()); 2]),
+    );
+    valid(
+        "slice of options".into(),
+        Type::Slice(boxed(Type::Option(boxed(Type::U8)))),
+        Value::Slice(vec![Value::Option(None), Value::Option(Some(Box::new(Value::U8(1))))]),
+    );
+    let map_ty = || Type::Map(boxed(Type::Uint), boxed(Type::String));
+    valid("map empty".into(), map_ty(), Value::Map(Vec::new()));
+    valid(
+        "map entries".into(),
+        map_ty(),
+        Value::Map(vec![(Value::Uint(1), Value::String("a".into())), (Value::Uint(300), Value::String(String::new()))]),
+    );
+    valid(
+        "map of empty slice".into(),
+        Type::Map(boxed(Type::String), boxed(u8_slice())),
+        Value::Map(vec![(Value::String(String::new()), bytes_of(&[]))]),
+    );
+    valid("option none".into(), Type::Option(boxed(Type::U8)), Value::Option(None));
+    valid("option some zero".into(), Type::Option(boxed(Type::U8)), Value::Option(Some(Box::new(Value::U8(0)))));
+    valid("option some empty".into(), Type::Option(boxed(u8_slice())), Value::Option(Some(Box::new(bytes_of(&[])))));
+
+    let inner = Schema::new().field("list", u8_slice()).field("map", map_ty());
+    let schema = Schema::new().field("id", Type::U8).field("inner", Type::Struct(inner));
+    valid(
+        "struct of empties".into(),
+        Type::Struct(schema),
+        Value::Struct(vec![
+            ("id".into(), Value::U8(0)),
+            ("inner".into(), Value::Struct(vec![("list".into(), bytes_of(&[])), ("map".into(), Value::Map(vec![]))])),
+        ]),
+    );
+
+    // Malformed inputs.
+    let mut invalid = |name: &str, ty: Type, encoded: &[u8], err: Error| {
+        out.push(Vector { name: name.into(), ty, encoded: encoded.to_vec(), expected: Err(err) });
+    };
+    invalid("uint truncated", Type::Uint, &[0x80], Error::BufferTooSmall { needed: 2, available: 1 });
+    invalid("uint overflow", Type::Uint, &[0xff; 11], Error::VarintOverflow);
+    invalid("u32 truncated", Type::U32, &[1, 2, 3], Error::BufferTooSmall { needed: 4, available: 3 });
+    invalid("string truncated", Type::String, &[5, b'a'], Error::BufferTooSmall { needed: 5, available: 1 });
+    invalid("slice missing terminator", u8_slice(), &[1, 5, 0, 0, 0, 0], Error::MissingTerminator);
+    invalid("slice truncated terminator", u8_slice(), &[0, 1, 1, 1], Error::BufferTooSmall { needed: 4, available: 3 });
+    invalid("slice count too small", u8_slice(), &[0, 5, 1, 1, 1, 1], Error::MissingTerminator);
+    invalid("u8 trailing bytes", Type::U8, &[1, 2], Error::TrailingBytes);
+    // The string error carries the position of the invalid byte.
+    let encoded = [1, 0xff];
+    let utf8_error = std::str::from_utf8(&encoded[1..]).unwrap_err();
+    invalid("string invalid utf-8", Type::String, &encoded, Error::InvalidUtf8(utf8_error));
+    out
+}
+
+/// Decodes a whole buffer as a value of the given type, as a port should.
+pub fn decode(encoded: &[u8], ty: &Type) -> Result<Value> {
+    let mut reader = encoded;
+    let value = unmarshal_value(&mut reader, ty)?;
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(value)
+}
+
+/// Checks that this crate decodes the vector as expected and encodes the decoded
+/// value back into the same bytes. Returns a description of the first mismatch.
+pub fn check(vector: &Vector) -> std::result::Result<(), String> {
+    let decoded = decode(&vector.encoded, &vector.ty);
+    match (&decoded, &vector.expected) {
+        // Values are compared by their encoding, under which NaN equals itself.
+        (Ok(value), Ok(expected)) if encode(value) == encode(expected) && encode(value) == vector.encoded => Ok(()),
+        (Err(err), Err(expected)) if error_name(err) == error_name(expected) => Ok(()),
+        _ => Err(format!("{}: expected {:?}, decoded {:?}", vector.name, vector.expected, decoded)),
+    }
+}
+
+/// Returns the name of the error variant, as written in the manifest.
+pub fn error_name(err: &Error) -> &'static str {
+    match err {
+        Error::BufferTooSmall { .. } => "BufferTooSmall",
+        Error::VarintOverflow => "VarintOverflow",
+        Error::NonMinimalVarint => "NonMinimalVarint",
+        Error::InvalidUtf8(_) => "InvalidUtf8",
+        Error::MissingTerminator => "MissingTerminator",
+        Error::OutOfRange => "OutOfRange",
+        Error::TrailingBytes => "TrailingBytes",
+        Error::InvalidValue => "InvalidValue",
+        Error::NonCanonical => "NonCanonical",
+        Error::Authentication => "Authentication",
+        Error::Validation(_) => "Validation",
+        Error::UnknownSchema(_) => "UnknownSchema",
+        Error::CapacityExceeded { .. } => "CapacityExceeded",
+        Error::Unsupported(_) => "Unsupported",
+        Error::TerminatorMismatch { .. } => "TerminatorMismatch",
+    }
+}
+
+/// Returns the name of the type, as written in the manifest.
+pub fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".into(),
+        Type::U8 => "u8".into(),
+        Type::U16 => "u16".into(),
+        Type::U32 => "u32".into(),
+        Type::U64 => "u64".into(),
+        Type::I8 => "i8".into(),
+        Type::I16 => "i16".into(),
+        Type::I32 => "i32".into(),
+        Type::I64 => "i64".into(),
+        Type::F32 => "f32".into(),
+        Type::F64 => "f64".into(),
+        Type::Uint => "uint".into(),
+        Type::Int => "int".into(),
+        Type::String => "string".into(),
+        Type::Bytes => "bytes".into(),
+        Type::Time => "time".into(),
+        Type::Slice(elem) => format!("slice<{}>", type_name(elem)),
+        Type::Map(k, v) => format!("map<{},{}>", type_name(k), type_name(v)),
+        Type::Option(inner) => format!("option<{}>", type_name(inner)),
+        Type::Struct(schema) => {
+            let fields: Vec<_> = schema.fields().iter().map(|f| format!("{}:{}", f.name, type_name(&f.ty))).collect();
+            format!("struct{{{}}}", fields.join(","))
+        }
+    }
+}
+
+fn push_hex(out: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        // Writing into a `String` cannot fail.
+        write!(out, "{b:02x}").unwrap();
+    }
+}
+
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn push_json_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Bool(v) => write!(out, "{v}").unwrap(),
+        Value::U8(v) => write!(out, "{v}").unwrap(),
+        Value::U16(v) => write!(out, "{v}").unwrap(),
+        Value::U32(v) => write!(out, "{v}").unwrap(),
+        Value::I8(v) => write!(out, "{v}").unwrap(),
+        Value::I16(v) => write!(out, "{v}").unwrap(),
+        Value::I32(v) => write!(out, "{v}").unwrap(),
+        Value::U64(v) | Value::Uint(v) => write!(out, "\"{v}\"").unwrap(),
+        Value::I64(v) | Value::Int(v) => write!(out, "\"{v}\"").unwrap(),
+        Value::F32(v) => write!(out, "\"{:#010x}\"", v.to_bits()).unwrap(),
+        Value::F64(v) => write!(out, "\"{:#018x}\"", v.to_bits()).unwrap(),
+        // Times are marshalled as nanoseconds, which is the value a port sees.
+        Value::Time(t) => write!(out, "\"{}\"", t.timestamp_nanos_opt().unwrap_or(0)).unwrap(),
+        Value::String(s) => push_json_string(out, s),
+        Value::Bytes(b) => {
+            out.push('"');
+            push_hex(out, b);
+            out.push('"');
+        }
+        Value::Slice(elems) => {
+            out.push('[');
+            for (i, elem) in elems.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                push_json_value(out, elem);
+            }
+            out.push(']');
+        }
+        Value::Map(entries) => {
+            out.push('[');
+            for (i, (k, v)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push('[');
+                push_json_value(out, k);
+                out.push(',');
+                push_json_value(out, v);
+                out.push(']');
+            }
+            out.push(']');
+        }
+        Value::Option(None) => out.push_str("null"),
+        Value::Option(Some(v)) => push_json_value(out, v),
+        Value::Struct(fields) => {
+            out.push('{');
+            for (i, (name, v)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                push_json_string(out, name);
+                out.push(':');
+                push_json_value(out, v);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Renders the vectors as a JSON manifest, one vector per line.
+pub fn manifest(vectors: &[Vector]) -> String {
+    let mut out = format!("{{\"format\":\"benc-conformance\",\"version\":{MANIFEST_VERSION},\"vectors\":[\n");
+    for (i, vector) in vectors.iter().enumerate() {
+        out.push_str("{\"name\":");
+        push_json_string(&mut out, &vector.name);
+        out.push_str(",\"type\":");
+        push_json_string(&mut out, &type_name(&vector.ty));
+        out.push_str(",\"hex\":\"");
+        push_hex(&mut out, &vector.encoded);
+        out.push('"');
+        match &vector.expected {
+            Ok(value) => {
+                out.push_str(",\"value\":");
+                push_json_value(&mut out, value);
+            }
+            Err(err) => write!(out, ",\"error\":\"{}\"", error_name(err)).unwrap(),
+        }
+        out.push('}');
+        if i + 1 < vectors.len() {
+            out.push(',');
+        }
+        out.push('\n');
+    }
+    out.push_str("]}\n");
+    out
+}
+
+/// Writes the manifest of all [`vectors`] to `path`, creating its directory.
+pub fn write_manifest(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, manifest(&vectors()))
+}
@@ -0,0 +1,108 @@
+//! Marshalling at compile time.
+//!
+//! The marshal functions of fixed-size types and varints are `const fn`s, so static
+//! data can be encoded by the compiler instead of at startup. [`ConstEncoder`] chains
+//! them into a message, turning a buffer of the wrong size into a compile error:
+//!
+//! ```
+//! use benc::ConstEncoder;
+//!
+//! static CONFIG: [u8; 10] = ConstEncoder::new().u16(8080).bool(true).uint(300).str("benc").finish();
+//! ```
+//!
+//! The marshal functions can also be called directly, but the `Result` they return
+//! cannot be dropped in a `const` context and must be passed to `std::mem::forget`
+//! once checked.
+
+use crate::{
+    Result, marshal_bool, marshal_f32, marshal_f64, marshal_i8, marshal_i16, marshal_i32, marshal_i64,
+    marshal_int, marshal_isize, marshal_u8, marshal_u16, marshal_u32, marshal_u64, marshal_uint,
+    marshal_usize, write_to_slice,
+};
+
+/// Encodes a message into an array of `N` bytes in a `const` context.
+///
+/// Every method appends a value in the format of the marshal function of the same
+/// type, and panics, which fails the build in a `const` context, if the value does not
+/// fit. [`finish`](Self::finish) panics unless the message fills the array exactly.
+pub struct ConstEncoder<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+/// Panics if a marshal function failed.
+const fn expect_written(result: Result<()>) {
+    if result.is_err() {
+        panic!("the message is larger than the ConstEncoder array");
+    }
+    // The result is `Ok`, but the destructor of `Result` cannot run in a `const fn`.
+    std::mem::forget(result);
+}
+
+macro_rules! const_encoder_fn {
+    ($($name:ident: $type:ty => $marshal_fn:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Appends a `", stringify!($type), "` in the format of `", stringify!($marshal_fn), "`.")]
+            pub const fn $name(mut self, v: $type) -> Self {
+                let mut writer = self.buf.split_at_mut(self.len).1;
+                let result = $marshal_fn(v, &mut writer);
+                self.len = N - writer.len();
+                expect_written(result);
+                self
+            }
+        )*
+    };
+}
+
+impl<const N: usize> ConstEncoder<N> {
+    /// Creates an encoder of an empty message.
+    pub const fn new() -> Self {
+        ConstEncoder { buf: [0; N], len: 0 }
+    }
+
+    const_encoder_fn! {
+        bool: bool => marshal_bool,
+        u8: u8 => marshal_u8,
+        u16: u16 => marshal_u16,
+        u32: u32 => marshal_u32,
+        u64: u64 => marshal_u64,
+        i8: i8 => marshal_i8,
+        i16: i16 => marshal_i16,
+        i32: i32 => marshal_i32,
+        i64: i64 => marshal_i64,
+        f32: f32 => marshal_f32,
+        f64: f64 => marshal_f64,
+        uint: u64 => marshal_uint,
+        int: i64 => marshal_int,
+        usize: usize => marshal_usize,
+        isize: isize => marshal_isize,
+    }
+
+    /// Appends a string in the format of `marshal_string`.
+    pub const fn str(self, v: &str) -> Self {
+        self.bytes(v.as_bytes())
+    }
+
+    /// Appends a byte slice in the format of `marshal_bytes`.
+    pub const fn bytes(self, v: &[u8]) -> Self {
+        let mut this = self.usize(v.len());
+        let mut writer = this.buf.split_at_mut(this.len).1;
+        expect_written(write_to_slice(&mut writer, v));
+        this.len += v.len();
+        this
+    }
+
+    /// Returns the encoded message.
+    ///
+    /// Panics if the message is shorter than the array.
+    pub const fn finish(self) -> [u8; N] {
+        assert!(self.len == N, "the message is smaller than the ConstEncoder array");
+        self.buf
+    }
+}
+
+impl<const N: usize> Default for ConstEncoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,39 @@
+//! Hashes of the encoding of values, enabled by the `sha2` feature.
+//!
+//! Two values with the same encoding are the same message, so the SHA-256 digest of
+//! the marshalled bytes identifies a value across processes and languages, which
+//! makes it usable as a deduplication key or an idempotency key. Unlike `std::hash`,
+//! the result is stable across builds and platforms.
+//!
+//! Values are hashed in the [canonical profile](crate::marshal_canonical), so maps
+//! holding the same entries hash alike whatever their iteration order. Types that do
+//! not describe their encoding through `BencEncode::benc_type` are hashed in their
+//! regular encoding.
+
+use sha2::{Digest, Sha256};
+
+use crate::{BencEncode, marshal_canonical};
+
+/// The size of a content hash in bytes.
+pub const CONTENT_HASH_LEN: usize = 32;
+
+/// Returns the SHA-256 digest of marshalled bytes.
+pub(crate) fn hash_bytes(bytes: &[u8]) -> [u8; CONTENT_HASH_LEN] {
+    Sha256::digest(bytes).into()
+}
+
+/// Returns the SHA-256 digest of the canonical encoding of a value.
+pub fn content_hash<T: BencEncode + ?Sized>(value: &T) -> [u8; CONTENT_HASH_LEN] {
+    match T::benc_type() {
+        // A value marshalled by its own type always canonicalizes.
+        Some(_) => hash_bytes(&marshal_canonical(value).expect("value does not match its benc type")),
+        None => hash_bytes(&value.to_vec()),
+    }
+}
+
+/// Returns the first 8 bytes of [`content_hash`] as a little-endian integer, for
+/// caches keyed by `u64`. Collisions become likely only after billions of values.
+pub fn content_hash_u64<T: BencEncode + ?Sized>(value: &T) -> u64 {
+    let hash = content_hash(value);
+    u64::from_le_bytes(hash[..8].try_into().unwrap())
+}
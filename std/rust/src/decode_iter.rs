@@ -0,0 +1,106 @@
+//! Blocking iteration over framed messages.
+//!
+//! [`DecodeIter`] reads frames from any `Read` and unmarshals them, so a batch job can
+//! process a file or socket with a plain `for` loop. A frame is the varint byte length
+//! of the marshalled value followed by the value, the layout written by `BencSink` and
+//! `BencLinesWriter`, split off by a [`FrameDecoder`].
+
+use std::io::{self, Read};
+use std::marker::PhantomData;
+
+use crate::{BencDecode, FrameDecoder, from_slice};
+
+/// The number of bytes `DecodeIter` asks the reader for at a time.
+const READ_SIZE: usize = 8 * 1024;
+
+/// The default limit on the length of a frame read by `DecodeIter` and `BencStream`.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Reads frames from a `Read` and unmarshals them into messages of type `T`.
+///
+/// The iterator ends when the reader reaches end of file between frames. A message
+/// that cannot be decoded yields an `InvalidData` error and the iterator moves on to
+/// the next frame. A frame cut short by end of file yields an `UnexpectedEof` error, a
+/// frame longer than the limit an `InvalidData` error, and both end the iterator, as
+/// do errors of the reader other than `Interrupted`.
+pub struct DecodeIter<R, T> {
+    reader: R,
+    decoder: FrameDecoder,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> DecodeIter<R, T> {
+    /// Creates an iterator reading from `reader`, accepting frames of up to
+    /// [`DEFAULT_MAX_FRAME_LEN`] bytes. The reader is read in large chunks, so it
+    /// needs no buffering of its own.
+    pub fn new(reader: R) -> Self {
+        DecodeIter { reader, decoder: FrameDecoder::new(), done: false, _marker: PhantomData }
+    }
+
+    /// Sets the largest frame length the iterator accepts, which bounds the memory a
+    /// corrupt input can make it allocate.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.decoder = self.decoder.with_max_frame_len(max_frame_len);
+        self
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying reader. Reading from it directly
+    /// corrupts the iteration.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader, discarding bytes that have been read but not
+    /// decoded.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read, T> DecodeIter<R, T> {
+    /// Reads more bytes into the decoder, returning `false` at end of file.
+    fn fill(&mut self) -> io::Result<bool> {
+        loop {
+            match self.reader.read(self.decoder.read_buf(READ_SIZE)) {
+                Ok(n) => {
+                    self.decoder.commit(n);
+                    return Ok(n > 0);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl<R: Read, T: for<'a> BencDecode<'a>> Iterator for DecodeIter<R, T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<io::Result<T>> {
+        if self.done {
+            return None;
+        }
+        let result = loop {
+            match self.decoder.next_frame() {
+                Ok(Some(frame)) => return Some(from_slice(frame).map_err(io::Error::from)),
+                Ok(None) => {}
+                Err(err) => break Err(err),
+            }
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => break self.decoder.finish(),
+                Err(err) => break Err(err),
+            }
+        };
+        // The frames after a bad header or a failed read cannot be found.
+        self.done = true;
+        self.decoder.clear();
+        result.err().map(Err)
+    }
+}
@@ -0,0 +1,240 @@
+//! Self-describing messages, which carry their own schema.
+//!
+//! A self-describing message starts with a schema header: a format version byte
+//! followed by a compact encoding of the [`Schema`] of the message. The message
+//! itself follows the header. Any reader can decode such a message into a [`Value`]
+//! without knowing its type in advance, which makes the format suitable for archives
+//! that must stay readable long after the code that wrote them is gone.
+//!
+//! A schema is encoded as a slice of fields, each holding the field name, a `bool`
+//! telling whether the field is sensitive, and its type. A type is a one-byte tag,
+//! followed by the element types of slices, maps and options, or the schema of a
+//! nested struct.
+//!
+//! A header with the version [`UNTERMINATED_SCHEMA_HEADER_VERSION`] marks a message
+//! whose slices and maps have no terminator, as written by early Go `bstd`
+//! revisions. The header itself always uses the current layout.
+
+use crate::{
+    BencEncode, Error, Layout, Result, Schema, Type, Value, marshal_bool, marshal_slice, marshal_string,
+    marshal_u8, size_bool, size_slice, size_string, size_u8, unmarshal_bool, unmarshal_slice,
+    unmarshal_string, unmarshal_u8, upgrade_unterminated, value_from_slice,
+};
+
+/// The version of the schema header format written by [`marshal_schema_header`].
+pub const SCHEMA_HEADER_VERSION: u8 = 1;
+
+/// The version of a schema header followed by a message in the
+/// [unterminated layout](Layout::Unterminated).
+pub const UNTERMINATED_SCHEMA_HEADER_VERSION: u8 = 0;
+
+/// The deepest nesting of types accepted when decoding a schema, which bounds the
+/// recursion a malicious header can cause.
+const MAX_DEPTH: usize = 64;
+
+const TAG_BOOL: u8 = 0;
+const TAG_U8: u8 = 1;
+const TAG_U16: u8 = 2;
+const TAG_U32: u8 = 3;
+const TAG_U64: u8 = 4;
+const TAG_I8: u8 = 5;
+const TAG_I16: u8 = 6;
+const TAG_I32: u8 = 7;
+const TAG_I64: u8 = 8;
+const TAG_F32: u8 = 9;
+const TAG_F64: u8 = 10;
+const TAG_UINT: u8 = 11;
+const TAG_INT: u8 = 12;
+const TAG_STRING: u8 = 13;
+const TAG_BYTES: u8 = 14;
+const TAG_TIME: u8 = 15;
+const TAG_SLICE: u8 = 16;
+const TAG_MAP: u8 = 17;
+const TAG_OPTION: u8 = 18;
+const TAG_STRUCT: u8 = 19;
+
+fn tag(ty: &Type) -> u8 {
+    match ty {
+        Type::Bool => TAG_BOOL,
+        Type::U8 => TAG_U8,
+        Type::U16 => TAG_U16,
+        Type::U32 => TAG_U32,
+        Type::U64 => TAG_U64,
+        Type::I8 => TAG_I8,
+        Type::I16 => TAG_I16,
+        Type::I32 => TAG_I32,
+        Type::I64 => TAG_I64,
+        Type::F32 => TAG_F32,
+        Type::F64 => TAG_F64,
+        Type::Uint => TAG_UINT,
+        Type::Int => TAG_INT,
+        Type::String => TAG_STRING,
+        Type::Bytes => TAG_BYTES,
+        Type::Time => TAG_TIME,
+        Type::Slice(_) => TAG_SLICE,
+        Type::Map(_, _) => TAG_MAP,
+        Type::Option(_) => TAG_OPTION,
+        Type::Struct(_) => TAG_STRUCT,
+    }
+}
+
+fn size_type(ty: &Type) -> usize {
+    size_u8()
+        + match ty {
+            Type::Slice(elem) | Type::Option(elem) => size_type(elem),
+            Type::Map(k, v) => size_type(k) + size_type(v),
+            Type::Struct(schema) => size_fields(schema),
+            _ => 0,
+        }
+}
+
+fn marshal_type(ty: &Type, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_u8(tag(ty), writer)?;
+    match ty {
+        Type::Slice(elem) | Type::Option(elem) => marshal_type(elem, writer),
+        Type::Map(k, v) => {
+            marshal_type(k, writer)?;
+            marshal_type(v, writer)
+        }
+        Type::Struct(schema) => marshal_fields(schema, writer),
+        _ => Ok(()),
+    }
+}
+
+fn unmarshal_type(reader: &mut &[u8], depth: usize) -> Result<Type> {
+    if depth > MAX_DEPTH {
+        return Err(Error::InvalidValue);
+    }
+    Ok(match unmarshal_u8(reader)? {
+        TAG_BOOL => Type::Bool,
+        TAG_U8 => Type::U8,
+        TAG_U16 => Type::U16,
+        TAG_U32 => Type::U32,
+        TAG_U64 => Type::U64,
+        TAG_I8 => Type::I8,
+        TAG_I16 => Type::I16,
+        TAG_I32 => Type::I32,
+        TAG_I64 => Type::I64,
+        TAG_F32 => Type::F32,
+        TAG_F64 => Type::F64,
+        TAG_UINT => Type::Uint,
+        TAG_INT => Type::Int,
+        TAG_STRING => Type::String,
+        TAG_BYTES => Type::Bytes,
+        TAG_TIME => Type::Time,
+        TAG_SLICE => Type::Slice(Box::new(unmarshal_type(reader, depth + 1)?)),
+        TAG_MAP => {
+            let k = unmarshal_type(reader, depth + 1)?;
+            Type::Map(Box::new(k), Box::new(unmarshal_type(reader, depth + 1)?))
+        }
+        TAG_OPTION => Type::Option(Box::new(unmarshal_type(reader, depth + 1)?)),
+        TAG_STRUCT => Type::Struct(unmarshal_fields(reader, depth + 1)?),
+        _ => return Err(Error::InvalidValue),
+    })
+}
+
+fn size_fields(schema: &Schema) -> usize {
+    size_slice(schema.fields(), |f| size_string(&f.name) + size_bool() + size_type(&f.ty))
+}
+
+fn marshal_fields(schema: &Schema, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_slice(schema.fields(), writer, |f, w| {
+        marshal_string(&f.name, w)?;
+        marshal_bool(f.sensitive, w)?;
+        marshal_type(&f.ty, w)
+    })
+}
+
+fn unmarshal_fields(reader: &mut &[u8], depth: usize) -> Result<Schema> {
+    let fields = unmarshal_slice(reader, |r| {
+        let name = unmarshal_string(r)?.to_owned();
+        let sensitive = unmarshal_bool(r)?;
+        Ok::<_, Error>((name, sensitive, unmarshal_type(r, depth)?))
+    })?;
+    Ok(fields.into_iter().fold(Schema::new(), |schema, (name, sensitive, ty)| {
+        if sensitive { schema.sensitive_field(name, ty) } else { schema.field(name, ty) }
+    }))
+}
+
+/// Returns the number of bytes needed to marshal the schema header.
+pub fn size_schema_header(schema: &Schema) -> usize {
+    size_u8() + size_fields(schema)
+}
+
+/// Marshals the schema header describing messages of the schema into the writer.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_schema_header(schema: &Schema, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_u8(SCHEMA_HEADER_VERSION, writer)?;
+    marshal_fields(schema, writer)
+}
+
+/// Marshals the schema header describing messages of the schema in the given layout
+/// into the writer, for tools that re-archive data of old producers as it is.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_schema_header_with_layout(schema: &Schema, layout: Layout, writer: &mut &mut [u8]) -> Result<()> {
+    let version = match layout {
+        Layout::Terminated => SCHEMA_HEADER_VERSION,
+        Layout::Unterminated => UNTERMINATED_SCHEMA_HEADER_VERSION,
+    };
+    marshal_u8(version, writer)?;
+    marshal_fields(schema, writer)
+}
+
+/// Unmarshals a schema header from the reader.
+///
+/// Returns an `InvalidValue` error if the header has an unknown version, an unknown
+/// type tag or types nested too deeply.
+///
+/// A header of a message in the unterminated layout is rejected as well, since the
+/// message would not decode; [`unmarshal_schema_header_with_layout`] accepts it.
+pub fn unmarshal_schema_header(reader: &mut &[u8]) -> Result<Schema> {
+    if unmarshal_u8(reader)? != SCHEMA_HEADER_VERSION {
+        return Err(Error::InvalidValue);
+    }
+    unmarshal_fields(reader, 0)
+}
+
+/// Unmarshals a schema header from the reader, returning the schema and the layout of
+/// the message that follows.
+///
+/// Returns an `InvalidValue` error if the header has an unknown version, an unknown
+/// type tag or types nested too deeply.
+pub fn unmarshal_schema_header_with_layout(reader: &mut &[u8]) -> Result<(Schema, Layout)> {
+    let layout = match unmarshal_u8(reader)? {
+        SCHEMA_HEADER_VERSION => Layout::Terminated,
+        UNTERMINATED_SCHEMA_HEADER_VERSION => Layout::Unterminated,
+        _ => return Err(Error::InvalidValue),
+    };
+    Ok((unmarshal_fields(reader, 0)?, layout))
+}
+
+/// Marshals a message prefixed with the header of its schema into a new vector. The
+/// schema must describe how `v` marshals.
+pub fn to_vec_self_describing<T: BencEncode>(schema: &Schema, v: &T) -> Vec<u8> {
+    let mut buf = vec![0u8; size_schema_header(schema) + v.size()];
+    let mut writer = buf.as_mut_slice();
+    // The buffer has exactly the size the header and the value reported.
+    marshal_schema_header(schema, &mut writer)
+        .and_then(|()| v.marshal(&mut writer))
+        .expect("size() is smaller than marshal() output");
+    buf
+}
+
+/// Unmarshals a self-describing message into a [`Value`], returning its schema and
+/// the message. Messages in either layout are accepted.
+///
+/// Returns a `TrailingBytes` error if the message is followed by unconsumed bytes.
+pub fn value_from_self_describing(buf: &[u8]) -> Result<(Schema, Value)> {
+    let mut reader = buf;
+    let (schema, layout) = unmarshal_schema_header_with_layout(&mut reader)?;
+    let value = match layout {
+        Layout::Terminated => value_from_slice(reader, &schema)?,
+        Layout::Unterminated => {
+            let upgraded = upgrade_unterminated(reader, &Type::Struct(schema.clone()))?;
+            value_from_slice(&upgraded, &schema)?
+        }
+    };
+    Ok((schema, value))
+}
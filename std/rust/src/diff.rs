@@ -0,0 +1,100 @@
+//! Structural comparison and patching of marshalled messages.
+
+use std::ops::Range;
+
+use crate::{
+    Error, Result, Schema, TERMINATOR, marshal_bytes, marshal_usize, read_terminator, size_bytes,
+    size_usize, skip_type, schema::field_ranges, unmarshal_bytes_cropped, unmarshal_usize,
+    write_to_slice,
+};
+
+/// A top-level field whose marshalled bytes differ between two messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    /// The index of the field in the schema.
+    pub index: usize,
+    /// The byte range of the field in the old message.
+    pub old: Range<usize>,
+    /// The byte range of the field in the new message.
+    pub new: Range<usize>,
+}
+
+/// Compares two marshalled messages field by field and reports every top-level field
+/// whose marshalled bytes differ.
+///
+/// Both buffers must hold exactly one message described by the schema; a
+/// `TrailingBytes` error is returned otherwise.
+pub fn diff(schema: &Schema, old: &[u8], new: &[u8]) -> Result<Vec<FieldChange>> {
+    let old_ranges = field_ranges(old, schema)?;
+    let new_ranges = field_ranges(new, schema)?;
+    Ok(old_ranges
+        .into_iter()
+        .zip(new_ranges)
+        .enumerate()
+        .filter(|(_, (o, n))| old[o.clone()] != new[n.clone()])
+        .map(|(index, (old, new))| FieldChange { index, old, new })
+        .collect())
+}
+
+// ===================================================================================
+// Patches
+// ===================================================================================
+
+/// Creates a patch that turns `old` into `new`.
+///
+/// The patch is itself marshalled with benc: a slice of entries, each holding the index
+/// of a changed field and its new marshalled bytes. Unchanged fields are not included,
+/// so the patch only grows with the size of the fields that actually changed.
+pub fn make_patch(schema: &Schema, old: &[u8], new: &[u8]) -> Result<Vec<u8>> {
+    let changes = diff(schema, old, new)?;
+    let size = size_usize(changes.len())
+        + changes
+            .iter()
+            .map(|c| size_usize(c.index) + size_bytes(&new[c.new.clone()]))
+            .sum::<usize>()
+        + TERMINATOR.len();
+    let mut patch = vec![0u8; size];
+    let mut writer = patch.as_mut_slice();
+    marshal_usize(changes.len(), &mut writer)?;
+    for change in &changes {
+        marshal_usize(change.index, &mut writer)?;
+        marshal_bytes(&new[change.new.clone()], &mut writer)?;
+    }
+    write_to_slice(&mut writer, &TERMINATOR)?;
+    Ok(patch)
+}
+
+/// Applies a patch created by `make_patch` to `old`, returning the patched message.
+///
+/// Returns an `OutOfRange` error if the patch refers to a field the schema does not
+/// have, a `NonCanonical` error if its entries are not in increasing field order and
+/// an `InvalidValue` error if a replacement is not exactly one value of the field's type.
+pub fn apply_patch(schema: &Schema, old: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let ranges = field_ranges(old, schema)?;
+    let mut reader = patch;
+    let count = unmarshal_usize(&mut reader)?;
+    let mut out = Vec::with_capacity(old.len());
+    let mut next_field = 0;
+    for _ in 0..count {
+        let index = unmarshal_usize(&mut reader)?;
+        let replacement = unmarshal_bytes_cropped(&mut reader)?;
+        if index < next_field {
+            return Err(Error::NonCanonical);
+        }
+        let field = schema.fields().get(index).ok_or(Error::OutOfRange)?;
+        let mut check = replacement;
+        if skip_type(&mut check, &field.ty).is_err() || !check.is_empty() {
+            return Err(Error::InvalidValue);
+        }
+        for range in &ranges[next_field..index] {
+            out.extend_from_slice(&old[range.clone()]);
+        }
+        out.extend_from_slice(replacement);
+        next_field = index + 1;
+    }
+    read_terminator(&mut reader)?;
+    for range in &ranges[next_field..] {
+        out.extend_from_slice(&old[range.clone()]);
+    }
+    Ok(out)
+}
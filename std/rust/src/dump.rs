@@ -0,0 +1,173 @@
+//! Human-readable views of marshalled messages, for debugging.
+//!
+//! Both [`dump`] and [`explain`] walk a message with its [`Schema`]. Fields marked
+//! as sensitive (see [`Schema::sensitive_field`]) are printed as `<redacted>`, and
+//! their bytes are never shown, so the output can be pasted into tickets safely.
+
+use std::fmt::Write;
+
+use crate::{
+    Error, Result, Schema, Type, read_terminator, skip_type, unmarshal_bool, unmarshal_bytes_cropped,
+    unmarshal_f32, unmarshal_f64, unmarshal_i8, unmarshal_i16, unmarshal_i32, unmarshal_i64,
+    unmarshal_int, unmarshal_string, unmarshal_time, unmarshal_u8, unmarshal_u16, unmarshal_u32,
+    unmarshal_u64, unmarshal_uint, unmarshal_usize,
+};
+
+const REDACTED: &str = "<redacted>";
+
+/// Writes a value of the given type. With `indent` set, collections and structs are
+/// spread over multiple lines at that depth; otherwise they are written on one line.
+fn write_value(out: &mut String, reader: &mut &[u8], ty: &Type, indent: Option<usize>) -> Result<()> {
+    // Writing into a `String` cannot fail.
+    match ty {
+        Type::Bool => write!(out, "{}", unmarshal_bool(reader)?).unwrap(),
+        Type::U8 => write!(out, "{}", unmarshal_u8(reader)?).unwrap(),
+        Type::U16 => write!(out, "{}", unmarshal_u16(reader)?).unwrap(),
+        Type::U32 => write!(out, "{}", unmarshal_u32(reader)?).unwrap(),
+        Type::U64 => write!(out, "{}", unmarshal_u64(reader)?).unwrap(),
+        Type::I8 => write!(out, "{}", unmarshal_i8(reader)?).unwrap(),
+        Type::I16 => write!(out, "{}", unmarshal_i16(reader)?).unwrap(),
+        Type::I32 => write!(out, "{}", unmarshal_i32(reader)?).unwrap(),
+        Type::I64 => write!(out, "{}", unmarshal_i64(reader)?).unwrap(),
+        Type::F32 => write!(out, "{:?}", unmarshal_f32(reader)?).unwrap(),
+        Type::F64 => write!(out, "{:?}", unmarshal_f64(reader)?).unwrap(),
+        Type::Uint => write!(out, "{}", unmarshal_uint(reader)?).unwrap(),
+        Type::Int => write!(out, "{}", unmarshal_int(reader)?).unwrap(),
+        Type::String => write!(out, "{:?}", unmarshal_string(reader)?).unwrap(),
+        Type::Bytes => {
+            out.push_str("0x");
+            for b in unmarshal_bytes_cropped(reader)? {
+                write!(out, "{b:02x}").unwrap();
+            }
+        }
+        Type::Time => out.push_str(&unmarshal_time(reader)?.to_rfc3339()),
+        Type::Option(inner) => {
+            if unmarshal_bool(reader)? {
+                out.push_str("Some(");
+                write_value(out, reader, inner, indent)?;
+                out.push(')');
+            } else {
+                out.push_str("None");
+            }
+        }
+        Type::Slice(elem) => {
+            let len = unmarshal_usize(reader)?;
+            out.push('[');
+            for i in 0..len {
+                separator(out, i, indent);
+                write_value(out, reader, elem, indent.map(|d| d + 1))?;
+            }
+            close(out, len, indent, ']');
+            read_terminator(reader)?;
+        }
+        Type::Map(k, v) => {
+            let len = unmarshal_usize(reader)?;
+            out.push('{');
+            for i in 0..len {
+                separator(out, i, indent);
+                write_value(out, reader, k, indent.map(|d| d + 1))?;
+                out.push_str(": ");
+                write_value(out, reader, v, indent.map(|d| d + 1))?;
+            }
+            close(out, len, indent, '}');
+            read_terminator(reader)?;
+        }
+        Type::Struct(schema) => write_struct(out, reader, schema, indent)?,
+    }
+    Ok(())
+}
+
+fn write_struct(out: &mut String, reader: &mut &[u8], schema: &Schema, indent: Option<usize>) -> Result<()> {
+    out.push('{');
+    for (i, field) in schema.fields().iter().enumerate() {
+        separator(out, i, indent);
+        write!(out, "{}: ", field.name).unwrap();
+        if field.sensitive {
+            skip_type(reader, &field.ty)?;
+            out.push_str(REDACTED);
+        } else {
+            write_value(out, reader, &field.ty, indent.map(|d| d + 1))?;
+        }
+    }
+    close(out, schema.fields().len(), indent, '}');
+    Ok(())
+}
+
+/// Starts the `i`-th element of a collection.
+fn separator(out: &mut String, i: usize, indent: Option<usize>) {
+    match indent {
+        Some(depth) => {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push('\n');
+            push_indent(out, depth + 1);
+        }
+        None if i > 0 => out.push_str(", "),
+        None => {}
+    }
+}
+
+/// Closes a collection of `len` elements.
+fn close(out: &mut String, len: usize, indent: Option<usize>, delimiter: char) {
+    if let (Some(depth), true) = (indent, len > 0) {
+        out.push('\n');
+        push_indent(out, depth);
+    }
+    out.push(delimiter);
+}
+
+fn push_indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+}
+
+/// Pretty-prints a marshalled message described by the schema, one field per line.
+///
+/// Returns a `TrailingBytes` error if the message is followed by unconsumed bytes.
+pub fn dump(schema: &Schema, buf: &[u8]) -> Result<String> {
+    let mut reader = buf;
+    let mut out = String::new();
+    write_struct(&mut out, &mut reader, schema, Some(0))?;
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(out)
+}
+
+/// Describes how a marshalled message is laid out: one line per top-level field with
+/// its byte range, name, type, value and raw bytes.
+///
+/// Returns a `TrailingBytes` error if the message is followed by unconsumed bytes.
+pub fn explain(schema: &Schema, buf: &[u8]) -> Result<String> {
+    let mut reader = buf;
+    let mut out = String::new();
+    for field in schema.fields() {
+        let start = buf.len() - reader.len();
+        let mut value = String::new();
+        if field.sensitive {
+            skip_type(&mut reader, &field.ty)?;
+        } else {
+            write_value(&mut value, &mut reader, &field.ty, None)?;
+        }
+        let end = buf.len() - reader.len();
+        write!(out, "{start:04x}..{end:04x}  {}: {} = ", field.name, field.ty).unwrap();
+        if field.sensitive {
+            writeln!(out, "{REDACTED}").unwrap();
+            continue;
+        }
+        write!(out, "{value}  [").unwrap();
+        for (i, b) in buf[start..end].iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            write!(out, "{b:02x}").unwrap();
+        }
+        out.push_str("]\n");
+    }
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(out)
+}
@@ -0,0 +1,88 @@
+//! Support for `either::Either`, enabled by the `either` feature.
+//!
+//! An `Either<L, R>` is marshalled as a one-byte tag, `0` for `Left` and `1` for
+//! `Right`, followed by the marshalled value. `Either` also implements the encoding
+//! traits, so structs defined with [`benc_struct!`](crate::benc_struct) can hold it.
+
+use either::Either;
+
+use crate::{BencDecode, BencEncode, Error, Result, marshal_u8, size_u8, unmarshal_u8};
+
+const TAG_LEFT: u8 = 0;
+const TAG_RIGHT: u8 = 1;
+
+/// Returns the number of bytes required to marshal an `Either<L, R>`.
+pub fn size_either<L, R>(v: &Either<L, R>, l_sizer: impl Fn(&L) -> usize, r_sizer: impl Fn(&R) -> usize) -> usize {
+    size_u8() + v.as_ref().either(l_sizer, r_sizer)
+}
+
+/// Marshals an `Either<L, R>` into the writer, as a tag byte followed by the value.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_either<L, R, E: From<Error>>(
+    v: &Either<L, R>,
+    writer: &mut &mut [u8],
+    l_marshaler: impl Fn(&L, &mut &mut [u8]) -> Result<(), E>,
+    r_marshaler: impl Fn(&R, &mut &mut [u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    match v {
+        Either::Left(l) => {
+            marshal_u8(TAG_LEFT, writer)?;
+            l_marshaler(l, writer)
+        }
+        Either::Right(r) => {
+            marshal_u8(TAG_RIGHT, writer)?;
+            r_marshaler(r, writer)
+        }
+    }
+}
+
+/// Unmarshals an `Either<L, R>` from the reader.
+///
+/// Returns an `InvalidValue` error if the tag is neither `0` nor `1`.
+pub fn unmarshal_either<'a, L, R, E: From<Error>>(
+    reader: &mut &'a [u8],
+    l_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<L, E>,
+    r_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<R, E>,
+) -> Result<Either<L, R>, E> {
+    match unmarshal_u8(reader)? {
+        TAG_LEFT => l_unmarshaler(reader).map(Either::Left),
+        TAG_RIGHT => r_unmarshaler(reader).map(Either::Right),
+        _ => Err(Error::InvalidValue.into()),
+    }
+}
+
+/// Skips over a marshalled `Either<L, R>` in the reader.
+///
+/// Returns an `InvalidValue` error if the tag is neither `0` nor `1`.
+pub fn skip_either<E: From<Error>>(
+    reader: &mut &[u8],
+    skip_left: impl Fn(&mut &[u8]) -> Result<(), E>,
+    skip_right: impl Fn(&mut &[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    match unmarshal_u8(reader)? {
+        TAG_LEFT => skip_left(reader),
+        TAG_RIGHT => skip_right(reader),
+        _ => Err(Error::InvalidValue.into()),
+    }
+}
+
+impl<L: BencEncode, R: BencEncode> BencEncode for Either<L, R> {
+    fn size(&self) -> usize {
+        size_either(self, L::size, R::size)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_either(self, writer, L::marshal, R::marshal)
+    }
+}
+
+impl<'a, L: BencDecode<'a>, R: BencDecode<'a>> BencDecode<'a> for Either<L, R> {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        unmarshal_either(reader, L::unmarshal, R::unmarshal)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_either(reader, L::skip, R::skip)
+    }
+}
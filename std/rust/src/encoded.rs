@@ -0,0 +1,218 @@
+//! Typed wrappers around still-marshalled messages.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::{Error, Result, Schema, field_ranges, skip_type, unmarshal_bytes_cropped, unmarshal_string};
+
+// ===================================================================================
+// Encoded
+// ===================================================================================
+
+/// The marshalled bytes of a `T`, kept encoded until they are needed.
+///
+/// The type parameter ties the bytes to the type they were marshalled from, so a
+/// message can be handed between subsystems without being decoded (or decoded twice)
+/// and without losing track of what it holds. The buffer `B` is any owned byte
+/// container, such as `Vec<u8>` or `bytes::Bytes`.
+pub struct Encoded<T, B = Vec<u8>> {
+    buf: B,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Encoded<T> {
+    /// Marshals a value into a new buffer of `size` bytes, as returned by the
+    /// matching sizer.
+    ///
+    /// Returns an error if the marshaler fails.
+    pub fn encode(size: usize, marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>) -> Result<Self> {
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshaler(&mut writer)?;
+        let unused = writer.len();
+        buf.truncate(size - unused);
+        Ok(Self::new(buf))
+    }
+}
+
+impl<T, B: AsRef<[u8]>> Encoded<T, B> {
+    /// Wraps bytes that hold a marshalled `T`. The bytes are not checked.
+    pub fn new(buf: B) -> Self {
+        Encoded { buf, _marker: PhantomData }
+    }
+
+    /// Returns the marshalled bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buf.as_ref()
+    }
+
+    /// Returns a reference to the underlying buffer.
+    pub fn as_inner(&self) -> &B {
+        &self.buf
+    }
+
+    /// Returns the underlying buffer.
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+
+    /// Returns the SHA-256 digest of the marshalled bytes, which equals the
+    /// [`content_hash`](crate::content_hash) of the value they were marshalled from if
+    /// they are in the canonical profile.
+    #[cfg(feature = "sha2")]
+    pub fn hash(&self) -> [u8; crate::CONTENT_HASH_LEN] {
+        crate::content_hash::hash_bytes(self.as_bytes())
+    }
+
+    /// Unmarshals the whole message with its unmarshaler.
+    ///
+    /// Returns a `TrailingBytes` error if the unmarshaler does not consume every byte.
+    pub fn decode<'a>(&'a self, unmarshaler: impl FnOnce(&mut &'a [u8]) -> Result<T>) -> Result<T> {
+        let mut reader = self.buf.as_ref();
+        let value = unmarshaler(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(value)
+    }
+
+    /// Unmarshals a single top-level field, skipping only the fields before it.
+    ///
+    /// Returns an `OutOfRange` error if the schema has no field with the given name.
+    pub fn decode_field<'a, F>(
+        &'a self,
+        schema: &Schema,
+        name: &str,
+        unmarshaler: impl FnOnce(&mut &'a [u8]) -> Result<F>,
+    ) -> Result<F> {
+        let index = schema.index_of(name).ok_or(Error::OutOfRange)?;
+        let mut reader = self.buf.as_ref();
+        for field in &schema.fields()[..index] {
+            skip_type(&mut reader, &field.ty)?;
+        }
+        unmarshaler(&mut reader)
+    }
+}
+
+impl<T, B: Clone> Clone for Encoded<T, B> {
+    fn clone(&self) -> Self {
+        Encoded { buf: self.buf.clone(), _marker: PhantomData }
+    }
+}
+
+impl<T, B: AsRef<[u8]>> fmt::Debug for Encoded<T, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encoded")
+            .field("type", &std::any::type_name::<T>())
+            .field("len", &self.buf.as_ref().len())
+            .finish()
+    }
+}
+
+impl<T, B: AsRef<[u8]>> PartialEq for Encoded<T, B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<T, B: AsRef<[u8]>> Eq for Encoded<T, B> {}
+
+impl<T, B: AsRef<[u8]>> AsRef<[u8]> for Encoded<T, B> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+// ===================================================================================
+// SharedMessage
+// ===================================================================================
+
+/// An immutable marshalled `T` behind a reference count, with the location of every
+/// top-level field recorded up front.
+///
+/// Cloning is cheap and clones share the same bytes, so a single received message can
+/// be handed to many threads, each decoding only the fields it needs. Strings and byte
+/// slices are borrowed from the shared buffer without copying.
+pub struct SharedMessage<T> {
+    buf: Arc<[u8]>,
+    fields: Arc<[Range<usize>]>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> SharedMessage<T> {
+    /// Takes ownership of a marshalled message described by the schema and records
+    /// where each of its fields is.
+    ///
+    /// Returns an error if the message does not match the schema, including a
+    /// `TrailingBytes` error if it is followed by unconsumed bytes.
+    pub fn new(buf: impl Into<Arc<[u8]>>, schema: &Schema) -> Result<Self> {
+        let buf = buf.into();
+        let fields = field_ranges(&buf, schema)?.into();
+        Ok(SharedMessage { buf, fields, _marker: PhantomData })
+    }
+
+    /// Returns the marshalled bytes of the whole message.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the number of top-level fields.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Returns `true` if the message has no fields.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the marshalled bytes of the field at the given index.
+    pub fn field(&self, index: usize) -> Option<&[u8]> {
+        self.fields.get(index).map(|range| &self.buf[range.clone()])
+    }
+
+    /// Unmarshals the field at the given index with its unmarshaler.
+    ///
+    /// Returns an `OutOfRange` error if there is no such field, and a `TrailingBytes`
+    /// error if the unmarshaler does not consume the whole field.
+    pub fn decode_field<'a, F>(
+        &'a self,
+        index: usize,
+        unmarshaler: impl FnOnce(&mut &'a [u8]) -> Result<F>,
+    ) -> Result<F> {
+        let mut reader = self.field(index).ok_or(Error::OutOfRange)?;
+        let value = unmarshaler(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(value)
+    }
+
+    /// Returns the string field at the given index, borrowed from the shared buffer.
+    pub fn string(&self, index: usize) -> Result<&str> {
+        self.decode_field(index, unmarshal_string)
+    }
+
+    /// Returns the byte slice field at the given index, borrowed from the shared buffer.
+    pub fn bytes(&self, index: usize) -> Result<&[u8]> {
+        self.decode_field(index, unmarshal_bytes_cropped)
+    }
+}
+
+impl<T> Clone for SharedMessage<T> {
+    fn clone(&self) -> Self {
+        SharedMessage { buf: self.buf.clone(), fields: self.fields.clone(), _marker: PhantomData }
+    }
+}
+
+impl<T> fmt::Debug for SharedMessage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedMessage")
+            .field("type", &std::any::type_name::<T>())
+            .field("len", &self.buf.len())
+            .field("fields", &self.fields.len())
+            .finish()
+    }
+}
@@ -0,0 +1,156 @@
+//! Sentinel-terminated byte streams, for data whose length is not known upfront.
+//!
+//! Instead of a length prefix, the payload is followed by a sentinel byte. Sentinel
+//! and escape bytes inside the payload are replaced by two-byte escape sequences, in
+//! the style of SLIP (RFC 1055):
+//!
+//! - `0xC0` (sentinel) is written as `0xDB 0xDC`,
+//! - `0xDB` (escape) is written as `0xDB 0xDD`.
+//!
+//! [`EscapedWriter`] produces a stream incrementally from any `io::Write`, which
+//! suits piping from non-seekable sources.
+
+use std::borrow::Cow;
+use std::io::{self, BufRead, Write};
+
+use crate::{Error, Result, advance, write_to_slice};
+
+const SENTINEL: u8 = 0xC0;
+const ESCAPE: u8 = 0xDB;
+const ESCAPED_SENTINEL: u8 = 0xDC;
+const ESCAPED_ESCAPE: u8 = 0xDD;
+
+/// Returns the escape sequence for a byte, if it needs one.
+#[inline]
+fn escape(b: u8) -> Option<[u8; 2]> {
+    match b {
+        SENTINEL => Some([ESCAPE, ESCAPED_SENTINEL]),
+        ESCAPE => Some([ESCAPE, ESCAPED_ESCAPE]),
+        _ => None,
+    }
+}
+
+/// Returns the byte an escape sequence stands for.
+#[inline]
+fn unescape(b: u8) -> Result<u8> {
+    match b {
+        ESCAPED_SENTINEL => Ok(SENTINEL),
+        ESCAPED_ESCAPE => Ok(ESCAPE),
+        _ => Err(Error::InvalidValue),
+    }
+}
+
+/// Returns the position of the sentinel that ends the stream at the start of the reader.
+fn find_sentinel(reader: &[u8]) -> Result<usize> {
+    reader
+        .iter()
+        .position(|&b| b == SENTINEL)
+        .ok_or(Error::BufferTooSmall { needed: reader.len() + 1, available: reader.len() })
+}
+
+/// Returns the number of bytes required to marshal a payload as an escaped stream.
+pub fn size_escaped(data: &[u8]) -> usize {
+    let escapes = data.iter().filter(|&&b| escape(b).is_some()).count();
+    data.len() + escapes + 1
+}
+
+/// Marshals a payload into the writer as an escaped, sentinel-terminated stream.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_escaped(data: &[u8], writer: &mut &mut [u8]) -> Result<()> {
+    for chunk in data.split_inclusive(|&b| escape(b).is_some()) {
+        let (last, rest) = chunk.split_last().unwrap();
+        match escape(*last) {
+            Some(seq) => {
+                write_to_slice(writer, rest)?;
+                write_to_slice(writer, &seq)?;
+            }
+            None => write_to_slice(writer, chunk)?,
+        }
+    }
+    write_to_slice(writer, &[SENTINEL])
+}
+
+/// Unmarshals an escaped stream from the reader. The payload is borrowed from the
+/// input buffer when it contains no escape sequences.
+///
+/// Returns a `BufferTooSmall` error if the sentinel is missing and an `InvalidValue`
+/// error on an invalid escape sequence.
+pub fn unmarshal_escaped<'a>(reader: &mut &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    let end = find_sentinel(reader)?;
+    let raw = advance(reader, end + 1)?;
+    let raw = &raw[..end];
+    if !raw.contains(&ESCAPE) {
+        return Ok(Cow::Borrowed(raw));
+    }
+    let mut data = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter();
+    while let Some(&b) = bytes.next() {
+        if b == ESCAPE {
+            data.push(unescape(*bytes.next().ok_or(Error::InvalidValue)?)?);
+        } else {
+            data.push(b);
+        }
+    }
+    Ok(Cow::Owned(data))
+}
+
+/// Skips over an escaped stream in the reader.
+pub fn skip_escaped(reader: &mut &[u8]) -> Result<()> {
+    let end = find_sentinel(reader)?;
+    advance(reader, end + 1)?;
+    Ok(())
+}
+
+/// Reads and unescapes one stream from a buffered source, consuming the sentinel.
+///
+/// Returns an `UnexpectedEof` error if the source ends before the sentinel.
+pub fn read_escaped<R: BufRead>(source: &mut R) -> io::Result<Vec<u8>> {
+    let mut raw = Vec::new();
+    source.read_until(SENTINEL, &mut raw)?;
+    if raw.pop() != Some(SENTINEL) {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    raw.push(SENTINEL);
+    Ok(unmarshal_escaped(&mut raw.as_slice())?.into_owned())
+}
+
+/// Writes an escaped stream incrementally.
+///
+/// Bytes written through the `io::Write` implementation are escaped and passed to the
+/// inner writer; `finish` writes the sentinel. A stream that is never finished is
+/// incomplete.
+#[derive(Debug)]
+pub struct EscapedWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> EscapedWriter<W> {
+    /// Starts a new stream on the inner writer.
+    pub fn new(inner: W) -> Self {
+        EscapedWriter { inner }
+    }
+
+    /// Terminates the stream and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(&[SENTINEL])?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EscapedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(pos) = buf.iter().position(|&b| escape(b).is_some()) else {
+            return self.inner.write(buf);
+        };
+        if pos > 0 {
+            return self.inner.write(&buf[..pos]);
+        }
+        self.inner.write_all(&escape(buf[0]).unwrap())?;
+        Ok(1)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
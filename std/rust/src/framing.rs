@@ -0,0 +1,671 @@
+//! Sans-IO framing and fragmentation.
+//!
+//! The types here hold the protocol logic of framed messaging without performing any
+//! I/O: bytes received from a transport are fed in, and complete frames, or bytes to
+//! send, come out. `DecodeIter`, `BencStream` and the RPC layer are thin wrappers over
+//! them for blocking and async readers, and a runtime they do not cover, such as
+//! io_uring or an embedded executor, can drive the same state machines directly.
+//!
+//! A frame is the varint byte length of a message followed by the message, the layout
+//! of `marshal_bytes`. A fragment, for transports that limit the size of a packet, is
+//! the varint id of the message, the varint index of the fragment, the varint number
+//! of fragments and the fragment's part of the message as a byte slice.
+//!
+//! A frame may start with a [`FrameHeader`] of routing metadata, so transports can
+//! prioritize, expire or trace messages without decoding them. The header is a flags
+//! byte telling which of the optional fields follow, each as fixed bytes: the
+//! priority as a `u8`, the time to live in milliseconds as a `u32` and the trace id as
+//! 16 bytes. Flag bits without a field are reserved and must be zero, so peers notice
+//! a header they do not understand.
+//!
+//! Over unreliable datagrams, such as UDP or QUIC datagrams, [`DatagramSender`] numbers
+//! messages in sequence and fragments them, and [`DatagramReceiver`] reassembles them,
+//! drops duplicates and reports the messages that will not complete.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io;
+
+use crate::{
+    BencDecode, BencEncode, DEFAULT_MAX_FRAME_LEN, Error, Result, advance, marshal_bytes, marshal_u8, marshal_u32,
+    marshal_uint, marshal_usize, size_bytes, size_u8, size_u32, size_uint, size_usize, unmarshal_bytes_cropped,
+    unmarshal_u8, unmarshal_u32, unmarshal_uint, unmarshal_usize, write_to_slice,
+};
+
+// ===================================================================================
+// Frames
+// ===================================================================================
+
+/// Splits received bytes into frames.
+///
+/// Bytes are added with [`feed`](Self::feed), or read directly into the decoder with
+/// [`read_buf`](Self::read_buf) and [`commit`](Self::commit), and complete frames are
+/// taken out with [`next_frame`](Self::next_frame).
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    pos: usize,
+    filled: usize,
+    max_frame_len: usize,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameDecoder {
+    /// Creates a decoder accepting frames of up to [`DEFAULT_MAX_FRAME_LEN`] bytes.
+    pub fn new() -> Self {
+        FrameDecoder { buf: Vec::new(), pos: 0, filled: 0, max_frame_len: DEFAULT_MAX_FRAME_LEN }
+    }
+
+    /// Sets the largest frame length the decoder accepts, which bounds the memory a
+    /// peer can make it allocate.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Returns the number of received bytes that are not part of a returned frame.
+    pub fn buffered(&self) -> usize {
+        self.filled - self.pos
+    }
+
+    /// Returns a buffer of `len` bytes to read received bytes into, which are added to
+    /// the decoder by a following call to `commit`.
+    pub fn read_buf(&mut self, len: usize) -> &mut [u8] {
+        if self.pos > 0 {
+            self.buf.copy_within(self.pos..self.filled, 0);
+            self.filled -= self.pos;
+            self.pos = 0;
+        }
+        if self.buf.len() < self.filled + len {
+            self.buf.resize(self.filled + len, 0);
+        }
+        &mut self.buf[self.filled..self.filled + len]
+    }
+
+    /// Adds the first `n` bytes of the buffer last returned by `read_buf`.
+    ///
+    /// Panics if `n` is larger than that buffer.
+    pub fn commit(&mut self, n: usize) {
+        assert!(self.filled + n <= self.buf.len(), "committed more bytes than read_buf returned");
+        self.filled += n;
+    }
+
+    /// Adds received bytes.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.read_buf(data.len()).copy_from_slice(data);
+        self.commit(data.len());
+    }
+
+    /// Returns the next complete frame, or `None` if more bytes are needed.
+    ///
+    /// Returns an `InvalidData` error if the frame header is malformed or the frame
+    /// is longer than the limit. The stream cannot be resynchronized after that.
+    pub fn next_frame(&mut self) -> io::Result<Option<&[u8]>> {
+        let mut reader = &self.buf[self.pos..self.filled];
+        let len = match unmarshal_usize(&mut reader) {
+            Ok(len) => len,
+            Err(Error::BufferTooSmall { .. }) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        if len > self.max_frame_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame of {len} bytes exceeds the limit of {} bytes", self.max_frame_len),
+            ));
+        }
+        if reader.len() < len {
+            return Ok(None);
+        }
+        let start = self.filled - reader.len();
+        self.pos = start + len;
+        Ok(Some(&self.buf[start..start + len]))
+    }
+
+    /// Checks that the peer closed the stream between frames.
+    ///
+    /// Returns an `UnexpectedEof` error if a frame was cut short.
+    pub fn finish(&self) -> io::Result<()> {
+        if self.buffered() > 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    /// Discards all buffered bytes.
+    pub fn clear(&mut self) {
+        self.buf = Vec::new();
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+/// Collects frames to send.
+///
+/// Frames are appended with [`encode`](Self::encode), and the transport sends the
+/// bytes returned by [`pending`](Self::pending) and reports how many it sent with
+/// [`consume`](Self::consume).
+#[derive(Debug, Clone, Default)]
+pub struct FrameEncoder {
+    buf: Vec<u8>,
+    sent: usize,
+}
+
+impl FrameEncoder {
+    /// Creates an encoder with nothing to send.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a frame holding the message.
+    ///
+    /// Returns an error, leaving the pending bytes unchanged, if the message fails to
+    /// marshal.
+    pub fn encode<T: BencEncode + ?Sized>(&mut self, message: &T) -> Result<()> {
+        let size = message.size();
+        let start = self.buf.len();
+        self.buf.resize(start + size_usize(size) + size, 0);
+        let mut writer = &mut self.buf[start..];
+        let result = marshal_usize(size, &mut writer).and_then(|()| message.marshal(&mut writer));
+        if result.is_err() {
+            self.buf.truncate(start);
+        }
+        result
+    }
+
+    /// Appends a frame holding the header followed by the message.
+    ///
+    /// Returns an error, leaving the pending bytes unchanged, if the message fails to
+    /// marshal.
+    pub fn encode_with_header<T: BencEncode + ?Sized>(&mut self, header: &FrameHeader, message: &T) -> Result<()> {
+        let size = header.size() + message.size();
+        let start = self.buf.len();
+        self.buf.resize(start + size_usize(size) + size, 0);
+        let mut writer = &mut self.buf[start..];
+        let result = marshal_usize(size, &mut writer)
+            .and_then(|()| header.marshal(&mut writer))
+            .and_then(|()| message.marshal(&mut writer));
+        if result.is_err() {
+            self.buf.truncate(start);
+        }
+        result
+    }
+
+    /// Appends a frame holding an already marshalled message.
+    pub fn encode_bytes(&mut self, message: &[u8]) {
+        let start = self.buf.len();
+        self.buf.resize(start + size_bytes(message), 0);
+        // The buffer was sized for the frame.
+        marshal_bytes(message, &mut &mut self.buf[start..]).unwrap();
+    }
+
+    /// Returns the bytes waiting to be sent.
+    pub fn pending(&self) -> &[u8] {
+        &self.buf[self.sent..]
+    }
+
+    /// Marks the first `n` pending bytes as sent.
+    ///
+    /// Panics if `n` is larger than the number of pending bytes.
+    pub fn consume(&mut self, n: usize) {
+        assert!(n <= self.pending().len(), "consumed more bytes than are pending");
+        self.sent += n;
+        if self.sent == self.buf.len() {
+            self.buf.clear();
+            self.sent = 0;
+        }
+    }
+
+    /// Returns `true` if there is nothing to send.
+    pub fn is_empty(&self) -> bool {
+        self.pending().is_empty()
+    }
+}
+
+// ===================================================================================
+// Frame headers
+// ===================================================================================
+
+const FLAG_PRIORITY: u8 = 1 << 0;
+const FLAG_TTL: u8 = 1 << 1;
+const FLAG_TRACE_ID: u8 = 1 << 2;
+const KNOWN_FLAGS: u8 = FLAG_PRIORITY | FLAG_TTL | FLAG_TRACE_ID;
+
+/// The number of bytes of a trace id in a `FrameHeader`.
+pub const TRACE_ID_LEN: usize = 16;
+
+/// Routing metadata at the start of a frame, for transports that handle messages
+/// without decoding them. Fields that are `None` take no space.
+///
+/// Decoding a header with a reserved flag bit set is an `InvalidValue` error.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FrameHeader {
+    /// The priority of the message; higher values are more urgent.
+    pub priority: Option<u8>,
+    /// The number of milliseconds after which the message is no longer useful.
+    pub ttl_ms: Option<u32>,
+    /// The id of the trace the message belongs to, such as a W3C trace id.
+    pub trace_id: Option<[u8; TRACE_ID_LEN]>,
+}
+
+impl FrameHeader {
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.priority.is_some() {
+            flags |= FLAG_PRIORITY;
+        }
+        if self.ttl_ms.is_some() {
+            flags |= FLAG_TTL;
+        }
+        if self.trace_id.is_some() {
+            flags |= FLAG_TRACE_ID;
+        }
+        flags
+    }
+}
+
+impl BencEncode for FrameHeader {
+    fn size(&self) -> usize {
+        size_u8()
+            + self.priority.map_or(0, |_| size_u8())
+            + self.ttl_ms.map_or(0, |_| size_u32())
+            + self.trace_id.map_or(0, |_| TRACE_ID_LEN)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_u8(self.flags(), writer)?;
+        if let Some(priority) = self.priority {
+            marshal_u8(priority, writer)?;
+        }
+        if let Some(ttl_ms) = self.ttl_ms {
+            marshal_u32(ttl_ms, writer)?;
+        }
+        if let Some(trace_id) = &self.trace_id {
+            write_to_slice(writer, trace_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> BencDecode<'a> for FrameHeader {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        let flags = unmarshal_u8(reader)?;
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(Error::InvalidValue);
+        }
+        let priority = if flags & FLAG_PRIORITY != 0 { Some(unmarshal_u8(reader)?) } else { None };
+        let ttl_ms = if flags & FLAG_TTL != 0 { Some(unmarshal_u32(reader)?) } else { None };
+        let trace_id = if flags & FLAG_TRACE_ID != 0 {
+            // `advance` returns exactly the requested number of bytes.
+            Some(advance(reader, TRACE_ID_LEN)?.try_into().unwrap())
+        } else {
+            None
+        };
+        Ok(FrameHeader { priority, ttl_ms, trace_id })
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        Self::unmarshal(reader).map(|_| ())
+    }
+}
+
+/// Splits a frame written by `FrameEncoder::encode_with_header` into its header and
+/// the marshalled message.
+///
+/// Returns an `InvalidValue` error if a reserved flag bit is set.
+pub fn split_frame_header(frame: &[u8]) -> Result<(FrameHeader, &[u8])> {
+    let mut reader = frame;
+    let header = FrameHeader::unmarshal(&mut reader)?;
+    Ok((header, reader))
+}
+
+// ===================================================================================
+// Fragments
+// ===================================================================================
+
+/// The default limit on the number of messages `Reassembler` holds fragments of.
+pub const DEFAULT_MAX_PENDING_MESSAGES: usize = 64;
+
+/// Splits a message into fragments of at most `max_payload_len` bytes of its data
+/// each, tagged with `message_id` so the receiver can tell messages apart. A
+/// `max_payload_len` of zero is treated as one. An empty message is a single fragment.
+pub fn fragment(message_id: u64, message: &[u8], max_payload_len: usize) -> Vec<Vec<u8>> {
+    let parts: Vec<&[u8]> = if message.is_empty() { vec![message] } else { message.chunks(max_payload_len.max(1)).collect() };
+    let count = parts.len();
+    parts
+        .iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let size = size_uint(message_id) + size_usize(index) + size_usize(count) + size_bytes(part);
+            let mut out = vec![0u8; size];
+            let mut writer = out.as_mut_slice();
+            // The buffer was sized for the fragment.
+            marshal_uint(message_id, &mut writer).unwrap();
+            marshal_usize(index, &mut writer).unwrap();
+            marshal_usize(count, &mut writer).unwrap();
+            marshal_bytes(part, &mut writer).unwrap();
+            out
+        })
+        .collect()
+}
+
+/// The fragments received of a message, by index.
+#[derive(Debug)]
+struct Partial {
+    parts: BTreeMap<usize, Vec<u8>>,
+    count: usize,
+    len: usize,
+}
+
+/// Reassembles messages from fragments that may arrive in any order.
+///
+/// To bound its memory, the reassembler drops the oldest incomplete message when
+/// fragments of more than the limit of messages are outstanding, and rejects messages
+/// longer than the frame limit.
+#[derive(Debug)]
+pub struct Reassembler {
+    partial: HashMap<u64, Partial>,
+    order: VecDeque<u64>,
+    max_pending: usize,
+    max_message_len: usize,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reassembler {
+    /// Creates a reassembler holding fragments of up to
+    /// [`DEFAULT_MAX_PENDING_MESSAGES`] messages of up to [`DEFAULT_MAX_FRAME_LEN`]
+    /// bytes.
+    pub fn new() -> Self {
+        Reassembler {
+            partial: HashMap::new(),
+            order: VecDeque::new(),
+            max_pending: DEFAULT_MAX_PENDING_MESSAGES,
+            max_message_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    /// Sets the number of incomplete messages held before the oldest is dropped.
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending.max(1);
+        self
+    }
+
+    /// Sets the largest message length the reassembler accepts.
+    pub fn with_max_message_len(mut self, max_message_len: usize) -> Self {
+        self.max_message_len = max_message_len;
+        self
+    }
+
+    /// Returns the number of messages of which some fragments have been received.
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// Adds a fragment, returning the id and data of the message it completes.
+    ///
+    /// Returns an `InvalidValue` error if the fragment does not match the fragments
+    /// received of its message before or is an empty part of a message of several
+    /// fragments, and an `OutOfRange` error if the message is longer than the limit or
+    /// claims more fragments than the limit allows. A repeated fragment is ignored.
+    pub fn push(&mut self, fragment: &[u8]) -> Result<Option<(u64, Vec<u8>)>> {
+        let mut reader = fragment;
+        let id = unmarshal_uint(&mut reader)?;
+        let index = unmarshal_usize(&mut reader)?;
+        let count = unmarshal_usize(&mut reader)?;
+        let part = unmarshal_bytes_cropped(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+        if index >= count {
+            return Err(Error::InvalidValue);
+        }
+        // Every fragment of a message of several fragments carries at least a byte, so
+        // the count is bounded by the message length and the fragments held by it.
+        if count > self.max_message_len.max(1) {
+            return Err(Error::OutOfRange);
+        }
+
+        if count == 1 {
+            if part.len() > self.max_message_len {
+                return Err(Error::OutOfRange);
+            }
+            return Ok(Some((id, part.to_vec())));
+        }
+        if part.is_empty() {
+            return Err(Error::InvalidValue);
+        }
+        if !self.partial.contains_key(&id) {
+            if self.partial.len() >= self.max_pending
+                && let Some(oldest) = self.order.pop_front()
+            {
+                self.partial.remove(&oldest);
+            }
+            self.order.push_back(id);
+            self.partial.insert(id, Partial { parts: BTreeMap::new(), count, len: 0 });
+        }
+        // Inserted above if it was missing.
+        let partial = self.partial.get_mut(&id).unwrap();
+        if partial.count != count {
+            return Err(Error::InvalidValue);
+        }
+        if partial.parts.contains_key(&index) {
+            return Ok(None);
+        }
+        partial.len += part.len();
+        if partial.len > self.max_message_len {
+            self.remove(id);
+            return Err(Error::OutOfRange);
+        }
+        partial.parts.insert(index, part.to_vec());
+        if partial.parts.len() < count {
+            return Ok(None);
+        }
+
+        let partial = self.remove(id);
+        let mut message = Vec::with_capacity(partial.len);
+        for part in partial.parts.into_values() {
+            message.extend_from_slice(&part);
+        }
+        Ok(Some((id, message)))
+    }
+
+    fn remove(&mut self, id: u64) -> Partial {
+        self.order.retain(|&other| other != id);
+        // Only called for ids that are present.
+        self.partial.remove(&id).unwrap()
+    }
+}
+
+// ===================================================================================
+// Datagrams
+// ===================================================================================
+
+/// The number of most recent sequence numbers `DatagramReceiver` remembers, for
+/// duplicate detection.
+pub const DATAGRAM_WINDOW: u64 = 64;
+
+/// Fragments messages for an unreliable datagram transport, numbering them in
+/// sequence.
+///
+/// The sequence number of a message is the message id of its fragments, so a
+/// [`DatagramReceiver`] can tell duplicates and losses apart.
+#[derive(Debug, Clone)]
+pub struct DatagramSender {
+    next_seq: u64,
+    max_payload_len: usize,
+}
+
+impl DatagramSender {
+    /// Creates a sender of fragments holding at most `max_payload_len` bytes of a
+    /// message each, starting at sequence number 0.
+    pub fn new(max_payload_len: usize) -> Self {
+        DatagramSender { next_seq: 0, max_payload_len }
+    }
+
+    /// Returns the sequence number of the next message.
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Returns the datagrams of an already marshalled message.
+    pub fn send(&mut self, message: &[u8]) -> Vec<Vec<u8>> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        fragment(seq, message, self.max_payload_len)
+    }
+
+    /// Returns the datagrams of a message.
+    ///
+    /// Returns an error if the message fails to marshal; no sequence number is used.
+    pub fn encode<T: BencEncode + ?Sized>(&mut self, message: &T) -> Result<Vec<Vec<u8>>> {
+        let mut buf = vec![0u8; message.size()];
+        message.marshal(&mut buf.as_mut_slice())?;
+        Ok(self.send(&buf))
+    }
+}
+
+/// Reassembles messages from datagrams that may be lost, repeated or reordered.
+///
+/// The receiver remembers which of the last [`DATAGRAM_WINDOW`] sequence numbers,
+/// counted back from the highest delivered one, it has delivered, and ignores
+/// datagrams of those and of older messages. A message of which some fragments were
+/// received is lost when it falls out of the window before it completes, or when its
+/// fragments are dropped because too many messages are incomplete; its sequence
+/// number is then reported by [`take_incomplete`](Self::take_incomplete).
+#[derive(Debug, Default)]
+pub struct DatagramReceiver {
+    reassembler: Reassembler,
+    /// The highest delivered sequence number.
+    highest: Option<u64>,
+    /// Bit `i` is set if the sequence number `highest - i` has been delivered.
+    delivered: u64,
+    incomplete: Vec<u64>,
+    duplicates: u64,
+}
+
+impl DatagramReceiver {
+    /// Creates a receiver that has not delivered any message.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of incomplete messages held before the oldest is dropped.
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.reassembler = self.reassembler.with_max_pending(max_pending);
+        self
+    }
+
+    /// Sets the largest message length the receiver accepts.
+    pub fn with_max_message_len(mut self, max_message_len: usize) -> Self {
+        self.reassembler = self.reassembler.with_max_message_len(max_message_len);
+        self
+    }
+
+    /// Returns the sequence numbers of the messages of which some, but not all,
+    /// fragments have been received, in ascending order.
+    pub fn pending(&self) -> Vec<u64> {
+        let mut seqs: Vec<u64> = self.reassembler.partial.keys().copied().collect();
+        seqs.sort_unstable();
+        seqs
+    }
+
+    /// Returns the sequence numbers of the messages that were lost with only some of
+    /// their fragments received or rejected as longer than the limit, since the last
+    /// call.
+    pub fn take_incomplete(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.incomplete)
+    }
+
+    /// Returns the number of datagrams ignored because their message had already been
+    /// delivered or was older than the window.
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// Returns `true` if the message with the given sequence number has been
+    /// delivered or is older than the window.
+    fn is_done(&self, seq: u64) -> bool {
+        match self.highest {
+            Some(highest) if seq <= highest => {
+                let age = highest - seq;
+                age >= DATAGRAM_WINDOW || self.delivered & (1 << age) != 0
+            }
+            _ => false,
+        }
+    }
+
+    /// Marks a message as delivered, sliding the window forward if it is the newest.
+    fn deliver(&mut self, seq: u64) {
+        match self.highest {
+            Some(highest) if seq <= highest => {
+                self.delivered |= 1 << (highest - seq);
+                return;
+            }
+            Some(highest) => {
+                let shift = seq - highest;
+                self.delivered = if shift >= DATAGRAM_WINDOW { 0 } else { self.delivered << shift };
+                self.delivered |= 1;
+            }
+            None => self.delivered = 1,
+        }
+        self.highest = Some(seq);
+
+        // Incomplete messages older than the window are no longer accepted.
+        let floor = seq.saturating_sub(DATAGRAM_WINDOW - 1);
+        let mut stale: Vec<u64> = self.reassembler.partial.keys().copied().filter(|&other| other < floor).collect();
+        stale.sort_unstable();
+        for other in stale {
+            self.reassembler.remove(other);
+            self.incomplete.push(other);
+        }
+    }
+
+    /// Adds a datagram, returning the sequence number and data of the message it
+    /// completes.
+    ///
+    /// Returns the errors of [`Reassembler::push`]; a message longer than the limit is
+    /// reported as incomplete.
+    pub fn push(&mut self, datagram: &[u8]) -> Result<Option<(u64, Vec<u8>)>> {
+        let mut reader = datagram;
+        let seq = unmarshal_uint(&mut reader)?;
+        unmarshal_usize(&mut reader)?;
+        let count = unmarshal_usize(&mut reader)?;
+        if self.is_done(seq) {
+            self.duplicates += 1;
+            return Ok(None);
+        }
+        // A new incomplete message makes the reassembler drop the oldest when it is
+        // full.
+        let reassembler = &self.reassembler;
+        let evicted = (count > 1
+            && !reassembler.partial.contains_key(&seq)
+            && reassembler.partial.len() >= reassembler.max_pending)
+            .then(|| reassembler.order.front().copied())
+            .flatten();
+        let result = self.reassembler.push(datagram);
+        if let Some(evicted) = evicted
+            && !self.reassembler.partial.contains_key(&evicted)
+        {
+            self.incomplete.push(evicted);
+        }
+        match result {
+            Ok(Some((seq, message))) => {
+                self.deliver(seq);
+                Ok(Some((seq, message)))
+            }
+            Err(Error::OutOfRange) => {
+                self.incomplete.push(seq);
+                Err(Error::OutOfRange)
+            }
+            result => result,
+        }
+    }
+}
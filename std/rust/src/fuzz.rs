@@ -0,0 +1,214 @@
+//! Seed corpora for fuzzing decoders, enabled by the `fuzz` feature.
+//!
+//! Random bytes rarely get past the first length prefix of a message, so a fuzzer
+//! starting from nothing spends most of its time on shallow decode paths. A
+//! [`CorpusGenerator`] instead produces messages that follow a [`Schema`], then breaks
+//! them in the places decoders most often get wrong: it cuts them short at every
+//! boundary between values, corrupts the terminators of collections, inflates length
+//! prefixes and writes invalid option flags. The resulting inputs are written into a
+//! cargo-fuzz corpus directory with [`write_corpus`].
+//!
+//! Generation is seeded, so the same seed and schema always produce the same corpus.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{Schema, TERMINATOR, Type, Value, marshal_usize, marshal_value, size_usize, size_value};
+
+/// The default number of elements in generated slices and maps.
+pub const DEFAULT_MAX_COLLECTION_LEN: usize = 4;
+
+/// A position in a generated message that mutations target.
+#[derive(Debug, Clone, Copy)]
+enum Landmark {
+    /// The varint length of a collection, string or byte slice, spanning `len` bytes.
+    Length { offset: usize, len: usize },
+    /// The terminator of a slice or map.
+    Terminator { offset: usize },
+    /// The flag of an option.
+    Flag { offset: usize },
+    /// The start of a value.
+    Boundary { offset: usize },
+}
+
+/// Generates valid and mutated messages of a schema.
+pub struct CorpusGenerator<'s> {
+    schema: &'s Schema,
+    rng: StdRng,
+    max_collection_len: usize,
+}
+
+impl<'s> CorpusGenerator<'s> {
+    /// Creates a generator of messages of the schema, seeded with `seed`.
+    pub fn new(schema: &'s Schema, seed: u64) -> Self {
+        CorpusGenerator { schema, rng: StdRng::seed_from_u64(seed), max_collection_len: DEFAULT_MAX_COLLECTION_LEN }
+    }
+
+    /// Sets the largest number of elements in generated slices and maps, as well as
+    /// the largest length of generated strings and byte slices.
+    pub fn with_max_collection_len(mut self, max_collection_len: usize) -> Self {
+        self.max_collection_len = max_collection_len;
+        self
+    }
+
+    /// Returns a random valid message of the schema.
+    pub fn valid(&mut self) -> Vec<u8> {
+        self.message().0
+    }
+
+    /// Returns a random valid message of the schema followed by its mutations.
+    ///
+    /// The mutations are every prefix ending at a boundary between values, the message
+    /// with each terminator corrupted, with each length prefix inflated and with each
+    /// option flag set to an invalid value.
+    pub fn mutated(&mut self) -> Vec<Vec<u8>> {
+        let (buf, landmarks) = self.message();
+        let mut inputs = vec![buf.clone()];
+        for landmark in landmarks {
+            match landmark {
+                Landmark::Boundary { offset } => inputs.push(buf[..offset].to_vec()),
+                Landmark::Terminator { offset } => {
+                    let mut input = buf.clone();
+                    input[offset + self.rng.random_range(0..TERMINATOR.len())] ^= 0xff;
+                    inputs.push(input);
+                }
+                Landmark::Flag { offset } => {
+                    let mut input = buf.clone();
+                    input[offset] = 2;
+                    inputs.push(input);
+                }
+                Landmark::Length { offset, len } => {
+                    for inflated in [u32::MAX as usize, usize::MAX >> 1, buf.len()] {
+                        let mut input = buf[..offset].to_vec();
+                        append_usize(&mut input, inflated);
+                        input.extend_from_slice(&buf[offset + len..]);
+                        inputs.push(input);
+                    }
+                }
+            }
+        }
+        inputs
+    }
+
+    /// Returns `count` rounds of [`mutated`](Self::mutated) inputs, sorted and without
+    /// duplicates.
+    pub fn corpus(&mut self, count: usize) -> Vec<Vec<u8>> {
+        let mut inputs: Vec<_> = (0..count).flat_map(|_| self.mutated()).collect();
+        inputs.sort();
+        inputs.dedup();
+        inputs
+    }
+
+    fn message(&mut self) -> (Vec<u8>, Vec<Landmark>) {
+        let mut out = Vec::new();
+        let mut landmarks = Vec::new();
+        self.gen_fields(self.schema, &mut out, &mut landmarks);
+        (out, landmarks)
+    }
+
+    fn gen_fields(&mut self, schema: &Schema, out: &mut Vec<u8>, landmarks: &mut Vec<Landmark>) {
+        for field in schema.fields() {
+            self.gen_type(&field.ty, out, landmarks);
+        }
+    }
+
+    fn gen_type(&mut self, ty: &Type, out: &mut Vec<u8>, landmarks: &mut Vec<Landmark>) {
+        if !out.is_empty() {
+            landmarks.push(Landmark::Boundary { offset: out.len() });
+        }
+        let rng = &mut self.rng;
+        let value = match ty {
+            Type::Bool => Value::Bool(rng.random()),
+            Type::U8 => Value::U8(rng.random()),
+            Type::U16 => Value::U16(rng.random()),
+            Type::U32 => Value::U32(rng.random()),
+            Type::U64 => Value::U64(rng.random()),
+            Type::I8 => Value::I8(rng.random()),
+            Type::I16 => Value::I16(rng.random()),
+            Type::I32 => Value::I32(rng.random()),
+            Type::I64 => Value::I64(rng.random()),
+            Type::F32 => Value::F32(rng.random()),
+            Type::F64 => Value::F64(rng.random()),
+            // Varints of every length are equally likely.
+            Type::Uint => Value::Uint(rng.random::<u64>() >> rng.random_range(0..64)),
+            Type::Int => Value::Int(rng.random::<i64>() >> rng.random_range(0..64)),
+            Type::Time => Value::I64(rng.random()),
+            Type::String | Type::Bytes => {
+                let len = rng.random_range(0..=self.max_collection_len);
+                let bytes = (0..len).map(|_| rng.random_range(b'a'..=b'z')).collect::<Vec<_>>();
+                landmarks.push(Landmark::Length { offset: out.len(), len: size_usize(len) });
+                Value::Bytes(bytes)
+            }
+            Type::Slice(elem) => {
+                let len = self.gen_len(out, landmarks);
+                for _ in 0..len {
+                    self.gen_type(elem, out, landmarks);
+                }
+                self.gen_terminator(out, landmarks);
+                return;
+            }
+            Type::Map(k, v) => {
+                let len = self.gen_len(out, landmarks);
+                for _ in 0..len {
+                    self.gen_type(k, out, landmarks);
+                    self.gen_type(v, out, landmarks);
+                }
+                self.gen_terminator(out, landmarks);
+                return;
+            }
+            Type::Option(inner) => {
+                let some = rng.random_bool(0.5);
+                landmarks.push(Landmark::Flag { offset: out.len() });
+                append(out, &Value::Bool(some));
+                if some {
+                    self.gen_type(inner, out, landmarks);
+                }
+                return;
+            }
+            Type::Struct(schema) => return self.gen_fields(schema, out, landmarks),
+        };
+        append(out, &value);
+    }
+
+    fn gen_len(&mut self, out: &mut Vec<u8>, landmarks: &mut Vec<Landmark>) -> usize {
+        let len = self.rng.random_range(0..=self.max_collection_len);
+        landmarks.push(Landmark::Length { offset: out.len(), len: size_usize(len) });
+        append_usize(out, len);
+        len
+    }
+
+    fn gen_terminator(&mut self, out: &mut Vec<u8>, landmarks: &mut Vec<Landmark>) {
+        landmarks.push(Landmark::Terminator { offset: out.len() });
+        out.extend_from_slice(&TERMINATOR);
+    }
+}
+
+/// Appends the marshalled value to `out`.
+fn append(out: &mut Vec<u8>, v: &Value) {
+    let start = out.len();
+    out.resize(start + size_value(v), 0);
+    // The vector has grown by exactly the size of the value.
+    marshal_value(v, &mut &mut out[start..]).expect("size_value() is smaller than marshal_value() output");
+}
+
+fn append_usize(out: &mut Vec<u8>, v: usize) {
+    let start = out.len();
+    out.resize(start + size_usize(v), 0);
+    marshal_usize(v, &mut &mut out[start..]).expect("size_usize() is smaller than marshal_usize() output");
+}
+
+/// Writes every input into its own file in `dir`, creating the directory if needed.
+/// Files are named after the index of the input, so writing a corpus again replaces
+/// the files of the previous run.
+pub fn write_corpus(dir: impl AsRef<Path>, inputs: &[Vec<u8>]) -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    for (i, input) in inputs.iter().enumerate() {
+        fs::write(dir.join(format!("benc-{i:06}")), input)?;
+    }
+    Ok(())
+}
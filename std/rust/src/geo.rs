@@ -0,0 +1,215 @@
+//! Support for `geo_types` geometries with `f64` coordinates, enabled by the
+//! `geo-types` feature.
+//!
+//! A `Point` is marshalled as its `x` and `y` coordinates, two `f64`s. A `LineString`
+//! is a slice of coordinates packed as `f64` pairs, which is the layout of
+//! `size_fixed_slice` with 16-byte elements. A `Polygon` is its exterior ring followed
+//! by the slice of its interior rings. The geometries also implement the encoding
+//! traits, so structs defined with [`benc_struct!`](crate::benc_struct) can hold them.
+
+use geo_types::{Coord, LineString, Point, Polygon};
+
+use crate::{
+    BencDecode, BencEncode, Error, Result, Schema, TERMINATOR, Type, advance, marshal_slice, marshal_usize,
+    read_terminator, size_f64, size_fixed_slice, size_slice, skip_fixed_slice, skip_slice, unmarshal_slice,
+    unmarshal_usize, write_to_slice,
+};
+
+const COORD_SIZE: usize = 2 * size_f64();
+
+fn coord_bytes(c: Coord<f64>) -> [u8; COORD_SIZE] {
+    let mut buf = [0u8; COORD_SIZE];
+    buf[..8].copy_from_slice(&c.x.to_bits().to_le_bytes());
+    buf[8..].copy_from_slice(&c.y.to_bits().to_le_bytes());
+    buf
+}
+
+fn coord_from_bytes(bytes: &[u8]) -> Coord<f64> {
+    // Callers pass exactly `COORD_SIZE` bytes.
+    let x = f64::from_bits(u64::from_le_bytes(bytes[..8].try_into().unwrap()));
+    let y = f64::from_bits(u64::from_le_bytes(bytes[8..].try_into().unwrap()));
+    Coord { x, y }
+}
+
+fn point_type() -> Type {
+    Type::Struct(Schema::new().field("x", Type::F64).field("y", Type::F64))
+}
+
+fn line_string_type() -> Type {
+    Type::Slice(Box::new(point_type()))
+}
+
+// ===================================================================================
+// Point
+// ===================================================================================
+
+/// Returns the number of bytes required to marshal a `Point<f64>`.
+pub const fn size_point() -> usize {
+    COORD_SIZE
+}
+
+/// Marshals a `Point<f64>` into the writer as its `x` and `y` coordinates.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_point(p: Point<f64>, writer: &mut &mut [u8]) -> Result<()> {
+    write_to_slice(writer, &coord_bytes(p.0))
+}
+
+/// Unmarshals a `Point<f64>` from the reader.
+pub fn unmarshal_point(reader: &mut &[u8]) -> Result<Point<f64>> {
+    Ok(Point(coord_from_bytes(advance(reader, COORD_SIZE)?)))
+}
+
+/// Skips over a marshalled `Point<f64>` in the reader.
+pub fn skip_point(reader: &mut &[u8]) -> Result<()> {
+    advance(reader, COORD_SIZE).map(|_| ())
+}
+
+// ===================================================================================
+// LineString
+// ===================================================================================
+
+/// Returns the number of bytes required to marshal a `LineString<f64>`.
+pub fn size_line_string(ls: &LineString<f64>) -> usize {
+    size_fixed_slice(&ls.0, COORD_SIZE)
+}
+
+/// Marshals a `LineString<f64>` into the writer as a slice of packed coordinates.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_line_string(ls: &LineString<f64>, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_usize(ls.0.len(), writer)?;
+    for &c in &ls.0 {
+        write_to_slice(writer, &coord_bytes(c))?;
+    }
+    write_to_slice(writer, &TERMINATOR)
+}
+
+/// Unmarshals a `LineString<f64>` from the reader.
+///
+/// Returns an `OutOfRange` error if the size of the coordinates overflows a `usize`.
+pub fn unmarshal_line_string(reader: &mut &[u8]) -> Result<LineString<f64>> {
+    let len = unmarshal_usize(reader)?;
+    let bytes = advance(reader, len.checked_mul(COORD_SIZE).ok_or(Error::OutOfRange)?)?;
+    let coords = bytes.chunks_exact(COORD_SIZE).map(coord_from_bytes).collect();
+    read_terminator(reader)?;
+    Ok(LineString(coords))
+}
+
+/// Skips over a marshalled `LineString<f64>` in the reader.
+pub fn skip_line_string(reader: &mut &[u8]) -> Result<()> {
+    skip_fixed_slice(reader, COORD_SIZE)
+}
+
+// ===================================================================================
+// Polygon
+// ===================================================================================
+
+/// Returns the number of bytes required to marshal a `Polygon<f64>`.
+pub fn size_polygon(p: &Polygon<f64>) -> usize {
+    size_line_string(p.exterior()) + size_slice(p.interiors(), size_line_string)
+}
+
+/// Marshals a `Polygon<f64>` into the writer as its exterior ring followed by the
+/// slice of its interior rings.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_polygon(p: &Polygon<f64>, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_line_string(p.exterior(), writer)?;
+    marshal_slice(p.interiors(), writer, marshal_line_string)
+}
+
+/// Unmarshals a `Polygon<f64>` from the reader. Like `Polygon::new`, this closes rings
+/// whose last coordinate differs from their first.
+pub fn unmarshal_polygon(reader: &mut &[u8]) -> Result<Polygon<f64>> {
+    let exterior = unmarshal_line_string(reader)?;
+    let interiors = unmarshal_slice(reader, unmarshal_line_string)?;
+    Ok(Polygon::new(exterior, interiors))
+}
+
+/// Skips over a marshalled `Polygon<f64>` in the reader.
+pub fn skip_polygon(reader: &mut &[u8]) -> Result<()> {
+    skip_line_string(reader)?;
+    skip_slice(reader, skip_line_string)
+}
+
+// ===================================================================================
+// Traits
+// ===================================================================================
+
+impl BencEncode for Point<f64> {
+    const ENCODED_SIZE: Option<usize> = Some(COORD_SIZE);
+
+    fn benc_type() -> Option<Type> {
+        Some(point_type())
+    }
+
+    fn size(&self) -> usize {
+        size_point()
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_point(*self, writer)
+    }
+}
+
+impl BencDecode<'_> for Point<f64> {
+    fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+        unmarshal_point(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_point(reader)
+    }
+}
+
+impl BencEncode for LineString<f64> {
+    fn benc_type() -> Option<Type> {
+        Some(line_string_type())
+    }
+
+    fn size(&self) -> usize {
+        size_line_string(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_line_string(self, writer)
+    }
+}
+
+impl BencDecode<'_> for LineString<f64> {
+    fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+        unmarshal_line_string(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_line_string(reader)
+    }
+}
+
+impl BencEncode for Polygon<f64> {
+    fn benc_type() -> Option<Type> {
+        let schema = Schema::new()
+            .field("exterior", line_string_type())
+            .field("interiors", Type::Slice(Box::new(line_string_type())));
+        Some(Type::Struct(schema))
+    }
+
+    fn size(&self) -> usize {
+        size_polygon(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_polygon(self, writer)
+    }
+}
+
+impl BencDecode<'_> for Polygon<f64> {
+    fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+        unmarshal_polygon(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_polygon(reader)
+    }
+}
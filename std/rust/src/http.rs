@@ -0,0 +1,115 @@
+//! HTTP integration for `axum`, enabled by the `axum` feature.
+//!
+//! [`Benc`] decodes request bodies sent with the `application/x-benc` content type and
+//! encodes responses with it, mirroring axum's `Json`:
+//!
+//! ```ignore
+//! async fn create_user(Benc(user): Benc<User>) -> Benc<UserId> {
+//!     Benc(store(user))
+//! }
+//! ```
+//!
+//! Request bodies are read with axum's `Bytes` extractor, so the usual
+//! `DefaultBodyLimit` layer bounds their size (2 MB unless configured otherwise).
+
+use std::ops::{Deref, DerefMut};
+
+use axum::body::Bytes;
+use axum::extract::rejection::BytesRejection;
+use axum::extract::{FromRequest, Request};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+
+use crate::{BencDecode, BencEncode, Error, from_slice};
+
+/// The media type of benc-encoded HTTP bodies.
+pub const BENC_CONTENT_TYPE: &str = "application/x-benc";
+
+/// An extractor and response for benc-encoded HTTP bodies.
+///
+/// As an extractor, it requires the `application/x-benc` content type and decodes the
+/// whole body into `T`. As a response, it encodes the value and sets the content type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Benc<T>(pub T);
+
+impl<T> Deref for Benc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Benc<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Benc<T> {
+    fn from(value: T) -> Self {
+        Benc(value)
+    }
+}
+
+/// The reasons a request body can fail to extract as [`Benc`].
+#[derive(Debug, thiserror::Error)]
+pub enum BencRejection {
+    /// The request does not have the `application/x-benc` content type. Responds with
+    /// `415 Unsupported Media Type`.
+    #[error("expected a request with `Content-Type: application/x-benc`")]
+    UnsupportedContentType,
+    /// The body could not be read, for instance because it exceeds the body limit.
+    /// Responds like axum's own `BytesRejection`.
+    #[error(transparent)]
+    Body(#[from] BytesRejection),
+    /// The body is not a valid encoding of the type. Responds with `400 Bad Request`.
+    #[error("failed to decode the request body: {0}")]
+    Decode(#[from] Error),
+}
+
+impl IntoResponse for BencRejection {
+    fn into_response(self) -> Response {
+        match self {
+            BencRejection::UnsupportedContentType => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, self.to_string()).into_response()
+            }
+            BencRejection::Body(rejection) => rejection.into_response(),
+            BencRejection::Decode(_) => (StatusCode::BAD_REQUEST, self.to_string()).into_response(),
+        }
+    }
+}
+
+fn has_benc_content_type(headers: &HeaderMap) -> bool {
+    let Some(value) = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let media_type = value.split(';').next().unwrap_or_default().trim();
+    media_type.eq_ignore_ascii_case(BENC_CONTENT_TYPE)
+}
+
+impl<T, S> FromRequest<S> for Benc<T>
+where
+    T: for<'a> BencDecode<'a>,
+    S: Send + Sync,
+{
+    type Rejection = BencRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, BencRejection> {
+        if !has_benc_content_type(req.headers()) {
+            return Err(BencRejection::UnsupportedContentType);
+        }
+        let body = Bytes::from_request(req, state).await?;
+        Ok(Benc(from_slice(&body)?))
+    }
+}
+
+impl<T: BencEncode> IntoResponse for Benc<T> {
+    fn into_response(self) -> Response {
+        let mut buf = vec![0; self.0.size()];
+        if let Err(err) = self.0.marshal(&mut buf.as_mut_slice()) {
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+        }
+        ([(header::CONTENT_TYPE, HeaderValue::from_static(BENC_CONTENT_TYPE))], buf).into_response()
+    }
+}
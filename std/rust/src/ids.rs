@@ -0,0 +1,127 @@
+//! Fixed-size identifiers: `ulid::Ulid` (feature `ulid`) and `macaddr::MacAddr6`
+//! (feature `macaddr`).
+//!
+//! Both are marshalled as their raw bytes without a length prefix, and implement the
+//! encoding traits so structs defined with [`benc_struct!`](crate::benc_struct) can
+//! hold them.
+
+// ===================================================================================
+// ulid::Ulid
+// ===================================================================================
+
+#[cfg(feature = "ulid")]
+mod ulid_impl {
+    use ulid::Ulid;
+
+    use crate::{BencDecode, BencEncode, Result, advance, write_to_slice};
+
+    /// Returns the number of bytes required to marshal a `Ulid`.
+    pub const fn size_ulid() -> usize {
+        16
+    }
+
+    /// Marshals a `Ulid` as its 16 big-endian bytes, which keeps marshalled ULIDs
+    /// sortable by their timestamp.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_ulid(v: Ulid, writer: &mut &mut [u8]) -> Result<()> {
+        write_to_slice(writer, &v.to_bytes())
+    }
+
+    /// Unmarshals a `Ulid` from the reader.
+    pub fn unmarshal_ulid(reader: &mut &[u8]) -> Result<Ulid> {
+        let bytes = advance(reader, size_ulid())?;
+        Ok(Ulid::from_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Skips over a marshalled `Ulid` in the reader.
+    pub fn skip_ulid(reader: &mut &[u8]) -> Result<()> {
+        advance(reader, size_ulid())?;
+        Ok(())
+    }
+
+    impl BencEncode for Ulid {
+        const ENCODED_SIZE: Option<usize> = Some(size_ulid());
+
+        fn size(&self) -> usize {
+            size_ulid()
+        }
+
+        fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_ulid(*self, writer)
+        }
+    }
+
+    impl BencDecode<'_> for Ulid {
+        fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+            unmarshal_ulid(reader)
+        }
+
+        fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_ulid(reader)
+        }
+    }
+}
+
+#[cfg(feature = "ulid")]
+pub use ulid_impl::*;
+
+// ===================================================================================
+// macaddr::MacAddr6
+// ===================================================================================
+
+#[cfg(feature = "macaddr")]
+mod mac_impl {
+    use macaddr::MacAddr6;
+
+    use crate::{BencDecode, BencEncode, Result, advance, write_to_slice};
+
+    /// Returns the number of bytes required to marshal a `MacAddr6`.
+    pub const fn size_mac() -> usize {
+        6
+    }
+
+    /// Marshals a `MacAddr6` as its 6 bytes in transmission order.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_mac(v: MacAddr6, writer: &mut &mut [u8]) -> Result<()> {
+        write_to_slice(writer, v.as_bytes())
+    }
+
+    /// Unmarshals a `MacAddr6` from the reader.
+    pub fn unmarshal_mac(reader: &mut &[u8]) -> Result<MacAddr6> {
+        let bytes: [u8; 6] = advance(reader, size_mac())?.try_into().unwrap();
+        Ok(MacAddr6::from(bytes))
+    }
+
+    /// Skips over a marshalled `MacAddr6` in the reader.
+    pub fn skip_mac(reader: &mut &[u8]) -> Result<()> {
+        advance(reader, size_mac())?;
+        Ok(())
+    }
+
+    impl BencEncode for MacAddr6 {
+        const ENCODED_SIZE: Option<usize> = Some(size_mac());
+
+        fn size(&self) -> usize {
+            size_mac()
+        }
+
+        fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_mac(*self, writer)
+        }
+    }
+
+    impl BencDecode<'_> for MacAddr6 {
+        fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+            unmarshal_mac(reader)
+        }
+
+        fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_mac(reader)
+        }
+    }
+}
+
+#[cfg(feature = "macaddr")]
+pub use mac_impl::*;
@@ -0,0 +1,212 @@
+//! Footer-indexed containers for random access to the top-level fields of large
+//! messages.
+//!
+//! Fields are marshalled back to back, followed by a footer that maps every field
+//! id to the byte range it occupies, and finally the length of the footer as a
+//! little-endian `u32`. A reader locates the footer from the end of the container
+//! and jumps directly to the requested field instead of skipping everything
+//! before it.
+//!
+//! The footer is a varint length of the field data, a varint entry count and, per
+//! entry, the varint field id, offset (relative to the start of the container) and
+//! length.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::{
+    Error, Result, marshal_u32, marshal_uint, marshal_usize, size_u32, size_uint, size_usize,
+    unmarshal_u32, unmarshal_uint, unmarshal_usize,
+};
+
+/// The location of a single field inside a container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    id: u64,
+    offset: usize,
+    len: usize,
+}
+
+fn size_footer(data_len: usize, entries: &[Entry]) -> usize {
+    let entries_size: usize = entries
+        .iter()
+        .map(|e| size_uint(e.id) + size_usize(e.offset) + size_usize(e.len))
+        .sum();
+    size_usize(data_len) + size_usize(entries.len()) + entries_size
+}
+
+fn unmarshal_footer(mut footer: &[u8]) -> Result<(usize, Vec<Entry>)> {
+    let reader = &mut footer;
+    let data_len = unmarshal_usize(reader)?;
+    let count = unmarshal_usize(reader)?;
+    let mut entries = Vec::with_capacity(count.min(reader.len()));
+    for _ in 0..count {
+        let entry = Entry {
+            id: unmarshal_uint(reader)?,
+            offset: unmarshal_usize(reader)?,
+            len: unmarshal_usize(reader)?,
+        };
+        match entry.offset.checked_add(entry.len) {
+            Some(end) if end <= data_len => entries.push(entry),
+            _ => return Err(Error::OutOfRange),
+        }
+    }
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok((data_len, entries))
+}
+
+/// Moves `len` bytes back from position `end`, failing if that is before the start of
+/// the buffer.
+fn rewind(end: u64, len: u64) -> Result<u64> {
+    end.checked_sub(len).ok_or(Error::BufferTooSmall {
+        needed: usize::try_from(len).unwrap_or(usize::MAX),
+        available: usize::try_from(end).unwrap_or(usize::MAX),
+    })
+}
+
+// ===================================================================================
+// Encoding
+// ===================================================================================
+
+/// Returns the number of bytes needed for an indexed container holding fields with the
+/// given ids and marshalled sizes, in the order they will be written.
+pub fn size_indexed(fields: &[(u64, usize)]) -> usize {
+    let mut offset = 0;
+    let entries: Vec<Entry> = fields
+        .iter()
+        .map(|&(id, len)| {
+            let entry = Entry { id, offset, len };
+            offset += len;
+            entry
+        })
+        .collect();
+    offset + size_footer(offset, &entries) + size_u32()
+}
+
+/// Writes an indexed container into a writer, one top-level field at a time.
+///
+/// The footer is only written by `finish`; a container that is never finished
+/// cannot be read.
+pub struct IndexedWriter<'w, 'b> {
+    writer: &'w mut &'b mut [u8],
+    written: usize,
+    entries: Vec<Entry>,
+}
+
+impl<'w, 'b> IndexedWriter<'w, 'b> {
+    /// Starts a new container at the current position of the writer.
+    pub fn new(writer: &'w mut &'b mut [u8]) -> Self {
+        IndexedWriter { writer, written: 0, entries: Vec::new() }
+    }
+
+    /// Marshals a top-level field with the given id and records its location.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn field(
+        &mut self,
+        id: u64,
+        marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>,
+    ) -> Result<()> {
+        let before = self.writer.len();
+        marshaler(self.writer)?;
+        let len = before - self.writer.len();
+        self.entries.push(Entry { id, offset: self.written, len });
+        self.written += len;
+        Ok(())
+    }
+
+    /// Writes the footer, completing the container.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn finish(self) -> Result<()> {
+        let footer_len = size_footer(self.written, &self.entries);
+        let footer_len = u32::try_from(footer_len).map_err(|_| Error::OutOfRange)?;
+        marshal_usize(self.written, self.writer)?;
+        marshal_usize(self.entries.len(), self.writer)?;
+        for entry in &self.entries {
+            marshal_uint(entry.id, self.writer)?;
+            marshal_usize(entry.offset, self.writer)?;
+            marshal_usize(entry.len, self.writer)?;
+        }
+        marshal_u32(footer_len, self.writer)
+    }
+}
+
+// ===================================================================================
+// Decoding
+// ===================================================================================
+
+/// A parsed indexed container, giving direct access to its fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedReader<'a> {
+    data: &'a [u8],
+    entries: Vec<Entry>,
+}
+
+impl<'a> IndexedReader<'a> {
+    /// Returns the marshalled bytes of the field with the given id, or `None` if the
+    /// container has no such field. If an id was written more than once, the first
+    /// occurrence is returned.
+    pub fn field(&self, id: u64) -> Option<&'a [u8]> {
+        self.entries
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| &self.data[e.offset..e.offset + e.len])
+    }
+
+    /// Returns the ids of all fields in the order they were written.
+    pub fn ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.entries.iter().map(|e| e.id)
+    }
+
+    /// Returns the number of fields in the container.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the container holds no fields.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Parses an indexed container that ends exactly at the end of `buf`.
+/// Only the footer is read; field data is not touched until it is requested.
+pub fn unmarshal_indexed(buf: &[u8]) -> Result<IndexedReader<'_>> {
+    let footer_end = rewind(buf.len() as u64, size_u32() as u64)? as usize;
+    let footer_len = unmarshal_u32(&mut &buf[footer_end..])? as usize;
+    let footer_start = rewind(footer_end as u64, footer_len as u64)? as usize;
+    let (data_len, entries) = unmarshal_footer(&buf[footer_start..footer_end])?;
+    let data_start = rewind(footer_start as u64, data_len as u64)? as usize;
+    Ok(IndexedReader { data: &buf[data_start..footer_start], entries })
+}
+
+/// Reads a single field from an indexed container that ends at the end of `source`,
+/// seeking directly to it. Returns `Ok(None)` if the container has no such field.
+///
+/// Only the footer and the requested field are read, which makes this suitable for
+/// very large containers stored in files.
+pub fn read_indexed_field<R: Read + Seek>(source: &mut R, id: u64) -> io::Result<Option<Vec<u8>>> {
+    let end = source.seek(SeekFrom::End(0))?;
+    let footer_end = rewind(end, size_u32() as u64)?;
+    source.seek(SeekFrom::Start(footer_end))?;
+    let mut len_buf = [0u8; 4];
+    source.read_exact(&mut len_buf)?;
+    let footer_len = u32::from_le_bytes(len_buf) as u64;
+
+    let footer_start = rewind(footer_end, footer_len)?;
+    source.seek(SeekFrom::Start(footer_start))?;
+    let mut footer = vec![0u8; footer_len as usize];
+    source.read_exact(&mut footer)?;
+    let (data_len, entries) = unmarshal_footer(&footer)?;
+
+    let data_start = rewind(footer_start, data_len as u64)?;
+    let Some(entry) = entries.iter().find(|e| e.id == id) else {
+        return Ok(None);
+    };
+    source.seek(SeekFrom::Start(data_start + entry.offset as u64))?;
+    let mut field = vec![0u8; entry.len];
+    source.read_exact(&mut field)?;
+    Ok(Some(field))
+}
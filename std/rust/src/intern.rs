@@ -0,0 +1,273 @@
+//! Interning of strings across the messages of a connection.
+//!
+//! An [`InternContext`] remembers every string it has marshalled. The first time a
+//! string is seen it is written as a literal; once the message holding it is
+//! finished, later occurrences are written as a back-reference to it. Streams that
+//! keep repeating the same keys or labels shrink to a few bytes per string.
+//!
+//! Each interned string is a varint: `0` followed by a marshalled string for a
+//! literal, or `n > 0` referring to the `n`-th literal of the stream. Both ends must
+//! process every message, in order, with a context of their own and the same
+//! capacity. Once the table is full, new strings are still written as literals but
+//! neither end remembers them.
+//!
+//! A [`StringCache`] shares strings on the decoding side only, with the regular wire
+//! format: strings decoded through it come out as `Arc<str>`, and a string equal to
+//! one decoded before is a clone of the same allocation. Consumers of many messages
+//! with the same map keys keep one copy of each key instead of one per message.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::{
+    Error, Result, marshal_string, marshal_uint, read_terminator, size_string, size_uint,
+    unmarshal_string, unmarshal_uint,
+};
+
+/// The default number of literals an `InternContext` remembers.
+pub const DEFAULT_INTERN_CAPACITY: usize = 1 << 16;
+
+/// The interning table for one direction of a connection.
+#[derive(Debug, Clone)]
+pub struct InternContext {
+    /// The literals of the stream, in order, up to `capacity`.
+    strings: Vec<String>,
+    /// References usable by the encoder, filled in when a message is finished.
+    lookup: HashMap<String, u64>,
+    /// Literals recorded since the last finished message.
+    pending: usize,
+    capacity: usize,
+    /// The last literal decoded once the table was full.
+    overflow: String,
+}
+
+impl Default for InternContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InternContext {
+    /// Creates an empty context remembering up to [`DEFAULT_INTERN_CAPACITY`]
+    /// literals.
+    pub fn new() -> Self {
+        InternContext {
+            strings: Vec::new(),
+            lookup: HashMap::new(),
+            pending: 0,
+            capacity: DEFAULT_INTERN_CAPACITY,
+            overflow: String::new(),
+        }
+    }
+
+    /// Sets the number of literals the context remembers. Both ends of a stream
+    /// must use the same capacity.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Returns the number of literals seen so far.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns `true` if no literals have been seen.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Returns the number of bytes required to marshal an interned string.
+    ///
+    /// Strings first written in the current message are sized as literals, which
+    /// keeps sizes exact until `finish_message` is called.
+    pub fn size_interned(&self, s: &str) -> usize {
+        match self.lookup.get(s) {
+            Some(&reference) => size_uint(reference),
+            None => size_uint(0) + size_string(s),
+        }
+    }
+
+    /// Marshals an interned string into the writer, as a back-reference if it was
+    /// written in an earlier message and as a literal otherwise.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_interned(&mut self, s: &str, writer: &mut &mut [u8]) -> Result<()> {
+        if let Some(&reference) = self.lookup.get(s) {
+            return marshal_uint(reference, writer);
+        }
+        marshal_uint(0, writer)?;
+        marshal_string(s, writer)?;
+        if self.strings.len() < self.capacity {
+            self.strings.push(s.to_owned());
+            self.pending += 1;
+        }
+        Ok(())
+    }
+
+    /// Makes the literals of the current message available as back-references to
+    /// the following messages. Only the encoder needs to call this.
+    pub fn finish_message(&mut self) {
+        let start = self.strings.len() - self.pending;
+        for (i, s) in self.strings[start..].iter().enumerate() {
+            let reference = (start + i + 1) as u64;
+            self.lookup.entry(s.clone()).or_insert(reference);
+        }
+        self.pending = 0;
+    }
+
+    /// Forgets the literals of the current message, for an encoder whose message
+    /// failed to marshal and will not be sent. Only the encoder needs to call this.
+    pub fn abort_message(&mut self) {
+        self.strings.truncate(self.strings.len() - self.pending);
+        self.pending = 0;
+    }
+
+    /// Unmarshals an interned string from the reader. The returned `&str` borrows
+    /// from the context.
+    ///
+    /// Returns an `OutOfRange` error if a back-reference does not refer to an earlier
+    /// literal.
+    pub fn unmarshal_interned(&mut self, reader: &mut &[u8]) -> Result<&str> {
+        match self.read(reader)? {
+            Some(index) => Ok(&self.strings[index]),
+            None => Ok(&self.overflow),
+        }
+    }
+
+    /// Skips over an interned string in the reader, still recording it if it is a
+    /// literal.
+    pub fn skip_interned(&mut self, reader: &mut &[u8]) -> Result<()> {
+        self.read(reader)?;
+        Ok(())
+    }
+
+    /// Reads an interned string, returning its index in the table, or `None` for a
+    /// literal that did not fit and was left in `overflow`.
+    fn read(&mut self, reader: &mut &[u8]) -> Result<Option<usize>> {
+        match unmarshal_uint(reader)? {
+            0 => {
+                let s = unmarshal_string(reader)?;
+                if self.strings.len() >= self.capacity {
+                    s.clone_into(&mut self.overflow);
+                    return Ok(None);
+                }
+                self.strings.push(s.to_owned());
+                Ok(Some(self.strings.len() - 1))
+            }
+            reference => {
+                let index = usize::try_from(reference - 1).map_err(|_| Error::OutOfRange)?;
+                if index >= self.strings.len() {
+                    return Err(Error::OutOfRange);
+                }
+                Ok(Some(index))
+            }
+        }
+    }
+}
+
+// ===================================================================================
+// Decode-time sharing
+// ===================================================================================
+
+/// The default number of strings a `StringCache` holds.
+pub const DEFAULT_STRING_CACHE_CAPACITY: usize = 1024;
+
+/// The default length of the longest string a `StringCache` holds.
+pub const DEFAULT_STRING_CACHE_MAX_LEN: usize = 64;
+
+/// A cache of decoded strings, shared as `Arc<str>`.
+///
+/// Only short strings are cached, since keys and labels repeat while long strings
+/// rarely do, and the cache stops taking new strings once it is full, so a stream of
+/// unique values cannot grow it without bound. Its methods take `&self`, so they can
+/// be called from the closures of `unmarshal_slice`, `unmarshal_map_into` and the
+/// other combinators.
+#[derive(Debug)]
+pub struct StringCache {
+    strings: RefCell<HashSet<Arc<str>>>,
+    capacity: usize,
+    max_len: usize,
+}
+
+impl Default for StringCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StringCache {
+    /// Creates a cache of up to [`DEFAULT_STRING_CACHE_CAPACITY`] strings of up to
+    /// [`DEFAULT_STRING_CACHE_MAX_LEN`] bytes.
+    pub fn new() -> Self {
+        StringCache {
+            strings: RefCell::new(HashSet::new()),
+            capacity: DEFAULT_STRING_CACHE_CAPACITY,
+            max_len: DEFAULT_STRING_CACHE_MAX_LEN,
+        }
+    }
+
+    /// Sets the number of strings the cache holds.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the length in bytes of the longest string the cache holds.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Returns the number of cached strings.
+    pub fn len(&self) -> usize {
+        self.strings.borrow().len()
+    }
+
+    /// Returns `true` if no strings are cached.
+    pub fn is_empty(&self) -> bool {
+        self.strings.borrow().is_empty()
+    }
+
+    /// Removes all cached strings, making room for new ones.
+    pub fn clear(&self) {
+        self.strings.borrow_mut().clear();
+    }
+
+    /// Returns the cached copy of `s`, caching it first if there is room.
+    pub fn get(&self, s: &str) -> Arc<str> {
+        let mut strings = self.strings.borrow_mut();
+        if let Some(shared) = strings.get(s) {
+            return shared.clone();
+        }
+        let shared: Arc<str> = Arc::from(s);
+        if s.len() <= self.max_len && strings.len() < self.capacity {
+            strings.insert(shared.clone());
+        }
+        shared
+    }
+
+    /// Unmarshals a string from the reader, sharing it with earlier equal strings.
+    pub fn unmarshal_string(&self, reader: &mut &[u8]) -> Result<Arc<str>> {
+        Ok(self.get(unmarshal_string(reader)?))
+    }
+
+    /// Unmarshals a map with string keys from the reader into a `HashMap`, sharing
+    /// the keys with earlier equal strings.
+    pub fn unmarshal_map<V, E: From<Error>>(
+        &self,
+        reader: &mut &[u8],
+        v_unmarshaler: impl Fn(&mut &[u8]) -> Result<V, E>,
+    ) -> Result<HashMap<Arc<str>, V>, E> {
+        let len = unmarshal_uint(reader)? as usize;
+        let mut map = HashMap::with_capacity(len.min(reader.len()));
+        for _ in 0..len {
+            let k = self.unmarshal_string(reader)?;
+            let v = v_unmarshaler(reader)?;
+            map.insert(k, v);
+        }
+        read_terminator(reader)?;
+        Ok(map)
+    }
+}
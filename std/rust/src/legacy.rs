@@ -0,0 +1,102 @@
+//! Decoding of the collection layout of early Go `bstd` revisions.
+//!
+//! Before the terminator sequence was introduced, slices and maps were marshalled as
+//! the varint element count followed by the elements, with nothing after them. Data
+//! archived by producers of that time is otherwise identical to the current layout,
+//! so it stays readable through:
+//!
+//! - the `_unterminated` variants of the collection unmarshalers, for hand-written
+//!   decoders,
+//! - [`upgrade_unterminated`], which rewrites a message described by a [`Type`] into
+//!   the current layout, so that every decoder of this crate can read it,
+//! - a self-describing message whose schema header has the version
+//!   [`UNTERMINATED_SCHEMA_HEADER_VERSION`](crate::UNTERMINATED_SCHEMA_HEADER_VERSION),
+//!   which `value_from_self_describing` recognizes on its own.
+
+use crate::terminator::rewrite_terminators;
+use crate::{
+    BencDecode, BencEncode, Error, Result, Terminator, Type, from_slice, skip_elements, unmarshal_elements, unmarshal_uint,
+};
+
+/// The collection layout of marshalled data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Slices and maps end with the terminator sequence, as written by this crate.
+    #[default]
+    Terminated,
+    /// Slices and maps end after their last element, as written by early Go `bstd`
+    /// revisions.
+    Unterminated,
+}
+
+/// Unmarshals a slice without a terminator from the reader. The elements may borrow
+/// from the reader, as with [`unmarshal_slice`](crate::unmarshal_slice).
+pub fn unmarshal_slice_unterminated<'a, T, E: From<Error>>(
+    reader: &mut &'a [u8],
+    unmarshaler: impl Fn(&mut &'a [u8]) -> Result<T, E>,
+) -> Result<Vec<T>, E> {
+    unmarshal_elements(reader, unmarshaler, None)
+}
+
+/// Skips over a slice without a terminator in the reader.
+pub fn skip_slice_unterminated<E: From<Error>>(
+    reader: &mut &[u8],
+    skip_element: impl Fn(&mut &[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    skip_elements(reader, skip_element, None)
+}
+
+/// Unmarshals the entries of a map without a terminator from the reader, into any
+/// collection of pairs such as a `HashMap` or a `BTreeMap`.
+pub fn unmarshal_map_unterminated<K, V, M: Default + Extend<(K, V)>, E: From<Error>>(
+    reader: &mut &[u8],
+    key_unmarshaler: impl Fn(&mut &[u8]) -> Result<K, E>,
+    value_unmarshaler: impl Fn(&mut &[u8]) -> Result<V, E>,
+) -> Result<M, E> {
+    let len = unmarshal_uint(reader)?;
+    let mut map = M::default();
+    for _ in 0..len {
+        let key = key_unmarshaler(reader)?;
+        let value = value_unmarshaler(reader)?;
+        map.extend([(key, value)]);
+    }
+    Ok(map)
+}
+
+/// Skips over a map without a terminator in the reader.
+pub fn skip_map_unterminated<E: From<Error>>(
+    reader: &mut &[u8],
+    skip_key: impl Fn(&mut &[u8]) -> Result<(), E>,
+    skip_value: impl Fn(&mut &[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    let len = unmarshal_uint(reader)?;
+    for _ in 0..len {
+        skip_key(reader)?;
+        skip_value(reader)?;
+    }
+    Ok(())
+}
+
+/// Rewrites a value of the given type marshalled without collection terminators into
+/// the current layout.
+///
+/// Returns a `TrailingBytes` error if `buf` holds more than one value.
+pub fn upgrade_unterminated(buf: &[u8], ty: &Type) -> Result<Vec<u8>> {
+    rewrite_terminators(buf, ty, None, Some(Terminator::DEFAULT))
+}
+
+/// Unmarshals a value of type `T` from `buf`, which holds it in the given layout.
+///
+/// Returns an `Unsupported` error for data in the unterminated layout if `T` does not
+/// describe its encoding through `BencEncode::benc_type`, which the upgrade needs to
+/// find its collections.
+pub fn from_slice_with_layout<T: for<'a> BencDecode<'a> + BencEncode>(buf: &[u8], layout: Layout) -> Result<T> {
+    match layout {
+        Layout::Terminated => from_slice(buf),
+        Layout::Unterminated => {
+            let ty = T::benc_type()
+                .ok_or_else(|| Error::Unsupported(format!("{} has no benc type", std::any::type_name::<T>())))?;
+            from_slice(&upgrade_unterminated(buf, &ty)?)
+        }
+    }
+}
@@ -21,43 +21,330 @@
 //!
 //! ## Safety
 //!
-//! This implementation is completely safe and contains no `unsafe` code. The zero-copy
-//! string and byte slice conversions from the original Go code are achieved safely in
-//! Rust by returning borrowed slices (`&str`, `&[u8]`) tied to the lifetime of the
-//! input buffer.
-
+//! By default this implementation is completely safe and contains no `unsafe` code.
+//! The zero-copy string and byte slice conversions from the original Go code are
+//! achieved safely in Rust by returning borrowed slices (`&str`, `&[u8]`) tied to the
+//! lifetime of the input buffer.
+//!
+//! The opt-in `unsafe-fast` feature adds `unsafe` functions that skip validation
+//! (such as `unmarshal_string_unchecked`), for buffers the caller already knows to be
+//! valid. Their safety requirements are documented on each function.
+//!
+//! The `ring` feature uses `unsafe` internally to share a byte region between a
+//! producer and a consumer; its public API is safe. The `shm` feature maps memory
+//! that other processes can change or truncate, so `ShmSender::create` and
+//! `ShmReceiver::open` are `unsafe` and document what the caller must guarantee.
+//! The `bytemuck` feature enables the zero-copy `unmarshal_aligned_slice`, which leaves
+//! the cast from bytes to numbers to the `bytemuck` crate.
+
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::hash::Hash;
+use std::hash::{BuildHasher, Hash};
 use std::mem::size_of;
+use std::time::Duration;
 use thiserror::Error;
 // To use the new time functions, you would need to add `chrono` to your Cargo.toml:
 // `chrono = { version = "0.4" }`
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+
+mod aligned;
+mod array_writer;
+#[cfg(feature = "futures")]
+mod async_io;
+#[cfg(feature = "num-bigint")]
+mod bigint;
+#[cfg(feature = "bitvec")]
+mod bits;
+#[cfg(any(feature = "arrayvec", feature = "tinyvec"))]
+mod bounded;
+mod builder;
+#[cfg(feature = "bstr")]
+mod byte_string;
+mod canonical;
+mod chunked;
+pub mod codec;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+mod columnar;
+pub mod compat;
+#[cfg(feature = "zstd")]
+mod compress;
+#[cfg(feature = "testing")]
+pub mod conformance;
+mod const_encoder;
+#[cfg(feature = "sha2")]
+mod content_hash;
+mod decode_iter;
+mod described;
+mod diff;
+mod dump;
+#[cfg(feature = "either")]
+mod either;
+mod encoded;
+mod escaped;
+mod framing;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+#[cfg(feature = "geo-types")]
+mod geo;
+#[cfg(feature = "axum")]
+mod http;
+#[cfg(any(feature = "ulid", feature = "macaddr"))]
+mod ids;
+mod indexed;
+mod intern;
+mod legacy;
+mod lines;
+mod lint;
+mod macros;
+#[cfg(any(feature = "semver", feature = "url"))]
+mod manifest;
+#[cfg(feature = "glam")]
+mod math;
+mod measure;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(any(feature = "bincode", feature = "postcard"))]
+mod migrate;
+#[cfg(feature = "ordered-float")]
+mod ordered;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod patch;
+mod prefixed;
+mod registry;
+#[cfg(feature = "ring")]
+mod ring;
+mod rpc;
+mod schema;
+#[cfg(any(feature = "num-complex", feature = "ndarray"))]
+mod scientific;
+mod seal;
+#[cfg(feature = "zeroize")]
+mod secret;
+#[cfg(feature = "shm")]
+mod shm;
+mod splice;
+#[cfg(feature = "sqlx")]
+mod sql;
+mod stream;
+mod tagged;
+mod terminator;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod traits;
+mod truncated;
+#[cfg(feature = "chrono-tz")]
+mod tz;
+mod utf16;
+mod value;
+
+pub use aligned::*;
+pub use array_writer::*;
+#[cfg(feature = "futures")]
+pub use async_io::*;
+#[cfg(feature = "num-bigint")]
+pub use bigint::*;
+#[cfg(feature = "bitvec")]
+pub use bits::*;
+#[cfg(any(feature = "arrayvec", feature = "tinyvec"))]
+pub use bounded::*;
+pub use builder::*;
+#[cfg(feature = "bstr")]
+pub use byte_string::*;
+pub use canonical::*;
+pub use chunked::*;
+pub use columnar::*;
+#[cfg(feature = "zstd")]
+pub use compress::*;
+pub use const_encoder::*;
+#[cfg(feature = "sha2")]
+pub use content_hash::*;
+pub use decode_iter::*;
+pub use described::*;
+pub use diff::*;
+pub use dump::*;
+#[cfg(feature = "either")]
+pub use either::*;
+pub use encoded::*;
+pub use escaped::*;
+pub use framing::*;
+#[cfg(feature = "geo-types")]
+pub use geo::*;
+#[cfg(feature = "axum")]
+pub use http::*;
+#[cfg(any(feature = "ulid", feature = "macaddr"))]
+pub use ids::*;
+pub use indexed::*;
+pub use intern::*;
+pub use legacy::*;
+pub use lines::*;
+pub use lint::*;
+#[cfg(any(feature = "semver", feature = "url"))]
+pub use manifest::*;
+#[cfg(feature = "glam")]
+pub use math::*;
+pub use measure::*;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
+#[cfg(any(feature = "bincode", feature = "postcard"))]
+pub use migrate::*;
+#[cfg(feature = "ordered-float")]
+pub use ordered::*;
+#[cfg(feature = "rayon")]
+pub use parallel::*;
+pub use patch::*;
+pub use prefixed::*;
+pub use registry::*;
+#[cfg(feature = "ring")]
+pub use ring::*;
+pub use rpc::*;
+pub use schema::*;
+#[cfg(any(feature = "num-complex", feature = "ndarray"))]
+pub use scientific::*;
+pub use seal::*;
+#[cfg(feature = "zeroize")]
+pub use secret::*;
+#[cfg(feature = "shm")]
+pub use shm::*;
+pub use splice::*;
+pub use stream::*;
+pub use tagged::*;
+pub use terminator::*;
+pub use traits::*;
+pub use truncated::*;
+#[cfg(feature = "chrono-tz")]
+pub use tz::*;
+pub use utf16::*;
+pub use value::*;
+
+/// Items used by the code that `benc_struct!` generates. Not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use paste::paste;
+
+    /// Adds up the sizes of the fields of a struct, or returns `None` if any of them
+    /// is not constant.
+    pub const fn sum_sizes(sizes: &[Option<usize>]) -> Option<usize> {
+        let mut total = 0;
+        let mut i = 0;
+        while i < sizes.len() {
+            match sizes[i] {
+                Some(size) => total += size,
+                None => return None,
+            }
+            i += 1;
+        }
+        Some(total)
+    }
+
+    /// Returns `true` if no two fields of a tagged struct share an id. Skipped fields
+    /// are `None`.
+    pub const fn unique_ids(ids: &[Option<u64>]) -> bool {
+        let mut i = 0;
+        while i < ids.len() {
+            let mut j = i + 1;
+            while j < ids.len() {
+                if let (Some(a), Some(b)) = (ids[i], ids[j])
+                    && a == b
+                {
+                    return false;
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Builds the type of a struct from the names and types of its fields. Skipped
+    /// fields are `None`; a field whose type is unknown makes the struct type unknown.
+    pub fn struct_type<const N: usize>(fields: [(&str, Option<Option<crate::Type>>); N]) -> Option<crate::Type> {
+        let mut schema = crate::Schema::new();
+        for (name, ty) in fields {
+            if let Some(ty) = ty {
+                schema = schema.field(name, ty?);
+            }
+        }
+        Some(crate::Type::Struct(schema))
+    }
+
+    /// Skips over `size` bytes in the reader.
+    pub fn skip_fixed(reader: &mut &[u8], size: usize) -> crate::Result<()> {
+        crate::advance(reader, size).map(|_| ())
+    }
+}
 
 /// The terminator sequence used to mark the end of slices and maps.
 /// This specific sequence is chosen as it's unlikely to appear naturally
-/// in varint-encoded data.
-const TERMINATOR: [u8; 4] = [1, 1, 1, 1];
+/// in varint-encoded data. [`Terminator`] configures another sequence.
+pub(crate) const TERMINATOR: [u8; 4] = [1, 1, 1, 1];
 
 /// The maximum number of bytes a 64-bit varint can occupy.
 const MAX_VARINT_LEN_64: usize = 10;
 
 /// A specialized `Result` type for bstd operations.
-pub type Result<T> = std::result::Result<T, Error>;
+///
+/// The collection combinators (`marshal_slice`, `unmarshal_map`, `skip_option`, ...)
+/// are generic over the error type of their closures, which only has to implement
+/// `From<Error>`. Custom errors raised by user marshalers are passed through as is.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 /// Represents errors that can occur during serialization or deserialization.
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum Error {
-    #[error("buffer is too small to complete the operation")]
-    BufferTooSmall,
+    /// The buffer ended before the operation could complete. `needed` is the number
+    /// of bytes the failing read or write required and `available` the number of
+    /// bytes that were left. Marshalling through [`to_slice`], `marshal_string` or
+    /// `marshal_bytes` reports the size of the whole value, so the write can be
+    /// retried with a buffer of that size.
+    #[error("buffer is too small to complete the operation: needed {needed} bytes, {available} available")]
+    BufferTooSmall { needed: usize, available: usize },
     #[error("varint is too large and overflows")]
     VarintOverflow,
+    /// A varint used more bytes than its value needs, such as `0x80 0x00` for 0,
+    /// which the strict varint decoders reject.
+    #[error("varint is not minimally encoded")]
+    NonMinimalVarint,
     #[error("data is not a valid UTF-8 string")]
     InvalidUtf8(#[from] std::str::Utf8Error),
     #[error("expected a terminator sequence, but it was not found")]
     MissingTerminator,
     #[error("value is out of range for the target integer type")]
     OutOfRange,
+    #[error("unexpected trailing bytes after the encoded data")]
+    TrailingBytes,
+    #[error("data is not a valid value of the target type")]
+    InvalidValue,
+    #[error("data is not in canonical form, such as elements out of order")]
+    NonCanonical,
+    #[error("sealed data failed authentication")]
+    Authentication,
+    /// A `#[benc(validate = ...)]` hook rejected the decoded value.
+    #[error("validation failed: {0}")]
+    Validation(String),
+    #[error("schema {0} is not known")]
+    UnknownSchema(u64),
+    /// A collection held more elements than the bounded type decoding it can hold.
+    #[error("collection of {len} elements exceeds the capacity of {capacity}")]
+    CapacityExceeded { len: usize, capacity: usize },
+    /// The operation does not apply to its arguments, such as a type that does not
+    /// describe its encoding where a schema is needed or a field selector that names
+    /// no field.
+    #[error("unsupported operation: {0}")]
+    Unsupported(String),
+    /// A collection ended with the default terminator where the custom `expected` one
+    /// was configured, which means the encoder uses another [`Terminator`].
+    #[error("found the default terminator where {expected:?} was expected; the encoder is configured differently")]
+    TerminatorMismatch { expected: [u8; 4] },
+}
+
+impl From<Error> for std::io::Error {
+    /// Converts a decoding error into an `InvalidData` I/O error, for APIs that
+    /// operate on `std::io` readers and writers.
+    fn from(err: Error) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
 }
 
 // ===================================================================================
@@ -66,9 +353,9 @@ pub enum Error {
 
 /// A helper function to advance a slice cursor.
 #[inline]
-fn advance<'a>(slice: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+pub(crate) fn advance<'a>(slice: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
     if slice.len() < n {
-        return Err(Error::BufferTooSmall);
+        return Err(Error::BufferTooSmall { needed: n, available: slice.len() });
     }
     let (head, tail) = slice.split_at(n);
     *slice = tail;
@@ -77,17 +364,62 @@ fn advance<'a>(slice: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
 
 /// A helper function to write to a slice cursor.
 #[inline]
-fn write_to_slice<'a>(slice: &mut &'a mut [u8], data: &[u8]) -> Result<()> {
+pub(crate) const fn write_to_slice(slice: &mut &mut [u8], data: &[u8]) -> Result<()> {
     if slice.len() < data.len() {
-        return Err(Error::BufferTooSmall);
+        return Err(Error::BufferTooSmall { needed: data.len(), available: slice.len() });
     }
     // This cannot be a single call due to lifetime issues with mutable borrows.
-    let (head, tail) = std::mem::take(slice).split_at_mut(data.len());
+    // `mem::take` is not a `const fn`, so the slice is replaced by hand.
+    #[allow(clippy::mem_replace_with_default)]
+    let (head, tail) = std::mem::replace(slice, &mut []).split_at_mut(data.len());
     head.copy_from_slice(data);
     *slice = tail;
     Ok(())
 }
 
+/// A helper function to check up front that a value of `needed` bytes fits into the
+/// writer, so the error reports the size of the whole value rather than the part of
+/// it that did not fit.
+#[inline]
+pub(crate) fn ensure_capacity(writer: &[u8], needed: usize) -> Result<()> {
+    if writer.len() < needed {
+        return Err(Error::BufferTooSmall { needed, available: writer.len() });
+    }
+    Ok(())
+}
+
+/// A helper function to validate UTF-8, using SIMD instructions when the `simdutf8`
+/// feature is enabled.
+#[inline]
+pub(crate) fn from_utf8(bytes: &[u8]) -> Result<&str> {
+    #[cfg(feature = "simdutf8")]
+    if let Ok(s) = simdutf8::basic::from_utf8(bytes) {
+        return Ok(s);
+    }
+    // Without the feature, or to report where validation failed.
+    Ok(std::str::from_utf8(bytes)?)
+}
+
+/// Rejects a collection length that the data cannot back: an element that occupied
+/// no bytes gives no bound on the number of elements, so the length must not exceed
+/// the input that was left before the element.
+#[inline]
+pub(crate) fn check_element_progress(len: usize, remaining: usize, reader: &[u8]) -> Result<()> {
+    if reader.len() == remaining && len > remaining {
+        return Err(Error::InvalidValue);
+    }
+    Ok(())
+}
+
+/// A helper function to consume the terminator sequence from a slice cursor.
+#[inline]
+pub(crate) fn read_terminator(reader: &mut &[u8]) -> Result<()> {
+    if advance(reader, TERMINATOR.len())? != TERMINATOR {
+        return Err(Error::MissingTerminator);
+    }
+    Ok(())
+}
+
 // ===================================================================================
 // String
 // ===================================================================================
@@ -102,16 +434,47 @@ pub fn size_string(s: &str) -> usize {
 ///
 /// Returns an error if the writer is too small.
 pub fn marshal_string(s: &str, writer: &mut &mut [u8]) -> Result<()> {
+    ensure_capacity(writer, size_string(s))?;
     marshal_uint(s.len() as u64, writer)?;
     write_to_slice(writer, s.as_bytes())
 }
 
 /// Unmarshals a string slice from the reader without allocating.
 /// The returned `&str` is a slice of the input buffer.
+///
+/// With the `simdutf8` feature, UTF-8 validation uses SIMD instructions.
 pub fn unmarshal_string<'a>(reader: &mut &'a [u8]) -> Result<&'a str> {
     let len = unmarshal_uint(reader)? as usize;
     let bytes = advance(reader, len)?;
-    Ok(std::str::from_utf8(bytes)?)
+    from_utf8(bytes)
+}
+
+/// Unmarshals a string slice from the reader without allocating and without
+/// validating that it is UTF-8.
+///
+/// # Safety
+///
+/// The marshalled string must be valid UTF-8, e.g. because the buffer was produced by
+/// `marshal_string` in this process or was already validated. Decoding invalid UTF-8
+/// with this function is undefined behavior.
+#[cfg(feature = "unsafe-fast")]
+pub unsafe fn unmarshal_string_unchecked<'a>(reader: &mut &'a [u8]) -> Result<&'a str> {
+    let len = unmarshal_uint(reader)? as usize;
+    let bytes = advance(reader, len)?;
+    // SAFETY: the caller guarantees the bytes are valid UTF-8.
+    Ok(unsafe { std::str::from_utf8_unchecked(bytes) })
+}
+
+/// Unmarshals a string from the reader, replacing invalid UTF-8 sequences with
+/// `U+FFFD REPLACEMENT CHARACTER` instead of failing.
+/// Valid strings are borrowed from the input buffer; only invalid ones are copied.
+pub fn unmarshal_string_lossy<'a>(reader: &mut &'a [u8]) -> Result<Cow<'a, str>> {
+    let len = unmarshal_uint(reader)? as usize;
+    let bytes = advance(reader, len)?;
+    match from_utf8(bytes) {
+        Ok(s) => Ok(Cow::Borrowed(s)),
+        Err(_) => Ok(String::from_utf8_lossy(bytes)),
+    }
 }
 
 /// Skips over a marshalled string in the reader.
@@ -135,6 +498,7 @@ pub fn size_bytes(b: &[u8]) -> usize {
 ///
 /// Returns an error if the writer is too small.
 pub fn marshal_bytes(b: &[u8], writer: &mut &mut [u8]) -> Result<()> {
+    ensure_capacity(writer, size_bytes(b))?;
     marshal_uint(b.len() as u64, writer)?;
     write_to_slice(writer, b)
 }
@@ -153,6 +517,19 @@ pub fn unmarshal_bytes_copied(reader: &mut &[u8]) -> Result<Vec<u8>> {
     Ok(bytes.to_vec())
 }
 
+/// Unmarshals a byte slice from the reader by copying into an existing `Vec<u8>`,
+/// replacing its contents. The vector's allocation is reused, which avoids an
+/// allocation per call when decoding many byte slices in a loop.
+///
+/// The vector is left unchanged if an error is returned.
+pub fn unmarshal_bytes_into(reader: &mut &[u8], buf: &mut Vec<u8>) -> Result<()> {
+    let len = unmarshal_uint(reader)? as usize;
+    let bytes = advance(reader, len)?;
+    buf.clear();
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
 /// Skips over a marshalled byte slice in the reader.
 pub fn skip_bytes(reader: &mut &[u8]) -> Result<()> {
     let len = unmarshal_uint(reader)? as usize;
@@ -181,58 +558,152 @@ pub fn size_fixed_slice<T>(slice: &[T], element_size: usize) -> usize {
 /// Marshals a slice into the writer.
 ///
 /// Returns an error if the writer is too small.
-pub fn marshal_slice<T>(
+pub fn marshal_slice<T, E: From<Error>>(
     slice: &[T],
     writer: &mut &mut [u8],
-    marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<()>,
-) -> Result<()> {
+    marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<(), E>,
+) -> Result<(), E> {
     marshal_uint(slice.len() as u64, writer)?;
     for item in slice {
         marshaler(item, writer)?;
     }
-    write_to_slice(writer, &TERMINATOR)
+    Ok(write_to_slice(writer, &TERMINATOR)?)
 }
 
-/// Unmarshals a slice from the reader.
-pub fn unmarshal_slice<T>(
-    reader: &mut &[u8],
-    unmarshaler: impl Fn(&mut &[u8]) -> Result<T>,
-) -> Result<Vec<T>> {
+/// Unmarshals a slice from the reader. The elements may borrow from the reader, such
+/// as `&str` or `&[u8]`, so decoding does not have to allocate per element.
+///
+/// Returns an `InvalidValue` error if an element occupies no bytes while the length
+/// exceeds the remaining input, as such a length cannot be checked against the data.
+pub fn unmarshal_slice<'a, T, E: From<Error>>(
+    reader: &mut &'a [u8],
+    unmarshaler: impl Fn(&mut &'a [u8]) -> Result<T, E>,
+) -> Result<Vec<T>, E> {
+    unmarshal_elements(reader, unmarshaler, Some(Terminator::DEFAULT))
+}
+
+/// Unmarshals a slice ending with `terminator`, or after its last element if there is
+/// none. Shared by the default, custom-terminator and legacy layouts.
+pub(crate) fn unmarshal_elements<'a, T, E: From<Error>>(
+    reader: &mut &'a [u8],
+    unmarshaler: impl Fn(&mut &'a [u8]) -> Result<T, E>,
+    terminator: Option<Terminator>,
+) -> Result<Vec<T>, E> {
     let len = unmarshal_uint(reader)? as usize;
-    let mut vec = Vec::with_capacity(len);
+    // The length is untrusted, so the preallocation is bounded by the remaining input.
+    let mut vec = Vec::with_capacity(len.min(reader.len()));
     for _ in 0..len {
+        let remaining = reader.len();
         vec.push(unmarshaler(reader)?);
+        check_element_progress(len, remaining, reader)?;
     }
-    let terminator = advance(reader, TERMINATOR.len())?;
-    if terminator != TERMINATOR {
-        return Err(Error::MissingTerminator);
+    if let Some(terminator) = terminator {
+        terminator.read(reader)?;
     }
     Ok(vec)
 }
 
+/// Unmarshals a slice of strings borrowed from the reader.
+pub fn unmarshal_str_slice<'a>(reader: &mut &'a [u8]) -> Result<Vec<&'a str>> {
+    unmarshal_slice(reader, unmarshal_string)
+}
+
+/// Unmarshals a slice of byte slices borrowed from the reader.
+pub fn unmarshal_bytes_slice<'a>(reader: &mut &'a [u8]) -> Result<Vec<&'a [u8]>> {
+    unmarshal_slice(reader, unmarshal_bytes_cropped)
+}
+
+/// Unmarshals a slice from the reader, verifying that its elements are in strictly
+/// increasing order (sorted and free of duplicates).
+/// Returns a `NonCanonical` error otherwise.
+pub fn unmarshal_slice_sorted<T: Ord, E: From<Error>>(
+    reader: &mut &[u8],
+    unmarshaler: impl Fn(&mut &[u8]) -> Result<T, E>,
+) -> Result<Vec<T>, E> {
+    let len = unmarshal_uint(reader)? as usize;
+    let mut vec: Vec<T> = Vec::with_capacity(len.min(reader.len()));
+    for _ in 0..len {
+        let item = unmarshaler(reader)?;
+        if vec.last().is_some_and(|last| *last >= item) {
+            return Err(Error::NonCanonical.into());
+        }
+        vec.push(item);
+    }
+    read_terminator(reader)?;
+    Ok(vec)
+}
+
 /// Skips over a marshalled slice in the reader.
-pub fn skip_slice(
+///
+/// Returns an `InvalidValue` error if an element occupies no bytes while the length
+/// exceeds the remaining input.
+pub fn skip_slice<E: From<Error>>(
     reader: &mut &[u8],
-    skip_element: impl Fn(&mut &[u8]) -> Result<()>,
-) -> Result<()> {
+    skip_element: impl Fn(&mut &[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    skip_elements(reader, skip_element, Some(Terminator::DEFAULT))
+}
+
+/// Skips over a slice ending with `terminator`, or after its last element if there is
+/// none.
+pub(crate) fn skip_elements<E: From<Error>>(
+    reader: &mut &[u8],
+    skip_element: impl Fn(&mut &[u8]) -> Result<(), E>,
+    terminator: Option<Terminator>,
+) -> Result<(), E> {
     let len = unmarshal_uint(reader)? as usize;
     for _ in 0..len {
+        let remaining = reader.len();
         skip_element(reader)?;
+        check_element_progress(len, remaining, reader)?;
     }
-    let terminator = advance(reader, TERMINATOR.len())?;
-    if terminator != TERMINATOR {
-        return Err(Error::MissingTerminator);
+    if let Some(terminator) = terminator {
+        terminator.read(reader)?;
     }
     Ok(())
 }
 
+/// Skips over a marshalled slice whose elements all occupy `element_size` bytes.
+/// Unlike `skip_slice`, this jumps over the elements in one step.
+/// Returns an `OutOfRange` error if the total size overflows a `usize`.
+pub fn skip_fixed_slice(reader: &mut &[u8], element_size: usize) -> Result<()> {
+    let len = unmarshal_usize(reader)?;
+    let bytes = len.checked_mul(element_size).ok_or(Error::OutOfRange)?;
+    advance(reader, bytes)?;
+    read_terminator(reader)
+}
+
+/// Builds an index of a marshalled slice and advances the reader past it.
+///
+/// The returned vector holds the byte offset of every element, relative to the
+/// position of the reader when this function was called (the start of the marshalled
+/// slice). Given the original buffer, element `i` can later be read directly from
+/// `&buf[offsets[i]..]` without skipping the elements before it.
+pub fn index_slice<E: From<Error>>(
+    reader: &mut &[u8],
+    skip_element: impl Fn(&mut &[u8]) -> Result<(), E>,
+) -> Result<Vec<usize>, E> {
+    let start = reader.len();
+    let len = unmarshal_uint(reader)? as usize;
+    let mut offsets = Vec::with_capacity(len.min(reader.len()));
+    for _ in 0..len {
+        let remaining = reader.len();
+        offsets.push(start - remaining);
+        skip_element(reader)?;
+        check_element_progress(len, remaining, reader)?;
+    }
+    read_terminator(reader)?;
+    Ok(offsets)
+}
+
 // ===================================================================================
 // Map / HashMap<K, V>
 // ===================================================================================
 
 /// Returns the bytes needed to marshal a map.
-pub fn size_map<K, V>(
-    map: &HashMap<K, V>,
+/// Works with any hasher, the hasher does not affect the marshalled data.
+pub fn size_map<K, V, S>(
+    map: &HashMap<K, V, S>,
     k_sizer: impl Fn(&K) -> usize,
     v_sizer: impl Fn(&V) -> usize,
 ) -> usize {
@@ -246,63 +717,209 @@ pub fn size_map<K, V>(
 }
 
 /// Marshals a map into the writer.
+/// Works with any hasher, the hasher does not affect the marshalled data.
 ///
 /// Returns an error if the writer is too small.
-pub fn marshal_map<K, V>(
-    map: &HashMap<K, V>,
+pub fn marshal_map<K, V, S, E: From<Error>>(
+    map: &HashMap<K, V, S>,
     writer: &mut &mut [u8],
-    k_marshaler: impl Fn(&K, &mut &mut [u8]) -> Result<()>,
-    v_marshaler: impl Fn(&V, &mut &mut [u8]) -> Result<()>,
-) -> Result<()> {
+    k_marshaler: impl Fn(&K, &mut &mut [u8]) -> Result<(), E>,
+    v_marshaler: impl Fn(&V, &mut &mut [u8]) -> Result<(), E>,
+) -> Result<(), E> {
     marshal_uint(map.len() as u64, writer)?;
     for (k, v) in map.iter() {
         k_marshaler(k, writer)?;
         v_marshaler(v, writer)?;
     }
-    write_to_slice(writer, &TERMINATOR)
+    Ok(write_to_slice(writer, &TERMINATOR)?)
+}
+
+/// Returns the bytes needed to marshal key-value pairs as a map, as `marshal_pairs`
+/// writes them.
+pub fn size_pairs<'k, 'v, K: 'k, V: 'v>(
+    pairs: impl IntoIterator<Item = (&'k K, &'v V)>,
+    k_sizer: impl Fn(&K) -> usize,
+    v_sizer: impl Fn(&V) -> usize,
+) -> usize {
+    let mut len = 0;
+    let mut total_size = TERMINATOR.len();
+    for (k, v) in pairs {
+        len += 1;
+        total_size += k_sizer(k) + v_sizer(v);
+    }
+    total_size + size_uint(len)
+}
+
+/// Marshals key-value pairs as a map into the writer, in the order of the iterator,
+/// so collections such as `Vec<(K, V)>` or `BTreeMap` need no temporary `HashMap`.
+/// Pairs with equal keys are all written; decoding into a map keeps the last one.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_pairs<'k, 'v, K: 'k, V: 'v, E: From<Error>>(
+    pairs: impl ExactSizeIterator<Item = (&'k K, &'v V)>,
+    writer: &mut &mut [u8],
+    k_marshaler: impl Fn(&K, &mut &mut [u8]) -> Result<(), E>,
+    v_marshaler: impl Fn(&V, &mut &mut [u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    marshal_uint(pairs.len() as u64, writer)?;
+    for (k, v) in pairs {
+        k_marshaler(k, writer)?;
+        v_marshaler(v, writer)?;
+    }
+    Ok(write_to_slice(writer, &TERMINATOR)?)
+}
+
+/// Marshals key-value pairs as a map like `marshal_pairs`, ordered by key, which is
+/// the canonical form of a map that `unmarshal_map_verified` accepts.
+///
+/// Returns a `NonCanonical` error, before writing anything, if two keys are equal.
+pub fn marshal_pairs_sorted<'k, 'v, K: Ord + 'k, V: 'v, E: From<Error>>(
+    pairs: impl IntoIterator<Item = (&'k K, &'v V)>,
+    writer: &mut &mut [u8],
+    k_marshaler: impl Fn(&K, &mut &mut [u8]) -> Result<(), E>,
+    v_marshaler: impl Fn(&V, &mut &mut [u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut sorted: Vec<_> = pairs.into_iter().collect();
+    sorted.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    if sorted.windows(2).any(|w| w[0].0 == w[1].0) {
+        return Err(Error::NonCanonical.into());
+    }
+    marshal_pairs(sorted.into_iter(), writer, k_marshaler, v_marshaler)
 }
 
-/// Unmarshals a map from the reader.
-pub fn unmarshal_map<'a, K, V>(
+/// Unmarshals a map from the reader into a `HashMap` with the default hasher.
+pub fn unmarshal_map<'a, K, V, E: From<Error>>(
     reader: &mut &'a [u8],
-    k_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<K>,
-    v_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<V>,
-) -> Result<HashMap<K, V>>
+    k_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<K, E>,
+    v_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<V, E>,
+) -> Result<HashMap<K, V>, E>
 where
     K: Eq + Hash,
+{
+    unmarshal_map_with_hasher(reader, k_unmarshaler, v_unmarshaler)
+}
+
+/// Unmarshals a map from the reader into a `HashMap` using the hasher `S`
+/// (e.g. `FxBuildHasher` or `ahash::RandomState`).
+pub fn unmarshal_map_with_hasher<'a, K, V, S, E: From<Error>>(
+    reader: &mut &'a [u8],
+    k_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<K, E>,
+    v_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<V, E>,
+) -> Result<HashMap<K, V, S>, E>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
 {
     let len = unmarshal_uint(reader)? as usize;
-    let mut map = HashMap::with_capacity(len);
+    let mut map = HashMap::with_capacity_and_hasher(len.min(reader.len()), S::default());
     for _ in 0..len {
         let k = k_unmarshaler(reader)?;
         let v = v_unmarshaler(reader)?;
         map.insert(k, v);
     }
-    let terminator = advance(reader, TERMINATOR.len())?;
-    if terminator != TERMINATOR {
-        return Err(Error::MissingTerminator);
+    read_terminator(reader)?;
+    Ok(map)
+}
+
+/// Unmarshals a map from the reader into any collection that can be extended with
+/// key-value pairs, such as `BTreeMap`, `IndexMap` or `Vec<(K, V)>`.
+/// Entries are inserted in the order they appear in the reader.
+pub fn unmarshal_map_into<'a, K, V, M, E: From<Error>>(
+    reader: &mut &'a [u8],
+    k_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<K, E>,
+    v_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<V, E>,
+) -> Result<M, E>
+where
+    M: Default + Extend<(K, V)>,
+{
+    let len = unmarshal_uint(reader)? as usize;
+    let mut map = M::default();
+    for _ in 0..len {
+        let k = k_unmarshaler(reader)?;
+        let v = v_unmarshaler(reader)?;
+        map.extend(Some((k, v)));
     }
+    read_terminator(reader)?;
+    Ok(map)
+}
+
+/// Unmarshals a map from the reader into its entries, in the order they appear in the
+/// reader. Unlike the other map functions, keys need not be `Eq + Hash` or `Ord`, and
+/// repeated keys are all kept.
+pub fn unmarshal_pairs<'a, K, V, E: From<Error>>(
+    reader: &mut &'a [u8],
+    k_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<K, E>,
+    v_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<V, E>,
+) -> Result<Vec<(K, V)>, E> {
+    let len = unmarshal_uint(reader)? as usize;
+    let mut pairs = Vec::with_capacity(len.min(reader.len()));
+    for _ in 0..len {
+        let k = k_unmarshaler(reader)?;
+        let v = v_unmarshaler(reader)?;
+        pairs.push((k, v));
+    }
+    read_terminator(reader)?;
+    Ok(pairs)
+}
+
+/// Unmarshals a map like `unmarshal_map_into`, verifying that its keys are in strictly
+/// increasing order, which is the canonical form of a map.
+/// Returns a `NonCanonical` error otherwise.
+pub fn unmarshal_map_verified<'a, K, V, M, E: From<Error>>(
+    reader: &mut &'a [u8],
+    k_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<K, E>,
+    v_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<V, E>,
+) -> Result<M, E>
+where
+    K: Ord,
+    M: Default + Extend<(K, V)>,
+{
+    let len = unmarshal_uint(reader)? as usize;
+    let mut map = M::default();
+    // The previous entry is held back until the next key has been compared against it.
+    let mut prev: Option<(K, V)> = None;
+    for _ in 0..len {
+        let k = k_unmarshaler(reader)?;
+        if prev.as_ref().is_some_and(|(prev_k, _)| *prev_k >= k) {
+            return Err(Error::NonCanonical.into());
+        }
+        let v = v_unmarshaler(reader)?;
+        map.extend(prev.replace((k, v)));
+    }
+    map.extend(prev);
+    read_terminator(reader)?;
     Ok(map)
 }
 
 /// Skips over a marshalled map by skipping each key and value individually.
-pub fn skip_map(
+pub fn skip_map<E: From<Error>>(
     reader: &mut &[u8],
-    skip_key: impl Fn(&mut &[u8]) -> Result<()>,
-    skip_value: impl Fn(&mut &[u8]) -> Result<()>,
-) -> Result<()> {
+    skip_key: impl Fn(&mut &[u8]) -> Result<(), E>,
+    skip_value: impl Fn(&mut &[u8]) -> Result<(), E>,
+) -> Result<(), E> {
     let len = unmarshal_uint(reader)? as usize;
     for _ in 0..len {
         skip_key(reader)?;
         skip_value(reader)?;
     }
-    let terminator = advance(reader, TERMINATOR.len())?;
-    if terminator != TERMINATOR {
-        return Err(Error::MissingTerminator);
-    }
+    read_terminator(reader)?;
     Ok(())
 }
 
+/// Skips over a marshalled map whose keys all occupy `key_size` bytes and whose values
+/// all occupy `value_size` bytes. Unlike `skip_map`, this jumps over the entries in
+/// one step.
+/// Returns an `OutOfRange` error if the total size overflows a `usize`.
+pub fn skip_fixed_map(reader: &mut &[u8], key_size: usize, value_size: usize) -> Result<()> {
+    let len = unmarshal_usize(reader)?;
+    let bytes = key_size
+        .checked_add(value_size)
+        .and_then(|entry_size| len.checked_mul(entry_size))
+        .ok_or(Error::OutOfRange)?;
+    advance(reader, bytes)?;
+    read_terminator(reader)
+}
+
 // ===================================================================================
 // Varint (u64 / i64)
 // ===================================================================================
@@ -312,7 +929,7 @@ pub fn skip_map(
 /// This is a low-level helper function, primarily used internally for
 /// `marshal_int`, but exposed for testing purposes.
 #[inline]
-pub fn encode_zigzag(v: i64) -> u64 {
+pub const fn encode_zigzag(v: i64) -> u64 {
     ((v << 1) ^ (v >> 63)) as u64
 }
 
@@ -321,13 +938,13 @@ pub fn encode_zigzag(v: i64) -> u64 {
 /// This is a low-level helper function, primarily used internally for
 /// `unmarshal_int`, but exposed for testing purposes.
 #[inline]
-pub fn decode_zigzag(v: u64) -> i64 {
+pub const fn decode_zigzag(v: u64) -> i64 {
     ((v >> 1) as i64) ^ (-((v & 1) as i64))
 }
 
 
 /// Returns the number of bytes required to marshal a `u64` as a varint.
-pub fn size_uint(v: u64) -> usize {
+pub const fn size_uint(v: u64) -> usize {
     if v == 0 {
         return 1;
     }
@@ -339,7 +956,7 @@ pub fn size_uint(v: u64) -> usize {
 /// Marshals a `u64` as a varint into the writer.
 ///
 /// Returns an error if the writer is too small.
-pub fn marshal_uint(mut v: u64, writer: &mut &mut [u8]) -> Result<()> {
+pub const fn marshal_uint(mut v: u64, writer: &mut &mut [u8]) -> Result<()> {
     let mut buf = [0u8; MAX_VARINT_LEN_64];
     let mut i = 0;
     while v >= 0x80 {
@@ -349,7 +966,7 @@ pub fn marshal_uint(mut v: u64, writer: &mut &mut [u8]) -> Result<()> {
     }
     buf[i] = v as u8;
     i += 1;
-    write_to_slice(writer, &buf[..i])
+    write_to_slice(writer, buf.split_at(i).0)
 }
 
 /// Unmarshals a varint-encoded `u64` from the reader.
@@ -357,7 +974,9 @@ pub fn unmarshal_uint(reader: &mut &[u8]) -> Result<u64> {
     let mut val: u64 = 0;
     let mut shift: u32 = 0;
     for i in 0..MAX_VARINT_LEN_64 {
-        let byte = *reader.get(i).ok_or(Error::BufferTooSmall)?;
+        let byte = *reader
+            .get(i)
+            .ok_or(Error::BufferTooSmall { needed: i + 1, available: reader.len() })?;
         if byte < 0x80 {
             // Last byte
             if i == MAX_VARINT_LEN_64 - 1 && byte > 1 {
@@ -372,10 +991,26 @@ pub fn unmarshal_uint(reader: &mut &[u8]) -> Result<u64> {
     Err(Error::VarintOverflow)
 }
 
+/// Unmarshals a varint-encoded `u64` from the reader like `unmarshal_uint`, but
+/// returns a `NonMinimalVarint` error, leaving the reader unchanged, if the varint is
+/// longer than `marshal_uint` writes it. Every value then has a single encoding,
+/// which canonical and signed data require.
+pub fn unmarshal_uint_strict(reader: &mut &[u8]) -> Result<u64> {
+    let mut buf = *reader;
+    let val = unmarshal_uint(&mut buf)?;
+    if reader.len() - buf.len() != size_uint(val) {
+        return Err(Error::NonMinimalVarint);
+    }
+    *reader = buf;
+    Ok(val)
+}
+
 /// Skips over a marshalled varint in the reader.
 pub fn skip_uint(reader: &mut &[u8]) -> Result<()> {
     for i in 0..MAX_VARINT_LEN_64 {
-        let byte = *reader.get(i).ok_or(Error::BufferTooSmall)?;
+        let byte = *reader
+            .get(i)
+            .ok_or(Error::BufferTooSmall { needed: i + 1, available: reader.len() })?;
         if byte < 0x80 {
             // Add the same overflow check as in unmarshal_uint for the 10-byte case.
             if i == MAX_VARINT_LEN_64 - 1 && byte > 1 {
@@ -389,14 +1024,14 @@ pub fn skip_uint(reader: &mut &[u8]) -> Result<()> {
 }
 
 /// Returns the number of bytes required to marshal an `i64` as a varint.
-pub fn size_int(v: i64) -> usize {
+pub const fn size_int(v: i64) -> usize {
     size_uint(encode_zigzag(v))
 }
 
 /// Marshals an `i64` as a ZigZag-encoded varint into the writer.
 ///
 /// Returns an error if the writer is too small.
-pub fn marshal_int(v: i64, writer: &mut &mut [u8]) -> Result<()> {
+pub const fn marshal_int(v: i64, writer: &mut &mut [u8]) -> Result<()> {
     marshal_uint(encode_zigzag(v), writer)
 }
 
@@ -405,18 +1040,98 @@ pub fn unmarshal_int(reader: &mut &[u8]) -> Result<i64> {
     unmarshal_uint(reader).map(decode_zigzag)
 }
 
+/// Unmarshals a ZigZag-encoded varint `i64` from the reader, rejecting varints that
+/// are not minimal. See `unmarshal_uint_strict`.
+pub fn unmarshal_int_strict(reader: &mut &[u8]) -> Result<i64> {
+    unmarshal_uint_strict(reader).map(decode_zigzag)
+}
+
 /// Skips over a marshalled zigzag-encoded varint in the reader.
 pub fn skip_int(reader: &mut &[u8]) -> Result<()> {
     skip_uint(reader)
 }
 
+/// Decodes a varint from a buffer that is long enough for any varint, returning the
+/// value and the number of bytes it occupies. Indexing a fixed-size array needs no
+/// bounds checks.
+#[inline]
+fn decode_uint(bytes: &[u8; MAX_VARINT_LEN_64]) -> Result<(u64, usize)> {
+    let mut val: u64 = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if byte < 0x80 {
+            if i == MAX_VARINT_LEN_64 - 1 && byte > 1 {
+                return Err(Error::VarintOverflow);
+            }
+            return Ok((val | (u64::from(byte) << (7 * i)), i + 1));
+        }
+        val |= u64::from(byte & 0x7F) << (7 * i);
+    }
+    Err(Error::VarintOverflow)
+}
+
+/// Unmarshals `out.len()` consecutive varint-encoded `u64`s, as written by repeated
+/// calls to `marshal_uint`, from the reader.
+///
+/// This is considerably faster than calling `unmarshal_uint` in a loop: bounds are
+/// checked once per value rather than once per byte, and runs of single-byte varints
+/// are detected and decoded eight at a time using word-wide operations.
+///
+/// On error, the reader is left unchanged and the contents of `out` are unspecified.
+pub fn unmarshal_uints(reader: &mut &[u8], out: &mut [u64]) -> Result<()> {
+    let mut buf = *reader;
+    let mut i = 0;
+    while i < out.len() {
+        if out.len() - i >= 8
+            && let Some(word) = buf.first_chunk::<8>()
+            && u64::from_le_bytes(*word) & 0x8080_8080_8080_8080 == 0
+        {
+            for (o, &b) in out[i..i + 8].iter_mut().zip(word) {
+                *o = u64::from(b);
+            }
+            buf = &buf[8..];
+            i += 8;
+            continue;
+        }
+        out[i] = match buf.first_chunk::<MAX_VARINT_LEN_64>() {
+            Some(bytes) => {
+                let (v, len) = decode_uint(bytes)?;
+                buf = &buf[len..];
+                v
+            }
+            // Near the end of the buffer, fall back to the checked decoder.
+            None => unmarshal_uint(&mut buf)?,
+        };
+        i += 1;
+    }
+    *reader = buf;
+    Ok(())
+}
+
+/// Unmarshals `out.len()` consecutive zigzag-encoded varint `i64`s, as written by
+/// repeated calls to `marshal_int`, from the reader. See `unmarshal_uints`.
+///
+/// On error, the reader is left unchanged and the contents of `out` are unspecified.
+pub fn unmarshal_ints(reader: &mut &[u8], out: &mut [i64]) -> Result<()> {
+    let mut buf = *reader;
+    let mut raw = [0u64; 64];
+    for chunk in out.chunks_mut(raw.len()) {
+        let raw = &mut raw[..chunk.len()];
+        unmarshal_uints(&mut buf, raw)?;
+        for (o, &v) in chunk.iter_mut().zip(raw.iter()) {
+            *o = decode_zigzag(v);
+        }
+    }
+    *reader = buf;
+    Ok(())
+}
+
 // ===================================================================================
 // Varint (usize / isize) - Platform Dependent
 // ===================================================================================
 
 /// Returns the number of bytes required to marshal a `usize` as a varint.
 /// Note: The value is always marshalled as a `u64` for platform independence.
-pub fn size_usize(v: usize) -> usize {
+pub const fn size_usize(v: usize) -> usize {
     size_uint(v as u64)
 }
 
@@ -424,7 +1139,7 @@ pub fn size_usize(v: usize) -> usize {
 /// Note: The value is always marshalled as a `u64` for platform independence.
 ///
 /// Returns an error if the writer is too small.
-pub fn marshal_usize(v: usize, writer: &mut &mut [u8]) -> Result<()> {
+pub const fn marshal_usize(v: usize, writer: &mut &mut [u8]) -> Result<()> {
     marshal_uint(v as u64, writer)
 }
 
@@ -436,6 +1151,13 @@ pub fn unmarshal_usize(reader: &mut &[u8]) -> Result<usize> {
     usize::try_from(val).map_err(|_| Error::OutOfRange)
 }
 
+/// Unmarshals a `usize` from the reader, rejecting varints that are not minimal. See
+/// `unmarshal_uint_strict`.
+pub fn unmarshal_usize_strict(reader: &mut &[u8]) -> Result<usize> {
+    let val = unmarshal_uint_strict(reader)?;
+    usize::try_from(val).map_err(|_| Error::OutOfRange)
+}
+
 /// Skips over a marshalled `usize` in the reader.
 pub fn skip_usize(reader: &mut &[u8]) -> Result<()> {
     skip_uint(reader)
@@ -443,7 +1165,7 @@ pub fn skip_usize(reader: &mut &[u8]) -> Result<()> {
 
 /// Returns the number of bytes required to marshal an `isize` as a varint.
 /// Note: The value is always marshalled as an `i64` for platform independence.
-pub fn size_isize(v: isize) -> usize {
+pub const fn size_isize(v: isize) -> usize {
     size_int(v as i64)
 }
 
@@ -451,7 +1173,7 @@ pub fn size_isize(v: isize) -> usize {
 /// Note: The value is always marshalled as an `i64` for platform independence.
 ///
 /// Returns an error if the writer is too small.
-pub fn marshal_isize(v: isize, writer: &mut &mut [u8]) -> Result<()> {
+pub const fn marshal_isize(v: isize, writer: &mut &mut [u8]) -> Result<()> {
     marshal_int(v as i64, writer)
 }
 
@@ -463,6 +1185,13 @@ pub fn unmarshal_isize(reader: &mut &[u8]) -> Result<isize> {
     isize::try_from(val).map_err(|_| Error::OutOfRange)
 }
 
+/// Unmarshals an `isize` from the reader, rejecting varints that are not minimal. See
+/// `unmarshal_uint_strict`.
+pub fn unmarshal_isize_strict(reader: &mut &[u8]) -> Result<isize> {
+    let val = unmarshal_int_strict(reader)?;
+    isize::try_from(val).map_err(|_| Error::OutOfRange)
+}
+
 /// Skips over a marshalled `isize` in the reader.
 pub fn skip_isize(reader: &mut &[u8]) -> Result<()> {
     skip_int(reader)
@@ -488,7 +1217,7 @@ macro_rules! fixed_size_impl {
         /// Marshals a `$type` into the writer using little-endian encoding.
         ///
         /// Returns an error if the writer is too small.
-        pub fn $marshal_fn(v: $type, writer: &mut &mut [u8]) -> Result<()> {
+        pub const fn $marshal_fn(v: $type, writer: &mut &mut [u8]) -> Result<()> {
             let bytes = v.to_le_bytes();
             write_to_slice(writer, &bytes)
         }
@@ -521,7 +1250,7 @@ macro_rules! fixed_size_impl {
         /// Marshals a `$type` into the writer using little-endian encoding.
         ///
         /// Returns an error if the writer is too small.
-        pub fn $marshal_fn(v: $type, writer: &mut &mut [u8]) -> Result<()> {
+        pub const fn $marshal_fn(v: $type, writer: &mut &mut [u8]) -> Result<()> {
             let bytes = v.to_bits().to_le_bytes();
             write_to_slice(writer, &bytes)
         }
@@ -555,7 +1284,7 @@ fixed_size_impl!(float f64, size_f64, marshal_f64, unmarshal_f64, skip_f64, u64)
 pub const fn size_u8() -> usize { 1 }
 /// Marshals a `u8` (byte) into the writer.
 /// Returns an error if the writer is too small.
-pub fn marshal_u8(v: u8, writer: &mut &mut [u8]) -> Result<()> {
+pub const fn marshal_u8(v: u8, writer: &mut &mut [u8]) -> Result<()> {
     write_to_slice(writer, &[v])
 }
 /// Unmarshals a `u8` (byte) from the reader.
@@ -572,7 +1301,7 @@ pub fn skip_u8(reader: &mut &[u8]) -> Result<()> {
 pub const fn size_i8() -> usize { 1 }
 /// Marshals an `i8` into the writer.
 /// Returns an error if the writer is too small.
-pub fn marshal_i8(v: i8, writer: &mut &mut [u8]) -> Result<()> {
+pub const fn marshal_i8(v: i8, writer: &mut &mut [u8]) -> Result<()> {
     marshal_u8(v as u8, writer)
 }
 /// Unmarshals an `i8` from the reader.
@@ -590,7 +1319,7 @@ pub fn skip_i8(reader: &mut &[u8]) -> Result<()> {
 pub const fn size_bool() -> usize { 1 }
 /// Marshals a `bool` into the writer (1 for true, 0 for false).
 /// Returns an error if the writer is too small.
-pub fn marshal_bool(v: bool, writer: &mut &mut [u8]) -> Result<()> {
+pub const fn marshal_bool(v: bool, writer: &mut &mut [u8]) -> Result<()> {
     marshal_u8(if v { 1 } else { 0 }, writer)
 }
 /// Unmarshals a `bool` from the reader.
@@ -634,6 +1363,88 @@ pub fn skip_time(reader: &mut &[u8]) -> Result<()> {
     skip_i64(reader)
 }
 
+// ===================================================================================
+// Time with offset (chrono::DateTime<FixedOffset>)
+// ===================================================================================
+
+/// Returns the number of bytes required to marshal a `DateTime<FixedOffset>`.
+pub const fn size_time_offset() -> usize {
+    size_i64() + size_i32()
+}
+
+/// Marshals a `DateTime<FixedOffset>` as its UTC nanosecond timestamp (i64), laid out
+/// exactly like `marshal_time`, followed by the offset from UTC in seconds (i32).
+/// Returns an error if the writer is too small.
+pub fn marshal_time_offset(t: DateTime<FixedOffset>, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_time(t.to_utc(), writer)?;
+    marshal_i32(t.offset().local_minus_utc(), writer)
+}
+
+/// Unmarshals a `DateTime<FixedOffset>`, restoring the local time in its original offset.
+/// Returns an `InvalidValue` error if the offset is not within ±24 hours.
+pub fn unmarshal_time_offset(reader: &mut &[u8]) -> Result<DateTime<FixedOffset>> {
+    let utc = unmarshal_time(reader)?;
+    let offset = FixedOffset::east_opt(unmarshal_i32(reader)?).ok_or(Error::InvalidValue)?;
+    Ok(utc.with_timezone(&offset))
+}
+
+/// Skips over a marshalled `DateTime<FixedOffset>` in the reader.
+pub fn skip_time_offset(reader: &mut &[u8]) -> Result<()> {
+    skip_time(reader)?;
+    skip_i32(reader)
+}
+
+// ===================================================================================
+// Durations (std::time::Duration / chrono::TimeDelta)
+// ===================================================================================
+
+/// Returns the number of bytes required to marshal a `Duration`.
+pub fn size_duration(d: Duration) -> usize {
+    size_uint(u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+}
+
+/// Marshals a `Duration` as its number of nanoseconds (varint), so short intervals
+/// such as latencies take only a few bytes.
+/// Returns an `OutOfRange` error if the duration does not fit into a `u64` of
+/// nanoseconds, about 584 years, or an error if the writer is too small.
+pub fn marshal_duration(d: Duration, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_uint(u64::try_from(d.as_nanos()).map_err(|_| Error::OutOfRange)?, writer)
+}
+
+/// Unmarshals a `Duration` from its number of nanoseconds (varint).
+pub fn unmarshal_duration(reader: &mut &[u8]) -> Result<Duration> {
+    Ok(Duration::from_nanos(unmarshal_uint(reader)?))
+}
+
+/// Skips over a marshalled `Duration` in the reader.
+pub fn skip_duration(reader: &mut &[u8]) -> Result<()> {
+    skip_uint(reader)
+}
+
+/// Returns the number of bytes required to marshal a `TimeDelta` (`chrono::Duration`).
+pub fn size_time_delta(d: TimeDelta) -> usize {
+    size_int(d.num_nanoseconds().unwrap_or(i64::MAX))
+}
+
+/// Marshals a `TimeDelta` (`chrono::Duration`) as its signed number of nanoseconds
+/// (ZigZag varint).
+/// Returns an `OutOfRange` error if the delta does not fit into an `i64` of
+/// nanoseconds, about 292 years either way, or an error if the writer is too small.
+pub fn marshal_time_delta(d: TimeDelta, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_int(d.num_nanoseconds().ok_or(Error::OutOfRange)?, writer)
+}
+
+/// Unmarshals a `TimeDelta` (`chrono::Duration`) from its signed number of
+/// nanoseconds (ZigZag varint).
+pub fn unmarshal_time_delta(reader: &mut &[u8]) -> Result<TimeDelta> {
+    Ok(TimeDelta::nanoseconds(unmarshal_int(reader)?))
+}
+
+/// Skips over a marshalled `TimeDelta` in the reader.
+pub fn skip_time_delta(reader: &mut &[u8]) -> Result<()> {
+    skip_int(reader)
+}
+
 // ===================================================================================
 // Option<T> (for nullable/pointer types)
 // ===================================================================================
@@ -648,11 +1459,11 @@ pub fn size_option<T>(v: &Option<T>, sizer: impl Fn(&T) -> usize) -> usize {
 /// It writes a `bool` (true for `Some`, false for `None`), followed by the marshalled
 /// value if it is `Some`.
 /// Returns an error if the writer is too small.
-pub fn marshal_option<T>(
+pub fn marshal_option<T, E: From<Error>>(
     v: &Option<T>,
     writer: &mut &mut [u8],
-    marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<()>,
-) -> Result<()> {
+    marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<(), E>,
+) -> Result<(), E> {
     marshal_bool(v.is_some(), writer)?;
     if let Some(value) = v {
         marshaler(value, writer)?;
@@ -662,10 +1473,10 @@ pub fn marshal_option<T>(
 
 /// Unmarshals an `Option<T>` from the reader.
 /// It reads a `bool`. If true, it unmarshals the inner value; otherwise, it returns `None`.
-pub fn unmarshal_option<T>(
+pub fn unmarshal_option<T, E: From<Error>>(
     reader: &mut &[u8],
-    unmarshaler: impl Fn(&mut &[u8]) -> Result<T>,
-) -> Result<Option<T>> {
+    unmarshaler: impl Fn(&mut &[u8]) -> Result<T, E>,
+) -> Result<Option<T>, E> {
     if unmarshal_bool(reader)? {
         Ok(Some(unmarshaler(reader)?))
     } else {
@@ -674,10 +1485,10 @@ pub fn unmarshal_option<T>(
 }
 
 /// Skips over a marshalled `Option<T>` in the reader.
-pub fn skip_option(
+pub fn skip_option<E: From<Error>>(
     reader: &mut &[u8],
-    skip_element: impl Fn(&mut &[u8]) -> Result<()>,
-) -> Result<()> {
+    skip_element: impl Fn(&mut &[u8]) -> Result<(), E>,
+) -> Result<(), E> {
     if unmarshal_bool(reader)? {
         skip_element(reader)?;
     }
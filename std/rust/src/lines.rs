@@ -0,0 +1,204 @@
+//! Files of benc records, one message per record.
+//!
+//! Every record is the varint byte length of the marshalled message followed by the
+//! message, the same layout as `marshal_bytes`. [`BencLinesWriter`] appends records and
+//! flushes after each one, so a crash loses at most the record being written, and
+//! [`BencLinesReader`] iterates over them.
+//!
+//! A process that dies while writing leaves a truncated record at the end of the file.
+//! The reader treats it as the end of the file instead of an error, and reports it
+//! through [`BencLinesReader::is_truncated`].
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+use crate::{BencDecode, BencEncode, Error, from_slice, marshal_usize, size_usize, unmarshal_usize};
+
+/// The default limit on the length of a record read by `BencLinesReader`.
+pub const DEFAULT_MAX_RECORD_LEN: usize = 16 * 1024 * 1024;
+
+// ===================================================================================
+// Writer
+// ===================================================================================
+
+/// Writes messages of type `T` as records, flushing the writer after every record.
+pub struct BencLinesWriter<W, T> {
+    writer: W,
+    buf: Vec<u8>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> BencLinesWriter<File, T> {
+    /// Creates the file at `path`, replacing any existing file.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        File::create(path).map(Self::new)
+    }
+}
+
+impl<W, T> BencLinesWriter<W, T> {
+    /// Creates a writer of records to `writer`.
+    pub fn new(writer: W) -> Self {
+        BencLinesWriter { writer, buf: Vec::new(), _marker: PhantomData }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer. Writing to it directly
+    /// corrupts the file.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write, T: BencEncode> BencLinesWriter<W, T> {
+    /// Writes `message` as a record and flushes the writer.
+    ///
+    /// Encoding failures are reported with the kind `InvalidData`; nothing is written
+    /// in that case.
+    pub fn write(&mut self, message: &T) -> io::Result<()> {
+        let size = message.size();
+        self.buf.clear();
+        self.buf.resize(size_usize(size) + size, 0);
+        let mut out = self.buf.as_mut_slice();
+        marshal_usize(size, &mut out)?;
+        message.marshal(&mut out)?;
+        self.writer.write_all(&self.buf)?;
+        self.writer.flush()
+    }
+}
+
+// ===================================================================================
+// Reader
+// ===================================================================================
+
+/// Reads records and unmarshals them into messages of type `T`.
+///
+/// The iterator ends at the end of the file, including at a truncated final record.
+/// A message that cannot be decoded yields an `InvalidData` error and the iterator
+/// moves on to the next record; a record longer than the limit yields an
+/// `InvalidData` error and ends the iterator, as the records after it cannot be found.
+pub struct BencLinesReader<R, T> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+    max_record_len: usize,
+    truncated: bool,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> BencLinesReader<File, T> {
+    /// Opens the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        File::open(path).map(Self::new)
+    }
+}
+
+impl<R: Read, T> BencLinesReader<R, T> {
+    /// Creates a reader of records from `reader`, accepting records of up to
+    /// [`DEFAULT_MAX_RECORD_LEN`] bytes.
+    pub fn new(reader: R) -> Self {
+        BencLinesReader {
+            reader: BufReader::new(reader),
+            buf: Vec::new(),
+            max_record_len: DEFAULT_MAX_RECORD_LEN,
+            truncated: false,
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<R, T> BencLinesReader<R, T> {
+    /// Sets the largest record length the reader accepts, which bounds the memory a
+    /// corrupt file can make it allocate.
+    pub fn with_max_record_len(mut self, max_record_len: usize) -> Self {
+        self.max_record_len = max_record_len;
+        self
+    }
+
+    /// Returns `true` if the iterator ended at a truncated final record.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        self.reader.get_ref()
+    }
+
+    /// Returns the underlying reader, discarding bytes that have been buffered but not
+    /// decoded.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+impl<R: Read, T> BencLinesReader<R, T> {
+    /// Reads the next record into the buffer. Returns `false` at the end of the file,
+    /// setting `truncated` if the file ends inside a record.
+    fn read_record(&mut self) -> io::Result<bool> {
+        let mut header = [0; 10];
+        let mut filled = 0;
+        let len = loop {
+            match self.reader.read(&mut header[filled..filled + 1]) {
+                Ok(0) => {
+                    self.truncated = filled > 0;
+                    return Ok(false);
+                }
+                Ok(_) => filled += 1,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+            match unmarshal_usize(&mut &header[..filled]) {
+                Ok(len) => break len,
+                Err(Error::BufferTooSmall { .. }) if filled < header.len() => {}
+                Err(err) => return Err(err.into()),
+            }
+        };
+        if len > self.max_record_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("record of {len} bytes exceeds the limit of {} bytes", self.max_record_len),
+            ));
+        }
+
+        self.buf.clear();
+        (&mut self.reader).take(len as u64).read_to_end(&mut self.buf)?;
+        if self.buf.len() < len {
+            self.truncated = true;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read, T: for<'a> BencDecode<'a>> Iterator for BencLinesReader<R, T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<io::Result<T>> {
+        if self.done {
+            return None;
+        }
+        match self.read_record() {
+            Ok(true) => Some(from_slice(&self.buf).map_err(io::Error::from)),
+            Ok(false) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Detection of suspicious but decodable messages.
+//!
+//! Decoders accept some encodings that no benc encoder produces: varints padded with
+//! redundant continuation bytes, bools other than 0 and 1, maps repeating a key, and
+//! collections of zero-sized elements claiming more elements than the message has
+//! bytes. Such messages decode fine but usually come from hand-crafted or malicious
+//! traffic, and the last kind makes decoders that preallocate by length exhaust
+//! memory. [`lint`] reports them, so gateways can reject them before they reach
+//! business logic.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::{
+    Result, Schema, Type, read_terminator, size_int, size_uint, size_usize, skip_type, unmarshal_int,
+    unmarshal_string, unmarshal_u8, unmarshal_uint, unmarshal_usize,
+};
+
+/// A suspicious construct found by [`lint`], with the offset of its first byte in the
+/// linted message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// A varint takes more bytes than its value needs.
+    OverlongVarint { offset: usize },
+    /// A bool is neither 0 nor 1. Decoders read it as `false`.
+    NonCanonicalBool { offset: usize },
+    /// A map repeats a key. Decoders into a `HashMap` keep the last entry.
+    DuplicateKey { offset: usize },
+    /// A collection of zero-sized elements claims more elements than there are bytes
+    /// left in the message. Decoders reject it with an `InvalidValue` error.
+    ImplausibleLength { offset: usize, len: usize },
+}
+
+impl Warning {
+    /// Returns the offset of the construct in the linted message.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Warning::OverlongVarint { offset }
+            | Warning::NonCanonicalBool { offset }
+            | Warning::DuplicateKey { offset }
+            | Warning::ImplausibleLength { offset, .. } => offset,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::OverlongVarint { offset } => write!(f, "overlong varint at offset {offset}"),
+            Warning::NonCanonicalBool { offset } => write!(f, "bool other than 0 or 1 at offset {offset}"),
+            Warning::DuplicateKey { offset } => write!(f, "duplicate map key at offset {offset}"),
+            Warning::ImplausibleLength { offset, len } => {
+                write!(f, "collection of {len} zero-sized elements at offset {offset}")
+            }
+        }
+    }
+}
+
+struct Linter {
+    /// The length of the message, from which offsets are derived.
+    total: usize,
+    warnings: Vec<Warning>,
+}
+
+impl Linter {
+    fn offset(&self, reader: &[u8]) -> usize {
+        self.total - reader.len()
+    }
+
+    /// Reads a collection length, checking its varint.
+    fn len(&mut self, reader: &mut &[u8]) -> Result<usize> {
+        let offset = self.offset(reader);
+        let before = reader.len();
+        let len = unmarshal_usize(reader)?;
+        if before - reader.len() > size_usize(len) {
+            self.warnings.push(Warning::OverlongVarint { offset });
+        }
+        Ok(len)
+    }
+
+    /// Checks a collection of `len` elements with the given types, and returns
+    /// whether the elements should be walked. Zero-sized elements are not walked, as
+    /// there can be arbitrarily many of them.
+    fn elements(&mut self, reader: &[u8], offset: usize, len: usize, types: &[&Type]) -> bool {
+        if !types.iter().all(|ty| ty.fixed_size() == Some(0)) {
+            return true;
+        }
+        if len > reader.len() {
+            self.warnings.push(Warning::ImplausibleLength { offset, len });
+        }
+        false
+    }
+
+    fn lint_type(&mut self, reader: &mut &[u8], ty: &Type) -> Result<()> {
+        let offset = self.offset(reader);
+        let before = reader.len();
+        match ty {
+            Type::Bool => {
+                if unmarshal_u8(reader)? > 1 {
+                    self.warnings.push(Warning::NonCanonicalBool { offset });
+                }
+            }
+            Type::Uint => {
+                let v = unmarshal_uint(reader)?;
+                if before - reader.len() > size_uint(v) {
+                    self.warnings.push(Warning::OverlongVarint { offset });
+                }
+            }
+            Type::Int => {
+                let v = unmarshal_int(reader)?;
+                if before - reader.len() > size_int(v) {
+                    self.warnings.push(Warning::OverlongVarint { offset });
+                }
+            }
+            Type::String => {
+                self.len(&mut &**reader)?;
+                unmarshal_string(reader)?;
+            }
+            Type::Bytes => {
+                self.len(&mut &**reader)?;
+                skip_type(reader, ty)?;
+            }
+            Type::Option(inner) => {
+                let flag = unmarshal_u8(reader)?;
+                if flag > 1 {
+                    self.warnings.push(Warning::NonCanonicalBool { offset });
+                }
+                if flag == 1 {
+                    self.lint_type(reader, inner)?;
+                }
+            }
+            Type::Slice(elem) => {
+                let len = self.len(reader)?;
+                if self.elements(reader, offset, len, &[elem]) {
+                    for _ in 0..len {
+                        self.lint_type(reader, elem)?;
+                    }
+                }
+                read_terminator(reader)?;
+            }
+            Type::Map(k, v) => {
+                let len = self.len(reader)?;
+                if self.elements(reader, offset, len, &[k, v]) {
+                    let mut keys = HashSet::new();
+                    for _ in 0..len {
+                        let key_start = *reader;
+                        let key_offset = self.offset(reader);
+                        self.lint_type(reader, k)?;
+                        if !keys.insert(&key_start[..key_start.len() - reader.len()]) {
+                            self.warnings.push(Warning::DuplicateKey { offset: key_offset });
+                        }
+                        self.lint_type(reader, v)?;
+                    }
+                }
+                read_terminator(reader)?;
+            }
+            Type::Struct(schema) => self.lint_fields(reader, schema)?,
+            _ => skip_type(reader, ty)?,
+        }
+        Ok(())
+    }
+
+    fn lint_fields(&mut self, reader: &mut &[u8], schema: &Schema) -> Result<()> {
+        for field in schema.fields() {
+            self.lint_type(reader, &field.ty)?;
+        }
+        Ok(())
+    }
+}
+
+/// Walks a message described by the schema and returns the suspicious constructs in
+/// it, in the order they appear. The reader is advanced past the message.
+///
+/// Returns an error if the message cannot be decoded.
+pub fn lint(reader: &mut &[u8], schema: &Schema) -> Result<Vec<Warning>> {
+    let mut linter = Linter { total: reader.len(), warnings: Vec::new() };
+    linter.lint_fields(reader, schema)?;
+    Ok(linter.warnings)
+}
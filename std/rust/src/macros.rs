@@ -0,0 +1,733 @@
+//! Declarative macros for defining encodable types.
+
+/// Defines a struct and implements [`BencEncode`](crate::BencEncode) and
+/// [`BencDecode`](crate::BencDecode) for it.
+///
+/// The fields are marshalled one after another in declaration order, exactly as if
+/// each field's own encoding were called by hand, without any header. Every field
+/// type must implement both traits. Structs without fields, including unit structs,
+/// occupy no bytes.
+///
+/// A struct may have one lifetime parameter, in which case it is decoded from a
+/// buffer living for that lifetime and fields such as `&'a str` and `&'a [u8]`
+/// borrow from it without copying.
+///
+/// When every field has a constant size, so does the struct: its
+/// [`ENCODED_SIZE`](crate::BencEncode::ENCODED_SIZE) is the sum of the field sizes and
+/// skipping it is a single bounds check. Fields marked `#[benc(skip)]` count as zero
+/// bytes, while `#[benc(default)]` and `#[benc(with = path)]` fields make the size
+/// variable.
+///
+/// The struct also reports its fields through
+/// [`BencEncode::benc_type`](crate::BencEncode::benc_type), so
+/// [`schema_of`](crate::schema_of) returns its [`Schema`](crate::Schema). Skipped
+/// fields are left out; a field encoded `with` a module, or of a type that cannot be
+/// described, makes the schema unavailable, and so does the tagged mode.
+///
+/// # Field accessors
+///
+/// For every field `name`, the macro also generates an associated function
+/// `decode_field_name(reader)` that unmarshals only that field from a marshalled
+/// struct, skipping the fields before it instead of decoding them. This makes reading
+/// a single field of a large record cheap.
+///
+/// # Columns
+///
+/// Positional structs with fields implement [`BencColumns`](crate::BencColumns),
+/// with one column per field that is not skipped, so batches of them can be encoded
+/// column by column with [`marshal_records`](crate::marshal_records).
+///
+/// # Tagged mode
+///
+/// With `#[benc(tagged)]` on the struct, it is marshalled in the tagged wire mode (see
+/// [`TaggedWriter`](crate::TaggedWriter)): every field is preceded by a key made of
+/// its id and wire type, so fields are found by id rather than by position. Each
+/// field that is not skipped needs a `#[benc(id = N)]` attribute, and no two fields
+/// may share an id. Renaming or reordering fields does not change the encoding as
+/// long as their ids stay the same. A missing field is an `InvalidValue` error unless
+/// it is marked `#[benc(default)]`. Fields with an id the struct does not know are
+/// skipped, so a struct can drop a field and still read data written before, and an
+/// older reader can read data from a writer that added one.
+///
+/// Ids are checked at compile time, so a repeated id does not compile:
+///
+/// ```compile_fail
+/// benc::benc_struct! {
+///     #[benc(tagged)]
+///     struct Clash {
+///         #[benc(id = 1)]
+///         a: u32,
+///         #[benc(id = 1)]
+///         b: u32,
+///     }
+/// }
+/// ```
+///
+/// # Validation
+///
+/// `#[benc(validate = path)]` on the struct or on a field names a function
+/// `fn(&T) -> Result<()>` that is called with the decoded struct or field before the
+/// struct is returned, so values breaking an invariant never leave the decoder. Field
+/// validators run in declaration order, followed by the struct validator. A
+/// validator typically returns an [`Error::Validation`](crate::Error::Validation)
+/// describing the problem. Skipping a struct does not validate it.
+///
+/// # Field attributes
+///
+/// * `#[benc(id = N)]` sets the id of the field in the tagged mode. Ids are ignored
+///   by the default positional mode.
+/// * `#[benc(with = path)]` encodes the field through the functions of the module at
+///   `path` instead of the field type's trait impls, which allows types without impls
+///   or a nonstandard encoding. The module must provide `size(&T) -> usize`,
+///   `marshal(&T, &mut &mut [u8]) -> Result<()>`, `unmarshal(&mut &[u8]) -> Result<T>`
+///   and `skip(&mut &[u8]) -> Result<()>`.
+/// * `#[benc(prefixed)]` prefixes the field with its encoded byte length (see
+///   [`marshal_prefixed`](crate::marshal_prefixed)), so skipping it, as the field
+///   accessors of the fields after it do, takes one step however deep the field is.
+///   This suits nested structs. The field is encoded as a byte slice in the tagged
+///   mode, and makes the schema of the struct unavailable. It cannot be combined with
+///   `with`.
+/// * `#[benc(encrypt = path)]` seals the field with the [`Sealer`](crate::Sealer)
+///   returned by the function at `path`, which is called each time the field is
+///   marshalled or opened (see [`marshal_sealed`](crate::marshal_sealed)). The other
+///   fields stay readable without the key, and the sealed field can be skipped. The
+///   field type must decode without borrowing, since it is read from a decrypted
+///   copy. Like `prefixed`, it is encoded as a byte slice in the tagged mode, makes
+///   the schema of the struct unavailable and cannot be combined with `with`.
+/// * `#[benc(skip)]` leaves the field off the wire. It is set to `Default::default()`
+///   when decoding, which suits caches and other runtime-only state.
+/// * `#[benc(default)]` decodes the field as `Default::default()` when the reader is
+///   already exhausted, so trailing fields can be added to a message without breaking
+///   data written before they existed. In the positional mode, only
+///   [`BencDecode::unmarshal_whole`](crate::BencDecode::unmarshal_whole) knows where the
+///   struct ends, so it only applies to the last fields of a struct decoded by
+///   [`from_slice`](crate::from_slice) or as a `prefixed` or `encrypt` field. Nested
+///   anywhere else, the struct needs every field, and field accessors and skipping do
+///   too. In the tagged mode, it applies whenever the field is missing.
+///
+/// * `#[benc(validate = path)]` validates the decoded field, as described above.
+///
+/// Several options can be combined in one attribute, as in
+/// `#[benc(id = 3, default)]`.
+///
+/// ```
+/// use benc::{BencDecode, BencEncode, benc_struct};
+///
+/// benc_struct! {
+///     #[derive(Debug, PartialEq)]
+///     pub struct Point {
+///         pub x: i32,
+///         pub y: i32,
+///         pub label: String,
+///     }
+/// }
+///
+/// let p = Point { x: 1, y: -2, label: "origin".into() };
+/// let buf = p.to_vec();
+/// assert_eq!(Point::unmarshal(&mut buf.as_slice()).unwrap(), p);
+///
+/// benc_struct! {
+///     #[benc(tagged)]
+///     #[derive(Debug, PartialEq)]
+///     pub struct Account {
+///         #[benc(id = 1)]
+///         pub name: String,
+///         #[benc(id = 2, default)]
+///         pub email: Option<String>,
+///     }
+/// }
+///
+/// let a = Account { name: "ann".into(), email: None };
+/// let buf = a.to_vec();
+/// assert_eq!(Account::unmarshal(&mut buf.as_slice()).unwrap(), a);
+/// assert_eq!(Account::decode_field_name(&mut buf.as_slice()).unwrap(), "ann");
+/// ```
+#[macro_export]
+macro_rules! benc_struct {
+    // Munches the struct attributes, separating `#[benc(...)]` options from
+    // attributes that are passed through to the struct definition.
+    (@struct $mode:tt $validate:tt $attrs:tt #[benc($($opt:tt)*)] $($rest:tt)*) => {
+        $crate::benc_struct!(@struct_options $mode $validate $attrs [$($opt)*] $($rest)*);
+    };
+    (@struct $mode:tt $validate:tt [$($attrs:tt)*] #[$attr:meta] $($rest:tt)*) => {
+        $crate::benc_struct!(@struct $mode $validate [$($attrs)* #[$attr]] $($rest)*);
+    };
+    (@struct $mode:tt $validate:tt [$($attrs:tt)*] $vis:vis struct $name:ident;) => {
+        $($attrs)*
+        $vis struct $name;
+
+        impl $crate::BencEncode for $name {
+            const ENCODED_SIZE: Option<usize> = Some(0);
+
+            fn benc_type() -> Option<$crate::Type> {
+                Some($crate::Type::Struct($crate::Schema::new()))
+            }
+
+            fn size(&self) -> usize {
+                0
+            }
+
+            fn marshal(&self, _writer: &mut &mut [u8]) -> $crate::Result<()> {
+                Ok(())
+            }
+        }
+
+        impl<'a> $crate::BencDecode<'a> for $name {
+            fn unmarshal(_reader: &mut &'a [u8]) -> $crate::Result<Self> {
+                $crate::benc_struct!(@validate &$name, $validate);
+                Ok($name)
+            }
+
+            fn skip(_reader: &mut &[u8]) -> $crate::Result<()> {
+                Ok(())
+            }
+        }
+    };
+    (@struct $mode:tt $validate:tt $attrs:tt $vis:vis struct $name:ident { $($body:tt)* }) => {
+        $crate::benc_struct!(@parse [$mode $validate $attrs $vis struct $name [] [<'a>] 'a]
+            [] [] [] [] [] [] $($body)*);
+    };
+    (@struct $mode:tt $validate:tt $attrs:tt $vis:vis struct $name:ident<$lt:lifetime> { $($body:tt)* }) => {
+        $crate::benc_struct!(@parse [$mode $validate $attrs $vis struct $name [<$lt>] [<$lt>] $lt]
+            [] [] [] [] [] [] $($body)*);
+    };
+
+    // Applies the options of one `#[benc(...)]` struct attribute.
+    (@struct_options $mode:tt $validate:tt $attrs:tt [tagged $(, $($more:tt)*)?] $($rest:tt)*) => {
+        $crate::benc_struct!(@struct_options [tagged] $validate $attrs [$($($more)*)?] $($rest)*);
+    };
+    (@struct_options $mode:tt $validate:tt $attrs:tt
+        [validate = $($path:ident)::+ $(, $($more:tt)*)?] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@struct_options $mode [$($path)::+] $attrs [$($($more)*)?] $($rest)*);
+    };
+    (@struct_options $mode:tt $validate:tt $attrs:tt [] $($rest:tt)*) => {
+        $crate::benc_struct!(@struct $mode $validate $attrs $($rest)*);
+    };
+    (@struct_options $mode:tt $validate:tt $attrs:tt [$($opt:tt)*] $($rest:tt)*) => {
+        compile_error!(concat!("unknown benc struct option: ", stringify!($($opt)*)));
+    };
+
+    // Munches the fields one attribute at a time. The state after the parsed fields is
+    // the id, `with` path, presence (`skip` or `default`) and validator of the current
+    // field, followed by its passed-through attributes.
+    (@parse $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt
+        #[benc($($opt:tt)*)] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@options $header $fields $id $with $presence $validate $attrs [$($opt)*] $($rest)*);
+    };
+    (@parse $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt [$($attrs:tt)*]
+        #[$attr:meta] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@parse $header $fields $id $with $presence $validate [$($attrs)* #[$attr]] $($rest)*);
+    };
+    (@parse $header:tt [$($fields:tt)*] $id:tt $with:tt $presence:tt $validate:tt [$($attrs:tt)*]
+        $field_vis:vis $field:ident : $ty:ty $(, $($rest:tt)*)?
+    ) => {
+        $crate::benc_struct!(@parse $header
+            [$($fields)* { [$($attrs)*] $field_vis $field: $ty; $id $with $presence $validate }] [] [] [] [] []
+            $($($rest)*)?
+        );
+    };
+    (@parse
+        [$mode:tt $struct_validate:tt [$($attrs:tt)*] $vis:vis struct $name:ident
+            [$($generics:tt)*] $decode_generics:tt $lt:lifetime]
+        [$({ [$($field_attrs:tt)*] $field_vis:vis $field:ident : $ty:ty; $id:tt $with:tt $presence:tt $validate:tt })*]
+        [] [] [] [] []
+    ) => {
+        $($attrs)*
+        $vis struct $name $($generics)* {
+            $(
+                $($field_attrs)*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        $crate::benc_struct!(@impls $mode $struct_validate $name [$($generics)*] $decode_generics $lt
+            [$({ $field: $ty; $id $with $presence $validate })*]);
+        $crate::benc_struct!(@accessors $mode $name [$($generics)*] $decode_generics $lt []
+            [$({ $field: $ty; $id $with $presence $validate })*]
+            [$({ $field: $ty; $id $with $presence $validate })*]);
+    };
+
+    // Applies the options of one `#[benc(...)]` field attribute.
+    (@options $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt
+        [id = $new:literal $(, $($more:tt)*)?] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@options $header $fields [$new] $with $presence $validate $attrs [$($($more)*)?] $($rest)*);
+    };
+    (@options $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt
+        [with = $($path:ident)::+ $(, $($more:tt)*)?] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@options $header $fields $id [$($path)::+] $presence $validate $attrs [$($($more)*)?] $($rest)*);
+    };
+    (@options $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt
+        [prefixed $(, $($more:tt)*)?] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@options $header $fields $id [@prefixed] $presence $validate $attrs [$($($more)*)?] $($rest)*);
+    };
+    (@options $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt
+        [encrypt = $($path:ident)::+ $(, $($more:tt)*)?] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@options $header $fields $id [@sealed $($path)::+] $presence $validate $attrs [$($($more)*)?] $($rest)*);
+    };
+    (@options $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt
+        [skip $(, $($more:tt)*)?] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@options $header $fields $id $with [skip] $validate $attrs [$($($more)*)?] $($rest)*);
+    };
+    (@options $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt
+        [default $(, $($more:tt)*)?] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@options $header $fields $id $with [default] $validate $attrs [$($($more)*)?] $($rest)*);
+    };
+    (@options $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt
+        [validate = $($path:ident)::+ $(, $($more:tt)*)?] $($rest:tt)*
+    ) => {
+        $crate::benc_struct!(@options $header $fields $id $with $presence [$($path)::+] $attrs [$($($more)*)?] $($rest)*);
+    };
+    (@options $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt [] $($rest:tt)*) => {
+        $crate::benc_struct!(@parse $header $fields $id $with $presence $validate $attrs $($rest)*);
+    };
+    (@options $header:tt $fields:tt $id:tt $with:tt $presence:tt $validate:tt $attrs:tt [$($opt:tt)*] $($rest:tt)*) => {
+        compile_error!(concat!("unknown benc field option: ", stringify!($($opt)*)));
+    };
+
+    // Positional mode: the fields are marshalled back to back.
+    (@impls [] $struct_validate:tt $name:ident [$($generics:tt)*] [$($decode_generics:tt)*] $lt:lifetime
+        [$({ $field:ident : $ty:ty; $id:tt $with:tt $presence:tt $validate:tt })*]
+    ) => {
+        impl $($generics)* $crate::BencEncode for $name $($generics)* {
+            const ENCODED_SIZE: Option<usize> = $crate::__private::sum_sizes(&[
+                $($crate::benc_struct!(@fixed_size $ty, $with $presence)),*
+            ]);
+
+            fn benc_type() -> Option<$crate::Type> {
+                $crate::__private::struct_type([
+                    $((stringify!($field), $crate::benc_struct!(@field_type $ty, $with $presence))),*
+                ])
+            }
+
+            fn size(&self) -> usize {
+                0 $(+ $crate::benc_struct!(@size &self.$field, $with $presence))*
+            }
+
+            fn marshal(&self, writer: &mut &mut [u8]) -> $crate::Result<()> {
+                $($crate::benc_struct!(@marshal &self.$field, writer, $with $presence);)*
+                let _ = writer;
+                Ok(())
+            }
+        }
+
+        impl $($decode_generics)* $crate::BencDecode<$lt> for $name $($generics)* {
+            fn unmarshal(reader: &mut &$lt [u8]) -> $crate::Result<Self> {
+                let _ = &reader;
+                let value = $name {
+                    $($field: $crate::benc_struct!(@unmarshal reader, $ty, $lt, $with $presence),)*
+                };
+                $($crate::benc_struct!(@validate &value.$field, $validate);)*
+                $crate::benc_struct!(@validate &value, $struct_validate);
+                Ok(value)
+            }
+
+            fn unmarshal_whole(reader: &mut &$lt [u8]) -> $crate::Result<Self> {
+                let _ = &reader;
+                let value = $name {
+                    $($field: $crate::benc_struct!(@unmarshal_whole reader, $ty, $lt, $with $presence),)*
+                };
+                $($crate::benc_struct!(@validate &value.$field, $validate);)*
+                $crate::benc_struct!(@validate &value, $struct_validate);
+                Ok(value)
+            }
+
+            fn skip(reader: &mut &[u8]) -> $crate::Result<()> {
+                if let Some(size) = <Self as $crate::BencEncode>::ENCODED_SIZE {
+                    return $crate::__private::skip_fixed(reader, size);
+                }
+                $($crate::benc_struct!(@skip reader, $ty, $lt, $with $presence);)*
+                Ok(())
+            }
+        }
+
+        impl $($decode_generics)* $crate::BencColumns<$lt> for $name $($generics)* {
+            fn columns<'c>() -> ::std::vec::Vec<$crate::Column<'c, Self>> {
+                let mut columns = ::std::vec::Vec::new();
+                $($crate::benc_struct!(@column columns, $field, $with $presence);)*
+                columns
+            }
+
+            fn unmarshal_row(readers: &mut [&$lt [u8]]) -> $crate::Result<Self> {
+                let mut readers = readers.iter_mut();
+                let _ = &mut readers;
+                let value = $name {
+                    $($field: $crate::benc_struct!(@unmarshal_column readers, $ty, $lt, $with $presence),)*
+                };
+                $($crate::benc_struct!(@validate &value.$field, $validate);)*
+                $crate::benc_struct!(@validate &value, $struct_validate);
+                Ok(value)
+            }
+        }
+    };
+
+    // Tagged mode: the fields are marshalled with their keys, like a map.
+    (@impls [tagged] $struct_validate:tt $name:ident [$($generics:tt)*] [$($decode_generics:tt)*] $lt:lifetime
+        [$({ $field:ident : $ty:ty; $id:tt $with:tt $presence:tt $validate:tt })*]
+    ) => {
+        const _: () = assert!(
+            $crate::__private::unique_ids(&[$($crate::benc_struct!(@tagged_id $id $presence)),*]),
+            concat!("fields of ", stringify!($name), " share a #[benc(id = N)]")
+        );
+
+        impl $($generics)* $crate::BencEncode for $name $($generics)* {
+            fn size(&self) -> usize {
+                $crate::size_tagged(
+                    0 $(+ $crate::benc_struct!(@count $presence))*,
+                    0 $(+ $crate::benc_struct!(@tagged_size &self.$field, $ty, $id $with $presence))*,
+                )
+            }
+
+            fn marshal(&self, writer: &mut &mut [u8]) -> $crate::Result<()> {
+                let count = 0 $(+ $crate::benc_struct!(@count $presence))*;
+                let mut tagged = $crate::TaggedWriter::new(writer, count)?;
+                $($crate::benc_struct!(@tagged_marshal tagged, &self.$field, $ty, $id $with $presence);)*
+                tagged.finish()
+            }
+        }
+
+        impl $($decode_generics)* $crate::BencDecode<$lt> for $name $($generics)* {
+            fn unmarshal(reader: &mut &$lt [u8]) -> $crate::Result<Self> {
+                $($crate::benc_struct!(@tagged_slot $field, $ty, $presence);)*
+                let mut tagged = $crate::TaggedReader::new(reader)?;
+                while let Some((id, wire_type)) = tagged.next_key()? {
+                    $($crate::benc_struct!(@tagged_unmarshal tagged, id, wire_type, $field, $ty, $lt, $id $with $presence);)*
+                    tagged.skip_unknown(wire_type)?;
+                }
+                let value = $name {
+                    $($field: $crate::benc_struct!(@tagged_take $field, $ty, $presence),)*
+                };
+                $($crate::benc_struct!(@validate &value.$field, $validate);)*
+                $crate::benc_struct!(@validate &value, $struct_validate);
+                Ok(value)
+            }
+
+            fn skip(reader: &mut &[u8]) -> $crate::Result<()> {
+                let mut tagged = $crate::TaggedReader::new(reader)?;
+                while let Some((id, wire_type)) = tagged.next_key()? {
+                    $($crate::benc_struct!(@tagged_skip tagged, id, wire_type, $ty, $lt, $id $with $presence);)*
+                    tagged.skip_unknown(wire_type)?;
+                }
+                Ok(())
+            }
+        }
+    };
+
+    // Field accessors. Each step emits the accessor of the next field; the positional
+    // mode keeps the fields before it to skip them, the tagged mode all fields.
+    (@accessors [] $name:ident [$($generics:tt)*] [$($decode_generics:tt)*] $lt:lifetime
+        [$({ $prev:ident : $prev_ty:ty; $prev_id:tt $prev_with:tt $prev_presence:tt $prev_validate:tt })*]
+        [{ $field:ident : $ty:ty; $id:tt $with:tt $presence:tt $validate:tt } $($rest:tt)*]
+        $all:tt
+    ) => {
+        $crate::__private::paste! {
+            impl $($decode_generics)* $name $($generics)* {
+                #[doc = concat!(
+                    "Unmarshals only the `", stringify!($field), "` field of a marshalled `",
+                    stringify!($name), "`,\nskipping the fields before it. The reader is left ",
+                    "after the field."
+                )]
+                pub fn [<decode_field_ $field>](reader: &mut &$lt [u8]) -> $crate::Result<$ty> {
+                    $($crate::benc_struct!(@skip reader, $prev_ty, $lt, $prev_with $prev_presence);)*
+                    let value = $crate::benc_struct!(@unmarshal reader, $ty, $lt, $with $presence);
+                    $crate::benc_struct!(@validate &value, $validate);
+                    Ok(value)
+                }
+            }
+        }
+
+        $crate::benc_struct!(@accessors [] $name [$($generics)*] [$($decode_generics)*] $lt
+            [$({ $prev: $prev_ty; $prev_id $prev_with $prev_presence $prev_validate })*
+                { $field: $ty; $id $with $presence $validate }]
+            [$($rest)*]
+            $all);
+    };
+    (@accessors [tagged] $name:ident [$($generics:tt)*] [$($decode_generics:tt)*] $lt:lifetime $prev:tt
+        [{ $field:ident : $ty:ty; $id:tt $with:tt $presence:tt $validate:tt } $($rest:tt)*]
+        [$({ $other:ident : $other_ty:ty; $other_id:tt $other_with:tt $other_presence:tt $other_validate:tt })*]
+    ) => {
+        $crate::__private::paste! {
+            impl $($decode_generics)* $name $($generics)* {
+                #[doc = concat!(
+                    "Unmarshals only the `", stringify!($field), "` field of a marshalled `",
+                    stringify!($name), "`,\nscanning the keys of the struct for it and skipping the ",
+                    "values of other fields. The reader is left after the field, or after the ",
+                    "struct if the field is missing."
+                )]
+                pub fn [<decode_field_ $field>](reader: &mut &$lt [u8]) -> $crate::Result<$ty> {
+                    $crate::benc_struct!(@tagged_find reader, $ty, $lt, $id $with $presence $validate [
+                        $({ $other_ty; $other_id $other_with $other_presence })*
+                    ])
+                }
+            }
+        }
+
+        $crate::benc_struct!(@accessors [tagged] $name [$($generics)*] [$($decode_generics)*] $lt $prev
+            [$($rest)*]
+            [$({ $other: $other_ty; $other_id $other_with $other_presence $other_validate })*]);
+    };
+    (@accessors $mode:tt $name:ident $generics:tt $decode_generics:tt $lt:lifetime $prev:tt [] $all:tt) => {};
+    (@tagged_find $reader:ident, $ty:ty, $lt:lifetime, $id:tt $with:tt [skip] $validate:tt $others:tt) => {{
+        let _ = $reader;
+        let value = <$ty as ::core::default::Default>::default();
+        $crate::benc_struct!(@validate &value, $validate);
+        Ok(value)
+    }};
+    (@tagged_find $reader:ident, $ty:ty, $lt:lifetime, $id:tt $with:tt $presence:tt $validate:tt
+        [$({ $other_ty:ty; $other_id:tt $other_with:tt $other_presence:tt })*]
+    ) => {{
+        let mut tagged = $crate::TaggedReader::new($reader)?;
+        while let Some((id, wire_type)) = tagged.next_key()? {
+            if id == $crate::benc_struct!(@id $id) {
+                let expected = $crate::benc_struct!(@wire_type $ty, $with);
+                let value = tagged.value(wire_type, expected, |r| $crate::benc_struct!(@decode r, $ty, $lt, $with))?;
+                $crate::benc_struct!(@validate &value, $validate);
+                return Ok(value);
+            }
+            $($crate::benc_struct!(@tagged_skip tagged, id, wire_type, $other_ty, $lt, $other_id $other_with $other_presence);)*
+            tagged.skip_unknown(wire_type)?;
+        }
+        let value: $ty = $crate::benc_struct!(@tagged_take None, $ty, $presence);
+        $crate::benc_struct!(@validate &value, $validate);
+        Ok(value)
+    }};
+
+    // Encoding of a single value, through its trait impls, with a length prefix or
+    // through a `with` module.
+    (@encoded_size $value:expr, []) => {
+        $crate::BencEncode::size($value)
+    };
+    (@encoded_size $value:expr, [@prefixed]) => {
+        $crate::size_prefixed($value, $crate::BencEncode::size)
+    };
+    (@encoded_size $value:expr, [@sealed $($sealer:ident)::+]) => {
+        $crate::size_sealed(&$($sealer)::+(), $crate::BencEncode::size($value))
+    };
+    (@encoded_size $value:expr, [$($path:ident)::+]) => {
+        $($path)::+::size($value)
+    };
+    (@encode $value:expr, $writer:ident, []) => {
+        $crate::BencEncode::marshal($value, $writer)
+    };
+    (@encode $value:expr, $writer:ident, [@prefixed]) => {
+        $crate::marshal_prefixed($value, $writer, $crate::BencEncode::size, $crate::BencEncode::marshal)
+    };
+    (@encode $value:expr, $writer:ident, [@sealed $($sealer:ident)::+]) => {{
+        let value = $value;
+        $crate::marshal_sealed(&$($sealer)::+(), $crate::BencEncode::size(value), $writer, |w| {
+            $crate::BencEncode::marshal(value, w)
+        })
+    }};
+    (@encode $value:expr, $writer:ident, [$($path:ident)::+]) => {
+        $($path)::+::marshal($value, $writer)
+    };
+    (@decode $reader:ident, $ty:ty, $lt:lifetime, []) => {
+        <$ty as $crate::BencDecode<$lt>>::unmarshal($reader)
+    };
+    (@decode $reader:ident, $ty:ty, $lt:lifetime, [@prefixed]) => {
+        $crate::unmarshal_prefixed($reader, <$ty as $crate::BencDecode<$lt>>::unmarshal_whole)
+    };
+    (@decode $reader:ident, $ty:ty, $lt:lifetime, [@sealed $($sealer:ident)::+]) => {
+        $crate::unmarshal_sealed($reader, &$($sealer)::+(), |r| <$ty as $crate::BencDecode>::unmarshal_whole(r))
+    };
+    (@decode $reader:ident, $ty:ty, $lt:lifetime, [$($path:ident)::+]) => {
+        $($path)::+::unmarshal($reader)
+    };
+    (@skip_value $reader:ident, $ty:ty, $lt:lifetime, []) => {
+        <$ty as $crate::BencDecode<$lt>>::skip($reader)
+    };
+    (@skip_value $reader:ident, $ty:ty, $lt:lifetime, [@prefixed]) => {
+        $crate::skip_prefixed($reader)
+    };
+    (@skip_value $reader:ident, $ty:ty, $lt:lifetime, [@sealed $($sealer:ident)::+]) => {
+        $crate::skip_sealed($reader)
+    };
+    (@skip_value $reader:ident, $ty:ty, $lt:lifetime, [$($path:ident)::+]) => {
+        $($path)::+::skip($reader)
+    };
+    (@wire_type $ty:ty, []) => {
+        <$ty as $crate::BencEncode>::WIRE_TYPE
+    };
+    (@wire_type $ty:ty, [@prefixed]) => {
+        Some($crate::WireType::Bytes)
+    };
+    (@wire_type $ty:ty, [@sealed $($sealer:ident)::+]) => {
+        Some($crate::WireType::Bytes)
+    };
+    (@wire_type $ty:ty, [$($path:ident)::+]) => {
+        None
+    };
+    (@validate $value:expr, []) => {};
+    (@validate $value:expr, [$($path:ident)::+]) => {
+        $($path)::+($value)?;
+    };
+
+    // Positional fields, honouring `skip` and `default`. Fields that may be missing
+    // make the size of the struct variable.
+    (@fixed_size $ty:ty, $with:tt [skip]) => {
+        Some(0)
+    };
+    (@fixed_size $ty:ty, [] []) => {
+        <$ty as $crate::BencEncode>::ENCODED_SIZE
+    };
+    (@fixed_size $ty:ty, $with:tt $presence:tt) => {
+        None
+    };
+    (@field_type $ty:ty, $with:tt [skip]) => {
+        None
+    };
+    (@field_type $ty:ty, [] $presence:tt) => {
+        Some(<$ty as $crate::BencEncode>::benc_type())
+    };
+    (@field_type $ty:ty, $with:tt $presence:tt) => {
+        Some(None)
+    };
+    (@size $value:expr, $with:tt [skip]) => {
+        0
+    };
+    (@size $value:expr, $with:tt $presence:tt) => {
+        $crate::benc_struct!(@encoded_size $value, $with)
+    };
+    (@marshal $value:expr, $writer:ident, $with:tt [skip]) => {
+        ()
+    };
+    (@marshal $value:expr, $writer:ident, $with:tt $presence:tt) => {
+        $crate::benc_struct!(@encode $value, $writer, $with)?
+    };
+    (@unmarshal $reader:ident, $ty:ty, $lt:lifetime, $with:tt [skip]) => {
+        <$ty as ::core::default::Default>::default()
+    };
+    (@unmarshal $reader:ident, $ty:ty, $lt:lifetime, $with:tt $presence:tt) => {
+        $crate::benc_struct!(@decode $reader, $ty, $lt, $with)?
+    };
+    // Only a reader that ends with the struct tells a missing trailing field from the
+    // bytes of the value after it.
+    (@unmarshal_whole $reader:ident, $ty:ty, $lt:lifetime, $with:tt [default]) => {
+        if $reader.is_empty() {
+            <$ty as ::core::default::Default>::default()
+        } else {
+            $crate::benc_struct!(@decode $reader, $ty, $lt, $with)?
+        }
+    };
+    (@unmarshal_whole $reader:ident, $ty:ty, $lt:lifetime, $with:tt $presence:tt) => {
+        $crate::benc_struct!(@unmarshal $reader, $ty, $lt, $with $presence)
+    };
+    (@skip $reader:ident, $ty:ty, $lt:lifetime, $with:tt [skip]) => {
+        ()
+    };
+    (@skip $reader:ident, $ty:ty, $lt:lifetime, $with:tt $presence:tt) => {
+        $crate::benc_struct!(@skip_value $reader, $ty, $lt, $with)?
+    };
+    (@column $columns:ident, $field:ident, $with:tt [skip]) => {
+        ()
+    };
+    (@column $columns:ident, $field:ident, $with:tt $presence:tt) => {
+        $columns.push($crate::Column::new(
+            |record: &Self| $crate::benc_struct!(@encoded_size &record.$field, $with),
+            |record: &Self, writer| $crate::benc_struct!(@encode &record.$field, writer, $with),
+        ))
+    };
+    (@unmarshal_column $readers:ident, $ty:ty, $lt:lifetime, $with:tt [skip]) => {
+        <$ty as ::core::default::Default>::default()
+    };
+    (@unmarshal_column $readers:ident, $ty:ty, $lt:lifetime, $with:tt $presence:tt) => {{
+        let reader = $readers.next().ok_or($crate::Error::InvalidValue)?;
+        $crate::benc_struct!(@decode reader, $ty, $lt, $with)?
+    }};
+
+    // Tagged fields. A missing id is reported once, by `@tagged_size`.
+    (@count [skip]) => {
+        0
+    };
+    (@count $presence:tt) => {
+        1
+    };
+    (@id [$id:literal]) => {
+        $id
+    };
+    (@id []) => {
+        0
+    };
+    (@tagged_id $id:tt [skip]) => {
+        None
+    };
+    (@tagged_id $id:tt $presence:tt) => {
+        Some($crate::benc_struct!(@id $id))
+    };
+    (@tagged_size $value:expr, $ty:ty, $id:tt $with:tt [skip]) => {
+        0
+    };
+    (@tagged_size $value:expr, $ty:ty, [] $with:tt $presence:tt) => {
+        compile_error!("fields of a tagged struct need a #[benc(id = N)] attribute")
+    };
+    (@tagged_size $value:expr, $ty:ty, $id:tt $with:tt $presence:tt) => {
+        $crate::size_tagged_field(
+            $crate::benc_struct!(@id $id),
+            $crate::benc_struct!(@wire_type $ty, $with),
+            $crate::benc_struct!(@encoded_size $value, $with),
+        )
+    };
+    (@tagged_marshal $tagged:ident, $value:expr, $ty:ty, $id:tt $with:tt [skip]) => {
+        ()
+    };
+    (@tagged_marshal $tagged:ident, $value:expr, $ty:ty, $id:tt $with:tt $presence:tt) => {
+        $tagged.field(
+            $crate::benc_struct!(@id $id),
+            $crate::benc_struct!(@wire_type $ty, $with),
+            $crate::benc_struct!(@encoded_size $value, $with),
+            |writer| $crate::benc_struct!(@encode $value, writer, $with),
+        )?
+    };
+    (@tagged_slot $field:ident, $ty:ty, [skip]) => {};
+    (@tagged_slot $field:ident, $ty:ty, $presence:tt) => {
+        let mut $field: Option<$ty> = None;
+    };
+    (@tagged_unmarshal $tagged:ident, $found_id:ident, $wire_type:ident, $field:ident, $ty:ty, $lt:lifetime,
+        $id:tt $with:tt [skip]
+    ) => {
+        ()
+    };
+    (@tagged_unmarshal $tagged:ident, $found_id:ident, $wire_type:ident, $field:ident, $ty:ty, $lt:lifetime,
+        $id:tt $with:tt $presence:tt
+    ) => {
+        if $found_id == $crate::benc_struct!(@id $id) {
+            if $field.is_some() {
+                return Err($crate::Error::InvalidValue);
+            }
+            let expected = $crate::benc_struct!(@wire_type $ty, $with);
+            $field = Some($tagged.value($wire_type, expected, |r| $crate::benc_struct!(@decode r, $ty, $lt, $with))?);
+            continue;
+        }
+    };
+    (@tagged_skip $tagged:ident, $found_id:ident, $wire_type:ident, $ty:ty, $lt:lifetime, $id:tt $with:tt [skip]) => {
+        ()
+    };
+    (@tagged_skip $tagged:ident, $found_id:ident, $wire_type:ident, $ty:ty, $lt:lifetime, $id:tt $with:tt $presence:tt) => {
+        if $found_id == $crate::benc_struct!(@id $id) {
+            let expected = $crate::benc_struct!(@wire_type $ty, $with);
+            $tagged.skip($wire_type, expected, |r| $crate::benc_struct!(@skip_value r, $ty, $lt, $with))?;
+            continue;
+        }
+    };
+    (@tagged_take $field:expr, $ty:ty, [skip]) => {
+        <$ty as ::core::default::Default>::default()
+    };
+    (@tagged_take $field:expr, $ty:ty, [default]) => {
+        $field.unwrap_or_default()
+    };
+    (@tagged_take $field:expr, $ty:ty, []) => {
+        $field.ok_or($crate::Error::InvalidValue)?
+    };
+
+    ($($input:tt)*) => {
+        $crate::benc_struct!(@struct [] [] [] $($input)*);
+    };
+}
@@ -0,0 +1,160 @@
+//! Support for types commonly found in manifests: `semver::Version` (feature `semver`)
+//! and `url::Url` (feature `url`).
+//!
+//! Both also implement the encoding traits, so structs defined with
+//! [`benc_struct!`](crate::benc_struct) can hold them.
+
+// ===================================================================================
+// semver::Version
+// ===================================================================================
+
+#[cfg(feature = "semver")]
+mod version {
+    use semver::{BuildMetadata, Prerelease, Version};
+
+    use crate::{
+        BencDecode, BencEncode, Error, Result, Schema, Type, marshal_string, marshal_uint, size_string, size_uint,
+        skip_string, skip_uint, unmarshal_string, unmarshal_uint,
+    };
+
+    /// Returns the number of bytes required to marshal a `Version`.
+    pub fn size_version(v: &Version) -> usize {
+        size_uint(v.major)
+            + size_uint(v.minor)
+            + size_uint(v.patch)
+            + size_string(v.pre.as_str())
+            + size_string(v.build.as_str())
+    }
+
+    /// Marshals a `Version` as its major, minor and patch varints followed by the
+    /// pre-release and build metadata strings (empty if absent).
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_version(v: &Version, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_uint(v.major, writer)?;
+        marshal_uint(v.minor, writer)?;
+        marshal_uint(v.patch, writer)?;
+        marshal_string(v.pre.as_str(), writer)?;
+        marshal_string(v.build.as_str(), writer)
+    }
+
+    /// Unmarshals a `Version` from the reader.
+    /// Returns an `InvalidValue` error if the pre-release or build metadata is malformed.
+    pub fn unmarshal_version(reader: &mut &[u8]) -> Result<Version> {
+        let major = unmarshal_uint(reader)?;
+        let minor = unmarshal_uint(reader)?;
+        let patch = unmarshal_uint(reader)?;
+        let pre = Prerelease::new(unmarshal_string(reader)?).map_err(|_| Error::InvalidValue)?;
+        let build =
+            BuildMetadata::new(unmarshal_string(reader)?).map_err(|_| Error::InvalidValue)?;
+        Ok(Version { major, minor, patch, pre, build })
+    }
+
+    /// Skips over a marshalled `Version` in the reader.
+    pub fn skip_version(reader: &mut &[u8]) -> Result<()> {
+        skip_uint(reader)?;
+        skip_uint(reader)?;
+        skip_uint(reader)?;
+        skip_string(reader)?;
+        skip_string(reader)
+    }
+
+    impl BencEncode for Version {
+        fn benc_type() -> Option<Type> {
+            let schema = Schema::new()
+                .field("major", Type::Uint)
+                .field("minor", Type::Uint)
+                .field("patch", Type::Uint)
+                .field("pre", Type::String)
+                .field("build", Type::String);
+            Some(Type::Struct(schema))
+        }
+
+        fn size(&self) -> usize {
+            size_version(self)
+        }
+
+        fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_version(self, writer)
+        }
+    }
+
+    impl BencDecode<'_> for Version {
+        fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+            unmarshal_version(reader)
+        }
+
+        fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_version(reader)
+        }
+    }
+}
+
+#[cfg(feature = "semver")]
+pub use version::*;
+
+// ===================================================================================
+// url::Url
+// ===================================================================================
+
+#[cfg(feature = "url")]
+mod link {
+    use url::Url;
+
+    use crate::{
+        BencDecode, BencEncode, Error, Result, Type, WireType, marshal_string, size_string, skip_string,
+        unmarshal_string,
+    };
+
+    /// Returns the number of bytes required to marshal a `Url`.
+    pub fn size_url(v: &Url) -> usize {
+        size_string(v.as_str())
+    }
+
+    /// Marshals a `Url` as its serialized string.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_url(v: &Url, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_string(v.as_str(), writer)
+    }
+
+    /// Unmarshals a `Url` from the reader, parsing and validating it.
+    /// Returns an `InvalidValue` error if the string is not a valid URL.
+    pub fn unmarshal_url(reader: &mut &[u8]) -> Result<Url> {
+        Url::parse(unmarshal_string(reader)?).map_err(|_| Error::InvalidValue)
+    }
+
+    /// Skips over a marshalled `Url` in the reader.
+    pub fn skip_url(reader: &mut &[u8]) -> Result<()> {
+        skip_string(reader)
+    }
+
+    impl BencEncode for Url {
+        const WIRE_TYPE: Option<WireType> = Some(WireType::Bytes);
+
+        fn benc_type() -> Option<Type> {
+            Some(Type::String)
+        }
+
+        fn size(&self) -> usize {
+            size_url(self)
+        }
+
+        fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_url(self, writer)
+        }
+    }
+
+    impl BencDecode<'_> for Url {
+        fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+            unmarshal_url(reader)
+        }
+
+        fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_url(reader)
+        }
+    }
+}
+
+#[cfg(feature = "url")]
+pub use link::*;
@@ -0,0 +1,114 @@
+//! Support for `glam` game-math types, enabled by the `glam` feature.
+//!
+//! Vectors, quaternions and matrices are marshalled as their `f32` components packed
+//! back to back in little-endian order, without any length prefix. Matrices are
+//! stored in column-major order, matching `glam`'s in-memory layout. The types also
+//! implement the encoding traits, so structs defined with
+//! [`benc_struct!`](crate::benc_struct) can hold them.
+
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+
+use crate::{BencDecode, BencEncode, Result, Schema, Type, advance, size_f32, write_to_slice};
+
+/// Marshals a fixed number of `f32` components in a single write.
+#[inline]
+fn marshal_components<const N: usize>(components: [f32; N], writer: &mut &mut [u8]) -> Result<()> {
+    let mut buf = [[0u8; 4]; N];
+    for (dst, v) in buf.iter_mut().zip(components) {
+        *dst = v.to_bits().to_le_bytes();
+    }
+    write_to_slice(writer, buf.as_flattened())
+}
+
+/// Unmarshals a fixed number of `f32` components in a single read.
+#[inline]
+fn unmarshal_components<const N: usize>(reader: &mut &[u8]) -> Result<[f32; N]> {
+    let bytes = advance(reader, N * size_f32())?;
+    let mut components = [0f32; N];
+    for (dst, chunk) in components.iter_mut().zip(bytes.chunks_exact(size_f32())) {
+        *dst = f32::from_bits(u32::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    Ok(components)
+}
+
+/// Describes a struct of `f32` components with the given names.
+fn components_type(names: &[&str]) -> Type {
+    Type::Struct(names.iter().fold(Schema::new(), |schema, name| schema.field(*name, Type::F32)))
+}
+
+fn mat4_type() -> Type {
+    let column = components_type(&["x", "y", "z", "w"]);
+    let schema = ["x_axis", "y_axis", "z_axis", "w_axis"]
+        .iter()
+        .fold(Schema::new(), |schema, name| schema.field(*name, column.clone()));
+    Type::Struct(schema)
+}
+
+// Use a macro to generate the functions for every packed type to avoid boilerplate.
+macro_rules! packed_f32_impl {
+    ($type:ty,
+     $components:expr,
+     $size_fn:ident,
+     $marshal_fn:ident,
+     $unmarshal_fn:ident,
+     $skip_fn:ident,
+     $to_array:ident,
+     $from_array:expr,
+     $benc_type:expr
+    ) => {
+        #[doc = concat!("Returns the number of bytes required to marshal a `", stringify!($type), "`.")]
+        pub const fn $size_fn() -> usize {
+            $components * size_f32()
+        }
+
+        #[doc = concat!("Marshals a `", stringify!($type), "` into the writer as packed little-endian `f32` components.")]
+        ///
+        /// Returns an error if the writer is too small.
+        pub fn $marshal_fn(v: $type, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_components(v.$to_array(), writer)
+        }
+
+        #[doc = concat!("Unmarshals a `", stringify!($type), "` from packed little-endian `f32` components.")]
+        pub fn $unmarshal_fn(reader: &mut &[u8]) -> Result<$type> {
+            unmarshal_components::<$components>(reader).map($from_array)
+        }
+
+        #[doc = concat!("Skips over a marshalled `", stringify!($type), "` in the reader.")]
+        pub fn $skip_fn(reader: &mut &[u8]) -> Result<()> {
+            advance(reader, $size_fn())?;
+            Ok(())
+        }
+
+        impl BencEncode for $type {
+            const ENCODED_SIZE: Option<usize> = Some($size_fn());
+
+            fn benc_type() -> Option<Type> {
+                Some($benc_type)
+            }
+
+            fn size(&self) -> usize {
+                $size_fn()
+            }
+
+            fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+                $marshal_fn(*self, writer)
+            }
+        }
+
+        impl BencDecode<'_> for $type {
+            fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+                $unmarshal_fn(reader)
+            }
+
+            fn skip(reader: &mut &[u8]) -> Result<()> {
+                $skip_fn(reader)
+            }
+        }
+    };
+}
+
+packed_f32_impl!(Vec2, 2, size_vec2, marshal_vec2, unmarshal_vec2, skip_vec2, to_array, Vec2::from_array, components_type(&["x", "y"]));
+packed_f32_impl!(Vec3, 3, size_vec3, marshal_vec3, unmarshal_vec3, skip_vec3, to_array, Vec3::from_array, components_type(&["x", "y", "z"]));
+packed_f32_impl!(Vec4, 4, size_vec4, marshal_vec4, unmarshal_vec4, skip_vec4, to_array, Vec4::from_array, components_type(&["x", "y", "z", "w"]));
+packed_f32_impl!(Quat, 4, size_quat, marshal_quat, unmarshal_quat, skip_quat, to_array, Quat::from_array, components_type(&["x", "y", "z", "w"]));
+packed_f32_impl!(Mat4, 16, size_mat4, marshal_mat4, unmarshal_mat4, skip_mat4, to_cols_array, |c| Mat4::from_cols_array(&c), mat4_type());
@@ -0,0 +1,63 @@
+//! Measuring marshalled elements without consuming them.
+//!
+//! Every `measure_*` function returns how many bytes the next element of the reader
+//! occupies, leaving the reader untouched. Combined with slicing, this lets callers
+//! cut a sub-message out of a buffer and forward it verbatim.
+
+use crate::{
+    Result, skip_bytes, skip_int, skip_map, skip_option, skip_slice, skip_string, skip_time,
+    skip_uint,
+};
+
+/// Returns the number of bytes the next element occupies, using its skip function.
+pub fn measure(reader: &[u8], skip: impl FnOnce(&mut &[u8]) -> Result<()>) -> Result<usize> {
+    let mut cursor = reader;
+    skip(&mut cursor)?;
+    Ok(reader.len() - cursor.len())
+}
+
+/// Returns the number of bytes the next varint-encoded unsigned integer occupies.
+pub fn measure_uint(reader: &[u8]) -> Result<usize> {
+    measure(reader, skip_uint)
+}
+
+/// Returns the number of bytes the next ZigZag varint-encoded signed integer occupies.
+pub fn measure_int(reader: &[u8]) -> Result<usize> {
+    measure(reader, skip_int)
+}
+
+/// Returns the number of bytes the next string occupies.
+pub fn measure_string(reader: &[u8]) -> Result<usize> {
+    measure(reader, skip_string)
+}
+
+/// Returns the number of bytes the next byte slice occupies.
+pub fn measure_bytes(reader: &[u8]) -> Result<usize> {
+    measure(reader, skip_bytes)
+}
+
+/// Returns the number of bytes the next `DateTime<Utc>` occupies.
+pub fn measure_time(reader: &[u8]) -> Result<usize> {
+    measure(reader, skip_time)
+}
+
+/// Returns the number of bytes the next slice occupies, including its length prefix
+/// and terminator.
+pub fn measure_slice(reader: &[u8], skip_element: impl Fn(&mut &[u8]) -> Result<()>) -> Result<usize> {
+    measure(reader, |r| skip_slice(r, skip_element))
+}
+
+/// Returns the number of bytes the next map occupies, including its length prefix
+/// and terminator.
+pub fn measure_map(
+    reader: &[u8],
+    skip_key: impl Fn(&mut &[u8]) -> Result<()>,
+    skip_value: impl Fn(&mut &[u8]) -> Result<()>,
+) -> Result<usize> {
+    measure(reader, |r| skip_map(r, skip_key, skip_value))
+}
+
+/// Returns the number of bytes the next `Option<T>` occupies.
+pub fn measure_option(reader: &[u8], skip_element: impl Fn(&mut &[u8]) -> Result<()>) -> Result<usize> {
+    measure(reader, |r| skip_option(r, skip_element))
+}
@@ -0,0 +1,54 @@
+//! Process-wide hooks reporting the cost of encoding and decoding, enabled by the
+//! `metrics` feature.
+//!
+//! Once a [`CodecMetrics`] implementation is installed with [`set_codec_metrics`],
+//! every [`BencEncode::to_vec`](crate::BencEncode::to_vec) and
+//! [`from_slice`](crate::from_slice) reports the type, the number of bytes and the
+//! time taken, so a service can export its serialization cost to Prometheus or any
+//! other metrics system without touching its call sites. Until one is installed, the
+//! hooks cost a single atomic load and the clock is never read.
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::Error;
+
+/// Receives a report of every encode and decode.
+///
+/// The methods are called on the hot path of the codec and should be cheap, such as
+/// incrementing counters and recording into histograms.
+pub trait CodecMetrics: Send + Sync {
+    /// Called after a value of the type `type_name` was marshalled into `bytes` bytes.
+    fn on_encode(&self, type_name: &'static str, bytes: usize, elapsed: Duration);
+
+    /// Called after a buffer of `bytes` bytes was unmarshalled into a value of the type
+    /// `type_name`, with the error if decoding failed.
+    fn on_decode(&self, type_name: &'static str, bytes: usize, elapsed: Duration, error: Option<&Error>);
+}
+
+static METRICS: OnceLock<Box<dyn CodecMetrics>> = OnceLock::new();
+
+/// Installs the metrics hooks of the process.
+///
+/// Returns `false`, leaving the installed hooks in place, if hooks were already
+/// installed.
+pub fn set_codec_metrics(metrics: impl CodecMetrics + 'static) -> bool {
+    METRICS.set(Box::new(metrics)).is_ok()
+}
+
+/// Returns the start time of an operation, or `None` if no hooks are installed.
+pub(crate) fn start() -> Option<Instant> {
+    METRICS.get().map(|_| Instant::now())
+}
+
+pub(crate) fn record_encode<T: ?Sized>(start: Option<Instant>, bytes: usize) {
+    if let (Some(start), Some(metrics)) = (start, METRICS.get()) {
+        metrics.on_encode(std::any::type_name::<T>(), bytes, start.elapsed());
+    }
+}
+
+pub(crate) fn record_decode<T>(start: Option<Instant>, bytes: usize, error: Option<&Error>) {
+    if let (Some(start), Some(metrics)) = (start, METRICS.get()) {
+        metrics.on_decode(std::any::type_name::<T>(), bytes, start.elapsed(), error);
+    }
+}
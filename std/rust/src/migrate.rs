@@ -0,0 +1,78 @@
+//! Migration of existing bincode and postcard datasets to benc, enabled by the
+//! `bincode` and `postcard` features.
+//!
+//! A dataset is read as records of a type implementing both serde's `Deserialize` and
+//! [`BencEncode`], stored back to back. Every record is re-encoded and written as soon
+//! as it has been read, so datasets of any size are converted in constant memory.
+//!
+//! The output holds one frame per record: the varint byte length of the marshalled
+//! value followed by the value, the same layout `BencStream` reads.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use serde::de::DeserializeOwned;
+
+use crate::{BencEncode, marshal_usize, size_usize};
+
+/// Marshals a record into `buf` as a frame and writes it out.
+fn write_record<T: BencEncode>(record: &T, buf: &mut Vec<u8>, writer: &mut impl Write) -> io::Result<()> {
+    let size = record.size();
+    buf.clear();
+    buf.resize(size_usize(size) + size, 0);
+    let mut out = buf.as_mut_slice();
+    marshal_usize(size, &mut out)?;
+    record.marshal(&mut out)?;
+    writer.write_all(buf)
+}
+
+/// Reads records with `read_record` until the reader is exhausted, writing each one
+/// to `writer` as a frame. Returns the number of records.
+fn transcode<R: Read, T: BencEncode, E: std::error::Error + Send + Sync + 'static>(
+    reader: R,
+    mut writer: impl Write,
+    mut read_record: impl FnMut(&mut BufReader<R>) -> Result<T, E>,
+) -> io::Result<u64> {
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    let mut count = 0;
+    while !reader.fill_buf()?.is_empty() {
+        let record = read_record(&mut reader).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("failed to decode record {count}: {err}"))
+        })?;
+        write_record(&record, &mut buf, &mut writer)?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Converts a dataset of bincode records, encoded with the given bincode
+/// configuration, into benc frames. Returns the number of records converted.
+///
+/// Returns an `InvalidData` error naming the record if one cannot be decoded, and
+/// passes on I/O errors; records before it have already been written.
+#[cfg(feature = "bincode")]
+pub fn transcode_bincode<T, C>(reader: impl Read, writer: impl Write, config: C) -> io::Result<u64>
+where
+    T: DeserializeOwned + BencEncode,
+    C: bincode::config::Config,
+{
+    transcode(reader, writer, |reader| bincode::serde::decode_from_std_read::<T, _, _>(reader, config))
+}
+
+/// Converts a dataset of postcard records into benc frames. Returns the number of
+/// records converted.
+///
+/// Postcard decodes strings and byte arrays through a scratch buffer of
+/// `max_field_len` bytes, so no field of a record may be longer than that.
+///
+/// Returns an `InvalidData` error naming the record if one cannot be decoded, and
+/// passes on I/O errors; records before it have already been written.
+#[cfg(feature = "postcard")]
+pub fn transcode_postcard<T>(reader: impl Read, writer: impl Write, max_field_len: usize) -> io::Result<u64>
+where
+    T: DeserializeOwned + BencEncode,
+{
+    let mut scratch = vec![0u8; max_field_len];
+    transcode(reader, writer, |reader| postcard::from_io::<T, _>((reader, &mut scratch)).map(|(record, _)| record))
+}
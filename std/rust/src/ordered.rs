@@ -0,0 +1,131 @@
+//! Support for `ordered_float::OrderedFloat` and `ordered_float::NotNan`, enabled by
+//! the `ordered-float` feature.
+//!
+//! Both wrappers are transparent on the wire: they use exactly the same encoding as
+//! the `f32`/`f64` they wrap, so they can be introduced (for example as map keys)
+//! without changing existing data. They also implement the encoding traits, so a
+//! `HashMap<OrderedFloat<f64>, V>` marshals through [`BencEncode`] like any other map.
+
+use ordered_float::{NotNan, OrderedFloat};
+
+use crate::{
+    BencDecode, BencEncode, Error, Result, Type, WireType, marshal_f32, marshal_f64, size_f32, size_f64, skip_f32,
+    skip_f64, unmarshal_f32, unmarshal_f64,
+};
+
+// Use a macro to generate the functions for both float widths to avoid boilerplate.
+macro_rules! ordered_float_impl {
+    ($float:ty,
+     $size_ordered:ident, $marshal_ordered:ident, $unmarshal_ordered:ident, $skip_ordered:ident,
+     $size_not_nan:ident, $marshal_not_nan:ident, $unmarshal_not_nan:ident, $skip_not_nan:ident,
+     $size:ident, $marshal:ident, $unmarshal:ident, $skip:ident
+    ) => {
+        #[doc = concat!("Returns the number of bytes required to marshal an `OrderedFloat<", stringify!($float), ">`.")]
+        pub const fn $size_ordered() -> usize {
+            $size()
+        }
+
+        #[doc = concat!("Marshals an `OrderedFloat<", stringify!($float), ">` exactly like an `", stringify!($float), "`.")]
+        ///
+        /// Returns an error if the writer is too small.
+        pub fn $marshal_ordered(v: OrderedFloat<$float>, writer: &mut &mut [u8]) -> Result<()> {
+            $marshal(v.into_inner(), writer)
+        }
+
+        #[doc = concat!("Unmarshals an `OrderedFloat<", stringify!($float), ">` from the reader.")]
+        pub fn $unmarshal_ordered(reader: &mut &[u8]) -> Result<OrderedFloat<$float>> {
+            $unmarshal(reader).map(OrderedFloat)
+        }
+
+        #[doc = concat!("Skips over a marshalled `OrderedFloat<", stringify!($float), ">` in the reader.")]
+        pub fn $skip_ordered(reader: &mut &[u8]) -> Result<()> {
+            $skip(reader)
+        }
+
+        #[doc = concat!("Returns the number of bytes required to marshal a `NotNan<", stringify!($float), ">`.")]
+        pub const fn $size_not_nan() -> usize {
+            $size()
+        }
+
+        #[doc = concat!("Marshals a `NotNan<", stringify!($float), ">` exactly like an `", stringify!($float), "`.")]
+        ///
+        /// Returns an error if the writer is too small.
+        pub fn $marshal_not_nan(v: NotNan<$float>, writer: &mut &mut [u8]) -> Result<()> {
+            $marshal(v.into_inner(), writer)
+        }
+
+        #[doc = concat!("Unmarshals a `NotNan<", stringify!($float), ">` from the reader.")]
+        /// Returns an `InvalidValue` error if the marshalled value is NaN.
+        pub fn $unmarshal_not_nan(reader: &mut &[u8]) -> Result<NotNan<$float>> {
+            NotNan::new($unmarshal(reader)?).map_err(|_| Error::InvalidValue)
+        }
+
+        #[doc = concat!("Skips over a marshalled `NotNan<", stringify!($float), ">` in the reader.")]
+        pub fn $skip_not_nan(reader: &mut &[u8]) -> Result<()> {
+            $skip(reader)
+        }
+
+        impl BencEncode for OrderedFloat<$float> {
+            const WIRE_TYPE: Option<WireType> = <$float as BencEncode>::WIRE_TYPE;
+            const ENCODED_SIZE: Option<usize> = <$float as BencEncode>::ENCODED_SIZE;
+
+            fn benc_type() -> Option<Type> {
+                <$float as BencEncode>::benc_type()
+            }
+
+            fn size(&self) -> usize {
+                $size_ordered()
+            }
+
+            fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+                $marshal_ordered(*self, writer)
+            }
+        }
+
+        impl BencDecode<'_> for OrderedFloat<$float> {
+            fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+                $unmarshal_ordered(reader)
+            }
+
+            fn skip(reader: &mut &[u8]) -> Result<()> {
+                $skip_ordered(reader)
+            }
+        }
+
+        impl BencEncode for NotNan<$float> {
+            const WIRE_TYPE: Option<WireType> = <$float as BencEncode>::WIRE_TYPE;
+            const ENCODED_SIZE: Option<usize> = <$float as BencEncode>::ENCODED_SIZE;
+
+            fn benc_type() -> Option<Type> {
+                <$float as BencEncode>::benc_type()
+            }
+
+            fn size(&self) -> usize {
+                $size_not_nan()
+            }
+
+            fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+                $marshal_not_nan(*self, writer)
+            }
+        }
+
+        impl BencDecode<'_> for NotNan<$float> {
+            fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+                $unmarshal_not_nan(reader)
+            }
+
+            fn skip(reader: &mut &[u8]) -> Result<()> {
+                $skip_not_nan(reader)
+            }
+        }
+    };
+}
+
+ordered_float_impl!(f32,
+    size_ordered_f32, marshal_ordered_f32, unmarshal_ordered_f32, skip_ordered_f32,
+    size_not_nan_f32, marshal_not_nan_f32, unmarshal_not_nan_f32, skip_not_nan_f32,
+    size_f32, marshal_f32, unmarshal_f32, skip_f32);
+ordered_float_impl!(f64,
+    size_ordered_f64, marshal_ordered_f64, unmarshal_ordered_f64, skip_ordered_f64,
+    size_not_nan_f64, marshal_not_nan_f64, unmarshal_not_nan_f64, skip_not_nan_f64,
+    size_f64, marshal_f64, unmarshal_f64, skip_f64);
@@ -0,0 +1,91 @@
+//! Parallel encoding of large slices using `rayon`, enabled by the `rayon` feature.
+//!
+//! The output is identical to `marshal_slice`. The elements are sized first, which
+//! gives every chunk of elements its own disjoint region of the output buffer, and
+//! the chunks are then marshalled on the rayon thread pool.
+//!
+//! Chunked slices can also be decoded in parallel, since their index locates every
+//! chunk before any element is read.
+
+use rayon::prelude::*;
+
+use crate::{Error, TERMINATOR, marshal_usize, size_usize, unmarshal_chunked_index, write_to_slice};
+
+/// Returns the number of bytes needed to marshal a slice of elements with dynamic
+/// sizes, sizing the elements in parallel.
+pub fn size_slice_parallel<T: Sync>(slice: &[T], sizer: impl Fn(&T) -> usize + Sync) -> usize {
+    size_usize(slice.len()) + slice.par_iter().map(&sizer).sum::<usize>() + TERMINATOR.len()
+}
+
+/// Marshals a slice of elements into the writer, encoding chunks of elements in
+/// parallel.
+///
+/// `sizer` must return exactly the number of bytes `marshaler` writes for an element,
+/// since it determines where every element is placed.
+///
+/// Returns an error if the writer is too small or a marshaler fails; the writer is
+/// only advanced on success.
+///
+/// # Panics
+///
+/// Panics if the marshaler writes fewer bytes than the sizer reported.
+pub fn marshal_slice_parallel<T: Sync, E: From<Error> + Send>(
+    slice: &[T],
+    writer: &mut &mut [u8],
+    sizer: impl Fn(&T) -> usize + Sync,
+    marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<(), E> + Sync,
+) -> Result<(), E> {
+    let sizes: Vec<usize> = slice.par_iter().map(&sizer).collect();
+    let header_len = size_usize(slice.len());
+    let data_len: usize = sizes.iter().sum();
+    let needed = header_len + data_len + TERMINATOR.len();
+    if writer.len() < needed {
+        return Err(Error::BufferTooSmall { needed, available: writer.len() }.into());
+    }
+
+    let mut out = &mut writer[..needed];
+    marshal_usize(slice.len(), &mut out)?;
+    let (mut data, mut terminator) = out.split_at_mut(data_len);
+
+    // A few chunks per thread balance the load without much bookkeeping.
+    let chunk_len = slice.len().div_ceil(rayon::current_num_threads() * 4).max(1);
+    let mut regions = Vec::with_capacity(slice.len().div_ceil(chunk_len));
+    for chunk_sizes in sizes.chunks(chunk_len) {
+        let (region, tail) = std::mem::take(&mut data).split_at_mut(chunk_sizes.iter().sum());
+        regions.push(region);
+        data = tail;
+    }
+
+    slice.par_chunks(chunk_len).zip(regions).try_for_each(|(elements, mut region)| {
+        for element in elements {
+            marshaler(element, &mut region)?;
+        }
+        assert!(region.is_empty(), "marshaler wrote fewer bytes than the sizer reported");
+        Ok::<_, E>(())
+    })?;
+
+    write_to_slice(&mut terminator, &TERMINATOR)?;
+    let written = std::mem::take(writer);
+    *writer = &mut written[needed..];
+    Ok(())
+}
+
+/// Unmarshals a chunked slice written by `marshal_chunked`, decoding the chunks in
+/// parallel and concatenating the results in order.
+///
+/// Returns the first error encountered; the reader is only advanced on success.
+pub fn unmarshal_chunked_parallel<'a, T: Send, E: From<Error> + Send>(
+    reader: &mut &'a [u8],
+    unmarshaler: impl Fn(&mut &'a [u8]) -> Result<T, E> + Sync,
+) -> Result<Vec<T>, E> {
+    let mut input = *reader;
+    let chunked = unmarshal_chunked_index(&mut input)?;
+    let decoded: Vec<Vec<T>> =
+        chunked.chunks().par_iter().map(|chunk| chunk.decode(&unmarshaler)).collect::<Result<_, E>>()?;
+    let mut out = Vec::with_capacity(chunked.len());
+    for values in decoded {
+        out.extend(values);
+    }
+    *reader = input;
+    Ok(out)
+}
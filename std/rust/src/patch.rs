@@ -0,0 +1,52 @@
+//! In-place updates of fixed-size fields.
+//!
+//! A fixed-size value always occupies the same bytes, so it can be overwritten inside
+//! an encoded message without re-encoding the rest, for example to bump a counter in a
+//! memory-mapped record. The offset of a field comes from an index of the message, or
+//! from [`Schema::fixed_field_offset`](crate::Schema::fixed_field_offset) when all
+//! fields before it have a fixed size.
+//!
+//! The patch functions do not know what the bytes at the offset hold; writing a value
+//! of the wrong type corrupts the message.
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    Error, Result, marshal_bool, marshal_f32, marshal_f64, marshal_i8, marshal_i16, marshal_i32,
+    marshal_i64, marshal_time, marshal_u8, marshal_u16, marshal_u32, marshal_u64,
+};
+
+/// Returns the writer over the bytes of `buf` from `offset`.
+fn writer_at(buf: &mut [u8], offset: usize) -> Result<&mut [u8]> {
+    let available = buf.len();
+    buf.get_mut(offset..).ok_or(Error::BufferTooSmall { needed: offset, available })
+}
+
+macro_rules! patch_impl {
+    ($($patch_fn:ident: $type:ty => $marshal_fn:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Overwrites the `", stringify!($type), "` at `offset` in an encoded message.")]
+            ///
+            /// Returns an error if the value does not fit in the buffer, leaving it
+            /// unchanged.
+            pub fn $patch_fn(buf: &mut [u8], offset: usize, v: $type) -> Result<()> {
+                $marshal_fn(v, &mut writer_at(buf, offset)?)
+            }
+        )*
+    };
+}
+
+patch_impl! {
+    patch_bool_at: bool => marshal_bool,
+    patch_u8_at: u8 => marshal_u8,
+    patch_u16_at: u16 => marshal_u16,
+    patch_u32_at: u32 => marshal_u32,
+    patch_u64_at: u64 => marshal_u64,
+    patch_i8_at: i8 => marshal_i8,
+    patch_i16_at: i16 => marshal_i16,
+    patch_i32_at: i32 => marshal_i32,
+    patch_i64_at: i64 => marshal_i64,
+    patch_f32_at: f32 => marshal_f32,
+    patch_f64_at: f64 => marshal_f64,
+    patch_time_at: DateTime<Utc> => marshal_time,
+}
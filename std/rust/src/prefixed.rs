@@ -0,0 +1,62 @@
+//! Values prefixed with their encoded byte length.
+//!
+//! Skipping a nested struct normally means skipping each of its fields in turn, all
+//! the way down, which is what makes lazy access to a late field of a deep message
+//! slow. A length-prefixed value is marshalled as the varint number of bytes of its
+//! encoding followed by the encoding itself, which is the byte slice format, so it is
+//! skipped in one step. The `#[benc(prefixed)]` field attribute of
+//! [`benc_struct!`](crate::benc_struct) applies it to a field.
+//!
+//! Since the decoder of the value only sees its own bytes, `#[benc(default)]` fields
+//! at the end of a prefixed struct work even when it is nested inside another value.
+
+use crate::{Error, Result, advance, marshal_usize, size_usize, skip_bytes, unmarshal_usize};
+
+/// Returns the number of bytes required to marshal a length-prefixed value.
+pub fn size_prefixed<T: ?Sized>(v: &T, sizer: impl Fn(&T) -> usize) -> usize {
+    let size = sizer(v);
+    size_usize(size) + size
+}
+
+/// Marshals a value prefixed with its encoded byte length into the writer. The sizer
+/// must return exactly the number of bytes the marshaler writes.
+///
+/// Returns an `InvalidValue` error if the marshaler writes a different number of
+/// bytes, or an error if the writer is too small.
+pub fn marshal_prefixed<T: ?Sized, E: From<Error>>(
+    v: &T,
+    writer: &mut &mut [u8],
+    sizer: impl Fn(&T) -> usize,
+    marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    let size = sizer(v);
+    marshal_usize(size, writer)?;
+    let available = writer.len();
+    marshaler(v, writer)?;
+    if available - writer.len() != size {
+        return Err(Error::InvalidValue.into());
+    }
+    Ok(())
+}
+
+/// Unmarshals a length-prefixed value from the reader. The unmarshaler only sees the
+/// bytes of the value.
+///
+/// Returns a `TrailingBytes` error if the unmarshaler does not consume all of them.
+pub fn unmarshal_prefixed<'a, T, E: From<Error>>(
+    reader: &mut &'a [u8],
+    unmarshaler: impl Fn(&mut &'a [u8]) -> Result<T, E>,
+) -> Result<T, E> {
+    let len = unmarshal_usize(reader)?;
+    let mut inner = advance(reader, len)?;
+    let v = unmarshaler(&mut inner)?;
+    if !inner.is_empty() {
+        return Err(Error::TrailingBytes.into());
+    }
+    Ok(v)
+}
+
+/// Skips over a length-prefixed value in the reader, without looking into it.
+pub fn skip_prefixed(reader: &mut &[u8]) -> Result<()> {
+    skip_bytes(reader)
+}
@@ -0,0 +1,115 @@
+//! Messages prefixed with a schema id, in the style of a schema registry.
+//!
+//! A registered message starts with the varint id of the schema it was written with,
+//! followed by the message itself. Producers and consumers agree on ids through a
+//! registry; a consumer that has the message type compiled in checks the id and
+//! decodes as usual, while generic consumers look the schema up with a
+//! [`SchemaResolver`] and decode the message into a [`Value`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    BencDecode, BencEncode, Error, Result, Schema, Value, from_slice, marshal_uint, size_uint,
+    unmarshal_uint, value_from_slice,
+};
+
+/// Looks up schemas by id.
+///
+/// Implementations typically fetch unknown schemas from a registry service; wrap
+/// them in a [`SchemaCache`] to fetch every schema only once.
+pub trait SchemaResolver {
+    /// Returns the schema with the given id.
+    ///
+    /// Returns an `UnknownSchema` error if there is no such schema.
+    fn resolve(&self, id: u64) -> Result<Arc<Schema>>;
+}
+
+impl<S: std::hash::BuildHasher> SchemaResolver for HashMap<u64, Arc<Schema>, S> {
+    fn resolve(&self, id: u64) -> Result<Arc<Schema>> {
+        self.get(&id).cloned().ok_or(Error::UnknownSchema(id))
+    }
+}
+
+/// A resolver that fetches schemas with a closure and keeps them for later lookups.
+/// Failed fetches are not cached.
+pub struct SchemaCache<F> {
+    fetch: F,
+    schemas: Mutex<HashMap<u64, Arc<Schema>>>,
+}
+
+impl<F: Fn(u64) -> Result<Schema>> SchemaCache<F> {
+    /// Creates an empty cache fetching schemas with `fetch`.
+    pub fn new(fetch: F) -> Self {
+        SchemaCache { fetch, schemas: Mutex::new(HashMap::new()) }
+    }
+
+    /// Adds a schema to the cache, so it is never fetched.
+    pub fn insert(&self, id: u64, schema: Schema) {
+        self.schemas.lock().unwrap().insert(id, Arc::new(schema));
+    }
+}
+
+impl<F: Fn(u64) -> Result<Schema>> SchemaResolver for SchemaCache<F> {
+    fn resolve(&self, id: u64) -> Result<Arc<Schema>> {
+        if let Some(schema) = self.schemas.lock().unwrap().get(&id) {
+            return Ok(schema.clone());
+        }
+        // The lock is not held while fetching, so a slow fetch does not block lookups
+        // of other ids. Concurrent fetches of the same id keep the first result.
+        let schema = Arc::new((self.fetch)(id)?);
+        Ok(self.schemas.lock().unwrap().entry(id).or_insert(schema).clone())
+    }
+}
+
+/// Returns the number of bytes needed to marshal a schema id.
+pub fn size_schema_id(id: u64) -> usize {
+    size_uint(id)
+}
+
+/// Marshals a schema id into the writer. The message follows it.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_schema_id(id: u64, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_uint(id, writer)
+}
+
+/// Unmarshals the schema id at the start of a registered message.
+pub fn unmarshal_schema_id(reader: &mut &[u8]) -> Result<u64> {
+    unmarshal_uint(reader)
+}
+
+/// Marshals a message prefixed with its schema id into a new vector.
+pub fn to_vec_with_schema_id<T: BencEncode>(id: u64, v: &T) -> Vec<u8> {
+    let mut buf = vec![0u8; size_schema_id(id) + v.size()];
+    let mut writer = buf.as_mut_slice();
+    // The buffer has exactly the size the id and the value reported.
+    marshal_schema_id(id, &mut writer).and_then(|()| v.marshal(&mut writer)).expect("size() is smaller than marshal() output");
+    buf
+}
+
+/// Unmarshals a registered message of a compiled-in type, which must have been written
+/// with the schema `id`.
+///
+/// Returns an `UnknownSchema` error carrying the message's id if it differs, and a
+/// `TrailingBytes` error if the message is followed by unconsumed bytes.
+pub fn from_slice_with_schema_id<'a, T: BencDecode<'a>>(id: u64, buf: &'a [u8]) -> Result<T> {
+    let mut reader = buf;
+    let found = unmarshal_schema_id(&mut reader)?;
+    if found != id {
+        return Err(Error::UnknownSchema(found));
+    }
+    from_slice(reader)
+}
+
+/// Unmarshals a registered message into a [`Value`], looking its schema up with the
+/// resolver. Returns the schema id and the message.
+///
+/// Returns a `TrailingBytes` error if the message is followed by unconsumed bytes, and
+/// passes on errors of the resolver.
+pub fn value_from_slice_with_resolver(buf: &[u8], resolver: &impl SchemaResolver) -> Result<(u64, Value)> {
+    let mut reader = buf;
+    let id = unmarshal_schema_id(&mut reader)?;
+    let schema = resolver.resolve(id)?;
+    Ok((id, value_from_slice(reader, &schema)?))
+}
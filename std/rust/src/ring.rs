@@ -0,0 +1,240 @@
+//! A lock-free single-producer, single-consumer ring buffer of framed messages,
+//! enabled by the `ring` feature.
+//!
+//! [`ring`] returns a [`RingProducer`] and a [`RingConsumer`] sharing a byte region.
+//! The producer marshals messages directly into the region and the consumer decodes
+//! them in place, so a message is never copied on its way through.
+//!
+//! Every record is a varint holding the message length plus one, followed by the
+//! message. Records are never split: when a record does not fit before the end of the
+//! region, the producer writes a zero byte and continues at the start. When the ring
+//! is empty, the producer instead moves both positions to the start of the region, so
+//! every record that fits into the region can be pushed.
+//!
+//! The producer publishes a record by advancing the write position only after the
+//! whole record has been written, and the consumer frees it by advancing the read
+//! position only after it is done with it, so neither side ever sees a partially
+//! written record or has its record overwritten.
+
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Error, Result, marshal_usize, size_usize, unmarshal_usize};
+
+/// The marker telling the consumer that the next record is at the start of the region.
+const WRAP: u8 = 0;
+
+struct Shared {
+    buf: Box<[UnsafeCell<u8>]>,
+    /// The total number of bytes published by the producer.
+    head: AtomicUsize,
+    /// The total number of bytes freed by the consumer.
+    tail: AtomicUsize,
+}
+
+// SAFETY: the producer only writes bytes between `head` and `tail + capacity`, which
+// the consumer does not read, and the consumer only reads bytes between `tail` and
+// `head`, which the producer does not write. The consumer only moves the tail while it
+// holds a record, and the producer only while the ring is empty, so the two never move
+// it at the same time. The positions are published with release stores and read with
+// acquire loads.
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the tail and the number of published bytes after it.
+    fn published(&self) -> (usize, usize) {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        // A tail ahead of the head is the producer moving both positions to the start
+        // of the empty region; the head follows once the record is written.
+        let available = head.wrapping_sub(tail);
+        (tail, if available > self.capacity() { 0 } else { available })
+    }
+
+    /// Returns `len` bytes of the region starting at `start`.
+    ///
+    /// # Safety
+    ///
+    /// The range must be in bounds and published to the consumer.
+    unsafe fn slice(&self, start: usize, len: usize) -> &[u8] {
+        debug_assert!(start + len <= self.capacity());
+        unsafe { std::slice::from_raw_parts(UnsafeCell::raw_get(self.buf.as_ptr().add(start)), len) }
+    }
+
+    /// Returns `len` bytes of the region starting at `start` for writing.
+    ///
+    /// # Safety
+    ///
+    /// The range must be in bounds and free space owned by the producer.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn slice_mut(&self, start: usize, len: usize) -> &mut [u8] {
+        debug_assert!(start + len <= self.capacity());
+        unsafe { std::slice::from_raw_parts_mut(UnsafeCell::raw_get(self.buf.as_ptr().add(start)), len) }
+    }
+}
+
+/// Creates a ring buffer over a region of `capacity` bytes, returning its two ends.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn ring(capacity: usize) -> (RingProducer, RingConsumer) {
+    assert!(capacity > 0, "ring capacity must not be zero");
+    let shared = Arc::new(Shared {
+        buf: (0..capacity).map(|_| UnsafeCell::new(0)).collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+    (RingProducer { shared: shared.clone(), head: 0 }, RingConsumer { shared })
+}
+
+// ===================================================================================
+// Producer
+// ===================================================================================
+
+/// The writing end of a ring buffer.
+pub struct RingProducer {
+    shared: Arc<Shared>,
+    head: usize,
+}
+
+impl RingProducer {
+    /// Returns the size of the region.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    /// Marshals a message of exactly `size` bytes, as returned by the matching sizer,
+    /// into the ring and publishes it. Returns `false` without calling the marshaler
+    /// if the ring does not have enough free space.
+    ///
+    /// Returns a `BufferTooSmall` error if the record can never fit into the region, an
+    /// `OutOfRange` error if its size overflows a `usize`, an `InvalidValue` error if
+    /// the marshaler writes fewer than `size` bytes, and passes on errors of the
+    /// marshaler. Nothing is published on error.
+    pub fn try_push(&mut self, size: usize, marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>) -> Result<bool> {
+        let capacity = self.capacity();
+        let header = size.checked_add(1).ok_or(Error::OutOfRange)?;
+        let record = size_usize(header).checked_add(size).ok_or(Error::OutOfRange)?;
+        if record > capacity {
+            return Err(Error::BufferTooSmall { needed: record, available: capacity });
+        }
+        let mut start = self.head % capacity;
+        let contiguous = capacity - start;
+        let mut tail = self.shared.tail.load(Ordering::Acquire);
+        if record > contiguous && tail == self.head {
+            // The consumer holds no record, so both positions can move to the start of
+            // the region instead of wrapping around free space that is too short.
+            self.head = self.head.wrapping_add(contiguous);
+            tail = self.head;
+            self.shared.tail.store(tail, Ordering::Release);
+            start = 0;
+        }
+        let contiguous = capacity - start;
+        let needed = if record <= contiguous { record } else { contiguous + record };
+        if needed > capacity - self.head.wrapping_sub(tail) {
+            return Ok(false);
+        }
+
+        if record > contiguous {
+            // SAFETY: the byte is free space owned by the producer.
+            unsafe { self.shared.slice_mut(start, 1)[0] = WRAP };
+            start = 0;
+        }
+        // SAFETY: the record is free space owned by the producer, as checked above.
+        let mut writer = unsafe { self.shared.slice_mut(start, record) };
+        marshal_usize(header, &mut writer)?;
+        marshaler(&mut writer)?;
+        if !writer.is_empty() {
+            return Err(Error::InvalidValue);
+        }
+
+        self.head = self.head.wrapping_add(needed);
+        self.shared.head.store(self.head, Ordering::Release);
+        Ok(true)
+    }
+}
+
+// ===================================================================================
+// Consumer
+// ===================================================================================
+
+/// The reading end of a ring buffer.
+pub struct RingConsumer {
+    shared: Arc<Shared>,
+}
+
+impl RingConsumer {
+    /// Returns `true` if no message is waiting.
+    pub fn is_empty(&self) -> bool {
+        self.shared.published().1 == 0
+    }
+
+    /// Returns the next message, or `None` if the ring is empty. The message stays in
+    /// the ring until the returned record is dropped.
+    ///
+    /// Returns an `InvalidValue` error if the record header is corrupt.
+    pub fn try_pop(&mut self) -> Result<Option<RingRecord<'_>>> {
+        let shared = &*self.shared;
+        let (tail, mut available) = shared.published();
+        if available == 0 {
+            return Ok(None);
+        }
+        let capacity = shared.capacity();
+        let mut start = tail % capacity;
+        let mut skipped = 0;
+        // SAFETY: published bytes between the tail and the head belong to the consumer.
+        if unsafe { shared.slice(start, 1)[0] } == WRAP {
+            skipped = capacity - start;
+            available = available.checked_sub(skipped).ok_or(Error::InvalidValue)?;
+            start = 0;
+        }
+
+        // SAFETY: as above; the record is never split at the end of the region.
+        let region = unsafe { shared.slice(start, available.min(capacity - start)) };
+        let mut reader = region;
+        let len = unmarshal_usize(&mut reader)?.checked_sub(1).ok_or(Error::InvalidValue)?;
+        let header = region.len() - reader.len();
+        let data = reader.get(..len).ok_or(Error::InvalidValue)?;
+        Ok(Some(RingRecord { data, shared, tail, consumed: skipped + header + len }))
+    }
+}
+
+/// A message read from a ring buffer, which frees its space when dropped.
+pub struct RingRecord<'a> {
+    data: &'a [u8],
+    shared: &'a Shared,
+    tail: usize,
+    consumed: usize,
+}
+
+impl RingRecord<'_> {
+    /// Returns the marshalled message.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data
+    }
+
+    /// Unmarshals the message in place. Strings and byte slices borrow from the ring,
+    /// so the value cannot outlive the record.
+    ///
+    /// Returns a `TrailingBytes` error if the unmarshaler does not consume every byte.
+    pub fn decode<'r, T>(&'r self, unmarshaler: impl FnOnce(&mut &'r [u8]) -> Result<T>) -> Result<T> {
+        let mut reader = self.data;
+        let value = unmarshaler(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(value)
+    }
+}
+
+impl Drop for RingRecord<'_> {
+    fn drop(&mut self) {
+        self.shared.tail.store(self.tail.wrapping_add(self.consumed), Ordering::Release);
+    }
+}
@@ -0,0 +1,323 @@
+//! Request/response messaging over framed streams.
+//!
+//! Every frame holds an envelope: a kind byte followed by
+//!
+//! - a request (`0`): the varint call id, the method name and the payload bytes,
+//! - a response (`1`): the varint call id and the payload bytes,
+//! - an error response (`2`): the varint call id and the error message,
+//! - a notification (`3`): the method name and the payload bytes.
+//!
+//! Payloads are marshalled messages whose type the method implies. [`RpcEndpoint`]
+//! implements the protocol without I/O, matching responses to the calls in flight,
+//! and [`BlockingRpc`] drives it over any `Read + Write` stream. Either side of a
+//! connection can call the other.
+
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, Read, Write};
+
+use crate::{
+    BencEncode, Error, FrameDecoder, FrameEncoder, Result, marshal_bytes, marshal_string, marshal_u8, marshal_uint,
+    size_bytes, size_string, size_u8, size_uint, unmarshal_bytes_cropped, unmarshal_string, unmarshal_u8,
+    unmarshal_uint,
+};
+
+const KIND_REQUEST: u8 = 0;
+const KIND_RESPONSE: u8 = 1;
+const KIND_ERROR: u8 = 2;
+const KIND_NOTIFICATION: u8 = 3;
+
+/// The number of bytes `BlockingRpc` asks the stream for at a time.
+const READ_SIZE: usize = 8 * 1024;
+
+/// An envelope to send, borrowing its contents.
+enum Envelope<'a> {
+    Request { id: u64, method: &'a str, payload: &'a [u8] },
+    Response { id: u64, payload: &'a [u8] },
+    Error { id: u64, message: &'a str },
+    Notification { method: &'a str, payload: &'a [u8] },
+}
+
+impl BencEncode for Envelope<'_> {
+    fn size(&self) -> usize {
+        size_u8()
+            + match self {
+                Envelope::Request { id, method, payload } => size_uint(*id) + size_string(method) + size_bytes(payload),
+                Envelope::Response { id, payload } => size_uint(*id) + size_bytes(payload),
+                Envelope::Error { id, message } => size_uint(*id) + size_string(message),
+                Envelope::Notification { method, payload } => size_string(method) + size_bytes(payload),
+            }
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        match self {
+            Envelope::Request { id, method, payload } => {
+                marshal_u8(KIND_REQUEST, writer)?;
+                marshal_uint(*id, writer)?;
+                marshal_string(method, writer)?;
+                marshal_bytes(payload, writer)
+            }
+            Envelope::Response { id, payload } => {
+                marshal_u8(KIND_RESPONSE, writer)?;
+                marshal_uint(*id, writer)?;
+                marshal_bytes(payload, writer)
+            }
+            Envelope::Error { id, message } => {
+                marshal_u8(KIND_ERROR, writer)?;
+                marshal_uint(*id, writer)?;
+                marshal_string(message, writer)
+            }
+            Envelope::Notification { method, payload } => {
+                marshal_u8(KIND_NOTIFICATION, writer)?;
+                marshal_string(method, writer)?;
+                marshal_bytes(payload, writer)
+            }
+        }
+    }
+}
+
+/// Something that happened on an RPC connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcEvent {
+    /// The peer called a method, and expects a response with the same id.
+    Request { id: u64, method: String, payload: Vec<u8> },
+    /// The peer responded to a call, with the payload or an error message.
+    Response { id: u64, result: std::result::Result<Vec<u8>, String> },
+    /// The peer sent a message that expects no response.
+    Notification { method: String, payload: Vec<u8> },
+}
+
+fn decode_event(frame: &[u8]) -> Result<RpcEvent> {
+    let mut reader = frame;
+    let event = match unmarshal_u8(&mut reader)? {
+        KIND_REQUEST => RpcEvent::Request {
+            id: unmarshal_uint(&mut reader)?,
+            method: unmarshal_string(&mut reader)?.to_owned(),
+            payload: unmarshal_bytes_cropped(&mut reader)?.to_vec(),
+        },
+        KIND_RESPONSE => {
+            RpcEvent::Response { id: unmarshal_uint(&mut reader)?, result: Ok(unmarshal_bytes_cropped(&mut reader)?.to_vec()) }
+        }
+        KIND_ERROR => {
+            RpcEvent::Response { id: unmarshal_uint(&mut reader)?, result: Err(unmarshal_string(&mut reader)?.to_owned()) }
+        }
+        KIND_NOTIFICATION => RpcEvent::Notification {
+            method: unmarshal_string(&mut reader)?.to_owned(),
+            payload: unmarshal_bytes_cropped(&mut reader)?.to_vec(),
+        },
+        _ => return Err(Error::InvalidValue),
+    };
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(event)
+}
+
+// ===================================================================================
+// Sans-IO endpoint
+// ===================================================================================
+
+/// One end of an RPC connection, without I/O.
+///
+/// Calls, responses and notifications queue frames that the transport sends from
+/// [`transmit`](Self::transmit), and bytes received from the peer are passed to
+/// [`receive`](Self::receive) and come out as events of
+/// [`poll_event`](Self::poll_event).
+#[derive(Debug, Default)]
+pub struct RpcEndpoint {
+    decoder: FrameDecoder,
+    encoder: FrameEncoder,
+    next_id: u64,
+    in_flight: HashSet<u64>,
+}
+
+impl RpcEndpoint {
+    /// Creates an endpoint with no calls in flight.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the largest frame length the endpoint accepts from the peer.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.decoder = self.decoder.with_max_frame_len(max_frame_len);
+        self
+    }
+
+    fn send(&mut self, envelope: Envelope<'_>) {
+        // Envelopes of strings and bytes always marshal into their size.
+        self.encoder.encode(&envelope).unwrap();
+    }
+
+    /// Queues a call of `method` and returns its id, which the response will carry.
+    pub fn call(&mut self, method: &str, payload: &[u8]) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.in_flight.insert(id);
+        self.send(Envelope::Request { id, method, payload });
+        id
+    }
+
+    /// Queues a notification, which the peer does not respond to.
+    pub fn notify(&mut self, method: &str, payload: &[u8]) {
+        self.send(Envelope::Notification { method, payload });
+    }
+
+    /// Queues the response to the request with the given id.
+    pub fn respond(&mut self, id: u64, payload: &[u8]) {
+        self.send(Envelope::Response { id, payload });
+    }
+
+    /// Queues an error response to the request with the given id.
+    pub fn respond_error(&mut self, id: u64, message: &str) {
+        self.send(Envelope::Error { id, message });
+    }
+
+    /// Returns the number of calls that have not been responded to.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Returns the bytes waiting to be sent to the peer.
+    pub fn transmit(&self) -> &[u8] {
+        self.encoder.pending()
+    }
+
+    /// Marks the first `n` bytes returned by `transmit` as sent.
+    pub fn consume(&mut self, n: usize) {
+        self.encoder.consume(n);
+    }
+
+    /// Adds bytes received from the peer.
+    pub fn receive(&mut self, data: &[u8]) {
+        self.decoder.feed(data);
+    }
+
+    /// Returns the frame decoder, to read received bytes into it directly.
+    pub fn decoder_mut(&mut self) -> &mut FrameDecoder {
+        &mut self.decoder
+    }
+
+    /// Returns the next event, or `None` if more bytes are needed.
+    ///
+    /// Returns an `InvalidData` error if a frame is malformed or answers a call that is
+    /// not in flight.
+    pub fn poll_event(&mut self) -> io::Result<Option<RpcEvent>> {
+        let Some(frame) = self.decoder.next_frame()? else {
+            return Ok(None);
+        };
+        let event = decode_event(frame)?;
+        if let RpcEvent::Response { id, .. } = event
+            && !self.in_flight.remove(&id)
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("response to unknown call {id}")));
+        }
+        Ok(Some(event))
+    }
+
+    /// Checks that the peer closed the connection between frames.
+    ///
+    /// Returns an `UnexpectedEof` error if a frame was cut short.
+    pub fn finish(&self) -> io::Result<()> {
+        self.decoder.finish()
+    }
+}
+
+// ===================================================================================
+// Blocking wrapper
+// ===================================================================================
+
+/// An RPC connection over a blocking stream.
+///
+/// Events that arrive while [`call`](Self::call) waits for its response are kept
+/// and returned by [`next_event`](Self::next_event).
+pub struct BlockingRpc<S> {
+    stream: S,
+    endpoint: RpcEndpoint,
+    events: VecDeque<RpcEvent>,
+}
+
+impl<S: Read + Write> BlockingRpc<S> {
+    /// Creates a connection over `stream`.
+    pub fn new(stream: S) -> Self {
+        BlockingRpc { stream, endpoint: RpcEndpoint::new(), events: VecDeque::new() }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let pending = self.endpoint.transmit();
+        self.stream.write_all(pending)?;
+        let n = pending.len();
+        self.endpoint.consume(n);
+        self.stream.flush()
+    }
+
+    /// Reads the next event from the stream, or `None` at end of file.
+    fn read_event(&mut self) -> io::Result<Option<RpcEvent>> {
+        loop {
+            if let Some(event) = self.endpoint.poll_event()? {
+                return Ok(Some(event));
+            }
+            let decoder = self.endpoint.decoder_mut();
+            let n = match self.stream.read(decoder.read_buf(READ_SIZE)) {
+                Ok(n) => n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            decoder.commit(n);
+            if n == 0 {
+                return self.endpoint.finish().map(|()| None);
+            }
+        }
+    }
+
+    /// Calls `method` and waits for the response, which is the payload or the error
+    /// message of the peer.
+    ///
+    /// Returns an `UnexpectedEof` error if the peer closes the connection first.
+    pub fn call(&mut self, method: &str, payload: &[u8]) -> io::Result<std::result::Result<Vec<u8>, String>> {
+        let id = self.endpoint.call(method, payload);
+        self.flush()?;
+        loop {
+            match self.read_event()? {
+                Some(RpcEvent::Response { id: got, result }) if got == id => return Ok(result),
+                Some(event) => self.events.push_back(event),
+                None => return Err(io::ErrorKind::UnexpectedEof.into()),
+            }
+        }
+    }
+
+    /// Returns the next request, notification or response, or `None` when the peer
+    /// has closed the connection.
+    pub fn next_event(&mut self) -> io::Result<Option<RpcEvent>> {
+        match self.events.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None => self.read_event(),
+        }
+    }
+
+    /// Sends a notification.
+    pub fn notify(&mut self, method: &str, payload: &[u8]) -> io::Result<()> {
+        self.endpoint.notify(method, payload);
+        self.flush()
+    }
+
+    /// Sends the response to the request with the given id.
+    pub fn respond(&mut self, id: u64, payload: &[u8]) -> io::Result<()> {
+        self.endpoint.respond(id, payload);
+        self.flush()
+    }
+
+    /// Sends an error response to the request with the given id.
+    pub fn respond_error(&mut self, id: u64, message: &str) -> io::Result<()> {
+        self.endpoint.respond_error(id, message);
+        self.flush()
+    }
+}
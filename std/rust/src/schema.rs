@@ -0,0 +1,213 @@
+//! Runtime descriptions of marshalled data.
+//!
+//! A [`Schema`] lists the top-level fields of a message in the order they are
+//! marshalled, each with the [`Type`] used to encode it. Because the wire format
+//! carries no type information, a schema is what allows generic tooling to walk a
+//! message without knowing the Rust type it was produced from.
+//!
+//! Schemas can be written by hand, or taken from types defined with
+//! [`benc_struct!`](crate::benc_struct) through [`schema_of`].
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::{
+    BencEncode, Error, Result, skip_bool, skip_bytes, skip_f32, skip_f64, skip_i16, skip_i32,
+    skip_i64, skip_i8, skip_int, skip_map, skip_option, skip_slice, skip_string, skip_time,
+    skip_u16, skip_u32, skip_u64, skip_u8, skip_uint,
+};
+
+/// The encoding of a single value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    /// A varint-encoded unsigned integer (`marshal_uint`, `marshal_usize`).
+    Uint,
+    /// A ZigZag varint-encoded signed integer (`marshal_int`, `marshal_isize`).
+    Int,
+    String,
+    Bytes,
+    /// A `DateTime<Utc>` (`marshal_time`).
+    Time,
+    Slice(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Option(Box<Type>),
+    /// A nested message, marshalled as its fields back to back.
+    Struct(Schema),
+}
+
+impl Type {
+    /// Returns the size of every marshalled value of the type if it is constant.
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self {
+            Type::Bool | Type::U8 | Type::I8 => Some(1),
+            Type::U16 | Type::I16 => Some(2),
+            Type::U32 | Type::I32 | Type::F32 => Some(4),
+            Type::U64 | Type::I64 | Type::F64 | Type::Time => Some(8),
+            Type::Struct(schema) => schema.encoded_fixed_size(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Bool => f.write_str("bool"),
+            Type::U8 => f.write_str("u8"),
+            Type::U16 => f.write_str("u16"),
+            Type::U32 => f.write_str("u32"),
+            Type::U64 => f.write_str("u64"),
+            Type::I8 => f.write_str("i8"),
+            Type::I16 => f.write_str("i16"),
+            Type::I32 => f.write_str("i32"),
+            Type::I64 => f.write_str("i64"),
+            Type::F32 => f.write_str("f32"),
+            Type::F64 => f.write_str("f64"),
+            Type::Uint => f.write_str("uint"),
+            Type::Int => f.write_str("int"),
+            Type::String => f.write_str("string"),
+            Type::Bytes => f.write_str("bytes"),
+            Type::Time => f.write_str("time"),
+            Type::Slice(elem) => write!(f, "[{elem}]"),
+            Type::Map(k, v) => write!(f, "map<{k}, {v}>"),
+            Type::Option(inner) => write!(f, "option<{inner}>"),
+            Type::Struct(_) => f.write_str("struct"),
+        }
+    }
+}
+
+/// A named field of a [`Schema`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+    /// Whether the value must be hidden by debugging tools such as [`dump`](crate::dump).
+    pub sensitive: bool,
+}
+
+/// The ordered list of fields that make up a message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    fields: Vec<Field>,
+}
+
+impl Schema {
+    /// Creates a schema without fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a field, returning the schema for chaining.
+    pub fn field(mut self, name: impl Into<String>, ty: Type) -> Self {
+        self.fields.push(Field { name: name.into(), ty, sensitive: false });
+        self
+    }
+
+    /// Appends a field whose value is redacted by debugging tools, returning the
+    /// schema for chaining.
+    pub fn sensitive_field(mut self, name: impl Into<String>, ty: Type) -> Self {
+        self.fields.push(Field { name: name.into(), ty, sensitive: true });
+        self
+    }
+
+    /// Returns the fields in marshalling order.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns the types of the fields in marshalling order.
+    pub fn types(&self) -> impl Iterator<Item = &Type> {
+        self.fields.iter().map(|f| &f.ty)
+    }
+
+    /// Returns the size of every message described by the schema if it is constant.
+    pub fn encoded_fixed_size(&self) -> Option<usize> {
+        self.types().map(Type::fixed_size).sum()
+    }
+
+    /// Returns the index of the field with the given name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f.name == name)
+    }
+
+    /// Returns the offset of the field with the given name in every message described
+    /// by the schema, which is constant if the field and all fields before it have a
+    /// fixed size. Such fields can be overwritten in place with the `patch_*_at`
+    /// functions.
+    pub fn fixed_field_offset(&self, name: &str) -> Option<usize> {
+        let index = self.index_of(name)?;
+        self.fields[index].ty.fixed_size()?;
+        self.fields[..index].iter().map(|f| f.ty.fixed_size()).sum()
+    }
+}
+
+/// Returns the schema of a struct type, such as one defined with
+/// [`benc_struct!`](crate::benc_struct), or `None` if its encoding cannot be described
+/// by a schema.
+pub fn schema_of<T: BencEncode + ?Sized>() -> Option<Schema> {
+    match T::benc_type()? {
+        Type::Struct(schema) => Some(schema),
+        _ => None,
+    }
+}
+
+/// Skips over a marshalled value of the given type in the reader.
+pub fn skip_type(reader: &mut &[u8], ty: &Type) -> Result<()> {
+    match ty {
+        Type::Bool => skip_bool(reader),
+        Type::U8 => skip_u8(reader),
+        Type::U16 => skip_u16(reader),
+        Type::U32 => skip_u32(reader),
+        Type::U64 => skip_u64(reader),
+        Type::I8 => skip_i8(reader),
+        Type::I16 => skip_i16(reader),
+        Type::I32 => skip_i32(reader),
+        Type::I64 => skip_i64(reader),
+        Type::F32 => skip_f32(reader),
+        Type::F64 => skip_f64(reader),
+        Type::Uint => skip_uint(reader),
+        Type::Int => skip_int(reader),
+        Type::String => skip_string(reader),
+        Type::Bytes => skip_bytes(reader),
+        Type::Time => skip_time(reader),
+        Type::Slice(elem) => skip_slice(reader, |r| skip_type(r, elem)),
+        Type::Map(k, v) => skip_map(reader, |r| skip_type(r, k), |r| skip_type(r, v)),
+        Type::Option(inner) => skip_option(reader, |r| skip_type(r, inner)),
+        Type::Struct(schema) => skip_schema(reader, schema),
+    }
+}
+
+/// Skips over a marshalled message described by the schema.
+pub fn skip_schema(reader: &mut &[u8], schema: &Schema) -> Result<()> {
+    for field in &schema.fields {
+        skip_type(reader, &field.ty)?;
+    }
+    Ok(())
+}
+
+/// Returns the byte range of every top-level field of a marshalled message, relative
+/// to the start of `buf`, and advances past it.
+pub(crate) fn field_ranges(buf: &[u8], schema: &Schema) -> Result<Vec<Range<usize>>> {
+    let mut reader = buf;
+    let mut ranges = Vec::with_capacity(schema.fields.len());
+    for field in &schema.fields {
+        let start = buf.len() - reader.len();
+        skip_type(&mut reader, &field.ty)?;
+        ranges.push(start..buf.len() - reader.len());
+    }
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(ranges)
+}
@@ -0,0 +1,287 @@
+//! Support for scientific types: `num_complex::Complex` (feature `num-complex`) and
+//! `ndarray` arrays (feature `ndarray`).
+//!
+//! `Complex<f32>`, `Complex<f64>`, `Array1<T>` and `Array2<T>` also implement the
+//! encoding traits, so structs defined with [`benc_struct!`](crate::benc_struct) can
+//! hold them.
+
+// ===================================================================================
+// Complex<f32> / Complex<f64>
+// ===================================================================================
+
+#[cfg(feature = "num-complex")]
+mod complex {
+    use num_complex::Complex;
+
+    use crate::{
+        BencDecode, BencEncode, Result, Schema, Type, marshal_f32, marshal_f64, size_f32, size_f64, skip_f32,
+        skip_f64, unmarshal_f32, unmarshal_f64,
+    };
+
+    /// Returns the number of bytes required to marshal a `Complex<f32>`.
+    pub const fn size_complex_f32() -> usize {
+        2 * size_f32()
+    }
+
+    /// Marshals a `Complex<f32>` as its real part followed by its imaginary part.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_complex_f32(v: Complex<f32>, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_f32(v.re, writer)?;
+        marshal_f32(v.im, writer)
+    }
+
+    /// Unmarshals a `Complex<f32>` from the reader.
+    pub fn unmarshal_complex_f32(reader: &mut &[u8]) -> Result<Complex<f32>> {
+        let re = unmarshal_f32(reader)?;
+        let im = unmarshal_f32(reader)?;
+        Ok(Complex::new(re, im))
+    }
+
+    /// Skips over a marshalled `Complex<f32>` in the reader.
+    pub fn skip_complex_f32(reader: &mut &[u8]) -> Result<()> {
+        skip_f32(reader)?;
+        skip_f32(reader)
+    }
+
+    /// Returns the number of bytes required to marshal a `Complex<f64>`.
+    pub const fn size_complex_f64() -> usize {
+        2 * size_f64()
+    }
+
+    /// Marshals a `Complex<f64>` as its real part followed by its imaginary part.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_complex_f64(v: Complex<f64>, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_f64(v.re, writer)?;
+        marshal_f64(v.im, writer)
+    }
+
+    /// Unmarshals a `Complex<f64>` from the reader.
+    pub fn unmarshal_complex_f64(reader: &mut &[u8]) -> Result<Complex<f64>> {
+        let re = unmarshal_f64(reader)?;
+        let im = unmarshal_f64(reader)?;
+        Ok(Complex::new(re, im))
+    }
+
+    /// Skips over a marshalled `Complex<f64>` in the reader.
+    pub fn skip_complex_f64(reader: &mut &[u8]) -> Result<()> {
+        skip_f64(reader)?;
+        skip_f64(reader)
+    }
+
+    macro_rules! complex_traits_impl {
+        ($float:ty, $benc_type:ident, $size_fn:ident, $marshal_fn:ident, $unmarshal_fn:ident, $skip_fn:ident) => {
+            impl BencEncode for Complex<$float> {
+                const ENCODED_SIZE: Option<usize> = Some($size_fn());
+
+                fn benc_type() -> Option<Type> {
+                    Some(Type::Struct(Schema::new().field("re", Type::$benc_type).field("im", Type::$benc_type)))
+                }
+
+                fn size(&self) -> usize {
+                    $size_fn()
+                }
+
+                fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+                    $marshal_fn(*self, writer)
+                }
+            }
+
+            impl BencDecode<'_> for Complex<$float> {
+                fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+                    $unmarshal_fn(reader)
+                }
+
+                fn skip(reader: &mut &[u8]) -> Result<()> {
+                    $skip_fn(reader)
+                }
+            }
+        };
+    }
+
+    complex_traits_impl!(f32, F32, size_complex_f32, marshal_complex_f32, unmarshal_complex_f32, skip_complex_f32);
+    complex_traits_impl!(f64, F64, size_complex_f64, marshal_complex_f64, unmarshal_complex_f64, skip_complex_f64);
+}
+
+#[cfg(feature = "num-complex")]
+pub use complex::*;
+
+// ===================================================================================
+// ndarray::Array1 / ndarray::Array2
+// ===================================================================================
+
+#[cfg(feature = "ndarray")]
+mod nd {
+    use ndarray::{Array1, Array2, ArrayBase, Data, Ix1, Ix2};
+
+    use crate::{
+        BencDecode, BencEncode, Error, Result, TERMINATOR, Type, marshal_usize, read_terminator, size_usize,
+        unmarshal_usize, write_to_slice,
+    };
+
+    /// Returns the number of bytes needed to marshal a one-dimensional array.
+    pub fn size_array1<S, T>(array: &ArrayBase<S, Ix1>, sizer: impl Fn(&T) -> usize) -> usize
+    where
+        S: Data<Elem = T>,
+    {
+        size_usize(array.len()) + array.iter().map(sizer).sum::<usize>() + TERMINATOR.len()
+    }
+
+    /// Marshals a one-dimensional array into the writer.
+    /// The format is the varint-encoded length, the elements and the terminator,
+    /// which makes it identical to a marshalled slice.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_array1<S, T>(
+        array: &ArrayBase<S, Ix1>,
+        writer: &mut &mut [u8],
+        marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<()>,
+    ) -> Result<()>
+    where
+        S: Data<Elem = T>,
+    {
+        marshal_usize(array.len(), writer)?;
+        for item in array {
+            marshaler(item, writer)?;
+        }
+        write_to_slice(writer, &TERMINATOR)
+    }
+
+    /// Unmarshals a one-dimensional array from the reader.
+    pub fn unmarshal_array1<'a, T>(
+        reader: &mut &'a [u8],
+        unmarshaler: impl Fn(&mut &'a [u8]) -> Result<T>,
+    ) -> Result<Array1<T>> {
+        let len = unmarshal_usize(reader)?;
+        let mut data = Vec::with_capacity(len.min(reader.len()));
+        for _ in 0..len {
+            data.push(unmarshaler(reader)?);
+        }
+        read_terminator(reader)?;
+        Ok(Array1::from_vec(data))
+    }
+
+    /// Skips over a marshalled one-dimensional array in the reader.
+    pub fn skip_array1(
+        reader: &mut &[u8],
+        skip_element: impl Fn(&mut &[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let len = unmarshal_usize(reader)?;
+        for _ in 0..len {
+            skip_element(reader)?;
+        }
+        read_terminator(reader)
+    }
+
+    /// Returns the number of bytes needed to marshal a two-dimensional array.
+    pub fn size_array2<S, T>(array: &ArrayBase<S, Ix2>, sizer: impl Fn(&T) -> usize) -> usize
+    where
+        S: Data<Elem = T>,
+    {
+        let (rows, cols) = array.dim();
+        size_usize(rows) + size_usize(cols) + array.iter().map(sizer).sum::<usize>()
+            + TERMINATOR.len()
+    }
+
+    /// Marshals a two-dimensional array into the writer.
+    /// The format is the varint-encoded number of rows and columns, the elements in
+    /// row-major order and the terminator. The memory layout of the array does not
+    /// affect the output.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_array2<S, T>(
+        array: &ArrayBase<S, Ix2>,
+        writer: &mut &mut [u8],
+        marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<()>,
+    ) -> Result<()>
+    where
+        S: Data<Elem = T>,
+    {
+        let (rows, cols) = array.dim();
+        marshal_usize(rows, writer)?;
+        marshal_usize(cols, writer)?;
+        for item in array {
+            marshaler(item, writer)?;
+        }
+        write_to_slice(writer, &TERMINATOR)
+    }
+
+    /// Unmarshals a two-dimensional array from the reader.
+    /// Returns an `OutOfRange` error if the shape overflows a `usize`.
+    pub fn unmarshal_array2<'a, T>(
+        reader: &mut &'a [u8],
+        unmarshaler: impl Fn(&mut &'a [u8]) -> Result<T>,
+    ) -> Result<Array2<T>> {
+        let rows = unmarshal_usize(reader)?;
+        let cols = unmarshal_usize(reader)?;
+        let len = rows.checked_mul(cols).ok_or(Error::OutOfRange)?;
+        let mut data = Vec::with_capacity(len.min(reader.len()));
+        for _ in 0..len {
+            data.push(unmarshaler(reader)?);
+        }
+        read_terminator(reader)?;
+        Array2::from_shape_vec((rows, cols), data).map_err(|_| Error::OutOfRange)
+    }
+
+    /// Skips over a marshalled two-dimensional array in the reader.
+    pub fn skip_array2(
+        reader: &mut &[u8],
+        skip_element: impl Fn(&mut &[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let rows = unmarshal_usize(reader)?;
+        let cols = unmarshal_usize(reader)?;
+        let len = rows.checked_mul(cols).ok_or(Error::OutOfRange)?;
+        for _ in 0..len {
+            skip_element(reader)?;
+        }
+        read_terminator(reader)
+    }
+
+    impl<T: BencEncode> BencEncode for Array1<T> {
+        fn benc_type() -> Option<Type> {
+            T::benc_type().map(|ty| Type::Slice(Box::new(ty)))
+        }
+
+        fn size(&self) -> usize {
+            size_array1(self, T::size)
+        }
+
+        fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_array1(self, writer, T::marshal)
+        }
+    }
+
+    impl<'a, T: BencDecode<'a>> BencDecode<'a> for Array1<T> {
+        fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+            unmarshal_array1(reader, T::unmarshal)
+        }
+
+        fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_array1(reader, T::skip)
+        }
+    }
+
+    impl<T: BencEncode> BencEncode for Array2<T> {
+        fn size(&self) -> usize {
+            size_array2(self, T::size)
+        }
+
+        fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_array2(self, writer, T::marshal)
+        }
+    }
+
+    impl<'a, T: BencDecode<'a>> BencDecode<'a> for Array2<T> {
+        fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+            unmarshal_array2(reader, T::unmarshal)
+        }
+
+        fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_array2(reader, T::skip)
+        }
+    }
+}
+
+#[cfg(feature = "ndarray")]
+pub use nd::*;
@@ -0,0 +1,112 @@
+//! Field-level encryption.
+//!
+//! A sealed field is marshalled as usual into a temporary buffer, encrypted with a
+//! caller-provided [`Sealer`] and written as a byte slice. The surrounding message
+//! stays readable: other fields can be decoded (and a sealed field skipped) without
+//! the key, which keeps routing information available while protecting sensitive
+//! values.
+//!
+//! This crate does not ship a cipher; implement [`Sealer`] on top of an AEAD such as
+//! AES-GCM or ChaCha20-Poly1305. The `#[benc(encrypt = path)]` field attribute of
+//! [`benc_struct!`](crate::benc_struct) seals a field with the sealer returned by the
+//! function at `path`.
+//!
+//! With the `zeroize` feature, the intermediate plaintext buffers are scrubbed
+//! before they are freed.
+
+use crate::{Error, Result, marshal_bytes, size_usize, skip_bytes, unmarshal_bytes_cropped};
+
+/// A temporary buffer holding marshalled plaintext.
+#[cfg(feature = "zeroize")]
+type Plaintext = zeroize::Zeroizing<Vec<u8>>;
+#[cfg(not(feature = "zeroize"))]
+type Plaintext = Vec<u8>;
+
+/// Encrypts and authenticates marshalled field data.
+pub trait Sealer {
+    /// Returns how many bytes sealing adds to a plaintext (nonce, tag, etc.).
+    fn overhead(&self) -> usize;
+
+    /// Encrypts a plaintext. The result must be exactly `overhead()` bytes longer.
+    fn seal(&self, plaintext: &[u8]) -> Vec<u8>;
+
+    /// Decrypts a ciphertext produced by `seal`, returning `None` if it cannot be
+    /// authenticated (wrong key or tampered data).
+    fn open(&self, ciphertext: &[u8]) -> Option<Vec<u8>>;
+}
+
+impl<S: Sealer + ?Sized> Sealer for &S {
+    fn overhead(&self) -> usize {
+        (**self).overhead()
+    }
+
+    fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        (**self).seal(plaintext)
+    }
+
+    fn open(&self, ciphertext: &[u8]) -> Option<Vec<u8>> {
+        (**self).open(ciphertext)
+    }
+}
+
+/// Returns the number of bytes needed to marshal a sealed field whose plaintext
+/// marshals into `plain_size` bytes.
+pub fn size_sealed(sealer: &impl Sealer, plain_size: usize) -> usize {
+    let len = plain_size + sealer.overhead();
+    size_usize(len) + len
+}
+
+/// Marshals a field with its marshaler, seals the result and writes it as a byte slice.
+/// `plain_size` is the number of bytes the marshaler writes.
+///
+/// Returns an `InvalidValue` error if the marshaler writes fewer bytes or the sealer
+/// does not add exactly `overhead()` bytes, either of which would make the field
+/// differ from `size_sealed`, or an error if the writer is too small.
+pub fn marshal_sealed(
+    sealer: &impl Sealer,
+    plain_size: usize,
+    writer: &mut &mut [u8],
+    marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>,
+) -> Result<()> {
+    let mut plaintext = Plaintext::from(vec![0u8; plain_size]);
+    let mut plain_writer = plaintext.as_mut_slice();
+    marshaler(&mut plain_writer)?;
+    if !plain_writer.is_empty() {
+        return Err(Error::InvalidValue);
+    }
+    let sealed = sealer.seal(&plaintext);
+    if sealed.len() != plain_size + sealer.overhead() {
+        return Err(Error::InvalidValue);
+    }
+    marshal_bytes(&sealed, writer)
+}
+
+/// Opens a sealed field and unmarshals its value.
+///
+/// Returns an `Authentication` error if the field cannot be opened, and a
+/// `TrailingBytes` error if the unmarshaler does not consume the whole plaintext.
+pub fn unmarshal_sealed<T>(
+    reader: &mut &[u8],
+    sealer: &impl Sealer,
+    unmarshaler: impl FnOnce(&mut &[u8]) -> Result<T>,
+) -> Result<T> {
+    let ciphertext = unmarshal_bytes_cropped(reader)?;
+    let plaintext = Plaintext::from(sealer.open(ciphertext).ok_or(Error::Authentication)?);
+    let mut plain_reader = plaintext.as_slice();
+    let value = unmarshaler(&mut plain_reader)?;
+    if !plain_reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(value)
+}
+
+/// Skips over a sealed field in the reader. No key is required.
+pub fn skip_sealed(reader: &mut &[u8]) -> Result<()> {
+    skip_bytes(reader)
+}
+
+/// Returns the still-sealed bytes of a field, e.g. for forwarding it unchanged.
+pub fn unmarshal_sealed_raw<'a>(reader: &mut &'a [u8]) -> Result<&'a [u8]> {
+    unmarshal_bytes_cropped(reader)
+}
+
@@ -0,0 +1,28 @@
+//! Decoding of secret material, enabled by the `zeroize` feature.
+//!
+//! Values decoded with these functions are returned wrapped in
+//! [`Zeroizing`], which scrubs their memory when they are dropped, and no
+//! intermediate copies are left behind in freed heap memory.
+
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{Result, unmarshal_bytes_cropped, unmarshal_string};
+
+/// Unmarshals a secret value with its unmarshaler, wrapping the result so it is
+/// scrubbed when dropped.
+pub fn unmarshal_secret<T: Zeroize>(
+    reader: &mut &[u8],
+    unmarshaler: impl FnOnce(&mut &[u8]) -> Result<T>,
+) -> Result<Zeroizing<T>> {
+    unmarshaler(reader).map(Zeroizing::new)
+}
+
+/// Unmarshals a secret byte slice into an owned buffer that is scrubbed when dropped.
+pub fn unmarshal_secret_bytes(reader: &mut &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    unmarshal_bytes_cropped(reader).map(|bytes| Zeroizing::new(bytes.to_vec()))
+}
+
+/// Unmarshals a secret string into an owned string that is scrubbed when dropped.
+pub fn unmarshal_secret_string(reader: &mut &[u8]) -> Result<Zeroizing<String>> {
+    unmarshal_string(reader).map(|s| Zeroizing::new(s.to_owned()))
+}
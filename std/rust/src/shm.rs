@@ -0,0 +1,203 @@
+//! Message passing through shared memory, enabled by the `shm` feature.
+//!
+//! A [`ShmSender`] and a [`ShmReceiver`] map the same file, typically one under
+//! `/dev/shm`, and exchange one message at a time. The sender marshals a message
+//! directly into the region and publishes it by bumping a sequence number; the
+//! receiver decodes it in place, without copying, and acknowledges it once the
+//! [`ShmMessage`] is dropped, which frees the region for the next message.
+//!
+//! The region starts with a header of [`SHM_HEADER_LEN`] bytes holding the sequence
+//! number of the last published message, the length of its data and the sequence
+//! number of the last acknowledged message, each a native-endian `u64`. The message
+//! data follows the header.
+//!
+//! The sender only writes the data while no message is pending and the receiver only
+//! reads it while one is, so the two never touch the data at the same time.
+//!
+//! That protocol holds only if every process mapping the file follows it. A process
+//! that writes the data out of turn changes messages, and borrowed `&str`s, while
+//! they are read, and one that truncates the file makes the other's reads fault.
+//! Opening either side is therefore `unsafe`, like mapping a file with `memmap2`.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use memmap2::{MmapMut, MmapOptions};
+
+use crate::{Error, Result};
+
+/// The length of the header at the start of a shared-memory region.
+pub const SHM_HEADER_LEN: usize = 64;
+
+const SEQ_OFFSET: usize = 0;
+const LEN_OFFSET: usize = 8;
+const ACK_OFFSET: usize = 16;
+
+/// Returns a header word of the mapping.
+fn header_word(map: &MmapMut, offset: usize) -> &AtomicU64 {
+    debug_assert!(offset + 8 <= SHM_HEADER_LEN && offset.is_multiple_of(8));
+    // SAFETY: mappings are page-aligned and at least `SHM_HEADER_LEN` bytes long, so
+    // the word is in bounds and aligned. Header words are only accessed atomically.
+    unsafe { &*(map.as_ptr().add(offset) as *const AtomicU64) }
+}
+
+fn map_file(file: &File) -> io::Result<MmapMut> {
+    // SAFETY: the callers of `ShmSender::create` and `ShmReceiver::open` guarantee
+    // that every process mapping the file follows the header protocol and keeps its
+    // length.
+    let map = unsafe { MmapOptions::new().map_mut(file)? };
+    if map.len() < SHM_HEADER_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "shared-memory region is too small"));
+    }
+    Ok(map)
+}
+
+// ===================================================================================
+// Sender
+// ===================================================================================
+
+/// The sending side of a shared-memory channel.
+pub struct ShmSender {
+    map: MmapMut,
+}
+
+impl ShmSender {
+    /// Creates the region at `path` with room for messages of up to `capacity` bytes,
+    /// replacing any existing file.
+    ///
+    /// # Safety
+    ///
+    /// No receiver may still have a previous region at `path` mapped, since the file
+    /// is truncated and reads of the old mapping would fault. The receiver of the new
+    /// region must be the only other process mapping the file.
+    pub unsafe fn create(path: impl AsRef<Path>, capacity: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        file.set_len((SHM_HEADER_LEN + capacity) as u64)?;
+        Ok(ShmSender { map: map_file(&file)? })
+    }
+
+    /// Returns the largest message the region can hold.
+    pub fn capacity(&self) -> usize {
+        self.map.len() - SHM_HEADER_LEN
+    }
+
+    /// Returns the sequence number of the last message sent, or zero if none was.
+    pub fn sequence(&self) -> u64 {
+        header_word(&self.map, SEQ_OFFSET).load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the receiver has not yet acknowledged the last message.
+    pub fn is_pending(&self) -> bool {
+        header_word(&self.map, ACK_OFFSET).load(Ordering::Acquire) != self.sequence()
+    }
+
+    /// Marshals a message of `size` bytes, as returned by the matching sizer, into the
+    /// region and publishes it. Returns `false` without calling the marshaler if the
+    /// previous message has not been acknowledged yet.
+    ///
+    /// Returns a `BufferTooSmall` error if the message exceeds the capacity, and
+    /// passes on errors of the marshaler; nothing is published in either case.
+    pub fn try_send(&mut self, size: usize, marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>) -> Result<bool> {
+        if self.is_pending() {
+            return Ok(false);
+        }
+        let capacity = self.capacity();
+        if size > capacity {
+            return Err(Error::BufferTooSmall { needed: size, available: capacity });
+        }
+        let mut writer = &mut self.map[SHM_HEADER_LEN..SHM_HEADER_LEN + size];
+        marshaler(&mut writer)?;
+        let len = size - writer.len();
+
+        let seq = self.sequence() + 1;
+        header_word(&self.map, LEN_OFFSET).store(len as u64, Ordering::Relaxed);
+        header_word(&self.map, SEQ_OFFSET).store(seq, Ordering::Release);
+        Ok(true)
+    }
+}
+
+// ===================================================================================
+// Receiver
+// ===================================================================================
+
+/// The receiving side of a shared-memory channel.
+pub struct ShmReceiver {
+    map: MmapMut,
+}
+
+impl ShmReceiver {
+    /// Opens the region at `path`, which a [`ShmSender`] has created.
+    ///
+    /// # Safety
+    ///
+    /// The sender of the region must be the only other process mapping the file, and
+    /// the file must not be truncated or replaced while the receiver exists. Messages
+    /// and the values decoded from them borrow the mapping, so any write to the data
+    /// outside the header protocol is a data race.
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(ShmReceiver { map: map_file(&file)? })
+    }
+
+    /// Returns the pending message, or `None` if there is none. The message is
+    /// acknowledged when it is dropped.
+    ///
+    /// Returns an `InvalidValue` error if the header holds a length beyond the region.
+    pub fn try_recv(&mut self) -> Result<Option<ShmMessage<'_>>> {
+        let seq = header_word(&self.map, SEQ_OFFSET).load(Ordering::Acquire);
+        let ack = header_word(&self.map, ACK_OFFSET);
+        if seq == ack.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let len = header_word(&self.map, LEN_OFFSET).load(Ordering::Relaxed);
+        let data = usize::try_from(len)
+            .ok()
+            .and_then(|len| self.map.get(SHM_HEADER_LEN..SHM_HEADER_LEN.checked_add(len)?))
+            .ok_or(Error::InvalidValue)?;
+        Ok(Some(ShmMessage { data, seq, ack }))
+    }
+}
+
+/// A message received through shared memory, borrowed from the region until it is
+/// dropped.
+///
+/// The bytes stay unchanged only while the sender follows the header protocol, as the
+/// caller of [`ShmReceiver::open`] guarantees.
+pub struct ShmMessage<'a> {
+    data: &'a [u8],
+    seq: u64,
+    ack: &'a AtomicU64,
+}
+
+impl ShmMessage<'_> {
+    /// Returns the marshalled message.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data
+    }
+
+    /// Returns the sequence number of the message.
+    pub fn sequence(&self) -> u64 {
+        self.seq
+    }
+
+    /// Unmarshals the message in place. Strings and byte slices borrow from the region,
+    /// so the value cannot outlive the message.
+    ///
+    /// Returns a `TrailingBytes` error if the unmarshaler does not consume every byte.
+    pub fn decode<'m, T>(&'m self, unmarshaler: impl FnOnce(&mut &'m [u8]) -> Result<T>) -> Result<T> {
+        let mut reader = self.data;
+        let value = unmarshaler(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(value)
+    }
+}
+
+impl Drop for ShmMessage<'_> {
+    fn drop(&mut self) {
+        self.ack.store(self.seq, Ordering::Release);
+    }
+}
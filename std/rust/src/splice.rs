@@ -0,0 +1,52 @@
+//! Replacing one field of a marshalled message without re-encoding the others.
+//!
+//! The wire format has no offsets to fix up, so a message with one field changed is
+//! the bytes before the field, the new field and the bytes after it. [`field_range`]
+//! locates a field, [`replace_field`] builds the new message with a single copy, and
+//! [`replace_field_slices`] returns the three parts without copying at all, for
+//! vectored writes or for slicing reference-counted buffers such as `bytes::Bytes`.
+
+use std::ops::Range;
+
+use crate::{Error, Result, Schema, skip_type};
+
+/// Returns the byte range of the top-level field with the given name in a marshalled
+/// message described by the schema. Only the fields up to it are walked.
+///
+/// Returns an `OutOfRange` error if the schema has no field with the given name.
+pub fn field_range(buf: &[u8], schema: &Schema, name: &str) -> Result<Range<usize>> {
+    let index = schema.index_of(name).ok_or(Error::OutOfRange)?;
+    let mut reader = buf;
+    for field in &schema.fields()[..index] {
+        skip_type(&mut reader, &field.ty)?;
+    }
+    let start = buf.len() - reader.len();
+    skip_type(&mut reader, &schema.fields()[index].ty)?;
+    Ok(start..buf.len() - reader.len())
+}
+
+/// Returns the message in `buf` with the bytes in `field_range` replaced by the
+/// marshalled field `new_encoded`.
+///
+/// # Panics
+///
+/// Panics if the range is out of bounds of `buf`.
+pub fn replace_field(buf: &[u8], field_range: Range<usize>, new_encoded: &[u8]) -> Vec<u8> {
+    let [before, field, after] = replace_field_slices(buf, field_range, new_encoded);
+    let mut out = Vec::with_capacity(before.len() + field.len() + after.len());
+    out.extend_from_slice(before);
+    out.extend_from_slice(field);
+    out.extend_from_slice(after);
+    out
+}
+
+/// Returns the parts of the message in `buf` with the bytes in `field_range` replaced
+/// by `new_encoded`: the bytes before the field, `new_encoded` and the bytes after the
+/// field. Writing them in order produces the same bytes as [`replace_field`].
+///
+/// # Panics
+///
+/// Panics if the range is out of bounds of `buf`.
+pub fn replace_field_slices<'a>(buf: &'a [u8], field_range: Range<usize>, new_encoded: &'a [u8]) -> [&'a [u8]; 3] {
+    [&buf[..field_range.start], new_encoded, &buf[field_range.end..]]
+}
@@ -0,0 +1,51 @@
+//! Database integration for `sqlx`, enabled by the `sqlx` feature.
+//!
+//! [`Encoded`] implements `Type`, `Encode` and `Decode` for every database by
+//! delegating to its buffer, so a marshalled message is stored in a BYTEA or BLOB
+//! column as is and read back without copying it into a separate `Vec<u8>`:
+//!
+//! ```ignore
+//! let user: Encoded<User> = Encoded::encode(user.size(), |w| user.marshal(w))?;
+//! sqlx::query("INSERT INTO users (data) VALUES ($1)").bind(&user).execute(&pool).await?;
+//!
+//! let (user,): (Encoded<User>,) = sqlx::query_as("SELECT data FROM users").fetch_one(&pool).await?;
+//! ```
+//!
+//! The bytes are not checked when a row is decoded; `Encoded::decode` validates them
+//! when the message is unmarshalled.
+
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Database, Decode, Encode, Type};
+
+use crate::Encoded;
+
+impl<T, B: Type<DB>, DB: Database> Type<DB> for Encoded<T, B> {
+    fn type_info() -> DB::TypeInfo {
+        B::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        B::compatible(ty)
+    }
+}
+
+impl<'q, T, B: AsRef<[u8]> + Encode<'q, DB>, DB: Database> Encode<'q, DB> for Encoded<T, B> {
+    fn encode_by_ref(&self, buf: &mut DB::ArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        self.as_inner().encode_by_ref(buf)
+    }
+
+    fn produces(&self) -> Option<DB::TypeInfo> {
+        self.as_inner().produces()
+    }
+
+    fn size_hint(&self) -> usize {
+        self.as_inner().size_hint()
+    }
+}
+
+impl<'r, T, B: AsRef<[u8]> + Decode<'r, DB>, DB: Database> Decode<'r, DB> for Encoded<T, B> {
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        B::decode(value).map(Encoded::new)
+    }
+}
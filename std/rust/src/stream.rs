@@ -0,0 +1,341 @@
+//! Checksummed, optionally compressed byte streams for exports too large to hold in
+//! memory.
+//!
+//! [`StreamWriter`] cuts the bytes written to it into chunks as they are produced and
+//! writes every chunk out with a CRC-32 of its data, compressed with zstd when the
+//! `zstd` feature is enabled and compression is turned on. Only one chunk is ever
+//! buffered, so an export of any size can be streamed straight to a file or socket.
+//! [`StreamReader`] verifies and unpacks the chunks again.
+//!
+//! A chunk is the varint length of its data, a method byte (`0` for raw data, `1` for
+//! zstd), the data (as a byte slice if compressed) and the CRC-32 of the uncompressed
+//! data as a `u32`. The stream ends with a zero length followed by the CRC-32 of all
+//! the data, so a truncated stream is detected even when it is cut between chunks.
+//!
+//! Messages written with [`StreamWriter::write_message`] are framed like those of
+//! `BencLinesWriter`, so a [`DecodeIter`](crate::DecodeIter) over a `StreamReader`
+//! reads them back.
+
+use std::io::{self, Read, Write};
+
+use crate::{
+    BencEncode, DEFAULT_MAX_FRAME_LEN, Error, marshal_usize, size_usize, unmarshal_u8, unmarshal_u32,
+    unmarshal_usize,
+};
+
+/// The default number of bytes of data in a chunk written by `StreamWriter`.
+pub const DEFAULT_CHUNK_LEN: usize = 64 * 1024;
+
+const METHOD_RAW: u8 = 0;
+const METHOD_ZSTD: u8 = 1;
+
+/// The CRC-32 (IEEE) lookup table.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// A running CRC-32 (IEEE) checksum.
+#[derive(Clone, Copy)]
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Crc32(!0)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.0 = CRC_TABLE[((self.0 ^ u32::from(b)) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(self) -> u32 {
+        !self.0
+    }
+
+    fn of(data: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(data);
+        crc.finish()
+    }
+}
+
+fn write_varint(writer: &mut impl Write, v: usize) -> io::Result<()> {
+    let mut buf = [0u8; 10];
+    let mut out = &mut buf[..];
+    marshal_usize(v, &mut out)?;
+    let len = 10 - out.len();
+    writer.write_all(&buf[..len])
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+// ===================================================================================
+// Writer
+// ===================================================================================
+
+/// Writes a checksummed stream of chunks to a `Write`.
+///
+/// Bytes are buffered until a chunk is full. [`flush`](Write::flush) writes out the
+/// buffered bytes as a shorter chunk, and [`finish`](Self::finish) must be called to
+/// end the stream; a stream that is dropped unfinished reads as truncated.
+pub struct StreamWriter<W> {
+    writer: W,
+    buf: Vec<u8>,
+    chunk_len: usize,
+    #[cfg(feature = "zstd")]
+    level: Option<i32>,
+    crc: Crc32,
+    out: Vec<u8>,
+}
+
+impl<W: Write> StreamWriter<W> {
+    /// Creates a writer of uncompressed chunks of [`DEFAULT_CHUNK_LEN`] bytes.
+    pub fn new(writer: W) -> Self {
+        StreamWriter {
+            writer,
+            buf: Vec::new(),
+            chunk_len: DEFAULT_CHUNK_LEN,
+            #[cfg(feature = "zstd")]
+            level: None,
+            crc: Crc32::new(),
+            out: Vec::new(),
+        }
+    }
+
+    /// Sets the number of bytes of data in a chunk. A `chunk_len` of zero is treated as
+    /// one.
+    pub fn with_chunk_len(mut self, chunk_len: usize) -> Self {
+        self.chunk_len = chunk_len.max(1);
+        self
+    }
+
+    /// Compresses every chunk with zstd at the given level.
+    #[cfg(feature = "zstd")]
+    pub fn with_compression(mut self, level: i32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Writes a message framed by its varint byte length.
+    ///
+    /// Encoding failures are reported with the kind `InvalidData`.
+    pub fn write_message<T: BencEncode + ?Sized>(&mut self, message: &T) -> io::Result<()> {
+        let size = message.size();
+        let mut framed = std::mem::take(&mut self.out);
+        framed.clear();
+        framed.resize(size_usize(size) + size, 0);
+        let mut writer = framed.as_mut_slice();
+        let result = marshal_usize(size, &mut writer).and_then(|()| message.marshal(&mut writer));
+        let written = result.map_err(io::Error::from).and_then(|()| self.write_all(&framed));
+        // The buffer is kept for the next message.
+        self.out = framed;
+        written
+    }
+
+    /// Writes the buffered bytes as a chunk.
+    fn write_chunk(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let crc = Crc32::of(&self.buf);
+        self.crc.update(&self.buf);
+        write_varint(&mut self.writer, self.buf.len())?;
+        #[cfg(feature = "zstd")]
+        if let Some(level) = self.level {
+            let compressed = zstd::bulk::compress(&self.buf, level)?;
+            self.writer.write_all(&[METHOD_ZSTD])?;
+            write_varint(&mut self.writer, compressed.len())?;
+            self.writer.write_all(&compressed)?;
+            return self.end_chunk(crc);
+        }
+        self.writer.write_all(&[METHOD_RAW])?;
+        self.writer.write_all(&self.buf)?;
+        self.end_chunk(crc)
+    }
+
+    fn end_chunk(&mut self, crc: u32) -> io::Result<()> {
+        self.buf.clear();
+        self.writer.write_all(&crc.to_le_bytes())
+    }
+
+    /// Writes out the buffered bytes and the end of the stream, flushes the writer and
+    /// returns it.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_chunk()?;
+        write_varint(&mut self.writer, 0)?;
+        self.writer.write_all(&self.crc.finish().to_le_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for StreamWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = data.len().min(self.chunk_len - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        if self.buf.len() == self.chunk_len {
+            self.write_chunk()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_chunk()?;
+        self.writer.flush()
+    }
+}
+
+// ===================================================================================
+// Reader
+// ===================================================================================
+
+/// Reads a stream written by `StreamWriter`, verifying the checksum of every chunk.
+///
+/// A checksum mismatch or a malformed chunk is reported with the kind `InvalidData`,
+/// and a stream that ends before its end marker with the kind `UnexpectedEof`.
+pub struct StreamReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    pos: usize,
+    max_chunk_len: usize,
+    crc: Crc32,
+    done: bool,
+}
+
+impl<R: Read> StreamReader<R> {
+    /// Creates a reader accepting chunks of up to `DEFAULT_MAX_FRAME_LEN` bytes of data.
+    pub fn new(reader: R) -> Self {
+        StreamReader {
+            reader,
+            buf: Vec::new(),
+            pos: 0,
+            max_chunk_len: DEFAULT_MAX_FRAME_LEN,
+            crc: Crc32::new(),
+            done: false,
+        }
+    }
+
+    /// Sets the largest chunk length the reader accepts, which bounds the memory a
+    /// corrupt stream can make it allocate.
+    pub fn with_max_chunk_len(mut self, max_chunk_len: usize) -> Self {
+        self.max_chunk_len = max_chunk_len;
+        self
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the underlying reader, positioned after the end of the stream once it
+    /// has been read completely.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads a varint from the underlying reader one byte at a time.
+    fn read_varint(&mut self) -> io::Result<usize> {
+        let mut header = [0u8; 10];
+        for filled in 1..=header.len() {
+            self.reader.read_exact(&mut header[filled - 1..filled])?;
+            match unmarshal_usize(&mut &header[..filled]) {
+                Ok(len) => return Ok(len),
+                Err(Error::BufferTooSmall { .. }) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Err(Error::VarintOverflow.into())
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(unmarshal_u32(&mut &bytes[..])?)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(bytes)
+    }
+
+    /// Reads the next chunk into the buffer. Returns `false` at the end of the stream.
+    fn read_chunk(&mut self) -> io::Result<bool> {
+        let len = self.read_varint()?;
+        if len == 0 {
+            if self.read_u32()? != self.crc.finish() {
+                return Err(invalid_data("stream checksum mismatch"));
+            }
+            return Ok(false);
+        }
+        if len > self.max_chunk_len {
+            return Err(invalid_data(format!("chunk of {len} bytes exceeds the limit of {} bytes", self.max_chunk_len)));
+        }
+        let mut method = [0u8; 1];
+        self.reader.read_exact(&mut method)?;
+        // The chunk only replaces the buffer once it is verified, so a failed chunk never
+        // reaches a later read.
+        let data = match unmarshal_u8(&mut &method[..])? {
+            METHOD_RAW => self.read_bytes(len)?,
+            #[cfg(feature = "zstd")]
+            METHOD_ZSTD => {
+                let compressed_len = self.read_varint()?;
+                if compressed_len > zstd::zstd_safe::compress_bound(len) {
+                    return Err(invalid_data("compressed chunk is larger than its data can compress to"));
+                }
+                let compressed = self.read_bytes(compressed_len)?;
+                zstd::bulk::decompress(&compressed, len)?
+            }
+            #[cfg(not(feature = "zstd"))]
+            METHOD_ZSTD => return Err(invalid_data("compressed chunks require the zstd feature")),
+            method => return Err(invalid_data(format!("unsupported chunk method {method}"))),
+        };
+        if data.len() != len {
+            return Err(invalid_data("chunk length mismatch"));
+        }
+        if self.read_u32()? != Crc32::of(&data) {
+            return Err(invalid_data("chunk checksum mismatch"));
+        }
+        self.crc.update(&data);
+        self.buf = data;
+        self.pos = 0;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for StreamReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() && !self.done && !out.is_empty() {
+            if !self.read_chunk()? {
+                self.done = true;
+            }
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,256 @@
+//! The tagged wire mode, in which every field of a struct is identified by an id
+//! instead of its position.
+//!
+//! A tagged struct is marshalled like a map: a varint field count, the fields and the
+//! terminator. Each field starts with a varint key holding the field id shifted left
+//! by three bits, with the field's [`WireType`] in the low bits, followed by the value.
+//! Values whose encoding does not match one of the wire types are wrapped in a varint
+//! byte length, so a reader always knows how far a field extends.
+//!
+//! Because fields are found by id, a struct can reorder, rename or add fields without
+//! breaking data written before the change. A reader steps over fields it does not
+//! know with [`skip_any`], which needs nothing but the wire type from the key.
+
+use crate::{
+    Error, Result, TERMINATOR, advance, marshal_uint, marshal_usize, read_terminator, size_uint,
+    size_usize, skip_uint, unmarshal_uint, unmarshal_usize, write_to_slice,
+};
+
+/// How a value is laid out on the wire, which tells a reader how to find its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WireType {
+    /// A varint.
+    Varint = 0,
+    /// One byte.
+    Fixed8 = 1,
+    /// Two bytes.
+    Fixed16 = 2,
+    /// Four bytes.
+    Fixed32 = 3,
+    /// Eight bytes.
+    Fixed64 = 4,
+    /// A varint byte length followed by that many bytes.
+    Bytes = 5,
+}
+
+impl WireType {
+    /// Returns the wire type with the given number, or `None` if it is not defined.
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(WireType::Varint),
+            1 => Some(WireType::Fixed8),
+            2 => Some(WireType::Fixed16),
+            3 => Some(WireType::Fixed32),
+            4 => Some(WireType::Fixed64),
+            5 => Some(WireType::Bytes),
+            _ => None,
+        }
+    }
+}
+
+/// The largest field id that fits into a key.
+pub const MAX_FIELD_ID: u64 = u64::MAX >> 3;
+
+fn key(id: u64, wire_type: WireType) -> u64 {
+    debug_assert!(id <= MAX_FIELD_ID, "field id {id} is too large");
+    (id << 3) | wire_type as u64
+}
+
+// ===================================================================================
+// Keys
+// ===================================================================================
+
+/// Returns the number of bytes required to marshal a field key.
+pub fn size_key(id: u64, wire_type: WireType) -> usize {
+    size_uint(key(id, wire_type))
+}
+
+/// Marshals a field key. `id` must not be larger than [`MAX_FIELD_ID`].
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_key(id: u64, wire_type: WireType, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_uint(key(id, wire_type), writer)
+}
+
+/// Unmarshals a field key, returning the field id and wire type.
+///
+/// Returns an `InvalidValue` error if the wire type is not defined.
+pub fn unmarshal_key(reader: &mut &[u8]) -> Result<(u64, WireType)> {
+    let key = unmarshal_uint(reader)?;
+    let wire_type = WireType::from_u8((key & 0b111) as u8).ok_or(Error::InvalidValue)?;
+    Ok((key >> 3, wire_type))
+}
+
+// ===================================================================================
+// Encoding
+// ===================================================================================
+
+/// Returns the number of bytes required to marshal a field with the given id whose
+/// value occupies `size` bytes. `wire_type` is the wire type of the value's encoding,
+/// or `None` if the value is wrapped.
+pub fn size_tagged_field(id: u64, wire_type: Option<WireType>, size: usize) -> usize {
+    match wire_type {
+        Some(wire_type) => size_key(id, wire_type) + size,
+        None => size_key(id, WireType::Bytes) + size_usize(size) + size,
+    }
+}
+
+/// Returns the number of bytes required to marshal a tagged struct with `count`
+/// fields occupying `fields_size` bytes in total, as returned by `size_tagged_field`.
+pub fn size_tagged(count: usize, fields_size: usize) -> usize {
+    size_usize(count) + fields_size + TERMINATOR.len()
+}
+
+/// Writes a tagged struct into a writer, one field at a time.
+///
+/// The terminator is only written by `finish`; a struct that is never finished cannot
+/// be read.
+pub struct TaggedWriter<'w, 'b> {
+    writer: &'w mut &'b mut [u8],
+}
+
+impl<'w, 'b> TaggedWriter<'w, 'b> {
+    /// Starts a tagged struct with `count` fields at the current position of the
+    /// writer.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn new(writer: &'w mut &'b mut [u8], count: usize) -> Result<Self> {
+        marshal_usize(count, writer)?;
+        Ok(TaggedWriter { writer })
+    }
+
+    /// Marshals a field with the given id. `size` must be the number of bytes the
+    /// marshaler writes and `wire_type` the wire type of its encoding, or `None` to
+    /// wrap the value.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn field(
+        &mut self,
+        id: u64,
+        wire_type: Option<WireType>,
+        size: usize,
+        marshaler: impl FnOnce(&mut &mut [u8]) -> Result<()>,
+    ) -> Result<()> {
+        match wire_type {
+            Some(wire_type) => marshal_key(id, wire_type, self.writer)?,
+            None => {
+                marshal_key(id, WireType::Bytes, self.writer)?;
+                marshal_usize(size, self.writer)?;
+            }
+        }
+        marshaler(self.writer)
+    }
+
+    /// Writes the terminator, completing the struct.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn finish(self) -> Result<()> {
+        write_to_slice(self.writer, &TERMINATOR)
+    }
+}
+
+// ===================================================================================
+// Decoding
+// ===================================================================================
+
+/// Skips over a value of the given wire type, whatever its semantic type, which is how
+/// a reader steps over fields it does not know.
+pub fn skip_any(reader: &mut &[u8], wire_type: WireType) -> Result<()> {
+    match wire_type {
+        WireType::Varint => skip_uint(reader),
+        WireType::Fixed8 => advance(reader, 1).map(|_| ()),
+        WireType::Fixed16 => advance(reader, 2).map(|_| ()),
+        WireType::Fixed32 => advance(reader, 4).map(|_| ()),
+        WireType::Fixed64 => advance(reader, 8).map(|_| ()),
+        WireType::Bytes => {
+            let len = unmarshal_usize(reader)?;
+            advance(reader, len).map(|_| ())
+        }
+    }
+}
+
+/// Reads the fields of a tagged struct in the order they were written.
+///
+/// Call `next_key` to get the key of the next field, then exactly one of `value` or
+/// `skip` to consume it.
+pub struct TaggedReader<'r, 'a> {
+    reader: &'r mut &'a [u8],
+    remaining: usize,
+    finished: bool,
+}
+
+impl<'r, 'a> TaggedReader<'r, 'a> {
+    /// Starts reading a tagged struct at the current position of the reader.
+    pub fn new(reader: &'r mut &'a [u8]) -> Result<Self> {
+        let remaining = unmarshal_usize(reader)?;
+        Ok(TaggedReader { reader, remaining, finished: false })
+    }
+
+    /// Returns the id and wire type of the next field, or `None` once all fields have
+    /// been read and the terminator has been consumed.
+    pub fn next_key(&mut self) -> Result<Option<(u64, WireType)>> {
+        if self.remaining == 0 {
+            if !self.finished {
+                read_terminator(self.reader)?;
+                self.finished = true;
+            }
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        unmarshal_key(self.reader).map(Some)
+    }
+
+    /// Unmarshals the value of the field whose key was just read. `found` is the wire
+    /// type from the key and `expected` the wire type of the value's encoding, or
+    /// `None` if the value is wrapped.
+    ///
+    /// Returns an `InvalidValue` error if the wire types do not match, and a
+    /// `TrailingBytes` error if the unmarshaler does not consume a wrapped value
+    /// completely.
+    pub fn value<T>(
+        &mut self,
+        found: WireType,
+        expected: Option<WireType>,
+        unmarshaler: impl FnOnce(&mut &'a [u8]) -> Result<T>,
+    ) -> Result<T> {
+        if found != expected.unwrap_or(WireType::Bytes) {
+            return Err(Error::InvalidValue);
+        }
+        if expected.is_some() {
+            return unmarshaler(self.reader);
+        }
+        let len = unmarshal_usize(self.reader)?;
+        let mut value = advance(self.reader, len)?;
+        let v = unmarshaler(&mut value)?;
+        if !value.is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(v)
+    }
+
+    /// Skips over the value of the field whose key was just read, with the same
+    /// arguments as `value`. Wrapped values are skipped by their length without
+    /// calling the skipper.
+    ///
+    /// Returns an `InvalidValue` error if the wire types do not match.
+    pub fn skip(
+        &mut self,
+        found: WireType,
+        expected: Option<WireType>,
+        skipper: impl FnOnce(&mut &[u8]) -> Result<()>,
+    ) -> Result<()> {
+        if found != expected.unwrap_or(WireType::Bytes) {
+            return Err(Error::InvalidValue);
+        }
+        if expected.is_some() {
+            return skipper(self.reader);
+        }
+        skip_any(self.reader, WireType::Bytes)
+    }
+
+    /// Skips over the value of the field whose key was just read by its wire type
+    /// alone, for fields with an id the reader does not know.
+    pub fn skip_unknown(&mut self, found: WireType) -> Result<()> {
+        skip_any(self.reader, found)
+    }
+}
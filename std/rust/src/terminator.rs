@@ -0,0 +1,272 @@
+//! Collections ending with a terminator other than the default `[1, 1, 1, 1]`.
+//!
+//! The terminator lets a decoder notice when a count and the elements that follow it
+//! disagree. Payloads that often contain the default sequence, such as runs of small
+//! varints, let such a mismatch go unnoticed more often, so a deployment may pick its
+//! own sequence. A [`Terminator`] is the context holding that choice: its methods
+//! marshal and unmarshal slices and maps like the free functions of the same names,
+//! with the configured sequence in place of the default one. The terminator always
+//! occupies four bytes, so `size_slice` and `size_map` apply unchanged.
+//!
+//! Both sides must agree on the sequence. To catch a configuration mismatch early, a
+//! decoder with a custom terminator reports a `TerminatorMismatch` error when it
+//! finds the default sequence instead of its own, rather than the `MissingTerminator`
+//! error of a corrupt message. [`retarget_terminators`] rewrites a message described
+//! by a [`Type`] from one terminator to another, for types whose encoding is
+//! generated.
+
+use crate::{
+    Error, Result, TERMINATOR, Type, advance, check_element_progress, skip_elements, skip_string, skip_uint, unmarshal_bytes_cropped,
+    unmarshal_elements, unmarshal_u8, unmarshal_uint, unmarshal_usize, write_to_slice,
+};
+
+/// The sequence ending the slices and maps of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Terminator([u8; 4]);
+
+impl Default for Terminator {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Terminator {
+    /// The terminator written by the free functions of this crate.
+    pub const DEFAULT: Terminator = Terminator(TERMINATOR);
+
+    /// Creates a terminator of the given sequence.
+    pub const fn new(bytes: [u8; 4]) -> Self {
+        Terminator(bytes)
+    }
+
+    /// Returns the sequence of the terminator.
+    pub const fn bytes(&self) -> [u8; 4] {
+        self.0
+    }
+
+    /// Writes the terminator into the writer.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn write(&self, writer: &mut &mut [u8]) -> Result<()> {
+        write_to_slice(writer, &self.0)
+    }
+
+    /// Consumes the terminator from the reader.
+    ///
+    /// Returns a `MissingTerminator` error if the reader holds another sequence, or a
+    /// `TerminatorMismatch` error if it holds the default sequence where a custom one
+    /// is expected.
+    pub fn read(&self, reader: &mut &[u8]) -> Result<()> {
+        let found = advance(reader, self.0.len())?;
+        if found == self.0 {
+            return Ok(());
+        }
+        if found == TERMINATOR {
+            return Err(Error::TerminatorMismatch { expected: self.0 });
+        }
+        Err(Error::MissingTerminator)
+    }
+
+    /// Marshals a slice ending with the terminator into the writer.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_slice<T, E: From<Error>>(
+        &self,
+        slice: &[T],
+        writer: &mut &mut [u8],
+        marshaler: impl Fn(&T, &mut &mut [u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        crate::marshal_uint(slice.len() as u64, writer)?;
+        for item in slice {
+            marshaler(item, writer)?;
+        }
+        Ok(self.write(writer)?)
+    }
+
+    /// Unmarshals a slice ending with the terminator from the reader.
+    ///
+    /// Returns an `InvalidValue` error if an element occupies no bytes while the length
+    /// exceeds the remaining input, like [`unmarshal_slice`](crate::unmarshal_slice).
+    pub fn unmarshal_slice<'a, T, E: From<Error>>(
+        &self,
+        reader: &mut &'a [u8],
+        unmarshaler: impl Fn(&mut &'a [u8]) -> Result<T, E>,
+    ) -> Result<Vec<T>, E> {
+        unmarshal_elements(reader, unmarshaler, Some(*self))
+    }
+
+    /// Skips over a slice ending with the terminator in the reader.
+    pub fn skip_slice<E: From<Error>>(
+        &self,
+        reader: &mut &[u8],
+        skip_element: impl Fn(&mut &[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        skip_elements(reader, skip_element, Some(*self))
+    }
+
+    /// Marshals key-value pairs as a map ending with the terminator into the writer, in
+    /// the order of the iterator.
+    ///
+    /// Returns an error if the writer is too small.
+    pub fn marshal_pairs<'k, 'v, K: 'k, V: 'v, E: From<Error>>(
+        &self,
+        pairs: impl ExactSizeIterator<Item = (&'k K, &'v V)>,
+        writer: &mut &mut [u8],
+        k_marshaler: impl Fn(&K, &mut &mut [u8]) -> Result<(), E>,
+        v_marshaler: impl Fn(&V, &mut &mut [u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        crate::marshal_uint(pairs.len() as u64, writer)?;
+        for (k, v) in pairs {
+            k_marshaler(k, writer)?;
+            v_marshaler(v, writer)?;
+        }
+        Ok(self.write(writer)?)
+    }
+
+    /// Unmarshals a map ending with the terminator from the reader into any collection
+    /// that can be extended with key-value pairs, such as `HashMap` or `BTreeMap`.
+    pub fn unmarshal_map_into<'a, K, V, M, E: From<Error>>(
+        &self,
+        reader: &mut &'a [u8],
+        k_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<K, E>,
+        v_unmarshaler: impl Fn(&mut &'a [u8]) -> Result<V, E>,
+    ) -> Result<M, E>
+    where
+        M: Default + Extend<(K, V)>,
+    {
+        let len = unmarshal_uint(reader)? as usize;
+        let mut map = M::default();
+        for _ in 0..len {
+            let k = k_unmarshaler(reader)?;
+            let v = v_unmarshaler(reader)?;
+            map.extend(Some((k, v)));
+        }
+        self.read(reader)?;
+        Ok(map)
+    }
+
+    /// Skips over a map ending with the terminator in the reader.
+    pub fn skip_map<E: From<Error>>(
+        &self,
+        reader: &mut &[u8],
+        skip_key: impl Fn(&mut &[u8]) -> Result<(), E>,
+        skip_value: impl Fn(&mut &[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let len = unmarshal_uint(reader)?;
+        for _ in 0..len {
+            skip_key(reader)?;
+            skip_value(reader)?;
+        }
+        Ok(self.read(reader)?)
+    }
+}
+
+/// Copies the bytes that `f` reads from the reader to `out`, returning its result.
+fn copy_read<T>(reader: &mut &[u8], out: &mut Vec<u8>, f: impl FnOnce(&mut &[u8]) -> Result<T>) -> Result<T> {
+    let start = *reader;
+    let v = f(reader)?;
+    out.extend_from_slice(&start[..start.len() - reader.len()]);
+    Ok(v)
+}
+
+/// Copies a value of the given type to `out`, reading collections that end with
+/// `from` and writing them ending with `to`, where `None` stands for no terminator.
+fn rewrite_type(
+    reader: &mut &[u8],
+    ty: &Type,
+    from: Option<Terminator>,
+    to: Option<Terminator>,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let end = |reader: &mut &[u8], out: &mut Vec<u8>| {
+        if let Some(from) = from {
+            from.read(reader)?;
+        }
+        if let Some(to) = to {
+            out.extend_from_slice(&to.0);
+        }
+        Ok::<_, Error>(())
+    };
+    match ty {
+        Type::Bool
+        | Type::U8
+        | Type::I8
+        | Type::U16
+        | Type::I16
+        | Type::U32
+        | Type::I32
+        | Type::U64
+        | Type::I64
+        | Type::F32
+        | Type::F64
+        | Type::Time => {
+            // Checked by the match arm: these types have a fixed size.
+            out.extend_from_slice(advance(reader, ty.fixed_size().unwrap())?);
+        }
+        Type::Uint | Type::Int => copy_read(reader, out, skip_uint)?,
+        Type::String => copy_read(reader, out, skip_string)?,
+        Type::Bytes => copy_read(reader, out, |r| unmarshal_bytes_cropped(r).map(|_| ()))?,
+        Type::Slice(elem) => {
+            let len = copy_read(reader, out, unmarshal_usize)?;
+            for _ in 0..len {
+                let remaining = reader.len();
+                rewrite_type(reader, elem, from, to, out)?;
+                check_element_progress(len, remaining, reader)?;
+            }
+            end(reader, out)?;
+        }
+        Type::Map(k, v) => {
+            let len = copy_read(reader, out, unmarshal_usize)?;
+            for _ in 0..len {
+                let remaining = reader.len();
+                rewrite_type(reader, k, from, to, out)?;
+                rewrite_type(reader, v, from, to, out)?;
+                check_element_progress(len, remaining, reader)?;
+            }
+            end(reader, out)?;
+        }
+        Type::Option(inner) => {
+            if copy_read(reader, out, unmarshal_u8)? == 1 {
+                rewrite_type(reader, inner, from, to, out)?;
+            }
+        }
+        Type::Struct(schema) => {
+            for field in schema.fields() {
+                rewrite_type(reader, &field.ty, from, to, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites a marshalled value of the given type, replacing the terminators `from` of
+/// its collections, or their absence if `None`, with `to`, or nothing if `None`.
+///
+/// Returns a `TrailingBytes` error if `buf` holds more than one value.
+pub(crate) fn rewrite_terminators(
+    buf: &[u8],
+    ty: &Type,
+    from: Option<Terminator>,
+    to: Option<Terminator>,
+) -> Result<Vec<u8>> {
+    let mut reader = buf;
+    let mut out = Vec::with_capacity(buf.len());
+    rewrite_type(&mut reader, ty, from, to, &mut out)?;
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(out)
+}
+
+/// Rewrites a marshalled value of the given type whose collections end with `from`
+/// into one whose collections end with `to`.
+///
+/// A value marshalled with the free functions or `BencEncode` is retargeted from
+/// [`Terminator::DEFAULT`] before it is sent, and back to it on receipt so the regular
+/// decoders can read it.
+///
+/// Returns the errors of [`Terminator::read`] if a collection does not end with
+/// `from`, and a `TrailingBytes` error if `buf` holds more than one value.
+pub fn retarget_terminators(buf: &[u8], ty: &Type, from: Terminator, to: Terminator) -> Result<Vec<u8>> {
+    rewrite_terminators(buf, ty, Some(from), Some(to))
+}
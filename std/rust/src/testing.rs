@@ -0,0 +1,214 @@
+//! Golden-byte fixtures for wire format tests, enabled by the `testing` feature.
+//!
+//! [`assert_golden`] marshals sample values and compares them with the bytes stored in
+//! a fixture file, so a change to a message type, or to benc itself, that alters the
+//! wire format fails a test instead of surfacing as a decode error in production.
+//!
+//! Fixtures are text files with one sample per line, written as hex, so changes show
+//! up readably in code review. Lines starting with `#` are comments. A missing fixture
+//! is created from the samples; to accept an intended format change, run the tests
+//! with the `BENC_UPDATE_GOLDEN` environment variable set and commit the new files.
+//!
+//! [`assert_compatible`] checks two versions of a message type against each other
+//! before a schema change is deployed: data written by each version is read by the
+//! other, in the directions a [`Compatibility`] level requires. This covers the
+//! evolution rules of [`benc_struct!`](crate::benc_struct), such as adding a trailing
+//! `#[benc(default)]` field, removing a field that the old version decodes with a
+//! default, or renaming a field of a tagged struct while keeping its id.
+
+use std::fmt::{self, Debug, Write};
+use std::fs;
+use std::path::Path;
+
+use crate::{BencDecode, BencEncode, Error, from_slice};
+
+/// The environment variable that makes [`assert_golden`] rewrite fixtures.
+pub const UPDATE_GOLDEN_ENV: &str = "BENC_UPDATE_GOLDEN";
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        // Writing into a `String` cannot fail.
+        write!(out, "{b:02x}").unwrap();
+    }
+    out
+}
+
+fn from_hex(line: &str) -> Option<Vec<u8>> {
+    if !line.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..line.len()).step_by(2).map(|i| u8::from_str_radix(line.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Asserts that the samples marshal into the bytes stored in the fixture at `path`.
+///
+/// Creates the fixture, along with its directory, if it does not exist or if the
+/// [`UPDATE_GOLDEN_ENV`] environment variable is set.
+///
+/// Panics if the fixture holds a different number of samples, or if a sample marshals
+/// differently, reporting the first differing byte.
+pub fn assert_golden<T: BencEncode>(path: impl AsRef<Path>, samples: &[T]) {
+    let path = path.as_ref();
+    let encoded: Vec<_> = samples.iter().map(T::to_vec).collect();
+
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() || !path.exists() {
+        let mut out = format!("# Golden benc fixture of {}. Regenerate with {UPDATE_GOLDEN_ENV}=1.\n", std::any::type_name::<T>());
+        for bytes in &encoded {
+            out.push_str(&to_hex(bytes));
+            out.push('\n');
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).unwrap_or_else(|err| panic!("cannot create {}: {err}", dir.display()));
+        }
+        fs::write(path, out).unwrap_or_else(|err| panic!("cannot write {}: {err}", path.display()));
+        return;
+    }
+
+    let text = fs::read_to_string(path).unwrap_or_else(|err| panic!("cannot read {}: {err}", path.display()));
+    let golden: Vec<_> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| from_hex(line).unwrap_or_else(|| panic!("{}: invalid hex line {line:?}", path.display())))
+        .collect();
+
+    assert_eq!(
+        golden.len(),
+        encoded.len(),
+        "{}: the fixture holds {} samples, but {} were given; set {UPDATE_GOLDEN_ENV}=1 to rewrite it",
+        path.display(),
+        golden.len(),
+        encoded.len()
+    );
+    for (i, (expected, actual)) in golden.iter().zip(&encoded).enumerate() {
+        if expected != actual {
+            let offset = expected.iter().zip(actual).position(|(a, b)| a != b).unwrap_or(expected.len().min(actual.len()));
+            panic!(
+                "{}: sample {i} marshals differently from byte {offset}\n  golden: {}\n  actual: {}\n\
+                 set {UPDATE_GOLDEN_ENV}=1 to accept the new wire format",
+                path.display(),
+                to_hex(expected),
+                to_hex(actual)
+            );
+        }
+    }
+}
+
+// ===================================================================================
+// Cross-version compatibility
+// ===================================================================================
+
+/// The directions in which two versions of a type must understand each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    /// The new version reads data written by the old one, so consumers can be
+    /// upgraded before producers.
+    Backward,
+    /// The old version reads data written by the new one, so producers can be
+    /// upgraded before consumers.
+    Forward,
+    /// Both, and old data read and written back by the new version still reads as
+    /// the same value in the old one.
+    Full,
+}
+
+/// A check of [`check_compatibility`] that failed for a sample.
+#[derive(Debug, PartialEq)]
+pub enum Incompatibility {
+    /// The new version cannot read the old sample with the given index.
+    NewReadsOld { sample: usize, error: Error },
+    /// The old version cannot read the new sample with the given index.
+    OldReadsNew { sample: usize, error: Error },
+    /// The old sample with the given index changes when the new version reads it and
+    /// writes it back, as shown by the `Debug` output of the value the old version
+    /// reads, or that value cannot be read at all.
+    RoundTrip { sample: usize, found: std::result::Result<String, Error> },
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Incompatibility::NewReadsOld { sample, error } => {
+                write!(f, "the new version cannot read old sample {sample}: {error}")
+            }
+            Incompatibility::OldReadsNew { sample, error } => {
+                write!(f, "the old version cannot read new sample {sample}: {error}")
+            }
+            Incompatibility::RoundTrip { sample, found: Ok(found) } => {
+                write!(f, "old sample {sample} reads as {found} after a round trip through the new version")
+            }
+            Incompatibility::RoundTrip { sample, found: Err(error) } => {
+                write!(f, "old sample {sample} cannot be read after a round trip through the new version: {error}")
+            }
+        }
+    }
+}
+
+/// Checks that the versions `Old` and `New` of a type are compatible at the given
+/// level, reading the marshalled samples of each version with the other one.
+///
+/// Returns every failed check, or nothing if the versions are compatible.
+pub fn check_compatibility<Old, New>(
+    compatibility: Compatibility,
+    old_samples: &[Old],
+    new_samples: &[New],
+) -> Vec<Incompatibility>
+where
+    Old: BencEncode + for<'a> BencDecode<'a> + PartialEq + Debug,
+    New: BencEncode + for<'a> BencDecode<'a>,
+{
+    let mut found = Vec::new();
+    let backward = compatibility != Compatibility::Forward;
+    let forward = compatibility != Compatibility::Backward;
+    if backward {
+        for (sample, old) in old_samples.iter().enumerate() {
+            if let Err(error) = from_slice::<New>(&old.to_vec()) {
+                found.push(Incompatibility::NewReadsOld { sample, error });
+            }
+        }
+    }
+    if forward {
+        for (sample, new) in new_samples.iter().enumerate() {
+            if let Err(error) = from_slice::<Old>(&new.to_vec()) {
+                found.push(Incompatibility::OldReadsNew { sample, error });
+            }
+        }
+    }
+    if compatibility == Compatibility::Full {
+        for (sample, old) in old_samples.iter().enumerate() {
+            let Ok(new) = from_slice::<New>(&old.to_vec()) else {
+                continue;
+            };
+            match from_slice::<Old>(&new.to_vec()) {
+                Ok(back) if back == *old => {}
+                Ok(back) => found.push(Incompatibility::RoundTrip { sample, found: Ok(format!("{back:?}")) }),
+                Err(error) => found.push(Incompatibility::RoundTrip { sample, found: Err(error) }),
+            }
+        }
+    }
+    found
+}
+
+/// Asserts that the versions `Old` and `New` of a type are compatible at the given
+/// level, as checked by [`check_compatibility`].
+///
+/// Panics listing every failed check otherwise.
+pub fn assert_compatible<Old, New>(compatibility: Compatibility, old_samples: &[Old], new_samples: &[New])
+where
+    Old: BencEncode + for<'a> BencDecode<'a> + PartialEq + Debug,
+    New: BencEncode + for<'a> BencDecode<'a>,
+{
+    let found = check_compatibility(compatibility, old_samples, new_samples);
+    if !found.is_empty() {
+        let mut message = format!(
+            "{} and {} are not {compatibility:?} compatible:",
+            std::any::type_name::<Old>(),
+            std::any::type_name::<New>()
+        );
+        for incompatibility in &found {
+            write!(message, "\n  {incompatibility}").unwrap();
+        }
+        panic!("{message}");
+    }
+}
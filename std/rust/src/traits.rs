@@ -0,0 +1,484 @@
+//! Traits for types with a canonical encoding.
+//!
+//! [`BencEncode`] and [`BencDecode`] tie a type to the free functions that encode it,
+//! so generic code (and the [`benc_struct!`](crate::benc_struct) macro) can marshal
+//! values without being handed a sizer, marshaler and unmarshaler for every field.
+//!
+//! Implementations use the same format as the free functions: fixed-size integers
+//! for `u16`..`i64`, varints for `usize`/`isize`, the slice format for `Vec<T>` and
+//! the byte slice format for `&[u8]`. Zero-sized types such as `()` and
+//! `PhantomData<T>` occupy no bytes, so a `HashMap<K, ()>` is marshalled as a list of
+//! keys.
+//!
+//! Types whose encoding is a varint, a fixed number of bytes or a length-prefixed
+//! byte string report it as their [`WireType`], which the tagged mode uses to avoid
+//! wrapping them.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+
+use crate::{
+    Error, Result, Schema, Type, WireType, ensure_capacity, marshal_bool, marshal_bytes, marshal_duration, marshal_f32, marshal_f64, marshal_i8, marshal_i16,
+    marshal_i32, marshal_i64, marshal_isize, marshal_map, marshal_option, marshal_slice,
+    marshal_string, marshal_time, marshal_time_delta, marshal_time_offset, marshal_u8, marshal_u16, marshal_u32, marshal_u64, marshal_usize,
+    size_bool, size_bytes, size_duration, size_f32, size_f64, size_i8, size_i16, size_i32,
+    size_i64, size_isize, size_map, size_option, size_slice, size_string, size_time, size_time_delta, size_time_offset, size_u8,
+    size_u16, size_u32, size_u64, size_usize, skip_bool, skip_bytes, skip_duration, skip_f32, skip_f64, skip_i8,
+    skip_i16, skip_i32, skip_i64, skip_isize, skip_map, skip_option, skip_slice, skip_string,
+    skip_time, skip_time_delta, skip_time_offset, skip_u8, skip_u16, skip_u32, skip_u64, skip_usize, unmarshal_bool,
+    unmarshal_bytes_cropped, unmarshal_duration, unmarshal_f32, unmarshal_f64, unmarshal_i8, unmarshal_i16,
+    unmarshal_i32, unmarshal_i64, unmarshal_isize, unmarshal_map_with_hasher, unmarshal_slice, unmarshal_string,
+    unmarshal_time, unmarshal_time_delta, unmarshal_time_offset, unmarshal_u8, unmarshal_u16, unmarshal_u32, unmarshal_u64, unmarshal_usize,
+};
+
+/// A type that can be marshalled.
+pub trait BencEncode {
+    /// The wire type of the encoding, or `None` if it is not one of the wire types.
+    const WIRE_TYPE: Option<WireType> = None;
+
+    /// The size of every marshalled value if it is constant, which lets buffers be
+    /// sized at compile time and values be skipped in one step.
+    const ENCODED_SIZE: Option<usize> = None;
+
+    /// Returns the [`Type`] describing the encoding, or `None` if it cannot be
+    /// described by one. Structs defined with [`benc_struct!`](crate::benc_struct)
+    /// report their fields, which [`schema_of`] exposes as a [`Schema`].
+    fn benc_type() -> Option<Type> {
+        None
+    }
+
+    /// Returns the number of bytes required to marshal the value.
+    fn size(&self) -> usize;
+
+    /// Marshals the value into the writer.
+    ///
+    /// Returns an error if the writer is too small.
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()>;
+
+    /// Marshals the value into a new vector of exactly the right size.
+    fn to_vec(&self) -> Vec<u8> {
+        #[cfg(feature = "metrics")]
+        let start = crate::metrics::start();
+        let mut buf = vec![0u8; self.size()];
+        // The buffer has exactly the size the value reported.
+        self.marshal(&mut buf.as_mut_slice()).expect("size() is smaller than marshal() output");
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_encode::<Self>(start, buf.len());
+        buf
+    }
+
+    /// Marshals a value of a fixed-size type into an array on the stack.
+    ///
+    /// `N` is checked against [`ENCODED_SIZE`](Self::ENCODED_SIZE) at compile time, so
+    /// using the wrong length, or a type without a constant size, does not build:
+    ///
+    /// ```compile_fail
+    /// use benc::BencEncode;
+    ///
+    /// let buf: [u8; 3] = 7u32.marshal_array();
+    /// ```
+    fn marshal_array<const N: usize>(&self) -> [u8; N] {
+        const {
+            assert!(
+                matches!(Self::ENCODED_SIZE, Some(size) if size == N),
+                "the array length does not match ENCODED_SIZE"
+            );
+        }
+        let mut buf = [0u8; N];
+        // The array has exactly the constant size of the type.
+        self.marshal(&mut buf.as_mut_slice()).expect("ENCODED_SIZE is smaller than marshal() output");
+        buf
+    }
+}
+
+/// A type that can be unmarshalled from a buffer living for `'a`. Types that borrow
+/// from the buffer, such as `&'a str`, decode without copying.
+pub trait BencDecode<'a>: Sized {
+    /// Unmarshals a value from the reader.
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self>;
+
+    /// Unmarshals a value that ends where the reader ends, such as a whole message or
+    /// a length-prefixed value. Positional `benc_struct!` types read missing trailing
+    /// `#[benc(default)]` fields as their defaults here, and only here.
+    fn unmarshal_whole(reader: &mut &'a [u8]) -> Result<Self> {
+        Self::unmarshal(reader)
+    }
+
+    /// Skips over a marshalled value in the reader.
+    fn skip(reader: &mut &[u8]) -> Result<()>;
+}
+
+/// Marshals a value into the start of `buf` and returns the number of bytes written.
+///
+/// Returns a `BufferTooSmall` error whose `needed` is the size of the whole value if
+/// it does not fit.
+pub fn to_slice<T: BencEncode + ?Sized>(v: &T, buf: &mut [u8]) -> Result<usize> {
+    let size = v.size();
+    ensure_capacity(buf, size)?;
+    let mut writer = &mut buf[..size];
+    v.marshal(&mut writer)?;
+    Ok(size - writer.len())
+}
+
+/// Unmarshals a value that occupies the whole buffer.
+///
+/// Returns a `TrailingBytes` error if bytes are left after the value.
+pub fn from_slice<'a, T: BencDecode<'a>>(buf: &'a [u8]) -> Result<T> {
+    #[cfg(feature = "metrics")]
+    let start = crate::metrics::start();
+    let mut reader = buf;
+    let result =
+        T::unmarshal_whole(&mut reader).and_then(|value| if reader.is_empty() { Ok(value) } else { Err(Error::TrailingBytes) });
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_decode::<T>(start, buf.len(), result.as_ref().err());
+    result
+}
+
+// ===================================================================================
+// Primitives
+// ===================================================================================
+
+// Use a macro to generate the impls for types passed by value to avoid boilerplate.
+macro_rules! traits_impl {
+    (
+        $type:ty, $benc_type:ident, $wire_type:ident, $encoded_size:expr, $size:expr,
+        $marshal_fn:ident, $unmarshal_fn:ident, $skip_fn:ident
+    ) => {
+        impl BencEncode for $type {
+            const WIRE_TYPE: Option<WireType> = Some(WireType::$wire_type);
+            const ENCODED_SIZE: Option<usize> = $encoded_size;
+
+            fn benc_type() -> Option<Type> {
+                Some(Type::$benc_type)
+            }
+
+            fn size(&self) -> usize {
+                ($size)(*self)
+            }
+
+            fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+                $marshal_fn(*self, writer)
+            }
+        }
+
+        impl<'a> BencDecode<'a> for $type {
+            fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+                $unmarshal_fn(reader)
+            }
+
+            fn skip(reader: &mut &[u8]) -> Result<()> {
+                $skip_fn(reader)
+            }
+        }
+    };
+}
+
+traits_impl!(bool, Bool, Fixed8, Some(size_bool()), |_| size_bool(), marshal_bool, unmarshal_bool, skip_bool);
+traits_impl!(u8, U8, Fixed8, Some(size_u8()), |_| size_u8(), marshal_u8, unmarshal_u8, skip_u8);
+traits_impl!(u16, U16, Fixed16, Some(size_u16()), |_| size_u16(), marshal_u16, unmarshal_u16, skip_u16);
+traits_impl!(u32, U32, Fixed32, Some(size_u32()), |_| size_u32(), marshal_u32, unmarshal_u32, skip_u32);
+traits_impl!(u64, U64, Fixed64, Some(size_u64()), |_| size_u64(), marshal_u64, unmarshal_u64, skip_u64);
+traits_impl!(i8, I8, Fixed8, Some(size_i8()), |_| size_i8(), marshal_i8, unmarshal_i8, skip_i8);
+traits_impl!(i16, I16, Fixed16, Some(size_i16()), |_| size_i16(), marshal_i16, unmarshal_i16, skip_i16);
+traits_impl!(i32, I32, Fixed32, Some(size_i32()), |_| size_i32(), marshal_i32, unmarshal_i32, skip_i32);
+traits_impl!(i64, I64, Fixed64, Some(size_i64()), |_| size_i64(), marshal_i64, unmarshal_i64, skip_i64);
+traits_impl!(f32, F32, Fixed32, Some(size_f32()), |_| size_f32(), marshal_f32, unmarshal_f32, skip_f32);
+traits_impl!(f64, F64, Fixed64, Some(size_f64()), |_| size_f64(), marshal_f64, unmarshal_f64, skip_f64);
+traits_impl!(usize, Uint, Varint, None, size_usize, marshal_usize, unmarshal_usize, skip_usize);
+traits_impl!(isize, Int, Varint, None, size_isize, marshal_isize, unmarshal_isize, skip_isize);
+traits_impl!(DateTime<Utc>, Time, Fixed64, Some(size_time()), |_| size_time(), marshal_time, unmarshal_time, skip_time);
+traits_impl!(Duration, Uint, Varint, None, size_duration, marshal_duration, unmarshal_duration, skip_duration);
+traits_impl!(TimeDelta, Int, Varint, None, size_time_delta, marshal_time_delta, unmarshal_time_delta, skip_time_delta);
+
+impl BencEncode for DateTime<FixedOffset> {
+    const ENCODED_SIZE: Option<usize> = Some(size_time_offset());
+
+    fn benc_type() -> Option<Type> {
+        Some(Type::Struct(Schema::new().field("time", Type::Time).field("offset", Type::I32)))
+    }
+
+    fn size(&self) -> usize {
+        size_time_offset()
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_time_offset(*self, writer)
+    }
+}
+
+impl<'a> BencDecode<'a> for DateTime<FixedOffset> {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        unmarshal_time_offset(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_time_offset(reader)
+    }
+}
+
+// ===================================================================================
+// Zero-Sized Types
+// ===================================================================================
+
+impl BencEncode for () {
+    const ENCODED_SIZE: Option<usize> = Some(0);
+
+    fn benc_type() -> Option<Type> {
+        Some(Type::Struct(Schema::new()))
+    }
+
+    fn size(&self) -> usize {
+        0
+    }
+
+    fn marshal(&self, _writer: &mut &mut [u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> BencDecode<'a> for () {
+    fn unmarshal(_reader: &mut &'a [u8]) -> Result<Self> {
+        Ok(())
+    }
+
+    fn skip(_reader: &mut &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: ?Sized> BencEncode for PhantomData<T> {
+    const ENCODED_SIZE: Option<usize> = Some(0);
+
+    fn benc_type() -> Option<Type> {
+        Some(Type::Struct(Schema::new()))
+    }
+
+    fn size(&self) -> usize {
+        0
+    }
+
+    fn marshal(&self, _writer: &mut &mut [u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, T: ?Sized> BencDecode<'a> for PhantomData<T> {
+    fn unmarshal(_reader: &mut &'a [u8]) -> Result<Self> {
+        Ok(PhantomData)
+    }
+
+    fn skip(_reader: &mut &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+// ===================================================================================
+// Strings and Byte Slices
+// ===================================================================================
+
+impl BencEncode for str {
+    const WIRE_TYPE: Option<WireType> = Some(WireType::Bytes);
+
+    fn benc_type() -> Option<Type> {
+        Some(Type::String)
+    }
+
+    fn size(&self) -> usize {
+        size_string(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_string(self, writer)
+    }
+}
+
+impl BencEncode for String {
+    const WIRE_TYPE: Option<WireType> = Some(WireType::Bytes);
+
+    fn benc_type() -> Option<Type> {
+        Some(Type::String)
+    }
+
+    fn size(&self) -> usize {
+        size_string(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_string(self, writer)
+    }
+}
+
+impl<'a> BencDecode<'a> for String {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        unmarshal_string(reader).map(String::from)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_string(reader)
+    }
+}
+
+impl<'a> BencDecode<'a> for &'a str {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        unmarshal_string(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_string(reader)
+    }
+}
+
+impl BencEncode for [u8] {
+    const WIRE_TYPE: Option<WireType> = Some(WireType::Bytes);
+
+    fn benc_type() -> Option<Type> {
+        Some(Type::Bytes)
+    }
+
+    fn size(&self) -> usize {
+        size_bytes(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_bytes(self, writer)
+    }
+}
+
+impl<'a> BencDecode<'a> for &'a [u8] {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        unmarshal_bytes_cropped(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_bytes(reader)
+    }
+}
+
+// ===================================================================================
+// Wrappers and Collections
+// ===================================================================================
+
+impl<T: BencEncode + ?Sized> BencEncode for &T {
+    const WIRE_TYPE: Option<WireType> = T::WIRE_TYPE;
+    const ENCODED_SIZE: Option<usize> = T::ENCODED_SIZE;
+
+    fn benc_type() -> Option<Type> {
+        T::benc_type()
+    }
+
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        (**self).marshal(writer)
+    }
+}
+
+impl<T: BencEncode + ?Sized> BencEncode for Box<T> {
+    const WIRE_TYPE: Option<WireType> = T::WIRE_TYPE;
+    const ENCODED_SIZE: Option<usize> = T::ENCODED_SIZE;
+
+    fn benc_type() -> Option<Type> {
+        T::benc_type()
+    }
+
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        (**self).marshal(writer)
+    }
+}
+
+impl<'a, T: BencDecode<'a>> BencDecode<'a> for Box<T> {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        T::unmarshal(reader).map(Box::new)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        T::skip(reader)
+    }
+}
+
+impl<T: BencEncode> BencEncode for Option<T> {
+    fn benc_type() -> Option<Type> {
+        T::benc_type().map(|ty| Type::Option(Box::new(ty)))
+    }
+
+    fn size(&self) -> usize {
+        size_option(self, T::size)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_option(self, writer, T::marshal)
+    }
+}
+
+impl<'a, T: BencDecode<'a>> BencDecode<'a> for Option<T> {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        if unmarshal_bool(reader)? { T::unmarshal(reader).map(Some) } else { Ok(None) }
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_option(reader, T::skip)
+    }
+}
+
+impl<T: BencEncode> BencEncode for Vec<T> {
+    fn benc_type() -> Option<Type> {
+        T::benc_type().map(|ty| Type::Slice(Box::new(ty)))
+    }
+
+    fn size(&self) -> usize {
+        size_slice(self, T::size)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_slice(self, writer, T::marshal)
+    }
+}
+
+impl<'a, T: BencDecode<'a>> BencDecode<'a> for Vec<T> {
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        unmarshal_slice(reader, T::unmarshal)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_slice(reader, T::skip)
+    }
+}
+
+impl<K: BencEncode, V: BencEncode, S> BencEncode for HashMap<K, V, S> {
+    fn benc_type() -> Option<Type> {
+        Some(Type::Map(Box::new(K::benc_type()?), Box::new(V::benc_type()?)))
+    }
+
+    fn size(&self) -> usize {
+        size_map(self, K::size, V::size)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_map(self, writer, K::marshal, V::marshal)
+    }
+}
+
+impl<'a, K, V, S> BencDecode<'a> for HashMap<K, V, S>
+where
+    K: BencDecode<'a> + Eq + Hash,
+    V: BencDecode<'a>,
+    S: BuildHasher + Default,
+{
+    fn unmarshal(reader: &mut &'a [u8]) -> Result<Self> {
+        unmarshal_map_with_hasher(reader, K::unmarshal, V::unmarshal)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_map(reader, K::skip, V::skip)
+    }
+}
@@ -0,0 +1,89 @@
+//! Size-bounded snapshots of messages, for logging and tracing.
+//!
+//! [`marshal_truncated`] encodes as many of the top-level fields of a struct as fit
+//! into a byte budget, so a debug snapshot of a huge message has a known size. The
+//! fields are kept in declaration order up to the first one that does not fit, and
+//! are preceded by a header holding their number as a varint. A snapshot that kept
+//! every field is therefore the header followed by the regular encoding.
+//!
+//! [`unmarshal_truncated`] reads a snapshot back into a [`Value`] with the kept
+//! fields and reports the names of the dropped ones.
+
+use crate::{
+    BencEncode, Error, Result, Schema, Value, marshal_usize, schema::field_ranges, schema_of, size_usize,
+    unmarshal_usize, unmarshal_value,
+};
+
+/// A message decoded from a snapshot written by `marshal_truncated`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Truncated {
+    /// The fields that were kept, as a `Value::Struct`.
+    pub value: Value,
+    /// The names of the fields that were dropped, in declaration order.
+    pub dropped: Vec<String>,
+}
+
+impl Truncated {
+    /// Returns `true` if any field was dropped.
+    pub fn is_truncated(&self) -> bool {
+        !self.dropped.is_empty()
+    }
+}
+
+/// Marshals the leading top-level fields of a struct that fit into `max_bytes`,
+/// header included.
+///
+/// Returns an `Unsupported` error if `T` does not describe its fields through
+/// `BencEncode::benc_type`, or a `BufferTooSmall` error if `max_bytes` is too small
+/// even for the header.
+pub fn marshal_truncated<T: BencEncode + ?Sized>(v: &T, max_bytes: usize) -> Result<Vec<u8>> {
+    let schema = schema_of::<T>()
+        .ok_or_else(|| Error::Unsupported(format!("{} has no schema", std::any::type_name::<T>())))?;
+    let full = v.to_vec();
+    let ranges = field_ranges(&full, &schema)?;
+    // The header grows with the number of kept fields, so it is accounted for at
+    // every step.
+    let mut kept = 0;
+    let mut end = 0;
+    for range in &ranges {
+        if size_usize(kept + 1) + range.end > max_bytes {
+            break;
+        }
+        kept += 1;
+        end = range.end;
+    }
+    let header = size_usize(kept);
+    if header > max_bytes {
+        return Err(Error::BufferTooSmall { needed: header, available: max_bytes });
+    }
+    let mut buf = vec![0u8; header + end];
+    let mut writer = buf.as_mut_slice();
+    marshal_usize(kept, &mut writer)?;
+    writer.copy_from_slice(&full[..end]);
+    Ok(buf)
+}
+
+/// Unmarshals a snapshot written by `marshal_truncated` of a struct described by the
+/// schema.
+///
+/// Returns an `InvalidValue` error if the header claims more fields than the schema
+/// has, or a `TrailingBytes` error if bytes follow the kept fields.
+pub fn unmarshal_truncated(buf: &[u8], schema: &Schema) -> Result<Truncated> {
+    let mut reader = buf;
+    let kept = unmarshal_usize(&mut reader)?;
+    let fields = schema.fields();
+    if kept > fields.len() {
+        return Err(Error::InvalidValue);
+    }
+    let mut values = Vec::with_capacity(kept);
+    for field in &fields[..kept] {
+        values.push((field.name.clone(), unmarshal_value(&mut reader, &field.ty)?));
+    }
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(Truncated {
+        value: Value::Struct(values),
+        dropped: fields[kept..].iter().map(|field| field.name.clone()).collect(),
+    })
+}
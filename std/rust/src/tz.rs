@@ -0,0 +1,110 @@
+//! Support for `chrono_tz` time zones, enabled by the `chrono-tz` feature.
+//!
+//! A `DateTime<Tz>` is marshalled as its timestamp, laid out exactly like
+//! `marshal_time`, followed by the IANA name of its zone as a string, such as
+//! `"Europe/Berlin"`. Unlike a fixed offset, the zone keeps its daylight saving rules,
+//! so a decoded value still moves across a transition the way the original would,
+//! which matters for recurring schedules kept in wall-clock time.
+//!
+//! The zone is resolved by name against the database compiled into `chrono_tz`; data
+//! naming a zone that the database does not know fails to decode.
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::{
+    BencDecode, BencEncode, Error, Result, marshal_string, marshal_time, size_string, size_time, skip_string,
+    skip_time, unmarshal_string, unmarshal_time,
+};
+
+/// Returns the number of bytes required to marshal a `Tz` as its IANA name.
+pub fn size_tz(tz: Tz) -> usize {
+    size_string(tz.name())
+}
+
+/// Marshals a `Tz` into the writer as its IANA name.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_tz(tz: Tz, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_string(tz.name(), writer)
+}
+
+/// Unmarshals a `Tz` from its IANA name.
+///
+/// Returns an `InvalidValue` error if the zone is not known.
+pub fn unmarshal_tz(reader: &mut &[u8]) -> Result<Tz> {
+    unmarshal_string(reader)?.parse().map_err(|_| Error::InvalidValue)
+}
+
+/// Skips over a marshalled `Tz` in the reader.
+pub fn skip_tz(reader: &mut &[u8]) -> Result<()> {
+    skip_string(reader)
+}
+
+/// Returns the number of bytes required to marshal a `DateTime<Tz>`.
+pub fn size_time_tz(t: &DateTime<Tz>) -> usize {
+    size_time() + size_tz(t.timezone())
+}
+
+/// Marshals a `DateTime<Tz>` as its UTC nanosecond timestamp, laid out exactly like
+/// `marshal_time`, followed by the IANA name of its zone.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_time_tz(t: &DateTime<Tz>, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_time(t.with_timezone(&Utc), writer)?;
+    marshal_tz(t.timezone(), writer)
+}
+
+/// Unmarshals a `DateTime<Tz>`, restoring the local time in its zone.
+///
+/// Returns an `InvalidValue` error if the zone is not known.
+pub fn unmarshal_time_tz(reader: &mut &[u8]) -> Result<DateTime<Tz>> {
+    let utc = unmarshal_time(reader)?;
+    Ok(utc.with_timezone(&unmarshal_tz(reader)?))
+}
+
+/// Skips over a marshalled `DateTime<Tz>` in the reader.
+pub fn skip_time_tz(reader: &mut &[u8]) -> Result<()> {
+    skip_time(reader)?;
+    skip_tz(reader)
+}
+
+impl BencEncode for Tz {
+    fn size(&self) -> usize {
+        size_tz(*self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_tz(*self, writer)
+    }
+}
+
+impl BencDecode<'_> for Tz {
+    fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+        unmarshal_tz(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_tz(reader)
+    }
+}
+
+impl BencEncode for DateTime<Tz> {
+    fn size(&self) -> usize {
+        size_time_tz(self)
+    }
+
+    fn marshal(&self, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_time_tz(self, writer)
+    }
+}
+
+impl BencDecode<'_> for DateTime<Tz> {
+    fn unmarshal(reader: &mut &[u8]) -> Result<Self> {
+        unmarshal_time_tz(reader)
+    }
+
+    fn skip(reader: &mut &[u8]) -> Result<()> {
+        skip_time_tz(reader)
+    }
+}
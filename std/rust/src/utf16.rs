@@ -0,0 +1,70 @@
+//! UTF-16 strings, for interoperability with Windows APIs and other UTF-16 producers.
+//!
+//! A UTF-16 string is marshalled as the varint-encoded number of code units followed
+//! by the code units as little-endian `u16`s.
+
+use crate::{Error, Result, advance, marshal_usize, size_u16, size_usize, unmarshal_usize, write_to_slice};
+
+/// Returns the number of bytes required to marshal a string as UTF-16.
+pub fn size_utf16(s: &str) -> usize {
+    let units = s.encode_utf16().count();
+    size_usize(units) + units * size_u16()
+}
+
+/// Marshals a string into the writer as UTF-16.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_utf16(s: &str, writer: &mut &mut [u8]) -> Result<()> {
+    marshal_usize(s.encode_utf16().count(), writer)?;
+    for unit in s.encode_utf16() {
+        write_to_slice(writer, &unit.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Returns the number of bytes required to marshal raw UTF-16 code units.
+pub fn size_utf16_units(units: &[u16]) -> usize {
+    size_usize(units.len()) + units.len() * size_u16()
+}
+
+/// Marshals raw UTF-16 code units into the writer. The units are not validated, so
+/// unpaired surrogates are preserved.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_utf16_units(units: &[u16], writer: &mut &mut [u8]) -> Result<()> {
+    marshal_usize(units.len(), writer)?;
+    for unit in units {
+        write_to_slice(writer, &unit.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Reads the code units of a marshalled UTF-16 string.
+fn read_units<'a>(reader: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = unmarshal_usize(reader)?;
+    let bytes = len.checked_mul(size_u16()).ok_or(Error::OutOfRange)?;
+    advance(reader, bytes)
+}
+
+/// Unmarshals a UTF-16 string from the reader into a `String`.
+/// Returns an `InvalidValue` error if the string contains unpaired surrogates.
+pub fn unmarshal_utf16(reader: &mut &[u8]) -> Result<String> {
+    let bytes = read_units(reader)?;
+    let units = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+    char::decode_utf16(units)
+        .collect::<std::result::Result<String, _>>()
+        .map_err(|_| Error::InvalidValue)
+}
+
+/// Unmarshals the raw code units of a UTF-16 string from the reader without
+/// validating them.
+pub fn unmarshal_utf16_units(reader: &mut &[u8]) -> Result<Vec<u16>> {
+    let bytes = read_units(reader)?;
+    Ok(bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect())
+}
+
+/// Skips over a marshalled UTF-16 string in the reader.
+pub fn skip_utf16(reader: &mut &[u8]) -> Result<()> {
+    read_units(reader)?;
+    Ok(())
+}
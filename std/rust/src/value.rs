@@ -0,0 +1,359 @@
+//! Dynamically typed messages.
+//!
+//! A [`Value`] holds a marshalled value whose Rust type is not known at compile time.
+//! It is decoded with the [`Type`] or [`Schema`] describing the bytes, and marshals
+//! back into exactly the same bytes, which lets generic tooling inspect, transform and
+//! re-encode messages of any type.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    Error, Result, Schema, TERMINATOR, Type, check_element_progress, marshal_bool, marshal_bytes, marshal_f32, marshal_f64,
+    marshal_i8, marshal_i16, marshal_i32, marshal_i64, marshal_int, marshal_string, marshal_time,
+    marshal_u8, marshal_u16, marshal_u32, marshal_u64, marshal_uint, marshal_usize, read_terminator,
+    size_bool, size_bytes, size_f32, size_f64, size_i8, size_i16, size_i32, size_i64, size_int,
+    size_string, size_time, size_u8, size_u16, size_u32, size_u64, size_uint, size_usize,
+    unmarshal_bool, unmarshal_bytes_copied, unmarshal_f32, unmarshal_f64, unmarshal_i8,
+    unmarshal_i16, unmarshal_i32, unmarshal_i64, unmarshal_int, unmarshal_string, unmarshal_time,
+    unmarshal_u8, unmarshal_u16, unmarshal_u32, unmarshal_u64, unmarshal_uint, unmarshal_usize,
+    skip_type, write_to_slice,
+};
+
+/// A value of any [`Type`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Uint(u64),
+    Int(i64),
+    String(String),
+    Bytes(Vec<u8>),
+    Time(DateTime<Utc>),
+    Slice(Vec<Value>),
+    /// The entries of a map, in marshalling order.
+    Map(Vec<(Value, Value)>),
+    Option(Option<Box<Value>>),
+    /// The named fields of a nested message, in marshalling order.
+    Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Returns the field with the given name, if this is a struct that has one.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the number of bytes needed to marshal the value.
+pub fn size_value(v: &Value) -> usize {
+    match v {
+        Value::Bool(_) => size_bool(),
+        Value::U8(_) => size_u8(),
+        Value::U16(_) => size_u16(),
+        Value::U32(_) => size_u32(),
+        Value::U64(_) => size_u64(),
+        Value::I8(_) => size_i8(),
+        Value::I16(_) => size_i16(),
+        Value::I32(_) => size_i32(),
+        Value::I64(_) => size_i64(),
+        Value::F32(_) => size_f32(),
+        Value::F64(_) => size_f64(),
+        Value::Uint(v) => size_uint(*v),
+        Value::Int(v) => size_int(*v),
+        Value::String(s) => size_string(s),
+        Value::Bytes(b) => size_bytes(b),
+        Value::Time(_) => size_time(),
+        Value::Slice(elems) => size_usize(elems.len()) + elems.iter().map(size_value).sum::<usize>() + TERMINATOR.len(),
+        Value::Map(entries) => {
+            size_usize(entries.len())
+                + entries.iter().map(|(k, v)| size_value(k) + size_value(v)).sum::<usize>()
+                + TERMINATOR.len()
+        }
+        Value::Option(inner) => size_bool() + inner.as_deref().map_or(0, size_value),
+        Value::Struct(fields) => fields.iter().map(|(_, v)| size_value(v)).sum(),
+    }
+}
+
+/// Marshals the value into the writer.
+///
+/// Returns an error if the writer is too small.
+pub fn marshal_value(v: &Value, writer: &mut &mut [u8]) -> Result<()> {
+    match v {
+        Value::Bool(v) => marshal_bool(*v, writer),
+        Value::U8(v) => marshal_u8(*v, writer),
+        Value::U16(v) => marshal_u16(*v, writer),
+        Value::U32(v) => marshal_u32(*v, writer),
+        Value::U64(v) => marshal_u64(*v, writer),
+        Value::I8(v) => marshal_i8(*v, writer),
+        Value::I16(v) => marshal_i16(*v, writer),
+        Value::I32(v) => marshal_i32(*v, writer),
+        Value::I64(v) => marshal_i64(*v, writer),
+        Value::F32(v) => marshal_f32(*v, writer),
+        Value::F64(v) => marshal_f64(*v, writer),
+        Value::Uint(v) => marshal_uint(*v, writer),
+        Value::Int(v) => marshal_int(*v, writer),
+        Value::String(s) => marshal_string(s, writer),
+        Value::Bytes(b) => marshal_bytes(b, writer),
+        Value::Time(t) => marshal_time(*t, writer),
+        Value::Slice(elems) => {
+            marshal_usize(elems.len(), writer)?;
+            for elem in elems {
+                marshal_value(elem, writer)?;
+            }
+            write_to_slice(writer, &TERMINATOR)
+        }
+        Value::Map(entries) => {
+            marshal_usize(entries.len(), writer)?;
+            for (k, v) in entries {
+                marshal_value(k, writer)?;
+                marshal_value(v, writer)?;
+            }
+            write_to_slice(writer, &TERMINATOR)
+        }
+        Value::Option(inner) => {
+            marshal_bool(inner.is_some(), writer)?;
+            match inner {
+                Some(inner) => marshal_value(inner, writer),
+                None => Ok(()),
+            }
+        }
+        Value::Struct(fields) => {
+            for (_, v) in fields {
+                marshal_value(v, writer)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Unmarshals a value of the given type from the reader.
+///
+/// Returns an `InvalidValue` error if a slice or map of elements that occupy no bytes
+/// claims more elements than there are bytes left.
+pub fn unmarshal_value(reader: &mut &[u8], ty: &Type) -> Result<Value> {
+    Ok(match ty {
+        Type::Bool => Value::Bool(unmarshal_bool(reader)?),
+        Type::U8 => Value::U8(unmarshal_u8(reader)?),
+        Type::U16 => Value::U16(unmarshal_u16(reader)?),
+        Type::U32 => Value::U32(unmarshal_u32(reader)?),
+        Type::U64 => Value::U64(unmarshal_u64(reader)?),
+        Type::I8 => Value::I8(unmarshal_i8(reader)?),
+        Type::I16 => Value::I16(unmarshal_i16(reader)?),
+        Type::I32 => Value::I32(unmarshal_i32(reader)?),
+        Type::I64 => Value::I64(unmarshal_i64(reader)?),
+        Type::F32 => Value::F32(unmarshal_f32(reader)?),
+        Type::F64 => Value::F64(unmarshal_f64(reader)?),
+        Type::Uint => Value::Uint(unmarshal_uint(reader)?),
+        Type::Int => Value::Int(unmarshal_int(reader)?),
+        Type::String => Value::String(unmarshal_string(reader)?.to_owned()),
+        Type::Bytes => Value::Bytes(unmarshal_bytes_copied(reader)?),
+        Type::Time => Value::Time(unmarshal_time(reader)?),
+        Type::Slice(elem) => {
+            // The length is untrusted, so the vector grows as elements are decoded.
+            let len = unmarshal_usize(reader)?;
+            let mut elems = Vec::new();
+            for _ in 0..len {
+                let remaining = reader.len();
+                elems.push(unmarshal_value(reader, elem)?);
+                // Structs without fields occupy no bytes, so the length needs a bound.
+                check_element_progress(len, remaining, reader)?;
+            }
+            read_terminator(reader)?;
+            Value::Slice(elems)
+        }
+        Type::Map(k, v) => {
+            let len = unmarshal_usize(reader)?;
+            let mut entries = Vec::new();
+            for _ in 0..len {
+                let remaining = reader.len();
+                entries.push((unmarshal_value(reader, k)?, unmarshal_value(reader, v)?));
+                check_element_progress(len, remaining, reader)?;
+            }
+            read_terminator(reader)?;
+            Value::Map(entries)
+        }
+        Type::Option(inner) => {
+            Value::Option(if unmarshal_bool(reader)? { Some(Box::new(unmarshal_value(reader, inner)?)) } else { None })
+        }
+        Type::Struct(schema) => unmarshal_struct_value(reader, schema)?,
+    })
+}
+
+/// Unmarshals a message described by the schema from the reader into a
+/// [`Value::Struct`].
+pub fn unmarshal_struct_value(reader: &mut &[u8], schema: &Schema) -> Result<Value> {
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    for field in schema.fields() {
+        fields.push((field.name.clone(), unmarshal_value(reader, &field.ty)?));
+    }
+    Ok(Value::Struct(fields))
+}
+
+/// Unmarshals a whole buffer holding a message described by the schema.
+///
+/// Returns a `TrailingBytes` error if the message is followed by unconsumed bytes.
+pub fn value_from_slice(buf: &[u8], schema: &Schema) -> Result<Value> {
+    let mut reader = buf;
+    let value = unmarshal_struct_value(&mut reader, schema)?;
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(value)
+}
+
+/// A field selected by [`decode_fields`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldSel<'s> {
+    /// The top-level field with the given name.
+    Name(&'s str),
+    /// The top-level field at the given position in the schema.
+    Index(usize),
+    /// A field of a nested struct, given by the names of the fields leading to it
+    /// from the top level.
+    Path(&'s [&'s str]),
+}
+
+/// The selected fields of a struct, by field index, for `decode_fields`.
+#[derive(Default)]
+struct Selection {
+    /// The fields decoded whole, with the outputs that receive them and the rest of
+    /// the path to a nested field, if any.
+    whole: BTreeMap<usize, Vec<(usize, Vec<usize>)>>,
+    /// The nested structs some of whose fields are selected.
+    nested: BTreeMap<usize, Selection>,
+}
+
+impl Selection {
+    fn add(&mut self, path: &[usize], output: usize) {
+        match path {
+            [index] => self.whole.entry(*index).or_default().push((output, Vec::new())),
+            [index, rest @ ..] => self.nested.entry(*index).or_default().add(rest, output),
+            [] => unreachable!("selector paths are never empty"),
+        }
+    }
+
+    /// Moves the nested selections under fields decoded whole to those fields, so
+    /// every field is read once.
+    fn merge(&mut self) {
+        for (index, mut nested) in std::mem::take(&mut self.nested) {
+            if let Some(outputs) = self.whole.get_mut(&index) {
+                nested.flatten(&mut Vec::new(), outputs);
+            } else {
+                nested.merge();
+                self.nested.insert(index, nested);
+            }
+        }
+    }
+
+    fn flatten(self, prefix: &mut Vec<usize>, outputs: &mut Vec<(usize, Vec<usize>)>) {
+        for (index, whole) in self.whole {
+            prefix.push(index);
+            outputs.extend(whole.into_iter().map(|(output, _)| (output, prefix.clone())));
+            prefix.pop();
+        }
+        for (index, nested) in self.nested {
+            prefix.push(index);
+            nested.flatten(prefix, outputs);
+            prefix.pop();
+        }
+    }
+
+    /// Returns the index of the last field that is selected.
+    fn last(&self) -> Option<usize> {
+        self.whole.keys().chain(self.nested.keys()).copied().max()
+    }
+}
+
+/// Resolves a selector to the field indices leading to the selected field.
+fn resolve(schema: &Schema, sel: &FieldSel<'_>) -> Result<Vec<usize>> {
+    let names = match sel {
+        FieldSel::Index(index) if *index < schema.fields().len() => return Ok(vec![*index]),
+        FieldSel::Index(index) => return Err(Error::Unsupported(format!("no field at index {index}"))),
+        FieldSel::Name(name) => std::slice::from_ref(name),
+        FieldSel::Path(names) => names,
+    };
+    let mut path = Vec::with_capacity(names.len());
+    let mut schema = Some(schema);
+    for name in names {
+        let index = schema
+            .and_then(|schema| schema.index_of(name))
+            .ok_or_else(|| Error::Unsupported(format!("no field {}", names.join("."))))?;
+        path.push(index);
+        schema = match &schema.unwrap().fields()[index].ty {
+            Type::Struct(nested) => Some(nested),
+            _ => None,
+        };
+    }
+    if path.is_empty() {
+        return Err(Error::Unsupported("empty field path".into()));
+    }
+    Ok(path)
+}
+
+/// Returns the field of a struct value at the given indices.
+fn value_at(mut value: &Value, path: &[usize]) -> Value {
+    for &index in path {
+        // The path was resolved against the schema the value was decoded with.
+        let Value::Struct(fields) = value else { unreachable!("paths only lead into structs") };
+        value = &fields[index].1;
+    }
+    value.clone()
+}
+
+/// Decodes the selected fields of a struct, skipping the others. Past the last
+/// selected field, nested structs are skipped whole and the top level is not read.
+fn decode_selection(
+    reader: &mut &[u8],
+    schema: &Schema,
+    selection: &Selection,
+    read_all: bool,
+    out: &mut [Option<Value>],
+) -> Result<()> {
+    let end = if read_all { schema.fields().len() } else { selection.last().map_or(0, |last| last + 1) };
+    for (index, field) in schema.fields()[..end].iter().enumerate() {
+        if let Some(outputs) = selection.whole.get(&index) {
+            let value = unmarshal_value(reader, &field.ty)?;
+            for (output, path) in outputs {
+                out[*output] = Some(value_at(&value, path));
+            }
+        } else if let Some(nested) = selection.nested.get(&index) {
+            let Type::Struct(nested_schema) = &field.ty else { unreachable!("paths only lead into structs") };
+            decode_selection(reader, nested_schema, nested, true, out)?;
+        } else {
+            skip_type(reader, &field.ty)?;
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the selected fields of a message described by the schema in a single pass,
+/// skipping the fields that are not selected, and returns them in the order of the
+/// selectors.
+///
+/// The reader is left after the last selected top-level field. Returns a
+/// `Unsupported` error if a selector does not name a field of the schema.
+pub fn decode_fields(reader: &mut &[u8], schema: &Schema, selectors: &[FieldSel<'_>]) -> Result<Vec<Value>> {
+    let mut selection = Selection::default();
+    for (output, sel) in selectors.iter().enumerate() {
+        selection.add(&resolve(schema, sel)?, output);
+    }
+    selection.merge();
+    let mut out = vec![None; selectors.len()];
+    decode_selection(reader, schema, &selection, false, &mut out)?;
+    // Every selector names a field the selection decodes.
+    Ok(out.into_iter().map(Option::unwrap).collect())
+}
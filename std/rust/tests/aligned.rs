@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    /// Returns a zeroed buffer of `len` bytes starting at an address aligned to 16.
+    fn aligned_buffer(storage: &mut Vec<u8>, len: usize) -> &mut [u8] {
+        storage.resize(len + 16, 0);
+        let start = storage.as_ptr().align_offset(16);
+        &mut storage[start..start + len]
+    }
+
+    #[test]
+    fn test_aligned_slice() {
+        let values = [1u64, u64::MAX, 42];
+        let floats = [0.5f64, -1.25];
+
+        // A one-byte field leaves the elements unaligned without padding.
+        let first = size_u8() + size_aligned_slice(&values, 1);
+        let size = first + size_aligned_slice(&floats, first);
+        let mut storage = Vec::new();
+        let buf = aligned_buffer(&mut storage, size);
+        let total = buf.len();
+        let mut writer = &mut buf[..];
+        marshal_u8(7, &mut writer).unwrap();
+        marshal_aligned_slice(&values, total - writer.len(), &mut writer).unwrap();
+        marshal_aligned_slice(&floats, total - writer.len(), &mut writer).unwrap();
+        assert!(writer.is_empty());
+
+        // The count, the padding length and five zero bytes align the elements to 8.
+        assert_eq!(&buf[..8], [7, 3, 5, 0, 0, 0, 0, 0]);
+        assert_eq!(&buf[first..first + 4], [2, 2, 0, 0]);
+
+        #[cfg(feature = "bytemuck")]
+        {
+            let mut reader = &buf[..];
+            assert_eq!(unmarshal_u8(&mut reader).unwrap(), 7);
+            assert_eq!(unmarshal_aligned_slice::<u64>(&mut reader).unwrap(), values);
+            assert_eq!(unmarshal_aligned_slice::<f64>(&mut reader).unwrap(), floats);
+            assert!(reader.is_empty());
+        }
+
+        let mut reader = &buf[1..];
+        assert_eq!(unmarshal_aligned_slice_copied::<u64>(&mut reader).unwrap(), values);
+        skip_aligned_slice::<f64>(&mut reader).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_aligned_slice_errors() {
+        let values = [1u32, 2, 3];
+        let mut storage = Vec::new();
+        let size = size_aligned_slice(&values, 0);
+        let buf = aligned_buffer(&mut storage, size + 1);
+        let mut writer = &mut buf[1..];
+        marshal_aligned_slice(&values, 0, &mut writer).unwrap();
+
+        // Shifted by one byte, the elements cannot be viewed in place but can be copied.
+        #[cfg(feature = "bytemuck")]
+        assert_eq!(unmarshal_aligned_slice::<u32>(&mut &buf[1..]), Err(Error::InvalidValue));
+        assert_eq!(unmarshal_aligned_slice_copied::<u32>(&mut &buf[1..]).unwrap(), values);
+
+        // A padding length of at least the alignment is rejected.
+        assert_eq!(unmarshal_aligned_slice_copied::<u32>(&mut &[0, 4, 0, 0, 0, 0, 1, 1, 1, 1][..]), Err(Error::InvalidValue));
+
+        let mut small = [0u8; 8];
+        assert!(matches!(marshal_aligned_slice(&values, 0, &mut &mut small[..]), Err(Error::BufferTooSmall { .. })));
+    }
+}
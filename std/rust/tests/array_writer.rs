@@ -0,0 +1,35 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[test]
+    fn test_array_writer() {
+        let mut writer = ArrayWriter::<16>::new();
+        assert!(writer.is_empty());
+        writer.write(|w| marshal_uint(300, w)).unwrap();
+        writer.push("ack").unwrap();
+        writer.push(&7u32).unwrap();
+        assert_eq!(writer.as_bytes(), [0xac, 0x02, 3, b'a', b'c', b'k', 7, 0, 0, 0]);
+        assert_eq!(writer.len(), 10);
+        assert_eq!(writer.remaining(), 6);
+
+        let mut reader = &writer[..];
+        assert_eq!(unmarshal_uint(&mut reader).unwrap(), 300);
+        assert_eq!(unmarshal_string(&mut reader).unwrap(), "ack");
+        assert_eq!(unmarshal_u32(&mut reader).unwrap(), 7);
+
+        writer.clear();
+        assert!(writer.as_bytes().is_empty());
+    }
+
+    #[test]
+    fn test_array_writer_full() {
+        let mut writer = ArrayWriter::<6>::new();
+        writer.push(&1u32).unwrap();
+        assert_eq!(writer.push(&2u32), Err(Error::BufferTooSmall { needed: 4, available: 2 }));
+        assert_eq!(writer.push("abc"), Err(Error::BufferTooSmall { needed: 4, available: 2 }));
+        assert_eq!(writer.as_bytes(), [1, 0, 0, 0]);
+        writer.push(&3u16).unwrap();
+        assert_eq!(writer.remaining(), 0);
+    }
+}
@@ -0,0 +1,161 @@
+#![cfg(feature = "futures")]
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use benc::*;
+    use futures::executor::block_on;
+    use futures::io::{AsyncRead, Cursor};
+    use futures::{SinkExt, StreamExt, stream};
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Event {
+            id: u32,
+            name: String,
+        }
+    }
+
+    fn events(n: u32) -> Vec<Event> {
+        (0..n).map(|id| Event { id, name: "e".repeat(id as usize % 50) }).collect()
+    }
+
+    /// A reader that returns at most three bytes per read, splitting frames.
+    struct Trickle(Cursor<Vec<u8>>);
+
+    impl AsyncRead for Trickle {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            let len = buf.len().min(3);
+            Pin::new(&mut self.0).poll_read(cx, &mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_sink_and_stream() {
+        let mut sink = BencSink::new(Cursor::new(Vec::new()));
+        block_on(stream::iter(events(1000)).map(Ok).forward(&mut sink)).unwrap();
+        let buf = sink.into_inner().into_inner();
+
+        let decoded: Vec<Event> = block_on(BencStream::new(Trickle(Cursor::new(buf.clone()))).map(Result::unwrap).collect());
+        assert_eq!(decoded, events(1000));
+
+        // Every frame is the varint length followed by the value.
+        let mut reader = buf.as_slice();
+        for event in events(1000) {
+            assert_eq!(unmarshal_bytes_cropped(&mut reader).unwrap(), event.to_vec());
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_sink_buffers_until_flushed() {
+        let mut sink = BencSink::new(Cursor::new(Vec::new()));
+        block_on(sink.feed("hello".to_string())).unwrap();
+        assert!(sink.get_ref().get_ref().is_empty());
+        block_on(sink.flush()).unwrap();
+        assert_eq!(sink.get_ref().get_ref(), &[6, 5, b'h', b'e', b'l', b'l', b'o']);
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        // A frame cut short by end of file.
+        let mut stream = BencStream::<_, String>::new(Cursor::new(vec![1, 0, 7, 5, b'h']));
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), "");
+        assert_eq!(block_on(stream.next()).unwrap().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert!(block_on(stream.next()).is_none());
+
+        // A frame over the limit.
+        let mut stream = BencStream::<_, String>::new(Cursor::new(vec![200, 1])).with_max_frame_len(100);
+        assert_eq!(block_on(stream.next()).unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(block_on(stream.next()).is_none());
+
+        // A value that does not fill its frame.
+        let mut stream = BencStream::<_, String>::new(Cursor::new(vec![3, 1, b'a', b'b']));
+        let err = block_on(stream.next()).unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.into_inner().unwrap().downcast_ref::<Error>(), Some(&Error::TrailingBytes));
+    }
+
+    #[test]
+    fn test_async_rpc() {
+        let (client, server) = std::os::unix::net::UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rpc = BlockingRpc::new(server);
+            while let Some(event) = rpc.next_event().unwrap() {
+                if let RpcEvent::Request { id, payload, .. } = event {
+                    rpc.respond(id, &[payload.iter().sum()]).unwrap();
+                }
+            }
+        });
+
+        let mut rpc = AsyncRpc::new(futures::io::AllowStdIo::new(client));
+        assert_eq!(block_on(rpc.call("sum", &[1, 2, 3])).unwrap(), Ok(vec![6]));
+        assert_eq!(block_on(rpc.call("sum", &[])).unwrap(), Ok(vec![0]));
+        drop(rpc);
+        handle.join().unwrap();
+    }
+
+    /// A reader of the chunks sent through a channel, pending until one arrives.
+    struct ChannelReader {
+        chunks: futures::channel::mpsc::UnboundedReceiver<Vec<u8>>,
+    }
+
+    impl AsyncRead for ChannelReader {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            match self.chunks.poll_next_unpin(cx) {
+                Poll::Ready(Some(chunk)) => {
+                    // The chunks are smaller than the read buffer.
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Poll::Ready(Ok(chunk.len()))
+                }
+                Poll::Ready(None) => Poll::Ready(Ok(0)),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_cancellation() {
+        let expected = events(50);
+        let mut sink = BencSink::new(Cursor::new(Vec::new()));
+        sink.send_all(&mut stream::iter(expected.clone()).map(Ok)).await.unwrap();
+        let buf = sink.into_inner().into_inner();
+
+        let (chunks, rx) = futures::channel::mpsc::unbounded();
+        let (interrupts, mut interrupted) = futures::channel::mpsc::unbounded();
+        let mut reader = FrameReader::new(ChannelReader { chunks: rx });
+        let mut frames = Vec::new();
+        let mut cancelled = 0;
+        for chunk in buf.chunks(3) {
+            chunks.unbounded_send(chunk.to_vec()).unwrap();
+            interrupts.unbounded_send(()).unwrap();
+            // Reading takes the chunk and waits for more unless it completes a frame,
+            // then the interrupt wins and the read future is dropped mid-frame.
+            tokio::select! {
+                biased;
+                frame = reader.read_frame() => frames.push(frame.unwrap().unwrap()),
+                _ = interrupted.next() => cancelled += 1,
+            }
+        }
+        drop(chunks);
+        assert!(cancelled > expected.len());
+        assert_eq!(reader.read_frame().await.unwrap(), None);
+
+        let decoded: Vec<Event> = frames.iter().map(|frame| from_slice(frame).unwrap()).collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_read_frame_errors() {
+        let mut reader = FrameReader::new(Cursor::new(vec![1, 0, 7, 5, b'h']));
+        assert_eq!(block_on(reader.read_frame()).unwrap(), Some(vec![0]));
+        assert_eq!(reader.buffered(), 3);
+        assert_eq!(block_on(reader.read_frame()).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut reader = FrameReader::new(Cursor::new(vec![200, 1])).with_max_frame_len(100);
+        assert_eq!(block_on(reader.read_frame()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,543 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::marker::PhantomData;
+
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Inner {
+            pub id: u32,
+            pub tags: Vec<String>,
+        }
+    }
+
+    benc_struct! {
+        /// A struct using every kind of supported field.
+        #[derive(Debug, Clone, PartialEq)]
+        struct Outer {
+            flag: bool,
+            count: usize,
+            ratio: f64,
+            name: String,
+            inner: Inner,
+            maybe: Option<Box<Inner>>,
+            scores: HashMap<String, i64>,
+        }
+    }
+
+    fn outer() -> Outer {
+        Outer {
+            flag: true,
+            count: 300,
+            ratio: 0.25,
+            name: "outer".into(),
+            inner: Inner { id: 7, tags: vec!["a".into(), "b".into()] },
+            maybe: Some(Box::new(Inner { id: 8, tags: vec![] })),
+            scores: HashMap::from([("x".to_string(), -1)]),
+        }
+    }
+
+    #[test]
+    fn test_benc_struct_round_trip() {
+        let value = outer();
+        let buf = value.to_vec();
+        assert_eq!(buf.len(), value.size());
+        assert_eq!(from_slice::<Outer>(&buf).unwrap(), value);
+
+        let mut reader = buf.as_slice();
+        Outer::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        let mut trailing = buf.clone();
+        trailing.push(0);
+        assert_eq!(from_slice::<Outer>(&trailing), Err(Error::TrailingBytes));
+    }
+
+    #[test]
+    fn test_benc_struct_to_slice() {
+        let value = outer();
+        let size = value.size();
+        let mut buf = vec![0u8; size + 2];
+        assert_eq!(to_slice(&value, &mut buf).unwrap(), size);
+        assert_eq!(&buf[..size], value.to_vec().as_slice());
+
+        // The error reports the size of the whole nested value, not of the field that
+        // failed, so a buffer of that size is enough on the next try.
+        let mut small = vec![0u8; size - 1];
+        let err = to_slice(&value, &mut small).err();
+        assert_eq!(err, Some(Error::BufferTooSmall { needed: size, available: size - 1 }));
+        let mut retry = vec![0u8; size];
+        assert_eq!(to_slice(&value, &mut retry).unwrap(), size);
+    }
+
+    #[test]
+    fn test_benc_struct_matches_free_functions() {
+        let inner = Inner { id: 7, tags: vec!["a".into(), "b".into()] };
+        let size = size_u32() + size_slice(&inner.tags, |s| size_string(s));
+        let mut expected = vec![0; size];
+        let mut writer = expected.as_mut_slice();
+        marshal_u32(inner.id, &mut writer).unwrap();
+        marshal_slice(&inner.tags, &mut writer, |s, w| marshal_string(s, w)).unwrap();
+        assert_eq!(inner.to_vec(), expected);
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Unit;
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Empty {}
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Tagged {
+            id: u16,
+            unit: Unit,
+            empty: Empty,
+            marker: PhantomData<fn() -> String>,
+            set: HashMap<u8, ()>,
+        }
+    }
+
+    #[test]
+    fn test_zero_sized_types() {
+        assert_eq!(().size(), 0);
+        assert!(Unit.to_vec().is_empty());
+        assert!(Empty {}.to_vec().is_empty());
+        assert_eq!(from_slice::<Unit>(&[]).unwrap(), Unit);
+
+        let value = Tagged { id: 1, unit: Unit, empty: Empty {}, marker: PhantomData, set: HashMap::from([(9, ())]) };
+        let buf = value.to_vec();
+        // The set is marshalled as its keys only.
+        assert_eq!(buf, [1, 0, 1, 9, 1, 1, 1, 1]);
+        assert_eq!(from_slice::<Tagged>(&buf).unwrap(), value);
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Record<'a> {
+            id: u64,
+            name: &'a str,
+            payload: &'a [u8],
+            aliases: Vec<&'a str>,
+        }
+    }
+
+    #[test]
+    fn test_borrowed_fields() {
+        let record = Record { id: 3, name: "rec", payload: &[1, 2], aliases: vec!["r", "R"] };
+        let buf = record.to_vec();
+        let decoded: Record<'_> = from_slice(&buf).unwrap();
+        assert_eq!(decoded, record);
+        assert!(buf.as_ptr_range().contains(&decoded.name.as_ptr()));
+        assert!(buf.as_ptr_range().contains(&decoded.payload.as_ptr()));
+
+        let mut reader = buf.as_slice();
+        Record::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_borrowed_primitives() {
+        let mut buf = vec![0; "zero-copy".size() + b"bytes"[..].size()];
+        let mut writer = buf.as_mut_slice();
+        "zero-copy".marshal(&mut writer).unwrap();
+        b"bytes"[..].marshal(&mut writer).unwrap();
+
+        let mut reader = buf.as_slice();
+        let s = <&str>::unmarshal(&mut reader).unwrap();
+        let b = <&[u8]>::unmarshal(&mut reader).unwrap();
+        assert_eq!((s, b), ("zero-copy", &b"bytes"[..]));
+        assert!(buf.as_ptr_range().contains(&s.as_ptr()));
+    }
+
+    mod millis {
+        use std::time::Duration;
+
+        use benc::*;
+
+        pub fn size(v: &Duration) -> usize {
+            size_uint(v.as_millis() as u64)
+        }
+
+        pub fn marshal(v: &Duration, writer: &mut &mut [u8]) -> Result<()> {
+            marshal_uint(v.as_millis() as u64, writer)
+        }
+
+        pub fn unmarshal(reader: &mut &[u8]) -> Result<Duration> {
+            unmarshal_uint(reader).map(Duration::from_millis)
+        }
+
+        pub fn skip(reader: &mut &[u8]) -> Result<()> {
+            skip_uint(reader)
+        }
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Timeout {
+            name: String,
+            /// Stored as whole milliseconds.
+            #[benc(with = millis)]
+            after: std::time::Duration,
+        }
+    }
+
+    #[test]
+    fn test_with_module() {
+        let value = Timeout { name: "t".into(), after: std::time::Duration::from_millis(1500) };
+        let buf = value.to_vec();
+        assert_eq!(buf, [1, b't', 0xdc, 0x0b]);
+        assert_eq!(buf.len(), value.size());
+        assert_eq!(from_slice::<Timeout>(&buf).unwrap(), value);
+
+        let mut reader = buf.as_slice();
+        Timeout::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    benc_struct! {
+        #[derive(Debug, Default, PartialEq)]
+        struct Cached {
+            key: String,
+            #[benc(skip)]
+            hits: u64,
+            #[benc(default)]
+            version: u32,
+        }
+    }
+
+    #[test]
+    fn test_skip_and_default() {
+        let value = Cached { key: "k".into(), hits: 12, version: 3 };
+        let buf = value.to_vec();
+        assert_eq!(buf, [1, b'k', 3, 0, 0, 0]);
+        assert_eq!(buf.len(), value.size());
+        assert_eq!(from_slice::<Cached>(&buf).unwrap(), Cached { hits: 0, ..value });
+
+        let old = [1, b'k'];
+        assert_eq!(from_slice::<Cached>(&old).unwrap(), Cached { key: "k".into(), hits: 0, version: 0 });
+
+        // Skipping cannot know where the struct ends, so it needs every field.
+        assert!(Cached::skip(&mut &old[..]).is_err());
+        let mut reader = buf.as_slice();
+        Cached::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        assert_eq!(
+            from_slice::<Cached>(&[1, b'k', 3, 0]),
+            Err(Error::BufferTooSmall { needed: 4, available: 2 })
+        );
+
+        // Nested in a slice, the default field is required rather than read from the
+        // next element.
+        let values = vec![Cached { key: "k".into(), hits: 0, version: 3 }, Cached { key: "j".into(), hits: 0, version: 4 }];
+        assert_eq!(from_slice::<Vec<Cached>>(&values.to_vec()).unwrap()[1].version, 4);
+        let mut old = vec![2];
+        old.extend_from_slice(&[1, b'k', 1, b'j']);
+        old.extend_from_slice(&[1, 1, 1, 1]);
+        assert!(from_slice::<Vec<Cached>>(&old).is_err());
+    }
+
+    benc_struct! {
+        #[benc(tagged)]
+        #[derive(Debug, Clone, PartialEq)]
+        struct UserV1 {
+            #[benc(id = 1)]
+            id: u32,
+            #[benc(id = 2)]
+            name: String,
+            #[benc(id = 3)]
+            inner: Inner,
+        }
+    }
+
+    benc_struct! {
+        /// `UserV1` with its fields renamed and reordered, one field removed and one added.
+        #[derive(Debug, PartialEq)]
+        #[benc(tagged)]
+        struct UserV2 {
+            #[benc(id = 2)]
+            display_name: String,
+            #[benc(id = 1)]
+            user_id: u32,
+            #[benc(id = 4, default)]
+            age: Option<u8>,
+            #[benc(skip)]
+            cache: Vec<u8>,
+            #[benc(id = 5, with = millis, default)]
+            timeout: std::time::Duration,
+        }
+    }
+
+    #[test]
+    fn test_tagged_round_trip() {
+        let value = UserV1 { id: 7, name: "ann".into(), inner: Inner { id: 1, tags: vec![] } };
+        let buf = value.to_vec();
+        assert_eq!(buf.len(), value.size());
+        #[rustfmt::skip]
+        assert_eq!(buf, [
+            3,
+            (1 << 3) | 3, 7, 0, 0, 0,
+            (2 << 3) | 5, 3, b'a', b'n', b'n',
+            (3 << 3) | 5, 9, 1, 0, 0, 0, 0, 1, 1, 1, 1,
+            1, 1, 1, 1,
+        ]);
+        assert_eq!(from_slice::<UserV1>(&buf).unwrap(), value);
+
+        let mut reader = buf.as_slice();
+        UserV1::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_tagged_field_ids() {
+        let v2 = UserV2 {
+            display_name: "bob".into(),
+            user_id: 9,
+            age: Some(30),
+            cache: vec![1, 2, 3],
+            timeout: std::time::Duration::from_secs(2),
+        };
+        let buf = v2.to_vec();
+        assert_eq!(buf.len(), v2.size());
+        assert_eq!(from_slice::<UserV2>(&buf).unwrap(), UserV2 { cache: vec![], ..v2 });
+
+        let mut reader = buf.as_slice();
+        UserV2::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        // The fields unknown to V1 are skipped, but its required field 3 is missing.
+        assert_eq!(from_slice::<UserV1>(&buf), Err(Error::InvalidValue));
+
+        // V1 data is missing the default fields of V2, and has a field V2 does not know.
+        let v1 = UserV1 { id: 9, name: "bob".into(), inner: Inner { id: 1, tags: vec![] } };
+        let decoded = from_slice::<UserV2>(&v1.to_vec()).unwrap();
+        assert_eq!(
+            decoded,
+            UserV2 {
+                display_name: "bob".into(),
+                user_id: 9,
+                age: None,
+                cache: vec![],
+                timeout: std::time::Duration::ZERO,
+            }
+        );
+        let buf = v1.to_vec();
+        let mut reader = buf.as_slice();
+        UserV2::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(UserV2::decode_field_user_id(&mut buf.as_slice()).unwrap(), 9);
+        assert_eq!(UserV2::decode_field_age(&mut buf.as_slice()).unwrap(), None);
+    }
+
+    benc_struct! {
+        /// `UserV1` after a later release added fields of every wire type.
+        #[benc(tagged)]
+        #[derive(Debug, Clone, PartialEq)]
+        struct UserV1Extended {
+            #[benc(id = 1)]
+            id: u32,
+            #[benc(id = 6)]
+            score: u64,
+            #[benc(id = 2)]
+            name: String,
+            #[benc(id = 7)]
+            level: u8,
+            #[benc(id = 3)]
+            inner: Inner,
+            #[benc(id = 8)]
+            ratio: f32,
+            #[benc(id = 9)]
+            tags: Vec<String>,
+        }
+    }
+
+    #[test]
+    fn test_tagged_unknown_fields() {
+        let inner = Inner { id: 1, tags: vec!["x".into()] };
+        let newer = UserV1Extended {
+            id: 7,
+            score: 1 << 40,
+            name: "ann".into(),
+            level: 3,
+            inner: inner.clone(),
+            ratio: 0.5,
+            tags: vec!["a".into(), "b".into()],
+        };
+        let buf = newer.to_vec();
+        let old = UserV1 { id: 7, name: "ann".into(), inner };
+        assert_eq!(from_slice::<UserV1>(&buf).unwrap(), old);
+
+        let mut reader = buf.as_slice();
+        UserV1::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(UserV1::decode_field_inner(&mut buf.as_slice()).unwrap(), old.inner);
+
+        // An unknown field that extends past the end of the buffer is still an error.
+        assert!(matches!(from_slice::<UserV1>(&buf[..buf.len() - 8]), Err(Error::BufferTooSmall { .. })));
+    }
+
+    #[test]
+    fn test_tagged_invalid() {
+        // Field 2 missing.
+        let buf = [1, (1 << 3) | 3, 7, 0, 0, 0, 1, 1, 1, 1];
+        assert_eq!(from_slice::<UserV1>(&buf), Err(Error::InvalidValue));
+
+        // Field 1 with the wrong wire type.
+        let buf = [1, (1 << 3) | 4, 7, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1];
+        assert_eq!(from_slice::<UserV1>(&buf), Err(Error::InvalidValue));
+
+        // Field 1 twice.
+        let buf = [2, (1 << 3) | 3, 7, 0, 0, 0, (1 << 3) | 3, 7, 0, 0, 0, 1, 1, 1, 1];
+        assert_eq!(from_slice::<UserV1>(&buf), Err(Error::InvalidValue));
+
+        // Undefined wire type.
+        let buf = [1, (1 << 3) | 7, 1, 1, 1, 1];
+        assert_eq!(from_slice::<UserV1>(&buf), Err(Error::InvalidValue));
+
+        // A wrapped value that is longer than its contents.
+        let buf = [1, (3 << 3) | 5, 10, 1, 0, 0, 0, 0, 1, 1, 1, 1, 0, 1, 1, 1, 1];
+        assert_eq!(from_slice::<UserV1>(&buf), Err(Error::TrailingBytes));
+    }
+
+    fn check_percent(v: &u8) -> Result<()> {
+        if *v > 100 { Err(Error::Validation(format!("{v} is not a percentage"))) } else { Ok(()) }
+    }
+
+    fn check_range(r: &Range) -> Result<()> {
+        if r.low > r.high { Err(Error::Validation("low is above high".into())) } else { Ok(()) }
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        #[benc(validate = check_range)]
+        struct Range {
+            #[benc(validate = check_percent)]
+            low: u8,
+            #[benc(validate = check_percent)]
+            high: u8,
+        }
+    }
+
+    benc_struct! {
+        #[benc(tagged, validate = check_range_v2)]
+        #[derive(Debug, PartialEq)]
+        struct RangeV2 {
+            #[benc(id = 1, validate = check_percent)]
+            low: u8,
+        }
+    }
+
+    fn check_range_v2(r: &RangeV2) -> Result<()> {
+        if r.low == 0 { Err(Error::Validation("low is zero".into())) } else { Ok(()) }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(from_slice::<Range>(&[10, 20]).unwrap(), Range { low: 10, high: 20 });
+        assert_eq!(from_slice::<Range>(&[10, 200]), Err(Error::Validation("200 is not a percentage".into())));
+        assert_eq!(from_slice::<Range>(&[20, 10]), Err(Error::Validation("low is above high".into())));
+
+        // Skipping does not validate.
+        let mut reader = &[20u8, 10][..];
+        Range::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        let buf = RangeV2 { low: 5 }.to_vec();
+        assert_eq!(from_slice::<RangeV2>(&buf).unwrap(), RangeV2 { low: 5 });
+        let buf = RangeV2 { low: 0 }.to_vec();
+        assert_eq!(from_slice::<RangeV2>(&buf), Err(Error::Validation("low is zero".into())));
+        let buf = RangeV2 { low: 101 }.to_vec();
+        assert_eq!(from_slice::<RangeV2>(&buf), Err(Error::Validation("101 is not a percentage".into())));
+    }
+
+    #[test]
+    fn test_decode_field() {
+        let value = outer();
+        let buf = value.to_vec();
+        assert!(Outer::decode_field_flag(&mut buf.as_slice()).unwrap());
+        assert_eq!(Outer::decode_field_name(&mut buf.as_slice()).unwrap(), "outer");
+        assert_eq!(Outer::decode_field_scores(&mut buf.as_slice()).unwrap(), value.scores);
+
+        let mut reader = buf.as_slice();
+        assert_eq!(Outer::decode_field_inner(&mut reader).unwrap(), value.inner);
+        assert_eq!(reader.len(), value.maybe.size() + value.scores.size());
+
+        let record = Record { id: 3, name: "rec", payload: &[1, 2], aliases: vec!["r", "R"] };
+        let buf = record.to_vec();
+        let name: &str = Record::decode_field_name(&mut buf.as_slice()).unwrap();
+        assert_eq!(name, "rec");
+
+        assert_eq!(Range::decode_field_high(&mut &[1u8, 2][..]).unwrap(), 2);
+        assert!(matches!(Range::decode_field_high(&mut &[1u8, 200][..]), Err(Error::Validation(_))));
+        assert_eq!(
+            Range::decode_field_high(&mut &[1u8][..]),
+            Err(Error::BufferTooSmall { needed: 1, available: 0 })
+        );
+    }
+
+    #[test]
+    fn test_decode_field_tagged() {
+        let v2 = UserV2 {
+            display_name: "bob".into(),
+            user_id: 9,
+            age: None,
+            cache: vec![1],
+            timeout: std::time::Duration::from_millis(20),
+        };
+        let buf = v2.to_vec();
+        assert_eq!(UserV2::decode_field_user_id(&mut buf.as_slice()).unwrap(), 9);
+        assert_eq!(UserV2::decode_field_display_name(&mut buf.as_slice()).unwrap(), "bob");
+        assert_eq!(UserV2::decode_field_age(&mut buf.as_slice()).unwrap(), None);
+        assert_eq!(UserV2::decode_field_cache(&mut buf.as_slice()).unwrap(), Vec::<u8>::new());
+        assert_eq!(UserV2::decode_field_timeout(&mut buf.as_slice()).unwrap(), v2.timeout);
+
+        // Field 1 only: the required field 2 is missing, the default field 4 is not.
+        let buf = [1, (1 << 3) | 3, 7, 0, 0, 0, 1, 1, 1, 1];
+        assert_eq!(UserV2::decode_field_display_name(&mut &buf[..]), Err(Error::InvalidValue));
+        assert_eq!(UserV2::decode_field_age(&mut &buf[..]).unwrap(), None);
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Heartbeat {
+            seq: u64,
+            range: Range,
+            flags: (),
+            #[benc(skip)]
+            received: Option<String>,
+        }
+    }
+
+    #[test]
+    fn test_encoded_size() {
+        assert_eq!(Range::ENCODED_SIZE, Some(2));
+        assert_eq!(Heartbeat::ENCODED_SIZE, Some(10));
+        assert_eq!(Empty::ENCODED_SIZE, Some(0));
+        assert_eq!(Unit::ENCODED_SIZE, Some(0));
+        assert_eq!(Inner::ENCODED_SIZE, None);
+        assert_eq!(Cached::ENCODED_SIZE, None);
+        assert_eq!(UserV1::ENCODED_SIZE, None);
+        assert_eq!(<Box<Range>>::ENCODED_SIZE, Some(2));
+
+        const BUF_LEN: usize = Heartbeat::ENCODED_SIZE.unwrap();
+        let value = Heartbeat { seq: 1, range: Range { low: 1, high: 2 }, flags: (), received: None };
+        let mut buf = [0u8; BUF_LEN];
+        value.marshal(&mut buf.as_mut_slice()).unwrap();
+        assert_eq!(from_slice::<Heartbeat>(&buf).unwrap(), value);
+
+        assert_eq!(value.marshal_array::<BUF_LEN>(), buf);
+        assert_eq!(0x0102u16.marshal_array(), [2, 1]);
+
+        let mut reader = &buf[..];
+        Heartbeat::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(Heartbeat::skip(&mut &buf[1..]), Err(Error::BufferTooSmall { needed: 10, available: 9 }));
+    }
+}
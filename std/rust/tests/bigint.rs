@@ -0,0 +1,80 @@
+#![cfg(feature = "num-bigint")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use num_bigint::{BigInt, BigUint};
+
+    fn round_trip(v: &BigInt) {
+        let size = size_bigint(v);
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_bigint(v, &mut writer).unwrap();
+        assert!(writer.is_empty(), "marshal did not fill the buffer");
+
+        let mut skipper = buf.as_slice();
+        skip_bigint(&mut skipper).unwrap();
+        assert!(skipper.is_empty());
+
+        let mut reader = buf.as_slice();
+        assert_eq!(&unmarshal_bigint(&mut reader).unwrap(), v);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_bigint() {
+        let huge: BigInt = "-340282366920938463463374607431768211456123".parse().unwrap();
+        for v in [BigInt::from(0), BigInt::from(1), BigInt::from(-1), BigInt::from(i128::MIN), huge] {
+            round_trip(&v);
+        }
+    }
+
+    #[test]
+    fn test_bigint_layout() {
+        // Zero is a sign byte and an empty magnitude.
+        assert_eq!(size_bigint(&BigInt::from(0)), 2);
+
+        let v = BigInt::from(-0x0102);
+        let mut buf = vec![0u8; size_bigint(&v)];
+        marshal_bigint(&v, &mut buf.as_mut_slice()).unwrap();
+        assert_eq!(buf, [2, 2, 0x02, 0x01]);
+    }
+
+    #[test]
+    fn test_biguint() {
+        let v = BigUint::from(u128::MAX) * BigUint::from(u64::MAX);
+        let mut buf = vec![0u8; size_biguint(&v)];
+        marshal_biguint(&v, &mut buf.as_mut_slice()).unwrap();
+
+        let mut skipper = buf.as_slice();
+        skip_biguint(&mut skipper).unwrap();
+        assert!(skipper.is_empty());
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_biguint(&mut reader).unwrap(), v);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_bigint_errors() {
+        assert_eq!(unmarshal_bigint(&mut &[3u8, 0][..]).err(), Some(Error::OutOfRange));
+        assert_eq!(unmarshal_bigint(&mut &[1u8, 2, 1][..]).err(), Some(Error::BufferTooSmall { needed: 2, available: 1 }));
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Balance {
+            account: BigUint,
+            amount: BigInt,
+        }
+    }
+
+    #[test]
+    fn test_bigint_traits() {
+        let b = Balance { account: BigUint::from(u128::MAX) * 3u8, amount: BigInt::from(i128::MIN) - 1 };
+        let buf = b.to_vec();
+        assert_eq!(buf.len(), size_biguint(&b.account) + size_bigint(&b.amount));
+        assert_eq!(from_slice::<Balance>(&buf).unwrap(), b);
+        assert_eq!(<BigUint>::WIRE_TYPE, Some(WireType::Bytes));
+    }
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "bitvec")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use bitvec::prelude::*;
+
+    #[test]
+    fn test_bit_vec_round_trip() {
+        let bits = bitvec![u8, Lsb0; 1, 0, 1, 1, 0, 0, 0, 0, 1, 1];
+        let buf = bits.to_vec();
+        assert_eq!(buf, [10, 0b0000_1101, 0b11, 1, 1, 1, 1]);
+        assert_eq!(buf.len(), bits.size());
+        assert_eq!(from_slice::<BitVec<u8, Lsb0>>(&buf).unwrap(), bits);
+
+        // The layout does not depend on the storage or the bit order.
+        let msb = bitvec![u32, Msb0; 1, 0, 1, 1, 0, 0, 0, 0, 1, 1];
+        assert_eq!(msb.to_vec(), buf);
+        assert_eq!(BencEncode::to_vec(msb.as_bitslice()), buf);
+        assert_eq!(from_slice::<BitVec<u32, Msb0>>(&buf).unwrap(), msb);
+
+        let mut reader = buf.as_slice();
+        skip_bit_slice(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        let empty = BitVec::<u8>::new();
+        assert_eq!(empty.to_vec(), [0, 1, 1, 1, 1]);
+        assert_eq!(from_slice::<BitVec>(&empty.to_vec()).unwrap(), empty);
+    }
+
+    #[test]
+    fn test_bit_vec_errors() {
+        // Bit 3 is set, but only three bits are used.
+        assert_eq!(from_slice::<BitVec>(&[3, 0b1000, 1, 1, 1, 1]), Err(Error::InvalidValue));
+        assert_eq!(from_slice::<BitVec>(&[8, 0xff, 1, 1, 1, 0]), Err(Error::MissingTerminator));
+        assert!(matches!(from_slice::<BitVec>(&[200, 0]), Err(Error::BufferTooSmall { .. })));
+    }
+}
@@ -0,0 +1,50 @@
+#![cfg(any(feature = "arrayvec", feature = "tinyvec"))]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    fn marshal_u16s(values: &[u16]) -> Vec<u8> {
+        let mut buf = vec![0; size_fixed_slice(values, 2)];
+        marshal_slice(values, &mut buf.as_mut_slice(), |v, w| marshal_u16(*v, w)).unwrap();
+        buf
+    }
+
+    #[cfg(feature = "arrayvec")]
+    #[test]
+    fn test_array_vec() {
+        use arrayvec::ArrayVec;
+
+        let buf = marshal_u16s(&[1, 2, 3]);
+        let vec: ArrayVec<u16, 3> = from_slice(&buf).unwrap();
+        assert_eq!(vec.as_slice(), [1, 2, 3]);
+        assert_eq!(vec.to_vec(), buf);
+        assert_eq!(ArrayVec::<u16, 3>::benc_type(), Vec::<u16>::benc_type());
+
+        assert_eq!(from_slice::<ArrayVec<u16, 2>>(&buf), Err(Error::CapacityExceeded { len: 3, capacity: 2 }));
+        let mut reader = buf.as_slice();
+        let err = unmarshal_array_vec::<_, 2, Error>(&mut reader, unmarshal_u16).unwrap_err();
+        assert_eq!(err, Error::CapacityExceeded { len: 3, capacity: 2 });
+        // The capacity is checked before any element is read.
+        assert_eq!(reader.len(), buf.len() - 1);
+    }
+
+    #[cfg(feature = "tinyvec")]
+    #[test]
+    fn test_tiny_vec() {
+        use tinyvec::{ArrayVec, TinyVec};
+
+        let buf = marshal_u16s(&[1, 2, 3]);
+        let inline: TinyVec<[u16; 4]> = from_slice(&buf).unwrap();
+        assert!(inline.is_inline());
+        let spilled: TinyVec<[u16; 2]> = from_slice(&buf).unwrap();
+        assert!(spilled.is_heap());
+        assert_eq!(spilled.as_slice(), [1, 2, 3]);
+        assert_eq!(spilled.to_vec(), buf);
+
+        let vec: ArrayVec<[u16; 3]> = from_slice(&buf).unwrap();
+        assert_eq!(vec.as_slice(), [1, 2, 3]);
+        assert_eq!(vec.to_vec(), buf);
+        assert_eq!(from_slice::<ArrayVec<[u16; 2]>>(&buf), Err(Error::CapacityExceeded { len: 3, capacity: 2 }));
+    }
+}
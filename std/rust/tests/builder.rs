@@ -0,0 +1,182 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use benc::*;
+
+    fn encode(slice: &[&str]) -> Vec<u8> {
+        let mut buf = vec![0u8; size_slice(slice, |s| size_string(s))];
+        marshal_slice(slice, &mut buf.as_mut_slice(), |s, w| marshal_string(s, w)).unwrap();
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Vec<String> {
+        let mut reader = buf;
+        let ret = unmarshal_slice(&mut reader, |r| unmarshal_string(r).map(String::from)).unwrap();
+        assert!(reader.is_empty());
+        ret
+    }
+
+    #[test]
+    fn test_slice_builder_append() {
+        let mut builder = SliceBuilder::open(encode(&["first", "second"])).unwrap();
+        assert_eq!(builder.len(), 2);
+        builder.push(size_string("third"), |w| marshal_string("third", w)).unwrap();
+
+        let mut element = vec![0u8; size_string("fourth")];
+        marshal_string("fourth", &mut element.as_mut_slice()).unwrap();
+        builder.push_encoded(&element);
+
+        let buf = builder.finish();
+        assert_eq!(buf, encode(&["first", "second", "third", "fourth"]));
+        assert_eq!(decode(&buf), vec!["first", "second", "third", "fourth"]);
+    }
+
+    #[test]
+    fn test_slice_builder_new_and_header_growth() {
+        let mut builder = SliceBuilder::new();
+        assert!(builder.is_empty());
+        assert_eq!(SliceBuilder::new().finish(), encode(&[]));
+
+        // Growing past 127 elements widens the varint length prefix.
+        let expected: Vec<String> = (0..200).map(|i| i.to_string()).collect();
+        for s in &expected[..127] {
+            builder.push(size_string(s), |w| marshal_string(s, w)).unwrap();
+        }
+        let mut builder = SliceBuilder::open(builder.finish()).unwrap();
+        for s in &expected[127..] {
+            builder.push(size_string(s), |w| marshal_string(s, w)).unwrap();
+        }
+        let buf = builder.finish();
+        let refs: Vec<&str> = expected.iter().map(String::as_str).collect();
+        assert_eq!(buf, encode(&refs));
+    }
+
+    #[test]
+    fn test_slice_builder_errors() {
+        let mut corrupted = encode(&["a"]);
+        let last = corrupted.len() - 1;
+        corrupted[last] = 0;
+        assert_eq!(SliceBuilder::open(corrupted).err(), Some(Error::MissingTerminator));
+        assert_eq!(SliceBuilder::open(vec![0, 1, 1]).err(), Some(Error::BufferTooSmall { needed: 4, available: 2 }));
+
+        // A failing marshaler leaves the builder untouched.
+        let mut builder = SliceBuilder::open(encode(&["a"])).unwrap();
+        assert_eq!(builder.push(1, |w| marshal_string("too long", w)).err(), Some(Error::BufferTooSmall { needed: 9, available: 1 }));
+        assert_eq!(builder.len(), 1);
+        assert_eq!(builder.finish(), encode(&["a"]));
+    }
+
+    #[test]
+    fn test_map_encoder_reserved() {
+        // The map follows a field marshalled before it.
+        let mut buf = vec![7u8];
+        let mut encoder = MapEncoder::new(&mut buf, MapLength::Reserved);
+        for i in 0..200u32 {
+            encoder.insert(&i.to_string(), &i).unwrap();
+        }
+        encoder.push(size_string("x") + size_u32(), |w| {
+            marshal_string("x", w)?;
+            marshal_u32(1000, w)
+        }).unwrap();
+        encoder.push_encoded(&"y".to_vec(), &2000u32.to_vec());
+        assert_eq!(encoder.len(), 202);
+        encoder.finish();
+
+        let mut expected: HashMap<String, u32> = (0..200).map(|i| (i.to_string(), i)).collect();
+        expected.insert("x".to_string(), 1000);
+        expected.insert("y".to_string(), 2000);
+        let mut reader = &buf[1..];
+        let map: HashMap<String, u32> =
+            unmarshal_map(&mut reader, |r| unmarshal_string(r).map(String::from), unmarshal_u32).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(map, expected);
+        assert_eq!(from_slice::<HashMap<String, u32>>(&buf[1..]).unwrap(), expected);
+
+        // The padded length prefix is not canonical.
+        assert_eq!(unmarshal_uint_strict(&mut &buf[1..]).err(), Some(Error::NonMinimalVarint));
+    }
+
+    #[test]
+    fn test_map_encoder_terminator_only() {
+        let mut buf = Vec::new();
+        let mut encoder = MapEncoder::new(&mut buf, MapLength::TerminatorOnly);
+        assert!(encoder.is_empty());
+        encoder.insert("alpha", &1u64).unwrap();
+        encoder.insert("beta", &2u64).unwrap();
+        // A failing entry leaves the map unchanged.
+        assert!(encoder.push(1, |w| marshal_string("gamma", w)).is_err());
+        encoder.finish();
+        assert_eq!(buf.len(), size_string("alpha") + size_string("beta") + 2 * size_u64() + 4);
+
+        let mut reader = buf.as_slice();
+        let map: BTreeMap<&str, u64> = unmarshal_map_terminator_only(&mut reader, unmarshal_string, unmarshal_u64).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(map, BTreeMap::from([("alpha", 1), ("beta", 2)]));
+
+        let mut reader = buf.as_slice();
+        skip_map_terminator_only(&mut reader, skip_string, skip_u64).unwrap();
+        assert!(reader.is_empty());
+
+        let mut empty = Vec::new();
+        MapEncoder::new(&mut empty, MapLength::TerminatorOnly).finish();
+        let mut reader = empty.as_slice();
+        let map: Vec<(u8, u8)> = unmarshal_map_terminator_only(&mut reader, unmarshal_u8, unmarshal_u8).unwrap();
+        assert!(map.is_empty());
+        assert!(unmarshal_map_terminator_only::<u8, u8, Vec<_>, Error>(&mut &[3u8][..], unmarshal_u8, unmarshal_u8).is_err());
+    }
+
+    #[test]
+    fn test_message_builder_slots() {
+        let records = ["alpha", "beta", "gamma"];
+        let mut builder = MessageBuilder::new();
+        builder.put(&7u16).unwrap();
+        let count = builder.reserve_for::<u32>().unwrap();
+        let checksum = builder.reserve(size_u64());
+        let body_start = builder.len();
+        for r in &records {
+            builder.push(size_string(r), |w| marshal_string(r, w)).unwrap();
+        }
+        let sum: u64 = builder.as_slice()[body_start..].iter().map(|&b| u64::from(b)).sum();
+        builder.fill_with(count, &(records.len() as u32)).unwrap();
+        builder.fill(checksum, |w| marshal_u64(sum, w)).unwrap();
+        let buf = builder.finish().unwrap();
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_u16(&mut reader).unwrap(), 7);
+        assert_eq!(unmarshal_u32(&mut reader).unwrap(), 3);
+        assert_eq!(unmarshal_u64(&mut reader).unwrap(), sum);
+        for r in &records {
+            assert_eq!(unmarshal_string(&mut reader).unwrap(), *r);
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_message_builder_errors() {
+        let mut builder = MessageBuilder::with_capacity(16);
+        assert!(builder.is_empty());
+        assert!(matches!(builder.reserve_for::<String>(), Err(Error::Unsupported(_))));
+
+        let slot = builder.reserve(4);
+        assert_eq!((slot.offset(), slot.len()), (0, 4));
+        // A marshaler must fill the slot exactly.
+        assert_eq!(builder.fill(slot, |w| marshal_u16(1, w)).err(), Some(Error::InvalidValue));
+        assert!(matches!(builder.fill(slot, |w| marshal_u64(1, w)), Err(Error::BufferTooSmall { .. })));
+        assert_eq!(builder.as_slice(), &[0, 0, 0, 0]);
+        assert_eq!(builder.clone().finish().err(), Some(Error::InvalidValue));
+
+        // A slot of another builder is rejected.
+        let mut other = MessageBuilder::new();
+        assert_eq!(other.fill_with(slot, &1u32).err(), Some(Error::OutOfRange));
+        other.push_encoded(&[9, 9]);
+        let _ = other.reserve(4);
+        assert_eq!(other.fill_with(slot, &1u32).err(), Some(Error::OutOfRange));
+        assert_eq!(other.as_slice(), &[9, 9, 0, 0, 0, 0]);
+
+        // Filling a slot again overwrites it.
+        builder.fill_with(slot, &1u32).unwrap();
+        builder.fill_with(slot, &2u32).unwrap();
+        assert_eq!(builder.finish().unwrap(), 2u32.to_vec());
+    }
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "bstr")]
+
+#[cfg(test)]
+mod tests {
+    use bstr::{BStr, BString, ByteSlice};
+    use benc::*;
+
+    #[test]
+    fn test_bstr() {
+        let name = BStr::new(b"caf\xE9.txt");
+        let log = BString::from("plain line");
+        let mut buf = vec![0; size_bstr(name) + size_bstr(log.as_ref())];
+        let mut writer = buf.as_mut_slice();
+        marshal_bstr(name, &mut writer).unwrap();
+        marshal_bstr(log.as_ref(), &mut writer).unwrap();
+
+        let mut reader = buf.as_slice();
+        let decoded = unmarshal_bstr(&mut reader).unwrap();
+        assert_eq!(decoded, name);
+        assert_eq!(decoded.to_str_lossy(), "caf\u{FFFD}.txt");
+        assert_eq!(unmarshal_bstring(&mut reader).unwrap(), log);
+        assert!(reader.is_empty());
+
+        let mut reader = buf.as_slice();
+        skip_bstr(&mut reader).unwrap();
+        assert_eq!(unmarshal_string(&mut reader).unwrap(), "plain line");
+    }
+
+    #[test]
+    fn test_bstr_traits() {
+        benc_struct! {
+            #[derive(Debug, PartialEq)]
+            struct Entry<'a> {
+                path: BString,
+                label: &'a BStr,
+            }
+        }
+
+        let e = Entry { path: BString::from(&b"caf\xE9.txt"[..]), label: BStr::new("log") };
+        let buf = e.to_vec();
+        assert_eq!(buf.len(), size_bstr(e.path.as_ref()) + size_bstr(e.label));
+        assert_eq!(from_slice::<Entry>(&buf).unwrap(), e);
+        assert_eq!(BStr::new("log").to_vec(), BencEncode::to_vec(&b"log"[..]));
+    }
+}
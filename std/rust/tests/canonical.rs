@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Signed {
+            flag: bool,
+            ratio: f64,
+            count: usize,
+            labels: HashMap<u16, String>,
+        }
+    }
+
+    fn signed() -> Signed {
+        let labels = (0..20).map(|i| (i * 300, format!("label {i}"))).collect();
+        Signed { flag: true, ratio: 0.5, count: 300, labels }
+    }
+
+    #[test]
+    fn test_marshal_canonical() {
+        let value = signed();
+        let canonical = marshal_canonical(&value).unwrap();
+        verify_canonical::<Signed>(&canonical).unwrap();
+        assert_eq!(from_slice::<Signed>(&canonical).unwrap(), value);
+
+        // Maps with the same entries marshal alike, ordered by their key bytes.
+        let reversed = (0..20).rev().map(|i| (i * 300, format!("label {i}"))).collect();
+        assert_eq!(marshal_canonical(&Signed { labels: reversed, ..signed() }).unwrap(), canonical);
+        let mut reader = &canonical[1 + 8 + 2..];
+        let pairs = unmarshal_pairs(&mut reader, unmarshal_u16, |r| unmarshal_string(r)).unwrap();
+        let keys: Vec<_> = pairs.iter().map(|(k, _)| k.to_le_bytes()).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        // A boolean of 2, which decodes as false, a NaN with a payload and an overlong varint.
+        let mut buf = vec![2];
+        buf.extend_from_slice(&f64::from_bits(0x7ff8_0000_0000_0001).to_bits().to_le_bytes());
+        buf.extend_from_slice(&[0x80, 0x00]);
+        buf.extend_from_slice(&[0, 1, 1, 1, 1]);
+        assert_eq!(verify_canonical::<Signed>(&buf), Err(Error::NonCanonical));
+
+        let canonical = canonicalize(&buf, &Signed::benc_type().unwrap()).unwrap();
+        let mut expected = vec![0];
+        expected.extend_from_slice(&0x7ff8_0000_0000_0000u64.to_le_bytes());
+        expected.extend_from_slice(&[0, 0, 1, 1, 1, 1]);
+        assert_eq!(canonical, expected);
+        verify_canonical::<Signed>(&canonical).unwrap();
+
+        let negative_zero = (-0.0f32).to_le_bytes();
+        assert_eq!(canonicalize(&negative_zero, &Type::F32).unwrap(), [0; 4]);
+
+        // A map holding the same key twice has no canonical form.
+        let map = Type::Map(Box::new(Type::U8), Box::new(Type::U8));
+        assert_eq!(canonicalize(&[2, 1, 10, 1, 20, 1, 1, 1, 1], &map), Err(Error::NonCanonical));
+        assert_eq!(canonicalize(&[2, 2, 10, 1, 20, 1, 1, 1, 1], &map).unwrap(), [2, 1, 20, 2, 10, 1, 1, 1, 1]);
+        assert_eq!(canonicalize(&[0, 1, 1, 1, 1, 0], &map), Err(Error::TrailingBytes));
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[test]
+    fn test_chunked() {
+        let values: Vec<u32> = (0..10).collect();
+        let size = size_chunked(&values, 4, |_| size_u32());
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_chunked(&values, 4, &mut writer, |_| size_u32(), |v, w| marshal_u32(*v, w)).unwrap();
+        assert!(writer.is_empty());
+        assert_eq!(&buf[..8], [10, 3, 4, 16, 4, 16, 2, 8]);
+
+        let mut reader = buf.as_slice();
+        let chunked = unmarshal_chunked_index(&mut reader).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(chunked.len(), 10);
+        assert_eq!(chunked.chunks().iter().map(|c| c.len()).collect::<Vec<_>>(), [4, 4, 2]);
+        assert_eq!(chunked.chunks()[2].decode(unmarshal_u32).unwrap(), [8, 9]);
+        assert_eq!(chunked.decode(unmarshal_u32).unwrap(), values);
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_chunked(&mut reader, unmarshal_u32).unwrap(), values);
+        let mut reader = buf.as_slice();
+        skip_chunked(&mut reader).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_chunked_strings() {
+        let strings: Vec<String> = (0..100).map(|i| "y".repeat(i)).collect();
+        let size = size_chunked(&strings, 7, |s| size_string(s));
+        let mut buf = vec![0u8; size];
+        marshal_chunked(&strings, 7, &mut buf.as_mut_slice(), |s| size_string(s), |s, w| marshal_string(s, w))
+            .unwrap();
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_chunked(&mut reader, |r| unmarshal_string(r)).unwrap(), strings);
+
+        let empty: Vec<u32> = vec![];
+        let mut buf = vec![0u8; size_chunked(&empty, 4, |_| size_u32())];
+        marshal_chunked(&empty, 4, &mut buf.as_mut_slice(), |_| size_u32(), |v, w| marshal_u32(*v, w)).unwrap();
+        assert_eq!(buf, [0, 0, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_chunked_errors() {
+        // The element counts in the index must add up to the total.
+        let buf = [3, 1, 2, 8, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1];
+        assert_eq!(unmarshal_chunked_index(&mut buf.as_slice()), Err(Error::InvalidValue));
+
+        // A chunk whose elements do not fill it completely is rejected.
+        let buf = [2, 1, 2, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1];
+        let result = unmarshal_chunked(&mut buf.as_slice(), unmarshal_u32);
+        assert_eq!(result, Err(Error::TrailingBytes));
+
+        let buf = [2, 1, 2, 8, 0, 0];
+        assert!(matches!(skip_chunked(&mut buf.as_slice()), Err(Error::BufferTooSmall { .. })));
+    }
+}
@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use benc::codec::{self, Codec};
+    use benc::*;
+
+    fn round_trip<T: PartialEq + std::fmt::Debug>(c: &impl Codec<T>, value: &T) {
+        let mut buf = vec![0; c.size(value) + 1];
+        let mut writer = buf.as_mut_slice();
+        c.marshal(value, &mut writer).unwrap();
+        assert_eq!(writer.len(), 1, "size did not match marshalled length");
+        marshal_u8(0xAB, &mut writer).unwrap();
+
+        let mut reader = buf.as_slice();
+        assert_eq!(c.unmarshal(&mut reader).unwrap(), *value);
+        assert_eq!(reader, &[0xAB]);
+
+        let mut reader = buf.as_slice();
+        c.skip(&mut reader).unwrap();
+        assert_eq!(reader, &[0xAB]);
+    }
+
+    #[test]
+    fn test_primitive_codecs() {
+        round_trip(&codec::Bool, &true);
+        round_trip(&codec::U16, &0xBEEF);
+        round_trip(&codec::I64, &-5);
+        round_trip(&codec::F64, &1.5);
+        round_trip(&codec::Uint, &300);
+        round_trip(&codec::Int, &-300);
+        round_trip(&codec::Usize, &7);
+        round_trip(&codec::Str, &"hello".to_string());
+        round_trip(&codec::Bytes, &vec![1, 2, 3]);
+        assert_eq!(codec::U32.fixed_size(), Some(4));
+        assert_eq!(codec::Uint.fixed_size(), None);
+    }
+
+    #[test]
+    fn test_combinators() {
+        let nested = codec::slice_of(codec::option_of(codec::map_of(codec::Str, codec::slice_of(codec::U32))));
+        let value = vec![
+            Some(HashMap::from([("a".to_string(), vec![1, 2]), ("b".to_string(), vec![])])),
+            None,
+            Some(HashMap::new()),
+        ];
+        round_trip(&nested, &value);
+        round_trip(&codec::map_of(codec::U32, codec::F32), &HashMap::from([(1, 0.5), (2, 1.5)]));
+    }
+
+    #[test]
+    fn test_codec_matches_free_functions() {
+        let value = vec!["x".to_string(), "yz".to_string()];
+        let c = codec::slice_of(codec::Str);
+        let mut from_codec = vec![0; c.size(&value)];
+        c.marshal(&value, &mut from_codec.as_mut_slice()).unwrap();
+
+        let mut from_functions = vec![0; size_slice(&value, |s| size_string(s))];
+        marshal_slice(&value, &mut from_functions.as_mut_slice(), |s, w| marshal_string(s, w)).unwrap();
+        assert_eq!(from_codec, from_functions);
+        assert_eq!(c.skip(&mut &from_codec[..3]), Err(Error::BufferTooSmall { needed: 1, available: 0 }));
+    }
+}
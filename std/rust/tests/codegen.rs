@@ -0,0 +1,122 @@
+#![cfg(feature = "codegen")]
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use benc::codegen;
+    use benc::*;
+    use chrono::{DateTime, Utc};
+
+    benc_struct! {
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+    }
+
+    benc_struct! {
+        struct Event<'a> {
+            id: u64,
+            seq: usize,
+            user_name: String,
+            payload: Vec<u8>,
+            raw: &'a [u8],
+            at: DateTime<Utc>,
+            tags: HashMap<String, Option<i64>>,
+            path: Vec<Point>,
+            origin: Option<Point>,
+        }
+    }
+
+    #[test]
+    fn test_typescript() {
+        let point = schema_of::<Point>().unwrap();
+        let event = schema_of::<Event>().unwrap();
+        let ts = codegen::typescript("./std", &[("Event", &event), ("Point", &point)]);
+
+        assert!(ts.starts_with("// Code generated by benc. DO NOT EDIT.\n\nimport * as bstd from \"./std\";\n"));
+        // The nested struct equal to `Point` takes its name and is generated first.
+        assert!(!ts.contains("EventPath") && !ts.contains("EventOrigin"));
+        assert!(ts.find("export interface Point {").unwrap() < ts.find("export interface Event {").unwrap());
+        assert_eq!(ts.matches("export interface").count(), 2);
+
+        for line in [
+            "  id: bigint;",
+            "  seq: number | bigint;",
+            "  payload: Array<number>;",
+            "  raw: Uint8Array;",
+            "  tags: Map<string, bigint | null>;",
+            "  origin: Point | null;",
+            "  return bstd.sizeInt32()\n    + bstd.sizeInt32();",
+            "    + bstd.sizeMap(v.tags, (v0) => bstd.sizeString(v0), (w0) => bstd.sizePointer(w0, (v1) => bstd.sizeInt64()))",
+            "  n = bstd.marshalUint(n, b, v.seq);",
+            "  n = bstd.marshalSlice(n, b, v.path, marshalPoint);",
+            "  n = bstd.marshalMap(n, b, v.tags, bstd.marshalString, \
+             (n: number, b: Uint8Array, v0: bigint | null) => bstd.marshalPointer(n, b, v0, bstd.marshalInt64));",
+            "  [n, v.raw] = bstd.unmarshalBytesCopied(n, b);",
+            "  [n, v.origin] = bstd.unmarshalPointer(n, b, unmarshalPoint);",
+            "  n = bstd.skipVarint(n, b);",
+            "  n = bstd.skipMap(n, b, bstd.skipString, (n: number, b: Uint8Array) => bstd.skipPointer(n, b, bstd.skipInt64));",
+            "export function encodeEvent(v: Event): Uint8Array {",
+            "export function decodeEvent(b: Uint8Array): Event {",
+        ] {
+            assert!(ts.contains(line), "missing {line:?} in\n{ts}");
+        }
+    }
+
+    #[test]
+    fn test_go() {
+        let point = schema_of::<Point>().unwrap();
+        let event = schema_of::<Event>().unwrap();
+        let go = codegen::go("events", &[("Event", &event), ("Point", &point)]);
+
+        assert!(go.starts_with(
+            "// Code generated by benc. DO NOT EDIT.\n\npackage events\n\nimport (\n\t\"time\"\n\n\
+             \tbstd \"github.com/banditmoscow1337/benc/std/golang\"\n)\n"
+        ));
+        assert!(go.find("type Point struct {").unwrap() < go.find("type Event struct {").unwrap());
+        assert_eq!(go.matches(" struct {").count(), 2);
+
+        for line in [
+            "\tId uint64\n",
+            "\tSeq uint\n",
+            "\tUserName string\n",
+            "\tPayload []byte\n",
+            "\tAt time.Time\n",
+            "\tTags map[string]*int64\n",
+            "\tOrigin *Point\n",
+            "func (point *Point) Size() (s int) {\n\ts += bstd.SizeInt32()\n\ts += bstd.SizeInt32()\n\treturn\n}",
+            "\ts += bstd.SizeSlice(event.Payload, func(v0 byte) int { return bstd.SizeByte() })",
+            "\ts += bstd.SizeMap(event.Tags, func(v0 string) int { return bstd.SizeString(v0) }, \
+             func(w0 *int64) int { return bstd.SizePointer(w0, func(v1 int64) int { return bstd.SizeInt64() }) })",
+            "\ts += bstd.SizeSlice(event.Path, func(v0 Point) int { return v0.Size() })",
+            "\tn = bstd.MarshalUint(n, b, event.Seq)",
+            "\tn = bstd.MarshalSlice(n, b, event.Path, func(n int, b []byte, v0 Point) int { return v0.Marshal(n, b) })",
+            "\tn = bstd.MarshalMap(n, b, event.Tags, bstd.MarshalString, \
+             func(n int, b []byte, v0 *int64) int { return bstd.MarshalPointer(n, b, v0, bstd.MarshalInt64) })",
+            "\tif n, event.Raw, err = bstd.UnmarshalBytesCopied(n, b); err != nil {\n\t\treturn\n\t}",
+            "\tif n, event.Origin, err = bstd.UnmarshalPointer[Point](n, b, \
+             func(n int, b []byte, v *Point) (int, error) { return v.Unmarshal(n, b) }); err != nil {",
+            "\tif n, event.Tags, err = bstd.UnmarshalMap[string, *int64](n, b, bstd.UnmarshalString, \
+             func(n int, b []byte) (int, *int64, error) { return bstd.UnmarshalPointer[int64](n, b, bstd.UnmarshalInt64) }); err != nil {",
+        ] {
+            assert!(go.contains(line), "missing {line:?} in\n{go}");
+        }
+
+        // Without times, the time package is not imported.
+        assert!(!codegen::go("geo", &[("Point", &point)]).contains("\"time\""));
+    }
+
+    #[test]
+    fn test_nested_struct_names() {
+        let schema = Schema::new()
+            .field("user_info", Type::Struct(Schema::new().field("a", Type::U8)))
+            .field("index", Type::Map(Box::new(Type::String), Box::new(Type::Struct(Schema::new().field("b", Type::U8)))));
+        let ts = codegen::typescript("benc", &[("Doc", &schema)]);
+        assert!(ts.contains("export interface DocUserInfo {"));
+        assert!(ts.contains("export interface DocIndexValue {"));
+        assert!(ts.contains("  user_info: DocUserInfo;"));
+        assert!(ts.contains("  index: Map<string, DocIndexValue>;"));
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Trade {
+            id: u64,
+            symbol: String,
+            price: f64,
+            #[benc(skip)]
+            seen: bool,
+        }
+    }
+
+    fn trades() -> Vec<Trade> {
+        vec![
+            Trade { id: 1, symbol: "AAPL".into(), price: 189.5, seen: false },
+            Trade { id: 2, symbol: "MSFT".into(), price: 411.25, seen: false },
+            Trade { id: 300, symbol: "GOOG".into(), price: 141.0, seen: true },
+        ]
+    }
+
+    fn encode(records: &[Trade]) -> Vec<u8> {
+        let size = size_records(records);
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_records(records, &mut writer).unwrap();
+        assert!(writer.is_empty(), "marshal did not fill the buffer");
+        buf
+    }
+
+    #[test]
+    fn test_columns_round_trip() {
+        let records = trades();
+        let buf = encode(&records);
+
+        let mut skipper = buf.as_slice();
+        skip_columns(&mut skipper).unwrap();
+        assert!(skipper.is_empty());
+
+        let mut reader = buf.as_slice();
+        let batch = unmarshal_columns(&mut reader).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch.column_count(), 3);
+
+        let decoded: Vec<Trade> = batch.decode().unwrap();
+        let expected: Vec<Trade> = records.into_iter().map(|t| Trade { seen: false, ..t }).collect();
+        assert_eq!(decoded, expected);
+
+        // Hand-built columns decode the same way.
+        let decoded = batch
+            .decode_records(|cols| {
+                Ok(Trade {
+                    id: u64::unmarshal(&mut cols[0])?,
+                    symbol: unmarshal_string(&mut cols[1])?.to_string(),
+                    price: unmarshal_f64(&mut cols[2])?,
+                    seen: false,
+                })
+            })
+            .unwrap();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_columns_by_hand() {
+        let records = [(1u64, "a"), (300, "bc")];
+        let columns = [
+            Column::new(|r: &(u64, &str)| size_uint(r.0), |r, w| marshal_uint(r.0, w)),
+            Column::new(|r: &(u64, &str)| size_string(r.1), |r, w| marshal_string(r.1, w)),
+        ];
+        let mut buf = vec![0; size_columns(&records, &columns)];
+        marshal_columns(&records, &mut buf.as_mut_slice(), &columns).unwrap();
+        let batch = unmarshal_columns(&mut buf.as_slice()).unwrap();
+        assert_eq!(batch.decode_column(0, unmarshal_uint).unwrap(), [1, 300]);
+        assert_eq!(batch.decode_column(1, unmarshal_string).unwrap(), ["a", "bc"]);
+
+        // A marshaler that writes other than its sizer's count is rejected.
+        let columns = [Column::new(|_: &(u64, &str)| 1, |r, w| marshal_uint(r.0, w))];
+        let mut buf = vec![0; 64];
+        assert_eq!(marshal_columns(&records, &mut buf.as_mut_slice(), &columns).err(), Some(Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_columns_are_contiguous() {
+        let buf = encode(&trades());
+        let mut reader = buf.as_slice();
+        let batch = unmarshal_columns(&mut reader).unwrap();
+
+        // The price column is nothing but three packed f64 values.
+        assert_eq!(batch.column(2).unwrap().len(), 3 * size_f64());
+        let prices = batch.decode_column(2, unmarshal_f64).unwrap();
+        assert_eq!(prices, vec![189.5, 411.25, 141.0]);
+
+        let symbols = batch.decode_column(1, unmarshal_string).unwrap();
+        assert_eq!(symbols, vec!["AAPL", "MSFT", "GOOG"]);
+
+        assert_eq!(batch.column(3).err(), Some(Error::OutOfRange));
+    }
+
+    #[test]
+    fn test_columns_empty_batch() {
+        let buf = encode(&[]);
+        let mut reader = buf.as_slice();
+        let batch = unmarshal_columns(&mut reader).unwrap();
+        assert!(batch.is_empty());
+        assert_eq!(batch.column_count(), 3);
+        assert!(batch.decode_column(0, u64::unmarshal).unwrap().is_empty());
+        assert!(batch.decode::<Trade>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_columns_errors() {
+        let buf = encode(&trades());
+
+        // A record reader that ignores a column leaves trailing bytes behind.
+        let mut reader = buf.as_slice();
+        let batch = unmarshal_columns(&mut reader).unwrap();
+        let result = batch.decode_records(|cols| u64::unmarshal(&mut cols[0]));
+        assert_eq!(result.err(), Some(Error::TrailingBytes));
+
+        // Too few columns for the struct.
+        let ids = [Column::new(|t: &Trade| t.id.size(), |t, w| t.id.marshal(w))];
+        let mut short = vec![0; size_columns(&trades(), &ids)];
+        marshal_columns(&trades(), &mut short.as_mut_slice(), &ids).unwrap();
+        let batch = unmarshal_columns(&mut short.as_slice()).unwrap();
+        assert_eq!(batch.decode::<Trade>().err(), Some(Error::InvalidValue));
+
+        // A record count beyond the column data is rejected up front.
+        let mut forged = vec![0u8; size_uint(u64::MAX)];
+        marshal_uint(u64::MAX, &mut forged.as_mut_slice()).unwrap();
+        // One empty column, then the terminator.
+        forged.extend_from_slice(&[1, 0, 1, 1, 1, 1]);
+        let batch = unmarshal_columns(&mut forged.as_slice()).unwrap();
+        assert_eq!(batch.decode_records(|cols| <()>::unmarshal(&mut cols[0])).err(), Some(Error::InvalidValue));
+        assert_eq!(batch.decode_column(0, <()>::unmarshal).err(), Some(Error::InvalidValue));
+
+        let mut corrupted = buf.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] = 2;
+        assert_eq!(unmarshal_columns(&mut corrupted.as_slice()).err(), Some(Error::MissingTerminator));
+        assert_eq!(skip_columns(&mut corrupted.as_slice()).err(), Some(Error::MissingTerminator));
+
+        let truncated = &buf[..buf.len() - 1];
+        assert_eq!(unmarshal_columns(&mut &truncated[..]).err(), Some(Error::BufferTooSmall { needed: 4, available: 3 }));
+    }
+}
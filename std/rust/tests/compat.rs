@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use benc::Error;
+    use benc::compat::*;
+
+    #[test]
+    fn test_compat_primitives() {
+        let size = size_bool() + size_u16() + size_int(-300) + size_f64() + size_string("hello") + size_bytes(&[1, 2]);
+        let mut buf = vec![0u8; size];
+        let mut n = marshal_bool(0, &mut buf, true).unwrap();
+        n = marshal_u16(n, &mut buf, 513).unwrap();
+        n = marshal_int(n, &mut buf, -300).unwrap();
+        n = marshal_f64(n, &mut buf, 1.5).unwrap();
+        n = marshal_string(n, &mut buf, "hello").unwrap();
+        n = marshal_bytes(n, &mut buf, &[1, 2]).unwrap();
+        assert_eq!(n, size);
+
+        // The bytes match the cursor API.
+        let mut expected = vec![0u8; size];
+        let mut writer = expected.as_mut_slice();
+        benc::marshal_bool(true, &mut writer).unwrap();
+        benc::marshal_u16(513, &mut writer).unwrap();
+        benc::marshal_int(-300, &mut writer).unwrap();
+        benc::marshal_f64(1.5, &mut writer).unwrap();
+        benc::marshal_string("hello", &mut writer).unwrap();
+        benc::marshal_bytes(&[1, 2], &mut writer).unwrap();
+        assert_eq!(buf, expected);
+
+        let (n, b) = unmarshal_bool(0, &buf).unwrap();
+        let (n, u) = unmarshal_u16(n, &buf).unwrap();
+        let (n, i) = unmarshal_int(n, &buf).unwrap();
+        let (n, f) = unmarshal_f64(n, &buf).unwrap();
+        let (n, s) = unmarshal_string(n, &buf).unwrap();
+        let (n, bs) = unmarshal_bytes_cropped(n, &buf).unwrap();
+        assert_eq!((n, b, u, i, f, s, bs), (size, true, 513, -300, 1.5, "hello", &[1u8, 2][..]));
+
+        let n = skip_f64(skip_int(skip_u16(skip_bool(0, &buf).unwrap(), &buf).unwrap(), &buf).unwrap(), &buf).unwrap();
+        assert_eq!(skip_bytes(skip_string(n, &buf).unwrap(), &buf).unwrap(), size);
+    }
+
+    #[test]
+    fn test_compat_collections() {
+        let values = vec!["a".to_string(), "bc".to_string()];
+        let size = size_slice(&values, |s| size_string(s));
+        let mut buf = vec![0u8; 1 + size];
+        let n = marshal_slice(1, &mut buf, &values, |n, b, s| marshal_string(n, b, s)).unwrap();
+        assert_eq!(n, buf.len());
+        let (n, decoded) = unmarshal_slice(1, &buf, |n, b| unmarshal_string(n, b).map(|(n, s)| (n, s.to_string()))).unwrap();
+        assert_eq!((n, decoded), (buf.len(), values));
+        assert_eq!(skip_slice(1, &buf, skip_string).unwrap(), buf.len());
+
+        let map = HashMap::from([(1u32, true), (2, false)]);
+        let mut buf = vec![0u8; size_map(&map, |_| size_u32(), |_| size_bool())];
+        let n = marshal_map(0, &mut buf, &map, |n, b, k| marshal_u32(n, b, *k), |n, b, v| marshal_bool(n, b, *v)).unwrap();
+        assert_eq!(n, buf.len());
+        let (n, decoded) = unmarshal_map(0, &buf, unmarshal_u32, unmarshal_bool).unwrap();
+        assert_eq!((n, decoded), (buf.len(), map));
+        assert_eq!(skip_map(0, &buf, skip_u32, skip_bool).unwrap(), buf.len());
+    }
+
+    #[test]
+    fn test_compat_errors() {
+        let mut buf = [0u8; 3];
+        assert!(matches!(marshal_u32(0, &mut buf, 1), Err(Error::BufferTooSmall { .. })));
+        assert!(matches!(marshal_u8(4, &mut buf, 1), Err(Error::BufferTooSmall { .. })));
+        assert!(matches!(unmarshal_u16(2, &buf), Err(Error::BufferTooSmall { .. })));
+        assert!(matches!(skip_u8(5, &buf), Err(Error::BufferTooSmall { .. })));
+        assert_eq!(marshal_u8(2, &mut buf, 9).unwrap(), 3);
+        assert_eq!(unmarshal_u8(2, &buf).unwrap(), (3, 9));
+    }
+}
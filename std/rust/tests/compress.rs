@@ -0,0 +1,64 @@
+#![cfg(feature = "zstd")]
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use benc::*;
+
+    fn message(i: u64) -> Vec<u8> {
+        let method = ["GET", "POST", "PUT"][i as usize % 3];
+        let route = format!("/api/v2/accounts/{}/transactions", i % 97);
+        let size = size_u64() + size_string(method) + size_string(&route) + size_string("ok");
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_u64(i, &mut writer).unwrap();
+        marshal_string(method, &mut writer).unwrap();
+        marshal_string(&route, &mut writer).unwrap();
+        marshal_string("ok", &mut writer).unwrap();
+        buf
+    }
+
+    fn inner(err: std::io::Error) -> Error {
+        err.into_inner().unwrap().downcast::<Error>().map(|e| *e).unwrap()
+    }
+
+    #[test]
+    fn test_compress_without_dict() {
+        let data = b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".repeat(10);
+        let envelope = compress(&data, None, 3).unwrap();
+        assert!(envelope.len() < data.len());
+        assert_eq!(decompress(&envelope, &[], 1 << 20).unwrap(), data);
+    }
+
+    #[test]
+    fn test_compress_with_dict() {
+        let samples: Vec<Vec<u8>> = (0..2000).map(message).collect();
+        let dict = train_dict(&samples, 4096).unwrap();
+        assert_ne!(dict.id(), 0);
+        let reloaded = Dictionary::new(dict.as_bytes().to_vec()).unwrap();
+        assert_eq!(reloaded, dict);
+
+        let msg = message(123_456);
+        let plain = compress(&msg, None, 3).unwrap();
+        let with_dict = compress(&msg, Some(&dict), 3).unwrap();
+        assert!(with_dict.len() < plain.len(), "{} >= {}", with_dict.len(), plain.len());
+        assert_eq!(decompress(&with_dict, &[reloaded], 1024).unwrap(), msg);
+
+        let err = decompress(&with_dict, &[], 1024).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(inner(err), Error::InvalidValue);
+    }
+
+    #[test]
+    fn test_decompress_limits() {
+        let data = vec![7u8; 4096];
+        let envelope = compress(&data, None, 1).unwrap();
+        assert_eq!(inner(decompress(&envelope, &[], 4095).unwrap_err()), Error::OutOfRange);
+
+        let mut trailing = envelope.clone();
+        trailing.push(0);
+        assert_eq!(inner(decompress(&trailing, &[], 4096).unwrap_err()), Error::TrailingBytes);
+        assert!(Dictionary::new(vec![1, 2, 3]).is_err());
+    }
+}
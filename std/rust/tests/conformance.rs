@@ -0,0 +1,66 @@
+#![cfg(feature = "testing")]
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use benc::conformance::*;
+    use benc::*;
+
+    #[test]
+    fn test_vectors_pass() {
+        let vectors = vectors();
+        for vector in &vectors {
+            check(vector).unwrap();
+        }
+        let names: HashSet<_> = vectors.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names.len(), vectors.len());
+        assert!(vectors.iter().any(|v| v.expected.is_err()));
+    }
+
+    #[test]
+    fn test_vectors_boundaries() {
+        let vectors = vectors();
+        let find = |name: &str| vectors.iter().find(|v| v.name == name).unwrap();
+        assert_eq!(find("uint 2^7-1").encoded, [0x7f]);
+        assert_eq!(find("uint 2^7").encoded, [0x80, 0x01]);
+        assert_eq!(find("uint max").encoded.len(), 10);
+        assert_eq!(find("slice of terminator bytes").encoded, [4, 1, 1, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(find("string 16384 bytes").encoded[..3], [0x80, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn test_check_reports_mismatch() {
+        let mut vector = vectors().remove(0);
+        vector.encoded = vec![1];
+        assert!(check(&vector).unwrap_err().contains("uint 0"));
+        vector.expected = Err(Error::MissingTerminator);
+        assert!(check(&vector).is_err());
+    }
+
+    #[test]
+    fn test_manifest() {
+        let manifest = manifest(&vectors());
+        assert!(manifest.starts_with("{\"format\":\"benc-conformance\",\"version\":1,\"vectors\":[\n"));
+        assert!(manifest.ends_with("]}\n"));
+        assert!(manifest.contains("{\"name\":\"uint max\",\"type\":\"uint\",\"hex\":\"ffffffffffffffffff01\",\"value\":\"18446744073709551615\"}"));
+        assert!(manifest.contains("\"type\":\"f32\",\"hex\":\"0000c07f\",\"value\":\"0x7fc00000\"}"));
+        assert!(manifest.contains("{\"name\":\"string nul\",\"type\":\"string\",\"hex\":\"0100\",\"value\":\"\\u0000\"}"));
+        assert!(manifest.contains("\"type\":\"option<u8>\",\"hex\":\"00\",\"value\":null}"));
+        assert!(manifest.contains(
+            "\"type\":\"struct{id:u8,inner:struct{list:slice<u8>,map:map<uint,string>}}\",\"hex\":\"00000101010100010101 01\""
+                .replace(' ', "")
+                .as_str()
+        ));
+        assert!(manifest.contains("{\"name\":\"slice missing terminator\",\"type\":\"slice<u8>\",\"hex\":\"010500000000\",\"error\":\"MissingTerminator\"}"));
+        assert_eq!(manifest.lines().count(), vectors().len() + 2);
+    }
+
+    #[test]
+    fn test_write_manifest() {
+        let path = std::env::temp_dir().join(format!("benc-conformance-{}/vectors.json", std::process::id()));
+        write_manifest(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), manifest(&vectors()));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Config<'a> {
+            port: u16,
+            ratio: f32,
+            retries: usize,
+            offset: isize,
+            verbose: bool,
+            name: &'a str,
+            key: &'a [u8],
+        }
+    }
+
+    const NAME: &str = "edge-1";
+
+    static CONFIG: [u8; 22] = ConstEncoder::new()
+        .u16(8080)
+        .f32(0.5)
+        .usize(300)
+        .isize(-2)
+        .bool(true)
+        .str(NAME)
+        .bytes(&[0xde, 0xad, 0xbe, 0xef])
+        .finish();
+
+    #[test]
+    fn test_const_encoder() {
+        let config: Config = from_slice(&CONFIG).unwrap();
+        let expected = Config { port: 8080, ratio: 0.5, retries: 300, offset: -2, verbose: true, name: NAME, key: &[0xde, 0xad, 0xbe, 0xef] };
+        assert_eq!(config, expected);
+        assert_eq!(CONFIG.to_vec(), expected.to_vec());
+    }
+
+    #[test]
+    fn test_const_marshal_fns() {
+        const BUF: [u8; 11] = {
+            let mut buf = [0u8; 11];
+            let mut writer = buf.as_mut_slice();
+            // A `Result` cannot be dropped in a `const` context.
+            let result = marshal_u64(u64::MAX, &mut writer);
+            assert!(result.is_ok());
+            std::mem::forget(result);
+            let result = marshal_uint(300, &mut writer);
+            assert!(result.is_ok());
+            std::mem::forget(result);
+            let result = marshal_i8(-1, &mut writer);
+            assert!(result.is_ok());
+            std::mem::forget(result);
+            buf
+        };
+        assert_eq!(BUF, [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xac, 0x02, 0xff]);
+        const SIZE: usize = size_uint(300) + size_int(-65);
+        assert_eq!(SIZE, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "the message is larger than the ConstEncoder array")]
+    fn test_const_encoder_overflow() {
+        let _ = ConstEncoder::<3>::new().u16(1).u16(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "the message is smaller than the ConstEncoder array")]
+    fn test_const_encoder_short() {
+        let _ = ConstEncoder::<3>::new().u16(1).finish();
+    }
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "sha2")]
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Order {
+            id: u64,
+            items: Vec<String>,
+        }
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let hash = content_hash(&0u8);
+        assert_eq!(hash[..4], [0x6e, 0x34, 0x0b, 0x9c]);
+        assert_eq!(content_hash_u64(&0u8), u64::from_le_bytes(hash[..8].try_into().unwrap()));
+
+        let order = Order { id: 7, items: vec!["tea".into(), "milk".into()] };
+        let same = Order { id: 7, items: vec!["tea".into(), "milk".into()] };
+        let other = Order { id: 7, items: vec!["milk".into(), "tea".into()] };
+        assert_eq!(content_hash(&order), content_hash(&same));
+        assert_ne!(content_hash(&order), content_hash(&other));
+
+        let seen: HashSet<_> = [&order, &same, &other].into_iter().map(content_hash_u64).collect();
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn test_content_hash_maps() {
+        let forward: HashMap<u32, u32> = (0..50).map(|i| (i, i * 2)).collect();
+        let backward: HashMap<u32, u32> = (0..50).rev().map(|i| (i, i * 2)).collect();
+        assert_eq!(content_hash(&forward), content_hash(&backward));
+    }
+
+    #[test]
+    fn test_encoded_hash() {
+        let order = Order { id: 1, items: vec!["bread".into()] };
+        let encoded: Encoded<Order> = Encoded::new(order.to_vec());
+        assert_eq!(encoded.hash(), content_hash(&order));
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Job {
+            id: u32,
+            name: String,
+        }
+    }
+
+    fn jobs(n: u32) -> Vec<Job> {
+        (0..n).map(|id| Job { id, name: "j".repeat(id as usize * 13 % 3000) }).collect()
+    }
+
+    fn frames(jobs: &[Job]) -> Vec<u8> {
+        let mut writer = BencLinesWriter::new(Vec::new());
+        for job in jobs {
+            writer.write(job).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    /// A reader returning at most three bytes per call, interrupted every other call.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        calls: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(2) {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            let n = buf.len().min(3).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_decode_iter() {
+        let jobs = jobs(50);
+        let buf = frames(&jobs);
+        let decoded: Vec<Job> = DecodeIter::new(buf.as_slice()).collect::<io::Result<_>>().unwrap();
+        assert_eq!(decoded, jobs);
+
+        let decoded: Vec<Job> = DecodeIter::new(Trickle { data: &buf, calls: 0 }).collect::<io::Result<_>>().unwrap();
+        assert_eq!(decoded, jobs);
+
+        assert!(DecodeIter::<_, Job>::new(io::empty()).next().is_none());
+    }
+
+    #[test]
+    fn test_decode_iter_errors() {
+        let jobs = jobs(3);
+        let mut buf = frames(&jobs);
+
+        // A frame that does not decode is skipped.
+        let mut bad = vec![2, 0xff, 0xff];
+        bad.extend(frames(&jobs[..1]));
+        let results: Vec<_> = DecodeIter::<_, Job>::new(bad.as_slice()).collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(results[1].as_ref().unwrap(), &jobs[0]);
+
+        // A truncated frame ends the iterator with an error.
+        buf.pop();
+        let results: Vec<_> = DecodeIter::<_, Job>::new(buf.as_slice()).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2].as_ref().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        // So does a frame over the limit.
+        let mut iter = DecodeIter::<_, Job>::new(buf.as_slice()).with_max_frame_len(10);
+        assert!(iter.next().unwrap().is_ok());
+        assert_eq!(iter.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(iter.next().is_none());
+    }
+}
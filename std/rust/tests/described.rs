@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Record {
+            id: u64,
+            tags: Vec<String>,
+            note: Option<String>,
+        }
+    }
+
+    fn schema() -> Schema {
+        Schema::new()
+            .field("id", Type::U64)
+            .field("tags", Type::Slice(Box::new(Type::String)))
+            .sensitive_field("note", Type::Option(Box::new(Type::String)))
+    }
+
+    #[test]
+    fn test_self_describing_round_trip() {
+        let record = Record { id: 9, tags: vec!["x".into()], note: None };
+        let buf = to_vec_self_describing(&schema(), &record);
+        assert_eq!(buf[0], SCHEMA_HEADER_VERSION);
+        assert_eq!(&buf[size_schema_header(&schema())..], record.to_vec());
+
+        let (decoded, value) = value_from_self_describing(&buf).unwrap();
+        assert_eq!(decoded, schema());
+        assert!(decoded.fields()[2].sensitive);
+        assert_eq!(value.field("id"), Some(&Value::U64(9)));
+        assert_eq!(value.field("tags"), Some(&Value::Slice(vec![Value::String("x".into())])));
+        assert_eq!(value.field("note"), Some(&Value::Option(None)));
+    }
+
+    #[test]
+    fn test_self_describing_unterminated() {
+        let mut buf = vec![0; size_schema_header(&schema())];
+        marshal_schema_header_with_layout(&schema(), Layout::Unterminated, &mut buf.as_mut_slice()).unwrap();
+        assert_eq!(buf[0], UNTERMINATED_SCHEMA_HEADER_VERSION);
+        assert_eq!(unmarshal_schema_header(&mut buf.as_slice()), Err(Error::InvalidValue));
+        assert_eq!(
+            unmarshal_schema_header_with_layout(&mut buf.as_slice()).unwrap(),
+            (schema(), Layout::Unterminated)
+        );
+
+        // The message of an old producer: the tags end without a terminator.
+        buf.extend_from_slice(&9u64.to_le_bytes());
+        buf.extend_from_slice(&[1, 1, b'x', 0]);
+        let (_, value) = value_from_self_describing(&buf).unwrap();
+        assert_eq!(value.field("tags"), Some(&Value::Slice(vec![Value::String("x".into())])));
+        assert_eq!(value.field("note"), Some(&Value::Option(None)));
+    }
+
+    #[test]
+    fn test_self_describing_empty_structs() {
+        let schema = Schema::new().field("items", Type::Slice(Box::new(Type::Struct(Schema::new()))));
+        let mut buf = vec![0; size_schema_header(&schema)];
+        marshal_schema_header(&schema, &mut buf.as_mut_slice()).unwrap();
+        let mut message = buf.clone();
+        message.extend_from_slice(&[2, 1, 1, 1, 1]);
+        let (_, value) = value_from_self_describing(&message).unwrap();
+        assert_eq!(value.field("items"), Some(&Value::Slice(vec![Value::Struct(vec![]); 2])));
+
+        // Structs without fields occupy no bytes, so a forged count is not backed by the
+        // message and is rejected in either layout.
+        let count = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        let mut forged = buf.clone();
+        forged.extend_from_slice(&count);
+        forged.extend_from_slice(&[1, 1, 1, 1]);
+        assert_eq!(value_from_self_describing(&forged).err(), Some(Error::InvalidValue));
+
+        let mut forged = vec![0; size_schema_header(&schema)];
+        marshal_schema_header_with_layout(&schema, Layout::Unterminated, &mut forged.as_mut_slice()).unwrap();
+        forged.extend_from_slice(&count);
+        assert_eq!(value_from_self_describing(&forged).err(), Some(Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_schema_header_nested_types() {
+        let inner = Schema::new().field("at", Type::Time).field("raw", Type::Bytes);
+        let schema = Schema::new()
+            .field("m", Type::Map(Box::new(Type::Int), Box::new(Type::Slice(Box::new(Type::F32)))))
+            .field("s", Type::Struct(inner));
+        let mut buf = vec![0; size_schema_header(&schema)];
+        marshal_schema_header(&schema, &mut buf.as_mut_slice()).unwrap();
+        assert_eq!(unmarshal_schema_header(&mut buf.as_slice()).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_schema_header_errors() {
+        let mut buf = vec![0; size_schema_header(&schema())];
+        marshal_schema_header(&schema(), &mut buf.as_mut_slice()).unwrap();
+
+        let mut bad_version = buf.clone();
+        bad_version[0] = 2;
+        assert_eq!(unmarshal_schema_header(&mut bad_version.as_slice()), Err(Error::InvalidValue));
+
+        // The first field's type tag follows the version, count, name and flag.
+        let mut bad_tag = buf.clone();
+        bad_tag[6] = 200;
+        assert_eq!(unmarshal_schema_header(&mut bad_tag.as_slice()), Err(Error::InvalidValue));
+
+        // A thousand nested options are rejected instead of recursing.
+        let mut deep = vec![SCHEMA_HEADER_VERSION, 1, 1, b'f', 0];
+        deep.extend([18; 1000]);
+        deep.push(0);
+        deep.extend([1, 1, 1, 1]);
+        assert_eq!(unmarshal_schema_header(&mut deep.as_slice()), Err(Error::InvalidValue));
+    }
+}
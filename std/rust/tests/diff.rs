@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use std::collections::HashMap;
+
+    struct State {
+        id: u64,
+        name: String,
+        tags: Vec<String>,
+        scores: HashMap<String, i64>,
+        parent: Option<u32>,
+    }
+
+    fn schema() -> Schema {
+        Schema::new()
+            .field("id", Type::Uint)
+            .field("name", Type::String)
+            .field("tags", Type::Slice(Box::new(Type::String)))
+            .field("scores", Type::Map(Box::new(Type::String), Box::new(Type::Int)))
+            .field("parent", Type::Option(Box::new(Type::U32)))
+    }
+
+    fn encode(s: &State) -> Vec<u8> {
+        let size = size_uint(s.id)
+            + size_string(&s.name)
+            + size_slice(&s.tags, |t| size_string(t))
+            + size_map(&s.scores, |k| size_string(k), |v| size_int(*v))
+            + size_option(&s.parent, |_| size_u32());
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_uint(s.id, &mut writer).unwrap();
+        marshal_string(&s.name, &mut writer).unwrap();
+        marshal_slice(&s.tags, &mut writer, |t, w| marshal_string(t, w)).unwrap();
+        marshal_map(&s.scores, &mut writer, |k, w| marshal_string(k, w), |v, w| marshal_int(*v, w)).unwrap();
+        marshal_option(&s.parent, &mut writer, |v, w| marshal_u32(*v, w)).unwrap();
+        assert!(writer.is_empty());
+        buf
+    }
+
+    fn state() -> State {
+        State {
+            id: 1,
+            name: "node".into(),
+            tags: vec!["a".into(), "b".into()],
+            scores: HashMap::from([("x".to_string(), -5)]),
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn test_skip_schema() {
+        let buf = encode(&state());
+        let mut reader = buf.as_slice();
+        skip_schema(&mut reader, &schema()).unwrap();
+        assert!(reader.is_empty());
+
+        let nested = Type::Slice(Box::new(Type::Struct(schema())));
+        // A slice of two nested messages, assembled by hand.
+        let mut buf2 = vec![2u8];
+        buf2.extend_from_slice(&buf);
+        buf2.extend_from_slice(&buf);
+        buf2.extend_from_slice(&[1, 1, 1, 1]);
+        let mut reader = buf2.as_slice();
+        skip_type(&mut reader, &nested).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = state();
+        let mut new = state();
+        new.name = "renamed node".into();
+        new.parent = Some(7);
+
+        let old_buf = encode(&old);
+        let new_buf = encode(&new);
+        let changes = diff(&schema(), &old_buf, &new_buf).unwrap();
+        assert_eq!(changes.len(), 2);
+
+        assert_eq!(changes[0].index, 1);
+        assert_eq!(changes[0].old, 1..6);
+        assert_eq!(changes[0].new, 1..14);
+        let mut field = &new_buf[changes[0].new.clone()];
+        assert_eq!(unmarshal_string(&mut field).unwrap(), "renamed node");
+
+        assert_eq!(schema().fields()[changes[1].index].name, "parent");
+        assert_eq!(changes[1].new.end, new_buf.len());
+
+        assert!(diff(&schema(), &old_buf, &old_buf).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_diff_errors() {
+        let buf = encode(&state());
+        let mut longer = buf.clone();
+        longer.push(0);
+        assert_eq!(diff(&schema(), &buf, &longer).err(), Some(Error::TrailingBytes));
+        assert_eq!(diff(&schema(), &buf, &buf[..buf.len() - 1]).err(), Some(Error::BufferTooSmall { needed: 1, available: 0 }));
+    }
+
+    #[test]
+    fn test_patch_round_trip() {
+        let old = state();
+        let mut new = state();
+        new.tags.push("c".repeat(1000));
+        new.id = 99;
+
+        let old_buf = encode(&old);
+        let new_buf = encode(&new);
+        let patch = make_patch(&schema(), &old_buf, &new_buf).unwrap();
+        // Only the changed fields are shipped.
+        assert!(patch.len() < new_buf.len());
+        assert!(patch.len() > 1000);
+        assert_eq!(apply_patch(&schema(), &old_buf, &patch).unwrap(), new_buf);
+
+        // An empty patch is a no-op.
+        let patch = make_patch(&schema(), &old_buf, &old_buf).unwrap();
+        assert_eq!(patch, vec![0, 1, 1, 1, 1]);
+        assert_eq!(apply_patch(&schema(), &old_buf, &patch).unwrap(), old_buf);
+    }
+
+    #[test]
+    fn test_patch_errors() {
+        let old_buf = encode(&state());
+        let patch_with = |entries: &[(usize, &[u8])]| {
+            let mut patch = vec![entries.len() as u8];
+            for (index, bytes) in entries {
+                patch.push(*index as u8);
+                patch.push(bytes.len() as u8);
+                patch.extend_from_slice(bytes);
+            }
+            patch.extend_from_slice(&[1, 1, 1, 1]);
+            patch
+        };
+
+        // New id, applied by hand.
+        let patched = apply_patch(&schema(), &old_buf, &patch_with(&[(0, &[5])])).unwrap();
+        assert_eq!(unmarshal_uint(&mut patched.as_slice()).unwrap(), 5);
+
+        assert_eq!(apply_patch(&schema(), &old_buf, &patch_with(&[(9, &[5])])).err(), Some(Error::OutOfRange));
+        assert_eq!(apply_patch(&schema(), &old_buf, &patch_with(&[(1, &[0]), (0, &[5])])).err(), Some(Error::NonCanonical));
+        // A replacement id with a trailing byte is not a single varint.
+        assert_eq!(apply_patch(&schema(), &old_buf, &patch_with(&[(0, &[5, 5])])).err(), Some(Error::InvalidValue));
+    }
+}
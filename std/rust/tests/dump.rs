@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .field("id", Type::U32)
+            .field("user", Type::String)
+            .sensitive_field("password", Type::String)
+            .field("tags", Type::Slice(Box::new(Type::String)))
+            .field("note", Type::Option(Box::new(Type::Bytes)))
+    }
+
+    fn encode() -> Vec<u8> {
+        let tags = ["a", "b"];
+        let note: &[u8] = &[0xCA, 0xFE];
+        let size = size_u32()
+            + size_string("alice")
+            + size_string("hunter2")
+            + size_slice(&tags, |s| size_string(s))
+            + size_option(&Some(note), |b| size_bytes(b));
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_u32(7, &mut writer).unwrap();
+        marshal_string("alice", &mut writer).unwrap();
+        marshal_string("hunter2", &mut writer).unwrap();
+        marshal_slice(&tags, &mut writer, |s, w| marshal_string(s, w)).unwrap();
+        marshal_option(&Some(note), &mut writer, |b, w| marshal_bytes(b, w)).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_dump() {
+        let expected = "{\n  id: 7,\n  user: \"alice\",\n  password: <redacted>,\n  tags: [\n    \"a\",\n    \"b\"\n  ],\n  note: Some(0xcafe)\n}";
+        assert_eq!(dump(&schema(), &encode()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_explain() {
+        let out = explain(&schema(), &encode()).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "0000..0004  id: u32 = 7  [07 00 00 00]");
+        assert_eq!(lines[2], "000a..0012  password: string = <redacted>");
+        assert_eq!(lines[3], "0012..001b  tags: [string] = [\"a\", \"b\"]  [02 01 61 01 62 01 01 01 01]");
+        assert!(!out.contains("hunter2") && !out.contains("68 75 6e"), "sensitive value leaked");
+    }
+
+    #[test]
+    fn test_dump_errors() {
+        let mut buf = encode();
+        buf.push(0);
+        assert_eq!(dump(&schema(), &buf), Err(Error::TrailingBytes));
+        assert_eq!(explain(&schema(), &buf), Err(Error::TrailingBytes));
+        assert_eq!(dump(&schema(), &buf[..3]), Err(Error::BufferTooSmall { needed: 4, available: 3 }));
+    }
+}
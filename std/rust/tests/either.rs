@@ -0,0 +1,49 @@
+#![cfg(feature = "either")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use either::Either;
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Step {
+            input: Either<u32, String>,
+            output: Option<Either<u8, Vec<u16>>>,
+        }
+    }
+
+    #[test]
+    fn test_either_round_trip() {
+        for v in [Either::Left(7u32), Either::Right("x".to_string())] {
+            let size = size_either(&v, |_| size_u32(), |s| size_string(s));
+            let mut buf = vec![0; size];
+            marshal_either(&v, &mut buf.as_mut_slice(), |l, w| marshal_u32(*l, w), |r, w| marshal_string(r, w)).unwrap();
+            let tag = if v.is_left() { 0 } else { 1 };
+            assert_eq!(buf[0], tag);
+
+            let mut reader = buf.as_slice();
+            let decoded = unmarshal_either(&mut reader, unmarshal_u32, |r| unmarshal_string(r).map(str::to_owned));
+            assert_eq!(decoded.unwrap(), v);
+            assert!(reader.is_empty());
+            let mut reader = buf.as_slice();
+            skip_either(&mut reader, skip_u32, skip_string).unwrap();
+            assert!(reader.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_either_in_struct() {
+        let step = Step { input: Either::Right("a".into()), output: Some(Either::Right(vec![1, 2])) };
+        let buf = step.to_vec();
+        assert_eq!(buf.len(), step.size());
+        assert_eq!(from_slice::<Step>(&buf).unwrap(), step);
+        assert_eq!(Either::<u8, u8>::Left(3).to_vec(), [0, 3]);
+    }
+
+    #[test]
+    fn test_either_invalid_tag() {
+        assert_eq!(from_slice::<Either<u8, u8>>(&[2, 0]), Err(Error::InvalidValue));
+        assert_eq!(skip_either(&mut &[2u8, 0][..], skip_u8, skip_u8), Err(Error::InvalidValue));
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Event {
+        id: u64,
+        source: String,
+        payload: Vec<u8>,
+    }
+
+    fn schema() -> Schema {
+        Schema::new()
+            .field("id", Type::U64)
+            .field("source", Type::String)
+            .field("payload", Type::Bytes)
+    }
+
+    fn size_event(e: &Event) -> usize {
+        size_u64() + size_string(&e.source) + size_bytes(&e.payload)
+    }
+
+    fn marshal_event(e: &Event, writer: &mut &mut [u8]) -> Result<()> {
+        marshal_u64(e.id, writer)?;
+        marshal_string(&e.source, writer)?;
+        marshal_bytes(&e.payload, writer)
+    }
+
+    fn unmarshal_event(reader: &mut &[u8]) -> Result<Event> {
+        Ok(Event {
+            id: unmarshal_u64(reader)?,
+            source: unmarshal_string(reader)?.to_owned(),
+            payload: unmarshal_bytes_copied(reader)?,
+        })
+    }
+
+    fn event() -> Event {
+        Event { id: 9, source: "sensor-4".into(), payload: vec![1, 2, 3] }
+    }
+
+    #[test]
+    fn test_encoded_round_trip() {
+        let e = event();
+        let encoded: Encoded<Event> = Encoded::encode(size_event(&e), |w| marshal_event(&e, w)).unwrap();
+        assert_eq!(encoded.as_bytes().len(), size_event(&e));
+        assert_eq!(encoded.decode(unmarshal_event).unwrap(), e);
+
+        let copy = encoded.clone();
+        assert_eq!(copy, encoded);
+        let bytes = encoded.into_inner();
+        let rewrapped: Encoded<Event, &[u8]> = Encoded::new(&bytes[..]);
+        assert_eq!(rewrapped.decode(unmarshal_event).unwrap(), e);
+    }
+
+    #[test]
+    fn test_encoded_decode_field() {
+        let e = event();
+        let encoded: Encoded<Event> = Encoded::encode(size_event(&e), |w| marshal_event(&e, w)).unwrap();
+        let schema = schema();
+        assert_eq!(encoded.decode_field(&schema, "id", unmarshal_u64).unwrap(), 9);
+        assert_eq!(encoded.decode_field(&schema, "source", unmarshal_string).unwrap(), "sensor-4");
+        assert_eq!(encoded.decode_field(&schema, "payload", unmarshal_bytes_cropped).unwrap(), &[1, 2, 3]);
+        assert_eq!(encoded.decode_field(&schema, "missing", unmarshal_u64), Err(Error::OutOfRange));
+    }
+
+    #[test]
+    fn test_encoded_trailing_bytes() {
+        let mut bytes = Encoded::<Event>::encode(size_event(&event()), |w| marshal_event(&event(), w))
+            .unwrap()
+            .into_inner();
+        bytes.push(0);
+        let encoded: Encoded<Event> = Encoded::new(bytes);
+        assert_eq!(encoded.decode(unmarshal_event), Err(Error::TrailingBytes));
+    }
+}
@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::io::{BufReader, ErrorKind, Write};
+
+    use benc::*;
+
+    #[test]
+    fn test_escaped_round_trip() {
+        let payloads: [&[u8]; 4] = [b"", b"plain", &[0xC0, 1, 0xDB, 0xDB, 0xC0], &[0xDC, 0xDD]];
+        let size: usize = payloads.iter().map(|p| size_escaped(p)).sum();
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        for p in payloads {
+            marshal_escaped(p, &mut writer).unwrap();
+        }
+        assert!(writer.is_empty());
+
+        let mut reader = buf.as_slice();
+        for p in payloads {
+            assert_eq!(unmarshal_escaped(&mut reader).unwrap(), p);
+        }
+        assert!(reader.is_empty());
+
+        let mut reader = buf.as_slice();
+        assert!(matches!(unmarshal_escaped(&mut reader).unwrap(), Cow::Borrowed(b"")));
+        assert!(matches!(unmarshal_escaped(&mut reader).unwrap(), Cow::Borrowed(b"plain")));
+        skip_escaped(&mut reader).unwrap();
+        assert_eq!(unmarshal_escaped(&mut reader).unwrap(), &[0xDC, 0xDD][..]);
+    }
+
+    #[test]
+    fn test_escaped_writer() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut writer = EscapedWriter::new(Vec::new());
+        for chunk in data.chunks(7) {
+            writer.write_all(chunk).unwrap();
+        }
+        let stream = writer.finish().unwrap();
+
+        let mut expected = vec![0; size_escaped(&data)];
+        marshal_escaped(&data, &mut expected.as_mut_slice()).unwrap();
+        assert_eq!(stream, expected);
+
+        let mut source = BufReader::new(stream.as_slice());
+        assert_eq!(read_escaped(&mut source).unwrap(), data);
+        assert_eq!(read_escaped(&mut source).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_escaped_errors() {
+        assert_eq!(unmarshal_escaped(&mut &b"no end"[..]), Err(Error::BufferTooSmall { needed: 7, available: 6 }));
+        assert_eq!(unmarshal_escaped(&mut &[1, 0xDB, 0x00, 0xC0][..]), Err(Error::InvalidValue));
+        assert_eq!(unmarshal_escaped(&mut &[1, 0xDB, 0xC0][..]), Err(Error::InvalidValue));
+        let err = read_escaped(&mut &[0xDB, 7, 0xC0][..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
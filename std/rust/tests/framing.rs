@@ -0,0 +1,214 @@
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use benc::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let mut encoder = FrameEncoder::new();
+        assert!(encoder.is_empty());
+        encoder.encode("hello").unwrap();
+        encoder.encode_bytes(&[1, 2, 3]);
+        encoder.encode(&7u16).unwrap();
+        let wire = encoder.pending().to_vec();
+        assert_eq!(&wire[..7], [6, 5, b'h', b'e', b'l', b'l', b'o']);
+
+        // Bytes arrive one at a time, as a worst-case transport would deliver them.
+        let mut decoder = FrameDecoder::new();
+        let mut frames = Vec::new();
+        for &b in &wire {
+            decoder.feed(&[b]);
+            while let Some(frame) = decoder.next_frame().unwrap() {
+                frames.push(frame.to_vec());
+            }
+        }
+        assert_eq!(frames, [b"\x05hello".to_vec(), vec![1, 2, 3], vec![7, 0]]);
+        decoder.finish().unwrap();
+
+        encoder.consume(4);
+        assert_eq!(encoder.pending(), &wire[4..]);
+        encoder.consume(wire.len() - 4);
+        assert!(encoder.is_empty());
+    }
+
+    #[test]
+    fn test_frame_decoder_read_buf() {
+        let mut decoder = FrameDecoder::new().with_max_frame_len(4);
+        let buf = decoder.read_buf(16);
+        buf[..4].copy_from_slice(&[3, 9, 9, 9]);
+        decoder.commit(4);
+        assert_eq!(decoder.next_frame().unwrap(), Some(&[9, 9, 9][..]));
+        assert_eq!(decoder.next_frame().unwrap(), None);
+
+        decoder.feed(&[2, 1]);
+        assert_eq!(decoder.buffered(), 2);
+        assert_eq!(decoder.finish().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        decoder.feed(&[1, 5]);
+        assert_eq!(decoder.next_frame().unwrap(), Some(&[1, 1][..]));
+        assert_eq!(decoder.next_frame().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_frame_header() {
+        let header = FrameHeader { priority: Some(9), ttl_ms: Some(1500), trace_id: Some([0xab; TRACE_ID_LEN]) };
+        let mut encoder = FrameEncoder::new();
+        encoder.encode_with_header(&header, "payload").unwrap();
+        encoder.encode_with_header(&FrameHeader::default(), &7u16).unwrap();
+        encoder.encode_with_header(&FrameHeader { ttl_ms: Some(10), ..Default::default() }, &()).unwrap();
+
+        let mut decoder = FrameDecoder::new();
+        decoder.feed(encoder.pending());
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(frame.len(), 1 + 1 + 4 + TRACE_ID_LEN + size_string("payload"));
+        let (found, message) = split_frame_header(frame).unwrap();
+        assert_eq!(found, header);
+        assert_eq!(from_slice::<String>(message).unwrap(), "payload");
+
+        // An empty header is the flags byte alone.
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(frame, [0, 7, 0]);
+        assert_eq!(split_frame_header(frame).unwrap(), (FrameHeader::default(), &[7, 0][..]));
+
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(frame, [2, 10, 0, 0, 0]);
+        let (found, message) = split_frame_header(frame).unwrap();
+        assert_eq!(found.ttl_ms, Some(10));
+        assert!(message.is_empty());
+
+        // Reserved flag bits are rejected, as are truncated fields.
+        assert_eq!(split_frame_header(&[8]).err(), Some(Error::InvalidValue));
+        assert!(matches!(split_frame_header(&[4, 1, 2]), Err(Error::BufferTooSmall { .. })));
+        assert!(matches!(split_frame_header(&[]), Err(Error::BufferTooSmall { .. })));
+    }
+
+    #[test]
+    fn test_fragments() {
+        let message: Vec<u8> = (0..=255).collect();
+        let mut fragments = fragment(42, &message, 100);
+        assert_eq!(fragments.len(), 3);
+
+        // Fragments arrive out of order and repeated.
+        fragments.reverse();
+        let mut reassembler = Reassembler::new();
+        assert_eq!(reassembler.push(&fragments[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&fragments[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&fragments[1]).unwrap(), None);
+        assert_eq!(reassembler.pending(), 1);
+        assert_eq!(reassembler.push(&fragments[2]).unwrap(), Some((42, message)));
+        assert_eq!(reassembler.pending(), 0);
+
+        assert_eq!(reassembler.push(&fragment(1, &[], 10)[0]).unwrap(), Some((1, Vec::new())));
+    }
+
+    #[test]
+    fn test_reassembler_limits() {
+        let mut reassembler = Reassembler::new().with_max_pending(2).with_max_message_len(150);
+        for id in 0..3 {
+            assert_eq!(reassembler.push(&fragment(id, &[0; 20], 10)[0]).unwrap(), None);
+        }
+        // The first message was dropped to make room for the third.
+        assert_eq!(reassembler.pending(), 2);
+        assert_eq!(reassembler.push(&fragment(0, &[0; 20], 10)[1]).unwrap(), None);
+        assert_eq!(reassembler.push(&fragment(2, &[0; 20], 10)[1]).unwrap(), Some((2, vec![0; 20])));
+
+        let long = fragment(9, &[0; 200], 100);
+        assert_eq!(reassembler.push(&long[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&long[1]), Err(Error::OutOfRange));
+
+        // A fragment disagreeing with the count of its message is rejected.
+        let mut other = fragment(5, &[0; 30], 10);
+        reassembler.push(&other.remove(0)).unwrap();
+        assert_eq!(reassembler.push(&fragment(5, &[0; 40], 10)[1]), Err(Error::InvalidValue));
+
+        // Counts beyond the message limit and empty parts are rejected before anything
+        // is held for the message.
+        let forged = |index: usize, count: usize, part: &[u8]| {
+            let mut out = vec![0u8; size_uint(7) + size_usize(index) + size_usize(count) + size_bytes(part)];
+            let mut writer = out.as_mut_slice();
+            marshal_uint(7, &mut writer).unwrap();
+            marshal_usize(index, &mut writer).unwrap();
+            marshal_usize(count, &mut writer).unwrap();
+            marshal_bytes(part, &mut writer).unwrap();
+            out
+        };
+        let pending = reassembler.pending();
+        assert_eq!(reassembler.push(&forged(0, usize::MAX, &[])), Err(Error::OutOfRange));
+        assert_eq!(reassembler.push(&forged(0, 151, &[1])), Err(Error::OutOfRange));
+        assert_eq!(reassembler.push(&forged(0, 2, &[])), Err(Error::InvalidValue));
+        assert_eq!(reassembler.pending(), pending);
+        assert_eq!(reassembler.push(&forged(1, 150, &[1])).unwrap(), None);
+    }
+
+    #[test]
+    fn test_datagrams() {
+        let mut sender = DatagramSender::new(8);
+        let first = sender.send(&[1; 20]);
+        let second = sender.encode("hello world").unwrap();
+        let third = sender.send(&[3; 4]);
+        assert_eq!(sender.next_seq(), 3);
+        assert_eq!(first.len(), 3);
+
+        let mut receiver = DatagramReceiver::new();
+        // Datagrams arrive reordered and repeated.
+        assert_eq!(receiver.push(&third[0]).unwrap(), Some((2, vec![3; 4])));
+        assert_eq!(receiver.push(&third[0]).unwrap(), None);
+        assert_eq!(receiver.push(&first[2]).unwrap(), None);
+        assert_eq!(receiver.push(&first[0]).unwrap(), None);
+        assert_eq!(receiver.pending(), vec![0]);
+        assert_eq!(receiver.push(&first[0]).unwrap(), None);
+        assert_eq!(receiver.push(&first[1]).unwrap(), Some((0, vec![1; 20])));
+        assert_eq!(receiver.push(&first[1]).unwrap(), None);
+        assert_eq!(receiver.duplicates(), 2);
+
+        // The second message loses a fragment and is reported once it leaves the window.
+        assert_eq!(receiver.push(&second[0]).unwrap(), None);
+        for _ in 0..DATAGRAM_WINDOW {
+            let datagrams = sender.send(&[0]);
+            assert!(receiver.push(&datagrams[0]).unwrap().is_some());
+        }
+        assert_eq!(receiver.take_incomplete(), vec![1]);
+        assert!(receiver.take_incomplete().is_empty());
+        assert!(receiver.pending().is_empty());
+
+        // Late datagrams of messages outside the window are ignored.
+        assert_eq!(receiver.push(&second[1]).unwrap(), None);
+        assert_eq!(receiver.duplicates(), 3);
+    }
+
+    #[test]
+    fn test_datagram_losses() {
+        let mut sender = DatagramSender::new(4);
+        let messages: Vec<_> = (0..200u8).map(|i| sender.send(&[i; 6])).collect();
+
+        let mut receiver = DatagramReceiver::new().with_max_pending(2);
+        // Message 0 is lost entirely.
+        for datagrams in &messages[1..=35] {
+            assert!(receiver.push(&datagrams[0]).unwrap().is_none());
+            assert!(receiver.push(&datagrams[1]).unwrap().is_some());
+        }
+        // Messages 36 and 37 are incomplete, and 38 evicts 36 from the reassembler.
+        receiver.push(&messages[36][0]).unwrap();
+        receiver.push(&messages[37][0]).unwrap();
+        receiver.push(&messages[38][1]).unwrap();
+        assert_eq!(receiver.take_incomplete(), vec![36]);
+        assert_eq!(receiver.pending(), vec![37, 38]);
+
+        receiver.push(&messages[150][0]).unwrap();
+        assert!(receiver.push(&messages[150][1]).unwrap().is_some());
+        // Message 150 moves the window past the incomplete messages.
+        assert_eq!(receiver.take_incomplete(), vec![37, 38]);
+        assert!(receiver.pending().is_empty());
+
+        // Overlong messages are reported as incomplete.
+        let mut receiver = DatagramReceiver::new().with_max_message_len(5);
+        receiver.push(&messages[0][0]).unwrap();
+        assert_eq!(receiver.push(&messages[0][1]), Err(Error::OutOfRange));
+        assert_eq!(receiver.take_incomplete(), vec![0]);
+        let mut sender = DatagramSender::new(16);
+        let single = sender.send(&[0; 6]);
+        assert_eq!(single.len(), 1);
+        assert_eq!(receiver.push(&single[0]), Err(Error::OutOfRange));
+        assert_eq!(receiver.take_incomplete(), vec![0]);
+    }
+}
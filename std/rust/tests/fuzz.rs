@@ -0,0 +1,72 @@
+#![cfg(feature = "fuzz")]
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use benc::fuzz::{CorpusGenerator, write_corpus};
+    use benc::*;
+    use chrono::{DateTime, Utc};
+
+    benc_struct! {
+        struct Inner {
+            tag: Option<u16>,
+            names: Vec<String>,
+        }
+    }
+
+    benc_struct! {
+        struct Record {
+            id: u64,
+            seq: usize,
+            at: DateTime<Utc>,
+            inner: Inner,
+            index: HashMap<String, Vec<Inner>>,
+        }
+    }
+
+    #[test]
+    fn test_fuzz_valid() {
+        let schema = schema_of::<Record>().unwrap();
+        let mut generator = CorpusGenerator::new(&schema, 7);
+        for _ in 0..50 {
+            let buf = generator.valid();
+            assert!(from_slice::<Record>(&buf).is_ok());
+            assert!(value_from_slice(&buf, &schema).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_fuzz_mutated() {
+        let schema = schema_of::<Record>().unwrap();
+        let mut generator = CorpusGenerator::new(&schema, 7);
+        let mut errors = 0;
+        for _ in 0..20 {
+            let inputs = generator.mutated();
+            assert!(from_slice::<Record>(&inputs[0]).is_ok());
+            for input in &inputs[1..] {
+                errors += from_slice::<Record>(input).is_err() as usize;
+            }
+        }
+        assert!(errors > 100);
+
+        // Inflated lengths never make the decoder allocate for them.
+        let inflated = CorpusGenerator::new(&schema, 1).mutated();
+        assert!(inflated.iter().any(|input| matches!(from_slice::<Record>(input), Err(Error::BufferTooSmall { .. }))));
+    }
+
+    #[test]
+    fn test_fuzz_corpus() {
+        let schema = schema_of::<Record>().unwrap();
+        let corpus = CorpusGenerator::new(&schema, 3).with_max_collection_len(2).corpus(10);
+        assert_eq!(corpus, CorpusGenerator::new(&schema, 3).with_max_collection_len(2).corpus(10));
+        assert_ne!(corpus, CorpusGenerator::new(&schema, 4).with_max_collection_len(2).corpus(10));
+        assert!(corpus.windows(2).all(|w| w[0] < w[1]));
+
+        let dir = std::env::temp_dir().join(format!("benc-fuzz-{}", std::process::id()));
+        write_corpus(&dir, &corpus).unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), corpus.len());
+        assert_eq!(std::fs::read(dir.join("benc-000000")).unwrap(), corpus[0]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "geo-types")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use geo_types::{LineString, Point, Polygon, coord, line_string, point, polygon};
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Zone {
+            center: Point<f64>,
+            area: Polygon<f64>,
+        }
+    }
+
+    #[test]
+    fn test_point() {
+        let p = point! { x: 1.5, y: -2.0 };
+        let buf = p.to_vec();
+        assert_eq!(buf.len(), size_point());
+        assert_eq!(buf[..8], 1.5f64.to_le_bytes());
+        assert_eq!(from_slice::<Point<f64>>(&buf).unwrap(), p);
+        assert_eq!(Point::<f64>::ENCODED_SIZE, Some(16));
+    }
+
+    #[test]
+    fn test_line_string() {
+        let ls: LineString<f64> = line_string![(x: 0.0, y: 0.0), (x: 1.0, y: 2.0)];
+        let buf = ls.to_vec();
+        assert_eq!(buf.len(), size_fixed_slice(&ls.0, 16));
+        assert_eq!(buf[0], 2);
+        assert_eq!(from_slice::<LineString<f64>>(&buf).unwrap(), ls);
+        let mut reader = buf.as_slice();
+        skip_line_string(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        // The layout matches a slice of the point type it describes.
+        let points: Vec<Point<f64>> = ls.points().collect();
+        assert_eq!(points.to_vec(), buf);
+        assert_eq!(LineString::<f64>::benc_type(), Vec::<Point<f64>>::benc_type());
+
+        let mut overflow = [0xff; 10];
+        overflow[9] = 0x01;
+        assert_eq!(from_slice::<LineString<f64>>(&overflow), Err(Error::OutOfRange));
+    }
+
+    #[test]
+    fn test_polygon_in_struct() {
+        let area = polygon!(
+            exterior: [(x: 0.0, y: 0.0), (x: 4.0, y: 0.0), (x: 4.0, y: 4.0), (x: 0.0, y: 0.0)],
+            interiors: [[(x: 1.0, y: 1.0), (x: 2.0, y: 1.0), (x: 2.0, y: 2.0), (x: 1.0, y: 1.0)]],
+        );
+        let zone = Zone { center: point! { x: 2.0, y: 2.0 }, area };
+        let buf = zone.to_vec();
+        assert_eq!(buf.len(), zone.size());
+        assert_eq!(from_slice::<Zone>(&buf).unwrap(), zone);
+        let value = value_from_slice(&buf, &schema_of::<Zone>().unwrap()).unwrap();
+        let center = Value::Struct(vec![("x".into(), Value::F64(2.0)), ("y".into(), Value::F64(2.0))]);
+        assert_eq!(value.field("center"), Some(&center));
+
+        // Rings are closed on decode, like `Polygon::new` does.
+        let mut buf = line_string![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0)].to_vec();
+        buf.extend_from_slice(&[0, 1, 1, 1, 1]);
+        let polygon = unmarshal_polygon(&mut buf.as_slice()).unwrap();
+        assert_eq!(polygon.exterior().0.last(), Some(&coord! { x: 0.0, y: 0.0 }));
+        let mut reader = buf.as_slice();
+        skip_polygon(&mut reader).unwrap();
+        assert!(reader.is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "axum")]
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use axum::body::{Body, to_bytes};
+    use axum::extract::{FromRequest, Request};
+    use axum::http::{StatusCode, header};
+    use axum::response::IntoResponse;
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        struct User {
+            id: u64,
+            name: String,
+        }
+    }
+
+    /// Runs a future that never waits, which holds for in-memory bodies.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future is not ready"),
+        }
+    }
+
+    fn request(content_type: &str, body: Vec<u8>) -> Request {
+        Request::builder().header(header::CONTENT_TYPE, content_type).body(Body::from(body)).unwrap()
+    }
+
+    #[test]
+    fn test_extract() {
+        let user = User { id: 7, name: "ada".to_string() };
+        let req = request("application/x-benc", user.to_vec());
+        let Benc(decoded) = block_on(Benc::<User>::from_request(req, &())).unwrap();
+        assert_eq!(decoded, user);
+
+        // Media type parameters and case are ignored.
+        let req = request("Application/X-Benc; charset=binary", user.to_vec());
+        assert_eq!(block_on(Benc::<User>::from_request(req, &())).unwrap().0, user);
+    }
+
+    #[test]
+    fn test_extract_rejections() {
+        let user = User { id: 7, name: "ada".to_string() };
+
+        let req = request("application/json", user.to_vec());
+        let rejection = block_on(Benc::<User>::from_request(req, &())).unwrap_err();
+        assert!(matches!(rejection, BencRejection::UnsupportedContentType));
+        assert_eq!(rejection.into_response().status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let mut body = user.to_vec();
+        body.push(0);
+        let rejection = block_on(Benc::<User>::from_request(request(BENC_CONTENT_TYPE, body), &())).unwrap_err();
+        assert!(matches!(rejection, BencRejection::Decode(Error::TrailingBytes)));
+        assert_eq!(rejection.into_response().status(), StatusCode::BAD_REQUEST);
+
+        // Bodies over axum's default limit of 2 MB are rejected before decoding.
+        let req = request(BENC_CONTENT_TYPE, vec![0; 3 * 1024 * 1024]);
+        let rejection = block_on(Benc::<User>::from_request(req, &())).unwrap_err();
+        assert!(matches!(rejection, BencRejection::Body(_)));
+        assert_eq!(rejection.into_response().status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_response() {
+        let user = User { id: 1, name: "grace".to_string() };
+        let response = Benc(user.clone()).into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], BENC_CONTENT_TYPE);
+        let body = block_on(to_bytes(response.into_body(), usize::MAX)).unwrap();
+        assert_eq!(from_slice::<User>(&body).unwrap(), user);
+    }
+}
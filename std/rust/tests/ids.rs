@@ -0,0 +1,84 @@
+#![cfg(any(feature = "ulid", feature = "macaddr"))]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[cfg(feature = "ulid")]
+    #[test]
+    fn test_ulid() {
+        use ulid::Ulid;
+
+        let ids = [Ulid::nil(), Ulid::new(), Ulid::from_parts(1_700_000_000_000, 42)];
+        let mut buf = vec![0u8; ids.len() * size_ulid()];
+        let mut writer = buf.as_mut_slice();
+        for id in ids {
+            marshal_ulid(id, &mut writer).unwrap();
+        }
+        assert!(writer.is_empty(), "marshal did not fill the buffer");
+
+        let mut skipper = buf.as_slice();
+        for _ in ids {
+            skip_ulid(&mut skipper).unwrap();
+        }
+        assert!(skipper.is_empty());
+
+        let mut reader = buf.as_slice();
+        for id in ids {
+            assert_eq!(unmarshal_ulid(&mut reader).unwrap(), id);
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[cfg(feature = "ulid")]
+    #[test]
+    fn test_ulid_sort_order() {
+        let earlier = ulid::Ulid::from_parts(1, u128::MAX);
+        let later = ulid::Ulid::from_parts(2, 0);
+        let mut a = [0u8; 16];
+        let mut b = [0u8; 16];
+        marshal_ulid(earlier, &mut a.as_mut_slice()).unwrap();
+        marshal_ulid(later, &mut b.as_mut_slice()).unwrap();
+        assert!(a < b);
+    }
+
+    #[cfg(feature = "macaddr")]
+    #[test]
+    fn test_mac() {
+        use macaddr::MacAddr6;
+
+        let mac = MacAddr6::new(0x00, 0x1b, 0x44, 0x11, 0x3a, 0xb7);
+        let mut buf = [0u8; 6];
+        marshal_mac(mac, &mut buf.as_mut_slice()).unwrap();
+        assert_eq!(buf, [0x00, 0x1b, 0x44, 0x11, 0x3a, 0xb7]);
+
+        let mut skipper = &buf[..];
+        skip_mac(&mut skipper).unwrap();
+        assert!(skipper.is_empty());
+
+        let mut reader = &buf[..];
+        assert_eq!(unmarshal_mac(&mut reader).unwrap(), mac);
+        assert_eq!(unmarshal_mac(&mut &buf[..5]).err(), Some(Error::BufferTooSmall { needed: 6, available: 5 }));
+    }
+
+    #[cfg(all(feature = "ulid", feature = "macaddr"))]
+    #[test]
+    fn test_id_traits() {
+        use macaddr::MacAddr6;
+        use ulid::Ulid;
+
+        benc_struct! {
+            #[derive(Debug, PartialEq)]
+            struct Event {
+                id: Ulid,
+                device: MacAddr6,
+            }
+        }
+
+        let e = Event { id: Ulid::from_parts(1_700_000_000_000, 42), device: MacAddr6::new(0, 0x1b, 0x44, 0x11, 0x3a, 0xb7) };
+        assert_eq!(Event::ENCODED_SIZE, Some(size_ulid() + size_mac()));
+        let buf = e.to_vec();
+        assert_eq!(&buf[..size_ulid()], e.id.to_bytes().as_slice());
+        assert_eq!(from_slice::<Event>(&buf).unwrap(), e);
+    }
+}
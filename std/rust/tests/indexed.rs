@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use std::io::Cursor;
+
+    fn encode() -> Vec<u8> {
+        let name = "a rather long name";
+        let values: Vec<u64> = (0..1000).collect();
+        let size = size_indexed(&[
+            (1, size_string(name)),
+            (7, size_fixed_slice(&values, size_u64())),
+            (3, size_bool()),
+        ]);
+
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        let mut container = IndexedWriter::new(&mut writer);
+        container.field(1, |w| marshal_string(name, w)).unwrap();
+        container.field(7, |w| marshal_slice(&values, w, |v, w| marshal_u64(*v, w))).unwrap();
+        container.field(3, |w| marshal_bool(true, w)).unwrap();
+        container.finish().unwrap();
+        assert!(writer.is_empty(), "marshal did not fill the buffer");
+        buf
+    }
+
+    #[test]
+    fn test_indexed_random_access() {
+        let buf = encode();
+        let container = unmarshal_indexed(&buf).unwrap();
+        assert_eq!(container.len(), 3);
+        assert_eq!(container.ids().collect::<Vec<_>>(), vec![1, 7, 3]);
+
+        let mut field = container.field(3).unwrap();
+        assert!(unmarshal_bool(&mut field).unwrap());
+        assert!(field.is_empty());
+
+        let mut field = container.field(1).unwrap();
+        assert_eq!(unmarshal_string(&mut field).unwrap(), "a rather long name");
+
+        let mut field = container.field(7).unwrap();
+        let values = unmarshal_slice(&mut field, unmarshal_u64).unwrap();
+        assert_eq!(values.len(), 1000);
+        assert_eq!(values[999], 999);
+
+        assert_eq!(container.field(2), None);
+    }
+
+    #[test]
+    fn test_indexed_seek() {
+        // The container may follow other data in the same file.
+        let mut file = b"header".to_vec();
+        file.extend_from_slice(&encode());
+        let mut source = Cursor::new(file);
+
+        let field = read_indexed_field(&mut source, 3).unwrap().unwrap();
+        assert_eq!(field, vec![1]);
+        let field = read_indexed_field(&mut source, 1).unwrap().unwrap();
+        assert_eq!(unmarshal_string(&mut field.as_slice()).unwrap(), "a rather long name");
+        assert_eq!(read_indexed_field(&mut source, 42).unwrap(), None);
+    }
+
+    #[test]
+    fn test_indexed_empty() {
+        let mut buf = vec![0u8; size_indexed(&[])];
+        let mut writer = buf.as_mut_slice();
+        IndexedWriter::new(&mut writer).finish().unwrap();
+        assert!(writer.is_empty());
+        assert!(unmarshal_indexed(&buf).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_indexed_errors() {
+        assert_eq!(unmarshal_indexed(&[1, 0]).err(), Some(Error::BufferTooSmall { needed: 4, available: 2 }));
+
+        // Footer length pointing before the start of the buffer.
+        assert_eq!(unmarshal_indexed(&[9, 0, 0, 0]).err(), Some(Error::BufferTooSmall { needed: 9, available: 0 }));
+
+        // A field whose range exceeds the field data.
+        let footer = [2u8, 1, 5, 0, 3];
+        let mut buf = vec![0xAA, 0xBB];
+        buf.extend_from_slice(&footer);
+        buf.extend_from_slice(&(footer.len() as u32).to_le_bytes());
+        assert_eq!(unmarshal_indexed(&buf).err(), Some(Error::OutOfRange));
+
+        let mut source = Cursor::new(buf);
+        let err = read_indexed_field(&mut source, 5).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut small = vec![0u8; 2];
+        let mut writer = small.as_mut_slice();
+        let mut container = IndexedWriter::new(&mut writer);
+        assert_eq!(container.field(1, |w| marshal_u64(1, w)).err(), Some(Error::BufferTooSmall { needed: 8, available: 2 }));
+    }
+}
@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use benc::*;
+
+    fn encode_message(ctx: &mut InternContext, keys: &[&str]) -> Vec<u8> {
+        let size = size_usize(keys.len()) + keys.iter().map(|k| ctx.size_interned(k)).sum::<usize>();
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_usize(keys.len(), &mut writer).unwrap();
+        for key in keys {
+            ctx.marshal_interned(key, &mut writer).unwrap();
+        }
+        assert!(writer.is_empty(), "size did not match");
+        ctx.finish_message();
+        buf
+    }
+
+    fn decode_message(ctx: &mut InternContext, buf: &[u8]) -> Vec<String> {
+        let mut reader = buf;
+        let len = unmarshal_usize(&mut reader).unwrap();
+        let keys = (0..len).map(|_| ctx.unmarshal_interned(&mut reader).unwrap().to_owned()).collect();
+        assert!(reader.is_empty());
+        keys
+    }
+
+    #[test]
+    fn test_intern_across_messages() {
+        let mut encoder = InternContext::new();
+        let mut decoder = InternContext::new();
+        let messages: [&[&str]; 3] = [
+            &["service.name", "host.id", "service.name"],
+            &["service.name", "host.id", "http.route"],
+            &["http.route", "service.name"],
+        ];
+
+        let encoded: Vec<Vec<u8>> = messages.iter().map(|m| encode_message(&mut encoder, m)).collect();
+        // Repeats within the first message are literals; later messages use references.
+        assert_eq!(encoded[2], vec![2, 4, 1]);
+        assert!(encoded[1].len() < encoded[0].len());
+
+        for (message, buf) in messages.iter().zip(&encoded) {
+            assert_eq!(decode_message(&mut decoder, buf), *message);
+        }
+        assert_eq!(decoder.len(), encoder.len());
+    }
+
+    #[test]
+    fn test_intern_skip_keeps_table_in_sync() {
+        let mut encoder = InternContext::new();
+        let first = encode_message(&mut encoder, &["a", "b"]);
+        let second = encode_message(&mut encoder, &["b", "a"]);
+
+        let mut decoder = InternContext::new();
+        let mut reader = &first[1..];
+        decoder.skip_interned(&mut reader).unwrap();
+        decoder.skip_interned(&mut reader).unwrap();
+        assert_eq!(decode_message(&mut decoder, &second), ["b", "a"]);
+    }
+
+    #[test]
+    fn test_intern_invalid_reference() {
+        let mut decoder = InternContext::new();
+        assert_eq!(decoder.unmarshal_interned(&mut &[1][..]), Err(Error::OutOfRange));
+        assert_eq!(decoder.unmarshal_interned(&mut &[0, 1, b'x'][..]).unwrap(), "x");
+        assert_eq!(decoder.unmarshal_interned(&mut &[1][..]).unwrap(), "x");
+        assert_eq!(decoder.unmarshal_interned(&mut &[2][..]), Err(Error::OutOfRange));
+    }
+
+    #[test]
+    fn test_intern_abort_message() {
+        let mut encoder = InternContext::new();
+        let first = encode_message(&mut encoder, &["a"]);
+
+        // A message that fails to marshal leaves no literals behind once aborted.
+        let mut buf = vec![0; 3];
+        let mut writer = buf.as_mut_slice();
+        encoder.marshal_interned("b", &mut writer).unwrap();
+        assert!(matches!(encoder.marshal_interned("c", &mut writer), Err(Error::BufferTooSmall { .. })));
+        encoder.abort_message();
+        assert_eq!(encoder.len(), 1);
+
+        let second = encode_message(&mut encoder, &["c", "a"]);
+        let third = encode_message(&mut encoder, &["c"]);
+        assert_eq!(third, vec![1, 2]);
+
+        let mut decoder = InternContext::new();
+        assert_eq!(decode_message(&mut decoder, &first), ["a"]);
+        assert_eq!(decode_message(&mut decoder, &second), ["c", "a"]);
+        assert_eq!(decode_message(&mut decoder, &third), ["c"]);
+    }
+
+    #[test]
+    fn test_intern_capacity() {
+        let mut encoder = InternContext::new().with_capacity(1);
+        let messages: [&[&str]; 2] = [&["a", "b"], &["b", "a"]];
+        let encoded: Vec<Vec<u8>> = messages.iter().map(|m| encode_message(&mut encoder, m)).collect();
+        // "b" did not fit, so it stays a literal.
+        assert_eq!(encoded[1], vec![2, 0, 1, b'b', 1]);
+        assert_eq!(encoder.len(), 1);
+
+        let mut decoder = InternContext::new().with_capacity(1);
+        for (message, buf) in messages.iter().zip(&encoded) {
+            assert_eq!(decode_message(&mut decoder, buf), *message);
+        }
+        assert_eq!(decoder.len(), 1);
+
+        // Literals from the peer cannot grow the table past its capacity.
+        for _ in 0..3 {
+            decoder.skip_interned(&mut &[0, 1, b'x'][..]).unwrap();
+        }
+        assert_eq!(decoder.len(), 1);
+        assert_eq!(decoder.unmarshal_interned(&mut &[2][..]), Err(Error::OutOfRange));
+    }
+
+    #[test]
+    fn test_string_cache_shares_keys() {
+        let event: HashMap<&str, u32> = [("user_id", 1), ("trace_id", 2)].into();
+        let size = size_map(&event, |k| size_string(k), |_| size_u32());
+        let mut buf = vec![0; size];
+        marshal_map(&event, &mut buf.as_mut_slice(), |k, w| marshal_string(k, w), |v, w| marshal_u32(*v, w)).unwrap();
+
+        let cache = StringCache::new();
+        let first = cache.unmarshal_map(&mut buf.as_slice(), unmarshal_u32).unwrap();
+        let second = cache.unmarshal_map(&mut buf.as_slice(), unmarshal_u32).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[&Arc::<str>::from("user_id")], 1);
+        assert_eq!(cache.len(), 2);
+        for key in first.keys() {
+            let (other, _) = second.get_key_value(key).unwrap();
+            assert!(Arc::ptr_eq(key, other));
+        }
+
+        // The methods compose with the combinators through shared references.
+        let mut buf = vec![0; size_slice(&["user_id"], |s| size_string(s))];
+        marshal_slice(&["user_id"], &mut buf.as_mut_slice(), |s, w| marshal_string(s, w)).unwrap();
+        let keys = unmarshal_slice(&mut buf.as_slice(), |r| cache.unmarshal_string(r)).unwrap();
+        assert!(Arc::ptr_eq(&keys[0], first.get_key_value("user_id").unwrap().0));
+    }
+
+    #[test]
+    fn test_string_cache_limits() {
+        let cache = StringCache::new().with_capacity(1).with_max_len(4);
+        let long = cache.get("too long");
+        assert!(!Arc::ptr_eq(&long, &cache.get("too long")));
+        let a = cache.get("a");
+        assert!(Arc::ptr_eq(&a, &cache.get("a")));
+        assert!(!Arc::ptr_eq(&cache.get("b"), &cache.get("b")));
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Archived {
+            id: u32,
+            tags: Vec<String>,
+            scores: HashMap<u8, Vec<u16>>,
+            parent: Option<Vec<u8>>,
+        }
+    }
+
+    fn archived() -> Archived {
+        Archived {
+            id: 7,
+            tags: vec!["a".into(), "bc".into()],
+            scores: [(1, vec![10, 20])].into(),
+            parent: Some(vec![3]),
+        }
+    }
+
+    /// The encoding of `archived()` by a producer that wrote no terminators.
+    fn unterminated() -> Vec<u8> {
+        let mut buf = vec![7, 0, 0, 0];
+        buf.extend_from_slice(&[2, 1, b'a', 2, b'b', b'c']);
+        buf.extend_from_slice(&[1, 1, 2, 10, 0, 20, 0]);
+        buf.extend_from_slice(&[1, 1, 3]);
+        buf
+    }
+
+    #[test]
+    fn test_upgrade_unterminated() {
+        let ty = Archived::benc_type().unwrap();
+        assert_eq!(upgrade_unterminated(&unterminated(), &ty).unwrap(), archived().to_vec());
+        assert_eq!(from_slice_with_layout::<Archived>(&unterminated(), Layout::Unterminated).unwrap(), archived());
+        assert_eq!(from_slice_with_layout::<Archived>(&archived().to_vec(), Layout::Terminated).unwrap(), archived());
+
+        // Data in the current layout does not parse as the old one.
+        assert!(upgrade_unterminated(&archived().to_vec(), &ty).is_err());
+        let mut long = unterminated();
+        long.push(0);
+        assert_eq!(upgrade_unterminated(&long, &ty), Err(Error::TrailingBytes));
+        assert!(matches!(upgrade_unterminated(&unterminated()[..8], &ty), Err(Error::BufferTooSmall { .. })));
+    }
+
+    /// A hand-written type that does not describe its encoding.
+    #[derive(Debug)]
+    struct Opaque;
+
+    impl BencEncode for Opaque {
+        fn size(&self) -> usize {
+            0
+        }
+
+        fn marshal(&self, _: &mut &mut [u8]) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl BencDecode<'_> for Opaque {
+        fn unmarshal(_: &mut &[u8]) -> Result<Self> {
+            Ok(Opaque)
+        }
+
+        fn skip(_: &mut &[u8]) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_from_slice_with_layout_needs_benc_type() {
+        assert!(from_slice_with_layout::<Opaque>(&[], Layout::Terminated).is_ok());
+        assert!(matches!(from_slice_with_layout::<Opaque>(&[], Layout::Unterminated), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_unterminated_collections() {
+        let buf = [2, 5, 6, 9];
+        let mut reader = &buf[..];
+        assert_eq!(unmarshal_slice_unterminated(&mut reader, unmarshal_u8).unwrap(), [5, 6]);
+        assert_eq!(reader, [9]);
+
+        let mut reader = &buf[..];
+        skip_slice_unterminated(&mut reader, |r| unmarshal_u8(r).map(|_| ())).unwrap();
+        assert_eq!(reader, [9]);
+
+        // Strings borrow from the reader; zero-sized elements cannot outnumber the input.
+        let buf = [1, 2, b'a', b'b'];
+        assert_eq!(unmarshal_slice_unterminated(&mut &buf[..], unmarshal_string).unwrap(), ["ab"]);
+        assert_eq!(unmarshal_slice_unterminated(&mut &[0x80, 0x08][..], <()>::unmarshal), Err(Error::InvalidValue));
+        assert_eq!(skip_slice_unterminated(&mut &[0x80, 0x08][..], <()>::skip), Err(Error::InvalidValue));
+
+        let buf = [1, 5, 6, 9];
+        let mut reader = &buf[..];
+        let map: BTreeMap<u8, u8> = unmarshal_map_unterminated(&mut reader, unmarshal_u8, unmarshal_u8).unwrap();
+        assert_eq!(map, [(5, 6)].into());
+        assert_eq!(reader, [9]);
+
+        let mut reader = &buf[..];
+        let skip = |r: &mut &[u8]| unmarshal_u8(r).map(|_| ());
+        skip_map_unterminated(&mut reader, skip, skip).unwrap();
+        assert_eq!(reader, [9]);
+    }
+}
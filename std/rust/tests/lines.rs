@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Event {
+            id: u32,
+            name: String,
+        }
+    }
+
+    fn events(n: u32) -> Vec<Event> {
+        (0..n).map(|id| Event { id, name: "e".repeat(id as usize * 7 % 200) }).collect()
+    }
+
+    /// A writer counting how often it is flushed.
+    #[derive(Default)]
+    struct Counting {
+        buf: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl Write for Counting {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buf.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_lines_round_trip() {
+        let mut writer = BencLinesWriter::new(Counting::default());
+        for event in events(100) {
+            writer.write(&event).unwrap();
+        }
+        assert_eq!(writer.get_ref().flushes, 100);
+        let buf = writer.into_inner().buf;
+
+        // Every record is the varint length followed by the value.
+        let mut reader = buf.as_slice();
+        for event in events(100) {
+            assert_eq!(unmarshal_bytes_cropped(&mut reader).unwrap(), event.to_vec());
+        }
+        assert!(reader.is_empty());
+
+        let mut lines = BencLinesReader::new(buf.as_slice());
+        let decoded: Vec<Event> = lines.by_ref().map(Result::unwrap).collect();
+        assert_eq!(decoded, events(100));
+        assert!(!lines.is_truncated());
+    }
+
+    #[test]
+    fn test_lines_truncated_final_record() {
+        let mut writer = BencLinesWriter::new(Vec::new());
+        for event in events(3) {
+            writer.write(&event).unwrap();
+        }
+        let buf = writer.into_inner();
+        let whole = buf.len() - events(3)[2].size() - 1;
+
+        // Cut inside the last value, and inside a two-byte length.
+        for cut in [buf.len() - 1, whole + 1] {
+            let mut lines = BencLinesReader::<_, Event>::new(&buf[..cut]);
+            assert_eq!(lines.by_ref().map(Result::unwrap).collect::<Vec<_>>(), events(2));
+            assert!(lines.is_truncated());
+        }
+        assert_eq!(BencLinesReader::<_, Event>::new(&buf[..whole]).count(), 2);
+    }
+
+    #[test]
+    fn test_lines_errors() {
+        // A record that does not decode is skipped over.
+        let mut buf = vec![2, 1, 0xff];
+        buf.extend([3, 2, b'o', b'k']);
+        let mut lines = BencLinesReader::<_, String>::new(buf.as_slice());
+        assert_eq!(lines.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(lines.next().unwrap().unwrap(), "ok");
+        assert!(lines.next().is_none());
+
+        // A record over the limit ends the iterator.
+        let mut lines = BencLinesReader::<_, String>::new([3, 2, b'o', b'k', 3, 2, b'o', b'k'].as_slice()).with_max_record_len(2);
+        assert_eq!(lines.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(lines.next().is_none());
+        assert!(!lines.is_truncated());
+    }
+
+    #[test]
+    fn test_lines_file() {
+        let path = std::env::temp_dir().join(format!("benc-lines-{}", std::process::id()));
+        let mut writer = BencLinesWriter::create(&path).unwrap();
+        for event in events(10) {
+            writer.write(&event).unwrap();
+        }
+        drop(writer);
+        let decoded: Vec<Event> = BencLinesReader::open(&path).unwrap().map(Result::unwrap).collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(decoded, events(10));
+    }
+}
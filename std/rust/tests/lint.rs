@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use benc::*;
+
+    benc_struct! {
+        struct Message {
+            id: usize,
+            flag: Option<bool>,
+            tags: HashMap<String, i32>,
+            units: Vec<()>,
+        }
+    }
+
+    fn schema() -> Schema {
+        schema_of::<Message>().unwrap()
+    }
+
+    #[test]
+    fn test_lint_clean() {
+        let msg = Message { id: 300, flag: Some(true), tags: HashMap::from([("a".into(), 1), ("b".into(), 2)]), units: vec![(); 3] };
+        let buf = msg.to_vec();
+        let mut reader = buf.as_slice();
+        assert_eq!(lint(&mut reader, &schema()).unwrap(), vec![]);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_lint_warnings() {
+        let mut buf = vec![0xac, 0x82, 0x00]; // 300 padded with a continuation byte
+        buf.extend([1, 2]); // Some(bool) holding 2
+        buf.extend([0x82, 0x00]); // a map of 2 entries, with an overlong length
+        for _ in 0..2 {
+            buf.extend([1, b'k']);
+            buf.extend(7i32.to_vec());
+        }
+        buf.extend([1, 1, 1, 1]);
+        buf.extend([0xe8, 0x07]); // 1000 units in no bytes
+        buf.extend([1, 1, 1, 1]);
+
+        // Decoding rejects the units, whose length no input backs.
+        assert_eq!(from_slice::<Message>(&buf).err(), Some(Error::InvalidValue));
+
+        let warnings = lint(&mut buf.as_slice(), &schema()).unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::OverlongVarint { offset: 0 },
+                Warning::NonCanonicalBool { offset: 4 },
+                Warning::OverlongVarint { offset: 5 },
+                Warning::DuplicateKey { offset: 13 },
+                Warning::ImplausibleLength { offset: 23, len: 1000 },
+            ]
+        );
+        assert_eq!(warnings[3].offset(), 13);
+        assert_eq!(warnings[1].to_string(), "bool other than 0 or 1 at offset 4");
+    }
+
+    #[test]
+    fn test_lint_invalid() {
+        assert!(lint(&mut &[0x80][..], &schema()).is_err());
+    }
+}
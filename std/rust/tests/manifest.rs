@@ -0,0 +1,95 @@
+#![cfg(any(feature = "semver", feature = "url"))]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[cfg(feature = "semver")]
+    #[test]
+    fn test_version() {
+        use semver::Version;
+
+        for text in ["0.0.0", "1.2.3", "1.0.0-alpha.1+build.5", "18446744073709551615.2.3-rc"] {
+            let v = Version::parse(text).unwrap();
+            let size = size_version(&v);
+            let mut buf = vec![0u8; size];
+            let mut writer = buf.as_mut_slice();
+            marshal_version(&v, &mut writer).unwrap();
+            assert!(writer.is_empty(), "marshal did not fill the buffer");
+
+            let mut skipper = buf.as_slice();
+            skip_version(&mut skipper).unwrap();
+            assert!(skipper.is_empty());
+
+            let mut reader = buf.as_slice();
+            assert_eq!(unmarshal_version(&mut reader).unwrap(), v);
+            assert!(reader.is_empty());
+        }
+
+        // Numeric triple plus two empty strings.
+        assert_eq!(size_version(&Version::new(1, 2, 3)), 5);
+    }
+
+    #[cfg(feature = "semver")]
+    #[test]
+    fn test_version_invalid() {
+        let mut buf = vec![0u8; 32];
+        let mut writer = buf.as_mut_slice();
+        marshal_uint(1, &mut writer).unwrap();
+        marshal_uint(0, &mut writer).unwrap();
+        marshal_uint(0, &mut writer).unwrap();
+        marshal_string("not valid!", &mut writer).unwrap();
+        marshal_string("", &mut writer).unwrap();
+        assert_eq!(unmarshal_version(&mut buf.as_slice()).err(), Some(Error::InvalidValue));
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_url() {
+        use url::Url;
+
+        let v = Url::parse("https://example.com:8080/path?q=1#frag").unwrap();
+        let mut buf = vec![0u8; size_url(&v)];
+        marshal_url(&v, &mut buf.as_mut_slice()).unwrap();
+
+        let mut skipper = buf.as_slice();
+        skip_url(&mut skipper).unwrap();
+        assert!(skipper.is_empty());
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_url(&mut reader).unwrap(), v);
+        assert!(reader.is_empty());
+
+        let mut buf = vec![0u8; size_string("no scheme")];
+        marshal_string("no scheme", &mut buf.as_mut_slice()).unwrap();
+        assert_eq!(unmarshal_url(&mut buf.as_slice()).err(), Some(Error::InvalidValue));
+    }
+
+    #[cfg(all(feature = "semver", feature = "url"))]
+    #[test]
+    fn test_manifest_traits() {
+        use std::collections::HashMap;
+
+        use semver::Version;
+        use url::Url;
+
+        benc_struct! {
+            #[derive(Debug, PartialEq)]
+            struct Manifest {
+                version: Version,
+                homepage: Url,
+                dependencies: HashMap<String, Version>,
+            }
+        }
+
+        let m = Manifest {
+            version: Version::parse("1.2.3-beta.1+build.5").unwrap(),
+            homepage: Url::parse("https://example.com/crate").unwrap(),
+            dependencies: HashMap::from([("serde".to_string(), Version::new(1, 0, 200))]),
+        };
+        let buf = m.to_vec();
+        assert_eq!(buf.len(), m.size());
+        assert_eq!(from_slice::<Manifest>(&buf).unwrap(), m);
+        assert!(schema_of::<Manifest>().is_some());
+    }
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "glam")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+
+    #[test]
+    fn test_glam_types() {
+        let v2 = Vec2::new(1.5, -2.0);
+        let v3 = Vec3::new(0.1, 0.2, 0.3);
+        let v4 = Vec4::new(4.0, 3.0, 2.0, 1.0);
+        let q = Quat::from_rotation_y(1.25);
+        let m = Mat4::from_scale_rotation_translation(Vec3::splat(2.0), q, v3);
+
+        let total_size = size_vec2() + size_vec3() + size_vec4() + size_quat() + size_mat4();
+        assert_eq!(total_size, (2 + 3 + 4 + 4 + 16) * size_f32());
+
+        let mut buf = vec![0u8; total_size];
+        let mut writer = buf.as_mut_slice();
+        marshal_vec2(v2, &mut writer).unwrap();
+        marshal_vec3(v3, &mut writer).unwrap();
+        marshal_vec4(v4, &mut writer).unwrap();
+        marshal_quat(q, &mut writer).unwrap();
+        marshal_mat4(m, &mut writer).unwrap();
+        assert!(writer.is_empty(), "marshal did not fill the buffer");
+
+        let mut skipper = buf.as_slice();
+        skip_vec2(&mut skipper).unwrap();
+        skip_vec3(&mut skipper).unwrap();
+        skip_vec4(&mut skipper).unwrap();
+        skip_quat(&mut skipper).unwrap();
+        skip_mat4(&mut skipper).unwrap();
+        assert!(skipper.is_empty(), "skip did not consume the buffer");
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_vec2(&mut reader).unwrap(), v2);
+        assert_eq!(unmarshal_vec3(&mut reader).unwrap(), v3);
+        assert_eq!(unmarshal_vec4(&mut reader).unwrap(), v4);
+        assert_eq!(unmarshal_quat(&mut reader).unwrap(), q);
+        assert_eq!(unmarshal_mat4(&mut reader).unwrap(), m);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_glam_layout() {
+        // Components are packed in order, matrices column by column.
+        let m = Mat4::from_cols_array(&core::array::from_fn(|i| i as f32));
+        let mut buf = vec![0u8; size_mat4()];
+        marshal_mat4(m, &mut buf.as_mut_slice()).unwrap();
+        let mut reader = buf.as_slice();
+        for i in 0..16 {
+            assert_eq!(unmarshal_f32(&mut reader).unwrap(), i as f32);
+        }
+    }
+
+    #[test]
+    fn test_glam_errors() {
+        let mut short = [0u8; 11];
+        assert_eq!(marshal_vec3(Vec3::ONE, &mut short.as_mut_slice()).err(), Some(Error::BufferTooSmall { needed: 12, available: 11 }));
+        assert_eq!(unmarshal_vec3(&mut &short[..]).err(), Some(Error::BufferTooSmall { needed: 12, available: 11 }));
+        assert_eq!(skip_mat4(&mut &short[..]).err(), Some(Error::BufferTooSmall { needed: 64, available: 11 }));
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Transform {
+            position: Vec3,
+            rotation: Quat,
+            uv: Vec2,
+            color: Vec4,
+            matrix: Mat4,
+        }
+    }
+
+    #[test]
+    fn test_glam_traits() {
+        let t = Transform {
+            position: Vec3::new(1.0, 2.0, 3.0),
+            rotation: Quat::from_rotation_x(0.5),
+            uv: Vec2::new(0.25, 0.75),
+            color: Vec4::ONE,
+            matrix: Mat4::from_translation(Vec3::X),
+        };
+        let buf = t.to_vec();
+        assert_eq!(Transform::ENCODED_SIZE, Some((3 + 4 + 2 + 4 + 16) * size_f32()));
+        assert_eq!(buf.len(), t.size());
+        assert_eq!(&buf[..size_vec3()], t.position.to_vec().as_slice());
+        assert_eq!(from_slice::<Transform>(&buf).unwrap(), t);
+        assert_eq!(schema_of::<Transform>().unwrap().encoded_fixed_size(), Transform::ENCODED_SIZE);
+    }
+}
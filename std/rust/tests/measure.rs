@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[test]
+    fn test_measure() {
+        let names = ["ab", "cde"];
+        let size = size_string("hello")
+            + size_slice(&names, |s| size_string(s))
+            + size_option(&Some(300u64), |v| size_uint(*v))
+            + size_int(-70);
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_string("hello", &mut writer).unwrap();
+        marshal_slice(&names, &mut writer, |s, w| marshal_string(s, w)).unwrap();
+        marshal_option(&Some(300u64), &mut writer, |v, w| marshal_uint(*v, w)).unwrap();
+        marshal_int(-70, &mut writer).unwrap();
+
+        let mut reader = buf.as_slice();
+        let n = measure_string(reader).unwrap();
+        assert_eq!(n, size_string("hello"));
+        reader = &reader[n..];
+
+        let n = measure_slice(reader, skip_string).unwrap();
+        assert_eq!(n, size_slice(&names, |s| size_string(s)));
+        // The measured bytes can be forwarded and decoded on their own.
+        let forwarded = &reader[..n];
+        let decoded = unmarshal_slice(&mut &forwarded[..], |r| unmarshal_string(r).map(String::from)).unwrap();
+        assert_eq!(decoded, names);
+        reader = &reader[n..];
+
+        let n = measure_option(reader, skip_uint).unwrap();
+        assert_eq!(n, 3);
+        reader = &reader[n..];
+
+        assert_eq!(measure_int(reader).unwrap(), reader.len());
+    }
+
+    #[test]
+    fn test_measure_does_not_consume() {
+        let mut buf = vec![0u8; size_bytes(&[1, 2, 3])];
+        marshal_bytes(&[1, 2, 3], &mut buf.as_mut_slice()).unwrap();
+        let reader = buf.as_slice();
+        assert_eq!(measure_bytes(reader).unwrap(), 4);
+        assert_eq!(measure_bytes(reader).unwrap(), 4);
+        assert_eq!(measure_bytes(&reader[..3]), Err(Error::BufferTooSmall { needed: 3, available: 2 }));
+        assert_eq!(measure_map(&[1, 0, 0, 1, 1], skip_u8, skip_u8), Err(Error::BufferTooSmall { needed: 4, available: 2 }));
+    }
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "metrics")]
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use benc::*;
+
+    benc_struct! {
+        struct Sample {
+            id: u32,
+            name: String,
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl CodecMetrics for &'static Recorder {
+        fn on_encode(&self, type_name: &'static str, bytes: usize, _elapsed: Duration) {
+            self.events.lock().unwrap().push(format!("encode {type_name} {bytes}"));
+        }
+
+        fn on_decode(&self, type_name: &'static str, bytes: usize, _elapsed: Duration, error: Option<&Error>) {
+            self.events.lock().unwrap().push(format!("decode {type_name} {bytes} {error:?}"));
+        }
+    }
+
+    #[test]
+    fn test_codec_metrics() {
+        let recorder: &'static Recorder = Box::leak(Box::default());
+        assert!(set_codec_metrics(recorder));
+        assert!(!set_codec_metrics(recorder));
+
+        let buf = Sample { id: 1, name: "abc".into() }.to_vec();
+        let _: Sample = from_slice(&buf).unwrap();
+        let mut long = buf.clone();
+        long.push(0);
+        assert_eq!(from_slice::<Sample>(&long).err(), Some(Error::TrailingBytes));
+
+        let name = std::any::type_name::<Sample>();
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![
+                format!("encode {name} 8"),
+                format!("decode {name} 8 None"),
+                format!("decode {name} 9 Some(TrailingBytes)"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+#![cfg(any(feature = "bincode", feature = "postcard"))]
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use benc::*;
+    use serde::{Deserialize, Serialize};
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Record {
+            id: u64,
+            name: String,
+            tags: Vec<String>,
+        }
+    }
+
+    fn records() -> Vec<Record> {
+        (0..100).map(|id| Record { id, name: format!("record {id}"), tags: vec!["a".repeat(id as usize % 7)] }).collect()
+    }
+
+    fn read_frames(mut reader: &[u8]) -> Vec<Record> {
+        let mut out = Vec::new();
+        while !reader.is_empty() {
+            out.push(from_slice(unmarshal_bytes_cropped(&mut reader).unwrap()).unwrap());
+        }
+        out
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn test_transcode_bincode() {
+        let config = bincode::config::standard();
+        let mut input = Vec::new();
+        for record in records() {
+            input.extend(bincode::serde::encode_to_vec(&record, config).unwrap());
+        }
+
+        let mut output = Vec::new();
+        assert_eq!(transcode_bincode::<Record, _>(input.as_slice(), &mut output, config).unwrap(), 100);
+        assert_eq!(read_frames(&output), records());
+
+        // A truncated final record is reported with its index.
+        let mut output = Vec::new();
+        let err = transcode_bincode::<Record, _>(&input[..input.len() - 1], &mut output, config).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("record 99"));
+        assert_eq!(read_frames(&output).len(), 99);
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn test_transcode_postcard() {
+        let mut input = Vec::new();
+        for record in records() {
+            input.extend(postcard::to_stdvec(&record).unwrap());
+        }
+
+        let mut output = Vec::new();
+        assert_eq!(transcode_postcard::<Record>(input.as_slice(), &mut output, 1024).unwrap(), 100);
+        assert_eq!(read_frames(&output), records());
+
+        let mut output = Vec::new();
+        assert_eq!(transcode_postcard::<Record>(&[][..], &mut output, 1024).unwrap(), 0);
+        assert!(output.is_empty());
+
+        // Fields longer than the scratch buffer cannot be decoded.
+        let err = transcode_postcard::<Record>(input.as_slice(), &mut Vec::new(), 4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
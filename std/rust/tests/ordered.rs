@@ -0,0 +1,77 @@
+#![cfg(feature = "ordered-float")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use ordered_float::{NotNan, OrderedFloat};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_ordered_floats() {
+        let a = OrderedFloat(1.25f32);
+        let b = OrderedFloat(f64::NAN);
+        let c = NotNan::new(-0.5f32).unwrap();
+        let d = NotNan::new(1e300f64).unwrap();
+        let size = size_ordered_f32() + size_ordered_f64() + size_not_nan_f32() + size_not_nan_f64();
+        assert_eq!(size, 2 * (size_f32() + size_f64()));
+
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_ordered_f32(a, &mut writer).unwrap();
+        marshal_ordered_f64(b, &mut writer).unwrap();
+        marshal_not_nan_f32(c, &mut writer).unwrap();
+        marshal_not_nan_f64(d, &mut writer).unwrap();
+        assert!(writer.is_empty());
+
+        let mut skipper = buf.as_slice();
+        skip_ordered_f32(&mut skipper).unwrap();
+        skip_ordered_f64(&mut skipper).unwrap();
+        skip_not_nan_f32(&mut skipper).unwrap();
+        skip_not_nan_f64(&mut skipper).unwrap();
+        assert!(skipper.is_empty());
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_ordered_f32(&mut reader).unwrap(), a);
+        assert_eq!(unmarshal_ordered_f64(&mut reader).unwrap(), b);
+        assert_eq!(unmarshal_not_nan_f32(&mut reader).unwrap(), c);
+        assert_eq!(unmarshal_not_nan_f64(&mut reader).unwrap(), d);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_ordered_float_map_keys() {
+        let mut map = HashMap::new();
+        map.insert(OrderedFloat(0.5f64), "half");
+        map.insert(OrderedFloat(2.0f64), "two");
+
+        let size = size_map(&map, |_| size_ordered_f64(), |v| size_string(v));
+        let mut buf = vec![0u8; size];
+        marshal_map(&map, &mut buf.as_mut_slice(), |k, w| marshal_ordered_f64(*k, w), |v, w| marshal_string(v, w)).unwrap();
+
+        let mut reader = buf.as_slice();
+        let ret: HashMap<OrderedFloat<f64>, &str> = unmarshal_map(&mut reader, unmarshal_ordered_f64, unmarshal_string).unwrap();
+        assert_eq!(ret, map);
+    }
+
+    #[test]
+    fn test_not_nan_rejects_nan() {
+        let mut buf = vec![0u8; size_f64()];
+        marshal_f64(f64::NAN, &mut buf.as_mut_slice()).unwrap();
+        assert_eq!(unmarshal_not_nan_f64(&mut buf.as_slice()).err(), Some(Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_ordered_float_traits() {
+        let mut map = HashMap::new();
+        map.insert(OrderedFloat(0.5f64), NotNan::new(1.0f32).unwrap());
+        map.insert(OrderedFloat(-2.0f64), NotNan::new(4.0f32).unwrap());
+        let buf = map.to_vec();
+        assert_eq!(buf.len(), size_map(&map, |_| size_f64(), |_| size_f32()));
+        assert_eq!(from_slice::<HashMap<OrderedFloat<f64>, NotNan<f32>>>(&buf).unwrap(), map);
+
+        // The wrappers are interchangeable with the floats they wrap.
+        assert_eq!(OrderedFloat(0.5f64).to_vec(), 0.5f64.to_vec());
+        assert_eq!(<NotNan<f64>>::WIRE_TYPE, f64::WIRE_TYPE);
+        assert_eq!(from_slice::<NotNan<f64>>(&f64::NAN.to_vec()).err(), Some(Error::InvalidValue));
+    }
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "rayon")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[test]
+    fn test_marshal_slice_parallel() {
+        let strings: Vec<String> = (0..10_000).map(|i| "x".repeat(i % 300)).collect();
+        let size = size_slice(&strings, |s| size_string(s));
+        assert_eq!(size_slice_parallel(&strings, |s| size_string(s)), size);
+
+        let mut expected = vec![0u8; size];
+        marshal_slice(&strings, &mut expected.as_mut_slice(), |s, w| marshal_string(s, w)).unwrap();
+
+        let mut buf = vec![0u8; size + 3];
+        let mut writer = buf.as_mut_slice();
+        marshal_slice_parallel(&strings, &mut writer, |s| size_string(s), |s, w| marshal_string(s, w)).unwrap();
+        assert_eq!(writer.len(), 3);
+        assert_eq!(&buf[..size], expected);
+
+        let empty: Vec<u32> = vec![];
+        let mut buf = vec![0u8; 5];
+        marshal_slice_parallel(&empty, &mut buf.as_mut_slice(), |_| size_u32(), |v, w| marshal_u32(*v, w)).unwrap();
+        assert_eq!(buf, [0, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_marshal_slice_parallel_errors() {
+        let values: Vec<u32> = (0..100).collect();
+        let mut buf = vec![0u8; 100];
+        let mut writer = buf.as_mut_slice();
+        let result = marshal_slice_parallel(&values, &mut writer, |_| size_u32(), |v, w| marshal_u32(*v, w));
+        assert_eq!(result, Err(Error::BufferTooSmall { needed: 405, available: 100 }));
+        assert_eq!(writer.len(), 100);
+
+        // A failing marshaler is reported and the writer is not advanced.
+        let mut buf = vec![0u8; 405];
+        let mut writer = buf.as_mut_slice();
+        let result = marshal_slice_parallel(&values, &mut writer, |_| size_u32(), |v, w| {
+            if *v == 50 { Err(Error::InvalidValue) } else { marshal_u32(*v, w) }
+        });
+        assert_eq!(result, Err(Error::InvalidValue));
+        assert_eq!(writer.len(), 405);
+    }
+
+    #[test]
+    fn test_unmarshal_chunked_parallel() {
+        let strings: Vec<String> = (0..5_000).map(|i| "z".repeat(i % 100)).collect();
+        let size = size_chunked(&strings, 64, |s| size_string(s));
+        let mut buf = vec![0u8; size + 2];
+        marshal_chunked(&strings, 64, &mut buf.as_mut_slice(), |s| size_string(s), |s, w| marshal_string(s, w))
+            .unwrap();
+
+        let mut reader = buf.as_slice();
+        let decoded = unmarshal_chunked_parallel(&mut reader, |r| unmarshal_string(r)).unwrap();
+        assert_eq!(decoded, strings);
+        assert_eq!(reader.len(), 2);
+
+        // A failing unmarshaler is reported and the reader is not advanced.
+        let mut reader = buf.as_slice();
+        let result = unmarshal_chunked_parallel(&mut reader, |r| {
+            let s = unmarshal_string(r)?;
+            if s.len() == 50 { Err(Error::InvalidValue) } else { Ok(s) }
+        });
+        assert_eq!(result, Err(Error::InvalidValue));
+        assert_eq!(reader.len(), buf.len());
+    }
+}
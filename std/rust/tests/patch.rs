@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use chrono::{DateTime, Utc};
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Record {
+            id: u64,
+            hits: u32,
+            active: bool,
+            updated: DateTime<Utc>,
+            name: String,
+            score: f64,
+        }
+    }
+
+    #[test]
+    fn test_patch_fixed_fields() {
+        let mut record = Record { id: 7, hits: 1, active: false, updated: DateTime::from_timestamp_nanos(0), name: "r".into(), score: 0.5 };
+        let mut buf = record.to_vec();
+
+        let schema = schema_of::<Record>().unwrap();
+        assert_eq!(schema.fixed_field_offset("id"), Some(0));
+        assert_eq!(schema.fixed_field_offset("hits"), Some(8));
+        assert_eq!(schema.fixed_field_offset("active"), Some(12));
+        assert_eq!(schema.fixed_field_offset("updated"), Some(13));
+        // Variable-size fields, and fields after them, have no fixed offset.
+        assert_eq!(schema.fixed_field_offset("name"), None);
+        assert_eq!(schema.fixed_field_offset("score"), None);
+        assert_eq!(schema.fixed_field_offset("missing"), None);
+
+        let updated = DateTime::from_timestamp_nanos(1_700_000_000_000_000_000);
+        patch_u32_at(&mut buf, schema.fixed_field_offset("hits").unwrap(), 42).unwrap();
+        patch_bool_at(&mut buf, schema.fixed_field_offset("active").unwrap(), true).unwrap();
+        patch_time_at(&mut buf, schema.fixed_field_offset("updated").unwrap(), updated).unwrap();
+        // A fixed-size last field sits at a fixed distance from the end.
+        let score_at = buf.len() - size_f64();
+        patch_f64_at(&mut buf, score_at, 2.5).unwrap();
+
+        (record.hits, record.active, record.updated, record.score) = (42, true, updated, 2.5);
+        assert_eq!(from_slice::<Record>(&buf).unwrap(), record);
+    }
+
+    #[test]
+    fn test_patch_out_of_bounds() {
+        let mut buf = [0u8; 4];
+        assert_eq!(patch_u64_at(&mut buf, 0, 1), Err(Error::BufferTooSmall { needed: 8, available: 4 }));
+        assert_eq!(patch_u16_at(&mut buf, 3, 1), Err(Error::BufferTooSmall { needed: 2, available: 1 }));
+        assert_eq!(patch_u8_at(&mut buf, 5, 1), Err(Error::BufferTooSmall { needed: 5, available: 4 }));
+        assert_eq!(buf, [0; 4]);
+        patch_i16_at(&mut buf, 2, -1).unwrap();
+        assert_eq!(buf, [0, 0, 0xff, 0xff]);
+    }
+}
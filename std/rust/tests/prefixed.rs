@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Header {
+            pub names: Vec<String>,
+            pub version: u32,
+            #[benc(default)]
+            pub flags: u8,
+        }
+    }
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Envelope {
+            #[benc(prefixed)]
+            pub header: Header,
+            pub body: String,
+        }
+    }
+
+    benc_struct! {
+        #[benc(tagged)]
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct TaggedEnvelope {
+            #[benc(id = 1, prefixed)]
+            pub header: Header,
+            #[benc(id = 2)]
+            pub body: String,
+        }
+    }
+
+    fn header() -> Header {
+        Header { names: vec!["a".into(), "b".into()], version: 3, flags: 1 }
+    }
+
+    #[test]
+    fn test_prefixed_functions() {
+        let h = header();
+        let size = size_prefixed(&h, BencEncode::size);
+        assert_eq!(size, 1 + h.size());
+        let mut buf = vec![0u8; size];
+        marshal_prefixed(&h, &mut buf.as_mut_slice(), BencEncode::size, BencEncode::marshal).unwrap();
+        assert_eq!(buf[0] as usize, h.size());
+        assert_eq!(&buf[1..], h.to_vec().as_slice());
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_prefixed(&mut reader, Header::unmarshal).unwrap(), h);
+        assert!(reader.is_empty());
+
+        let mut reader = buf.as_slice();
+        skip_prefixed(&mut reader).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_prefixed_errors() {
+        // The unmarshaler must consume the whole value.
+        let buf = [2u8, 5, 6];
+        assert_eq!(unmarshal_prefixed(&mut &buf[..], unmarshal_u8).err(), Some(Error::TrailingBytes));
+        // The unmarshaler cannot read past the value.
+        let buf = [1u8, 5, 6];
+        assert!(matches!(unmarshal_prefixed(&mut &buf[..], unmarshal_u16), Err(Error::BufferTooSmall { .. })));
+
+        // A sizer that disagrees with the marshaler is caught.
+        let mut buf = [0u8; 8];
+        let err = marshal_prefixed(&7u32, &mut &mut buf[..], |_| 2, |v, w| marshal_u32(*v, w)).err();
+        assert_eq!(err, Some(Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_prefixed_field() {
+        let e = Envelope { header: header(), body: "hello".into() };
+        let buf = e.to_vec();
+        assert_eq!(buf.len(), e.size());
+        assert_eq!(from_slice::<Envelope>(&buf).unwrap(), e);
+        assert_eq!(Envelope::decode_field_body(&mut buf.as_slice()).unwrap(), "hello");
+        assert_eq!(Envelope::decode_field_header(&mut buf.as_slice()).unwrap(), header());
+        assert!(Envelope::benc_type().is_none());
+
+        let mut reader = buf.as_slice();
+        Envelope::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        // A prefixed struct written before its trailing default field existed still
+        // decodes, even though more fields follow it.
+        let mut old = vec![0u8; size_prefixed(&header(), |h| h.size() - 1)];
+        marshal_prefixed(&header(), &mut old.as_mut_slice(), |h| h.size() - 1, |h, w| {
+            marshal_slice(&h.names, w, |s, w| marshal_string(s, w))?;
+            marshal_u32(h.version, w)
+        })
+        .unwrap();
+        old.extend_from_slice(&"hello".to_vec());
+        let decoded = from_slice::<Envelope>(&old).unwrap();
+        assert_eq!(decoded.header, Header { flags: 0, ..header() });
+        assert_eq!(decoded.body, "hello");
+    }
+
+    #[test]
+    fn test_prefixed_tagged_field() {
+        let e = TaggedEnvelope { header: header(), body: "hello".into() };
+        let buf = e.to_vec();
+        assert_eq!(buf.len(), e.size());
+        assert_eq!(from_slice::<TaggedEnvelope>(&buf).unwrap(), e);
+        assert_eq!(TaggedEnvelope::decode_field_body(&mut buf.as_slice()).unwrap(), "hello");
+        assert_eq!(TaggedEnvelope::decode_field_header(&mut buf.as_slice()).unwrap(), header());
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct User {
+            id: u32,
+            name: String,
+        }
+    }
+
+    fn user_schema() -> Schema {
+        Schema::new().field("id", Type::U32).field("name", Type::String)
+    }
+
+    #[test]
+    fn test_schema_id_prefix() {
+        let user = User { id: 1, name: "ann".into() };
+        let buf = to_vec_with_schema_id(300, &user);
+        assert_eq!(&buf[..2], [0xac, 0x02]);
+        assert_eq!(&buf[2..], user.to_vec());
+        assert_eq!(unmarshal_schema_id(&mut buf.as_slice()).unwrap(), 300);
+
+        assert_eq!(from_slice_with_schema_id::<User>(300, &buf).unwrap(), user);
+        assert_eq!(from_slice_with_schema_id::<User>(301, &buf), Err(Error::UnknownSchema(300)));
+    }
+
+    #[test]
+    fn test_resolvers() {
+        let buf = to_vec_with_schema_id(5, &User { id: 2, name: "bo".into() });
+        let expected = Value::Struct(vec![("id".into(), Value::U32(2)), ("name".into(), Value::String("bo".into()))]);
+
+        let registry = HashMap::from([(5, Arc::new(user_schema()))]);
+        assert_eq!(value_from_slice_with_resolver(&buf, &registry).unwrap(), (5, expected.clone()));
+        assert_eq!(value_from_slice_with_resolver(&to_vec_with_schema_id(6, &0u8), &registry), Err(Error::UnknownSchema(6)));
+
+        let fetches = Cell::new(0);
+        let cache = SchemaCache::new(|id| {
+            fetches.set(fetches.get() + 1);
+            if id == 5 { Ok(user_schema()) } else { Err(Error::UnknownSchema(id)) }
+        });
+        for _ in 0..3 {
+            assert_eq!(value_from_slice_with_resolver(&buf, &cache).unwrap(), (5, expected.clone()));
+        }
+        assert_eq!(fetches.get(), 1);
+        assert_eq!(cache.resolve(9), Err(Error::UnknownSchema(9)));
+        assert_eq!(cache.resolve(9), Err(Error::UnknownSchema(9)));
+        assert_eq!(fetches.get(), 3);
+
+        cache.insert(9, Schema::new().field("v", Type::U8));
+        assert_eq!(cache.resolve(9).unwrap().fields().len(), 1);
+        assert_eq!(fetches.get(), 3);
+    }
+}
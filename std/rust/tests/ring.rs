@@ -0,0 +1,101 @@
+#![cfg(feature = "ring")]
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use benc::*;
+
+    #[test]
+    fn test_ring_push_and_pop() {
+        let (mut producer, mut consumer) = ring(16);
+        assert!(consumer.is_empty());
+        assert!(consumer.try_pop().unwrap().is_none());
+
+        // Each record is the varint length plus one and the message.
+        assert!(producer.try_push(size_string("abc"), |w| marshal_string("abc", w)).unwrap());
+        assert!(producer.try_push(0, |_| Ok(())).unwrap());
+        {
+            let record = consumer.try_pop().unwrap().unwrap();
+            assert_eq!(record.as_bytes(), [3, b'a', b'b', b'c']);
+            assert_eq!(record.decode(unmarshal_string).unwrap(), "abc");
+        }
+        assert!(consumer.try_pop().unwrap().unwrap().as_bytes().is_empty());
+        assert!(consumer.is_empty());
+
+        // Six bytes are used; a ten-byte record does not fit before the end and wraps.
+        assert!(producer.try_push(9, |w| marshal_u64(7, w).and_then(|()| marshal_u8(1, w))).unwrap());
+        let record = consumer.try_pop().unwrap().unwrap();
+        assert_eq!(record.decode(|r| Ok((unmarshal_u64(r)?, unmarshal_u8(r)?))).unwrap(), (7, 1));
+    }
+
+    #[test]
+    fn test_ring_full_and_errors() {
+        let (mut producer, mut consumer) = ring(8);
+        assert!(producer.try_push(size_u32(), |w| marshal_u32(1, w)).unwrap());
+        // Five bytes are used, and the record cannot be freed while it is borrowed.
+        assert!(!producer.try_push(size_u32(), |w| marshal_u32(2, w)).unwrap());
+        drop(consumer.try_pop().unwrap().unwrap());
+        assert!(producer.try_push(size_u32(), |w| marshal_u32(2, w)).unwrap());
+
+        assert_eq!(producer.try_push(8, |_| Ok(())), Err(Error::BufferTooSmall { needed: 9, available: 8 }));
+        assert_eq!(consumer.try_pop().unwrap().unwrap().decode(unmarshal_u32).unwrap(), 2);
+
+        // Failed pushes publish nothing.
+        assert_eq!(producer.try_push(2, |w| marshal_u8(1, w)), Err(Error::InvalidValue));
+        assert_eq!(producer.try_push(1, |_| Err(Error::OutOfRange)), Err(Error::OutOfRange));
+        assert!(consumer.try_pop().unwrap().is_none());
+
+        let size = usize::MAX;
+        assert_eq!(producer.try_push(size, |_| Ok(())), Err(Error::OutOfRange));
+    }
+
+    #[test]
+    fn test_ring_restarts_when_empty() {
+        let (mut producer, mut consumer) = ring(16);
+        assert!(producer.try_push(size_bytes(&[1]), |w| marshal_bytes(&[1], w)).unwrap());
+        drop(consumer.try_pop().unwrap().unwrap());
+
+        // Fourteen bytes fit neither before the end nor after the three used bytes, but
+        // the ring is empty and starts over.
+        let message = [7u8; 12];
+        assert!(producer.try_push(size_bytes(&message), |w| marshal_bytes(&message, w)).unwrap());
+        assert!(!producer.try_push(size_bytes(&[2, 2]), |w| marshal_bytes(&[2, 2], w)).unwrap());
+        assert_eq!(consumer.try_pop().unwrap().unwrap().decode(unmarshal_bytes_cropped).unwrap(), message);
+        assert!(consumer.is_empty());
+        assert!(producer.try_push(size_bytes(&[2, 2]), |w| marshal_bytes(&[2, 2], w)).unwrap());
+        assert_eq!(consumer.try_pop().unwrap().unwrap().decode(unmarshal_bytes_cropped).unwrap(), [2, 2]);
+    }
+
+    #[test]
+    fn test_ring_across_threads() {
+        let (mut producer, mut consumer) = ring(256);
+        let handle = thread::spawn(move || {
+            for i in 0..10_000u32 {
+                let text = "x".repeat(i as usize % 40);
+                while !producer
+                    .try_push(size_u32() + size_string(&text), |w| {
+                        marshal_u32(i, w)?;
+                        marshal_string(&text, w)
+                    })
+                    .unwrap()
+                {
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut i = 0;
+        while i < 10_000u32 {
+            let Some(record) = consumer.try_pop().unwrap() else {
+                thread::yield_now();
+                continue;
+            };
+            let (n, text) = record.decode(|r| Ok((unmarshal_u32(r)?, unmarshal_string(r)?))).unwrap();
+            assert_eq!(n, i);
+            assert_eq!(text.len(), i as usize % 40);
+            i += 1;
+        }
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    use benc::*;
+
+    /// Moves the pending bytes of one endpoint to the other.
+    fn deliver(from: &mut RpcEndpoint, to: &mut RpcEndpoint) {
+        let n = from.transmit().len();
+        to.receive(from.transmit());
+        from.consume(n);
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let mut client = RpcEndpoint::new();
+        let mut server = RpcEndpoint::new();
+
+        let first = client.call("add", &[1, 2]);
+        let second = client.call("div", &[1, 0]);
+        client.notify("log", b"started");
+        assert_eq!(client.in_flight(), 2);
+        deliver(&mut client, &mut server);
+
+        let mut requests = Vec::new();
+        while let Some(event) = server.poll_event().unwrap() {
+            requests.push(event);
+        }
+        assert_eq!(requests[0], RpcEvent::Request { id: first, method: "add".into(), payload: vec![1, 2] });
+        assert_eq!(requests[2], RpcEvent::Notification { method: "log".into(), payload: b"started".to_vec() });
+
+        server.respond_error(second, "division by zero");
+        server.respond(first, &[3]);
+        deliver(&mut server, &mut client);
+        assert_eq!(
+            client.poll_event().unwrap(),
+            Some(RpcEvent::Response { id: second, result: Err("division by zero".into()) })
+        );
+        assert_eq!(client.poll_event().unwrap(), Some(RpcEvent::Response { id: first, result: Ok(vec![3]) }));
+        assert_eq!(client.poll_event().unwrap(), None);
+        assert_eq!(client.in_flight(), 0);
+
+        // A response to a call that is not in flight is a protocol error.
+        server.respond(first, &[3]);
+        deliver(&mut server, &mut client);
+        assert_eq!(client.poll_event().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Serves `upper` requests until the client disconnects, notifying it first.
+    fn serve(stream: UnixStream) -> io::Result<usize> {
+        let mut rpc = BlockingRpc::new(stream);
+        let mut served = 0;
+        while let Some(event) = rpc.next_event()? {
+            if let RpcEvent::Request { id, method, payload } = event {
+                rpc.notify("progress", &[served as u8])?;
+                match method.as_str() {
+                    "upper" => rpc.respond(id, &payload.to_ascii_uppercase())?,
+                    _ => rpc.respond_error(id, &format!("unknown method {method}"))?,
+                }
+                served += 1;
+            }
+        }
+        Ok(served)
+    }
+
+    #[test]
+    fn test_blocking_rpc() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let handle = thread::spawn(move || serve(server));
+
+        let mut rpc = BlockingRpc::new(client);
+        assert_eq!(rpc.call("upper", b"benc").unwrap(), Ok(b"BENC".to_vec()));
+        assert_eq!(rpc.call("lower", b"BENC").unwrap(), Err("unknown method lower".into()));
+
+        // The notifications sent before each response were kept.
+        for i in 0..2 {
+            assert_eq!(rpc.next_event().unwrap(), Some(RpcEvent::Notification { method: "progress".into(), payload: vec![i] }));
+        }
+        drop(rpc);
+        assert_eq!(handle.join().unwrap().unwrap(), 2);
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use benc::*;
+    use chrono::{DateTime, Utc};
+
+    mod hex {
+        pub use benc::{marshal_string as marshal, size_string as size, skip_string as skip};
+
+        pub fn unmarshal(reader: &mut &[u8]) -> benc::Result<String> {
+            benc::unmarshal_string(reader).map(String::from)
+        }
+    }
+
+    benc_struct! {
+        struct Point {
+            x: i32,
+            y: i32,
+            at: DateTime<Utc>,
+        }
+    }
+
+    benc_struct! {
+        struct Doc<'a> {
+            title: &'a str,
+            count: usize,
+            #[benc(skip)]
+            cache: u64,
+            tags: Vec<String>,
+            meta: HashMap<String, Option<i64>>,
+            origin: Point,
+        }
+    }
+
+    benc_struct! {
+        struct Opaque {
+            id: u8,
+            #[benc(with = hex)]
+            hash: String,
+        }
+    }
+
+    benc_struct! {
+        struct Empty;
+    }
+
+    #[test]
+    fn test_schema_of_derived() {
+        let point = schema_of::<Point>().unwrap();
+        assert_eq!(point, Schema::new().field("x", Type::I32).field("y", Type::I32).field("at", Type::Time));
+        assert_eq!(point.types().collect::<Vec<_>>(), [&Type::I32, &Type::I32, &Type::Time]);
+        assert_eq!(point.encoded_fixed_size(), Some(16));
+        assert_eq!(point.encoded_fixed_size(), Point::ENCODED_SIZE);
+
+        let doc = schema_of::<Doc>().unwrap();
+        let names: Vec<_> = doc.fields().iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["title", "count", "tags", "meta", "origin"]);
+        assert_eq!(doc.fields()[1].ty, Type::Uint);
+        assert_eq!(doc.fields()[2].ty, Type::Slice(Box::new(Type::String)));
+        assert_eq!(
+            doc.fields()[3].ty,
+            Type::Map(Box::new(Type::String), Box::new(Type::Option(Box::new(Type::I64))))
+        );
+        assert_eq!(doc.fields()[4].ty, Type::Struct(point));
+        assert_eq!(doc.encoded_fixed_size(), None);
+
+        assert_eq!(schema_of::<Empty>(), Some(Schema::new()));
+        assert_eq!(schema_of::<Empty>().unwrap().encoded_fixed_size(), Some(0));
+        assert_eq!(schema_of::<Opaque>(), None);
+        assert_eq!(schema_of::<u32>(), None);
+    }
+
+    #[test]
+    fn test_derived_schema_drives_tooling() {
+        let doc = Doc {
+            title: "t",
+            count: 3,
+            cache: 99,
+            tags: vec!["a".into()],
+            meta: HashMap::from([("k".into(), Some(-1))]),
+            origin: Point { x: 1, y: 2, at: DateTime::from_timestamp_nanos(0) },
+        };
+        let buf = doc.to_vec();
+        let value = value_from_slice(&buf, &schema_of::<Doc>().unwrap()).unwrap();
+        assert_eq!(value.field("count"), Some(&Value::Uint(3)));
+        assert_eq!(value.field("origin").and_then(|o| o.field("y")), Some(&Value::I32(2)));
+        assert!(dump(&schema_of::<Doc>().unwrap(), &buf).unwrap().contains("title: \"t\""));
+    }
+}
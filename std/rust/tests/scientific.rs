@@ -0,0 +1,129 @@
+#![cfg(any(feature = "num-complex", feature = "ndarray"))]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[cfg(feature = "num-complex")]
+    #[test]
+    fn test_complex() {
+        use num_complex::Complex;
+
+        let c32 = Complex::new(1.5f32, -0.25);
+        let c64 = Complex::new(-3.0f64, 1e-9);
+        let size = size_complex_f32() + size_complex_f64();
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_complex_f32(c32, &mut writer).unwrap();
+        marshal_complex_f64(c64, &mut writer).unwrap();
+        assert!(writer.is_empty());
+
+        let mut skipper = buf.as_slice();
+        skip_complex_f32(&mut skipper).unwrap();
+        skip_complex_f64(&mut skipper).unwrap();
+        assert!(skipper.is_empty());
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_complex_f32(&mut reader).unwrap(), c32);
+        assert_eq!(unmarshal_complex_f64(&mut reader).unwrap(), c64);
+        assert!(reader.is_empty());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_array1() {
+        use ndarray::Array1;
+
+        let array = Array1::from_vec(vec![1.0f64, 2.0, 3.5]);
+        let size = size_array1(&array, |_| size_f64());
+        let mut buf = vec![0u8; size];
+        marshal_array1(&array, &mut buf.as_mut_slice(), |v, w| marshal_f64(*v, w)).unwrap();
+
+        // A one-dimensional array shares the slice layout.
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_slice(&mut reader, unmarshal_f64).unwrap(), array.iter().copied().collect::<Vec<_>>());
+
+        let mut skipper = buf.as_slice();
+        skip_array1(&mut skipper, skip_f64).unwrap();
+        assert!(skipper.is_empty());
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_array1(&mut reader, unmarshal_f64).unwrap(), array);
+        assert!(reader.is_empty());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_array2() {
+        use ndarray::array;
+
+        let array = array![[1i32, 2, 3], [4, 5, 6]];
+        // A transposed view must still be written in logical row-major order.
+        let transposed = array.t();
+        let size = size_array2(&transposed, |_| size_i32());
+        let mut buf = vec![0u8; size];
+        marshal_array2(&transposed, &mut buf.as_mut_slice(), |v, w| marshal_i32(*v, w)).unwrap();
+
+        let mut skipper = buf.as_slice();
+        skip_array2(&mut skipper, skip_i32).unwrap();
+        assert!(skipper.is_empty());
+
+        let mut reader = buf.as_slice();
+        let decoded = unmarshal_array2(&mut reader, unmarshal_i32).unwrap();
+        assert_eq!(decoded, array![[1, 4], [2, 5], [3, 6]]);
+        assert!(reader.is_empty());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_array2_errors() {
+        // Shape that overflows usize.
+        let mut buf = vec![0u8; 2 * size_usize(usize::MAX)];
+        let mut writer = buf.as_mut_slice();
+        marshal_usize(usize::MAX, &mut writer).unwrap();
+        marshal_usize(usize::MAX, &mut writer).unwrap();
+        assert_eq!(unmarshal_array2(&mut buf.as_slice(), unmarshal_u8).err(), Some(Error::OutOfRange));
+        assert_eq!(skip_array2(&mut buf.as_slice(), skip_u8).err(), Some(Error::OutOfRange));
+
+        // Missing terminator.
+        let mut reader = &[1u8, 1, 7, 0, 0, 0, 0][..];
+        assert_eq!(unmarshal_array2(&mut reader, unmarshal_u8).err(), Some(Error::MissingTerminator));
+    }
+
+    #[cfg(feature = "num-complex")]
+    #[test]
+    fn test_complex_traits() {
+        use num_complex::Complex;
+
+        let values = vec![Complex::new(1.0f64, 2.0), Complex::new(-0.5, 0.0)];
+        let buf = values.to_vec();
+        assert_eq!(<Complex<f64>>::ENCODED_SIZE, Some(size_complex_f64()));
+        assert_eq!(from_slice::<Vec<Complex<f64>>>(&buf).unwrap(), values);
+        assert_eq!(Complex::new(1.5f32, 2.5).to_vec().len(), size_complex_f32());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_array_traits() {
+        use ndarray::{Array1, Array2, array};
+
+        benc_struct! {
+            #[derive(Debug, PartialEq)]
+            struct Grid {
+                weights: Array1<f32>,
+                cells: Array2<u16>,
+            }
+        }
+
+        let grid = Grid { weights: array![0.5, 1.5], cells: array![[1, 2, 3], [4, 5, 6]] };
+        let buf = grid.to_vec();
+        assert_eq!(buf.len(), grid.size());
+        // A one-dimensional array is marshalled like a `Vec`.
+        assert_eq!(grid.weights.to_vec(), vec![0.5f32, 1.5].to_vec());
+        assert_eq!(from_slice::<Grid>(&buf).unwrap(), grid);
+
+        let mut reader = buf.as_slice();
+        Grid::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+    }
+}
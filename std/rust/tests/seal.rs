@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    /// A toy sealer for tests: XORs with the key and appends a one-byte checksum.
+    struct XorSealer(u8);
+
+    impl Sealer for XorSealer {
+        fn overhead(&self) -> usize {
+            1
+        }
+
+        fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+            let mut out: Vec<u8> = plaintext.iter().map(|b| b ^ self.0).collect();
+            out.push(plaintext.iter().fold(self.0, |acc, b| acc.wrapping_add(*b)));
+            out
+        }
+
+        fn open(&self, ciphertext: &[u8]) -> Option<Vec<u8>> {
+            let (tag, data) = ciphertext.split_last()?;
+            let plain: Vec<u8> = data.iter().map(|b| b ^ self.0).collect();
+            (plain.iter().fold(self.0, |acc, b| acc.wrapping_add(*b)) == *tag).then_some(plain)
+        }
+    }
+
+    fn encode(sealer: &XorSealer) -> Vec<u8> {
+        let route = "eu-west";
+        let ssn = "078-05-1120";
+        let size = size_string(route) + size_sealed(sealer, size_string(ssn)) + size_u32();
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_string(route, &mut writer).unwrap();
+        marshal_sealed(sealer, size_string(ssn), &mut writer, |w| marshal_string(ssn, w)).unwrap();
+        marshal_u32(42, &mut writer).unwrap();
+        assert!(writer.is_empty(), "marshal did not fill the buffer");
+        buf
+    }
+
+    #[test]
+    fn test_sealed_field() {
+        let sealer = XorSealer(0x5A);
+        let buf = encode(&sealer);
+        assert!(!buf.windows(11).any(|w| w == b"078-05-1120"), "plaintext leaked");
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_string(&mut reader).unwrap(), "eu-west");
+        let ssn = unmarshal_sealed(&mut reader, &sealer, |r| unmarshal_string(r).map(String::from)).unwrap();
+        assert_eq!(ssn, "078-05-1120");
+        assert_eq!(unmarshal_u32(&mut reader).unwrap(), 42);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_sealed_field_without_key() {
+        let buf = encode(&XorSealer(0x5A));
+
+        // Routing fields remain readable and the sealed field can be skipped.
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_string(&mut reader).unwrap(), "eu-west");
+        skip_sealed(&mut reader).unwrap();
+        assert_eq!(unmarshal_u32(&mut reader).unwrap(), 42);
+
+        let mut reader = buf.as_slice();
+        skip_string(&mut reader).unwrap();
+        assert_eq!(unmarshal_sealed_raw(&mut reader).unwrap().len(), size_string("078-05-1120") + 1);
+    }
+
+    #[test]
+    fn test_sealed_field_errors() {
+        let buf = encode(&XorSealer(0x5A));
+        let mut reader = buf.as_slice();
+        skip_string(&mut reader).unwrap();
+        let result = unmarshal_sealed(&mut reader, &XorSealer(0x11), |r| unmarshal_string(r).map(String::from));
+        assert_eq!(result.err(), Some(Error::Authentication));
+
+        let mut reader = buf.as_slice();
+        skip_string(&mut reader).unwrap();
+        let result = unmarshal_sealed(&mut reader, &XorSealer(0x5A), unmarshal_u8);
+        assert_eq!(result.err(), Some(Error::TrailingBytes));
+    }
+
+    /// Returns a sealer that appends nothing, although it claims one byte of overhead.
+    struct ShortSealer;
+
+    impl Sealer for ShortSealer {
+        fn overhead(&self) -> usize {
+            1
+        }
+
+        fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+            plaintext.to_vec()
+        }
+
+        fn open(&self, ciphertext: &[u8]) -> Option<Vec<u8>> {
+            Some(ciphertext.to_vec())
+        }
+    }
+
+    #[test]
+    fn test_sealed_field_size_mismatch() {
+        let size = size_sealed(&ShortSealer, size_u32());
+        let mut buf = vec![0u8; size];
+        let err = marshal_sealed(&ShortSealer, size_u32(), &mut buf.as_mut_slice(), |w| marshal_u32(7, w)).err();
+        assert_eq!(err, Some(Error::InvalidValue));
+
+        // A marshaler writing fewer bytes than announced is caught as well.
+        let sealer = XorSealer(0x5A);
+        let size = size_sealed(&sealer, 8);
+        let mut buf = vec![0u8; size];
+        let err = marshal_sealed(&sealer, 8, &mut buf.as_mut_slice(), |w| marshal_u32(7, w)).err();
+        assert_eq!(err, Some(Error::InvalidValue));
+    }
+
+    static KEY: XorSealer = XorSealer(0x5A);
+
+    fn key() -> &'static XorSealer {
+        &KEY
+    }
+
+    fn wrong_key() -> XorSealer {
+        XorSealer(0x11)
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Event {
+            route: String,
+            #[benc(encrypt = key)]
+            ssn: String,
+            count: u32,
+        }
+    }
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct WrongKeyEvent {
+            route: String,
+            #[benc(encrypt = wrong_key)]
+            ssn: String,
+            count: u32,
+        }
+    }
+
+    benc_struct! {
+        #[benc(tagged)]
+        #[derive(Debug, PartialEq)]
+        struct TaggedEvent {
+            #[benc(id = 1)]
+            route: String,
+            #[benc(id = 2, encrypt = key)]
+            ssn: String,
+        }
+    }
+
+    #[test]
+    fn test_encrypt_attribute() {
+        let event = Event { route: "eu-west".into(), ssn: "078-05-1120".into(), count: 42 };
+        let buf = event.to_vec();
+        assert_eq!(buf.len(), event.size());
+        // The field is laid out exactly as `marshal_sealed` writes it.
+        assert_eq!(buf, encode(&KEY));
+        assert_eq!(from_slice::<Event>(&buf).unwrap(), event);
+        assert_eq!(Event::decode_field_count(&mut buf.as_slice()).unwrap(), 42);
+        assert!(Event::benc_type().is_none());
+
+        let mut reader = buf.as_slice();
+        Event::skip(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        assert_eq!(from_slice::<WrongKeyEvent>(&buf).err(), Some(Error::Authentication));
+
+        let tagged = TaggedEvent { route: "eu-west".into(), ssn: "078-05-1120".into() };
+        let buf = tagged.to_vec();
+        assert!(!buf.windows(11).any(|w| w == b"078-05-1120"), "plaintext leaked");
+        assert_eq!(from_slice::<TaggedEvent>(&buf).unwrap(), tagged);
+        assert_eq!(TaggedEvent::decode_field_route(&mut buf.as_slice()).unwrap(), "eu-west");
+    }
+}
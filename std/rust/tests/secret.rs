@@ -0,0 +1,40 @@
+#![cfg(feature = "zeroize")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[test]
+    fn test_secret_values() {
+        let key = [7u8; 32];
+        let password = "correct horse battery staple";
+        let mut buf = vec![0u8; size_bytes(&key) + size_string(password) + size_u64()];
+        let mut writer = buf.as_mut_slice();
+        marshal_bytes(&key, &mut writer).unwrap();
+        marshal_string(password, &mut writer).unwrap();
+        marshal_u64(0xDEAD_BEEF, &mut writer).unwrap();
+
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_secret_bytes(&mut reader).unwrap().as_slice(), &key);
+        assert_eq!(unmarshal_secret_string(&mut reader).unwrap().as_str(), password);
+        assert_eq!(*unmarshal_secret(&mut reader, unmarshal_u64).unwrap(), 0xDEAD_BEEF);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_secret_string_invalid_utf8() {
+        let buf = [2, 0xFF, 0xFE];
+        assert!(matches!(unmarshal_secret_string(&mut buf.as_slice()), Err(Error::InvalidUtf8(_))));
+    }
+
+    #[test]
+    fn test_slice_builder_finish_zeroizing() {
+        let mut builder = SliceBuilder::new();
+        for i in 0..200u64 {
+            builder.push(size_u64(), |w| marshal_u64(i, w)).unwrap();
+        }
+        let buf = builder.finish_zeroizing();
+        let values = unmarshal_slice(&mut buf.as_slice(), unmarshal_u64).unwrap();
+        assert_eq!(values, (0..200).collect::<Vec<_>>());
+    }
+}
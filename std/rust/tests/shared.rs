@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use benc::*;
+
+    struct Order;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .field("id", Type::U64)
+            .field("symbol", Type::String)
+            .field("blob", Type::Bytes)
+            .field("qty", Type::Uint)
+    }
+
+    fn encode() -> Vec<u8> {
+        let size = size_u64() + size_string("ACME") + size_bytes(&[9, 9]) + size_uint(250);
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_u64(1, &mut writer).unwrap();
+        marshal_string("ACME", &mut writer).unwrap();
+        marshal_bytes(&[9, 9], &mut writer).unwrap();
+        marshal_uint(250, &mut writer).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_shared_message_fields() {
+        let msg: SharedMessage<Order> = SharedMessage::new(encode(), &schema()).unwrap();
+        assert_eq!(msg.len(), 4);
+        assert_eq!(msg.decode_field(0, unmarshal_u64).unwrap(), 1);
+        assert_eq!(msg.string(1).unwrap(), "ACME");
+        assert_eq!(msg.bytes(2).unwrap(), &[9, 9]);
+        assert_eq!(msg.decode_field(3, unmarshal_uint).unwrap(), 250);
+        assert_eq!(msg.field(3), Some(&[250, 1][..]));
+        assert_eq!(msg.field(4), None);
+        assert_eq!(msg.decode_field(4, unmarshal_uint), Err(Error::OutOfRange));
+        assert_eq!(msg.decode_field(0, unmarshal_u32), Err(Error::TrailingBytes));
+
+        // The string borrows from the shared buffer.
+        let range = msg.as_bytes().as_ptr_range();
+        assert!(range.contains(&msg.string(1).unwrap().as_ptr()));
+    }
+
+    #[test]
+    fn test_shared_message_threads() {
+        let msg: SharedMessage<Order> = SharedMessage::new(encode(), &schema()).unwrap();
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let msg = msg.clone();
+                thread::spawn(move || msg.string(1).unwrap().len() as u64 + msg.decode_field(3, unmarshal_uint).unwrap())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 254);
+        }
+    }
+
+    #[test]
+    fn test_shared_message_invalid() {
+        let mut buf = encode();
+        buf.push(0);
+        assert!(matches!(SharedMessage::<Order>::new(buf, &schema()), Err(Error::TrailingBytes)));
+        let buf = encode();
+        assert!(matches!(SharedMessage::<Order>::new(&buf[..10], &schema()), Err(Error::BufferTooSmall { needed: 4, available: 1 })));
+    }
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "shm")]
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::thread;
+
+    use benc::*;
+
+    fn region_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("benc-shm-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_shm_send_and_receive() {
+        let path = region_path("send");
+        // SAFETY: the region is private to this test.
+        let mut sender = unsafe { ShmSender::create(&path, 64) }.unwrap();
+        let mut receiver = unsafe { ShmReceiver::open(&path) }.unwrap();
+        assert_eq!(sender.capacity(), 64);
+        assert!(receiver.try_recv().unwrap().is_none());
+
+        assert!(sender.try_send(size_string("hello"), |w| marshal_string("hello", w)).unwrap());
+        assert!(sender.is_pending());
+        // The region holds one message at a time.
+        assert!(!sender.try_send(size_u8(), |w| marshal_u8(1, w)).unwrap());
+
+        {
+            let message = receiver.try_recv().unwrap().unwrap();
+            assert_eq!(message.sequence(), 1);
+            assert_eq!(message.decode(unmarshal_string).unwrap(), "hello");
+        }
+        assert!(!sender.is_pending());
+        assert!(receiver.try_recv().unwrap().is_none());
+
+        let result = sender.try_send(65, |_| Ok(()));
+        assert_eq!(result, Err(Error::BufferTooSmall { needed: 65, available: 64 }));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_shm_across_threads() {
+        let path = region_path("threads");
+        // SAFETY: the region is private to this test.
+        let mut sender = unsafe { ShmSender::create(&path, 1024) }.unwrap();
+        let mut receiver = unsafe { ShmReceiver::open(&path) }.unwrap();
+
+        let producer = thread::spawn(move || {
+            for i in 0..1000u32 {
+                let text = format!("message {i}");
+                while !sender.try_send(size_u32() + size_string(&text), |w| {
+                    marshal_u32(i, w)?;
+                    marshal_string(&text, w)
+                })
+                .unwrap()
+                {
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut received = 0;
+        while received < 1000 {
+            let Some(message) = receiver.try_recv().unwrap() else {
+                thread::yield_now();
+                continue;
+            };
+            let (i, text) = message.decode(|r| Ok((unmarshal_u32(r)?, unmarshal_string(r)?))).unwrap();
+            assert_eq!(i, received);
+            assert_eq!(text, format!("message {i}"));
+            assert_eq!(message.sequence(), u64::from(i) + 1);
+            received += 1;
+        }
+        producer.join().unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{IoSlice, Write};
+
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Document {
+            id: u64,
+            title: String,
+            body: Vec<u8>,
+            tags: Vec<String>,
+        }
+    }
+
+    fn document() -> Document {
+        Document { id: 1, title: "draft".into(), body: vec![7; 1000], tags: vec!["a".into(), "b".into()] }
+    }
+
+    #[test]
+    fn test_replace_field() {
+        let doc = document();
+        let buf = doc.to_vec();
+        let schema = schema_of::<Document>().unwrap();
+
+        let range = field_range(&buf, &schema, "title").unwrap();
+        assert_eq!(range, 8..14);
+        let title = "final version".to_string();
+        let replaced = replace_field(&buf, range.clone(), &title.to_vec());
+
+        let expected = Document { title, ..document() };
+        assert_eq!(replaced, expected.to_vec());
+        assert_eq!(from_slice::<Document>(&replaced).unwrap(), expected);
+
+        // The slices write out the same message without copying it first.
+        let new_title = expected.title.to_vec();
+        let parts = replace_field_slices(&buf, range, &new_title);
+        let mut written = Vec::new();
+        let n = written.write_vectored(&parts.map(IoSlice::new)).unwrap();
+        assert_eq!(n, replaced.len());
+        assert_eq!(written, replaced);
+
+        let last = field_range(&buf, &schema, "tags").unwrap();
+        assert_eq!(last.end, buf.len());
+    }
+
+    #[test]
+    fn test_field_range_errors() {
+        let buf = document().to_vec();
+        let schema = schema_of::<Document>().unwrap();
+        assert_eq!(field_range(&buf, &schema, "missing"), Err(Error::OutOfRange));
+        assert!(matches!(field_range(&buf[..20], &schema, "body"), Err(Error::BufferTooSmall { .. })));
+    }
+}
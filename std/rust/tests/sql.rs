@@ -0,0 +1,61 @@
+#![cfg(feature = "sqlx")]
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::{self, Thread};
+
+    use benc::*;
+    use sqlx::{Connection, SqliteConnection};
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        struct User {
+            id: u64,
+            name: String,
+        }
+    }
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Runs a future to completion on the current thread.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_encoded_blob_column() {
+        block_on(async {
+            let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+            sqlx::query("CREATE TABLE users (data BLOB NOT NULL)").execute(&mut conn).await.unwrap();
+
+            let user = User { id: 42, name: "ada".to_string() };
+            let encoded: Encoded<User> = Encoded::encode(user.size(), |w| user.marshal(w)).unwrap();
+            sqlx::query("INSERT INTO users (data) VALUES (?)").bind(&encoded).execute(&mut conn).await.unwrap();
+
+            let (stored,): (Encoded<User>,) =
+                sqlx::query_as("SELECT data FROM users").fetch_one(&mut conn).await.unwrap();
+            assert_eq!(stored, encoded);
+            assert_eq!(stored.decode(User::unmarshal).unwrap(), user);
+
+            let (raw,): (Vec<u8>,) = sqlx::query_as("SELECT data FROM users").fetch_one(&mut conn).await.unwrap();
+            assert_eq!(raw, user.to_vec());
+        });
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Row {
+            id: u64,
+            name: String,
+        }
+    }
+
+    fn rows(n: u64) -> Vec<Row> {
+        (0..n).map(|id| Row { id, name: format!("row {}", "x".repeat(id as usize % 50)) }).collect()
+    }
+
+    fn read_all(stream: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        StreamReader::new(stream).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_stream_layout() {
+        let mut writer = StreamWriter::new(Vec::new());
+        writer.write_all(b"123456789").unwrap();
+        let stream = writer.finish().unwrap();
+
+        // The CRC-32 of "123456789" is 0xcbf43926, for the chunk and the whole stream.
+        let mut expected = vec![9, 0];
+        expected.extend_from_slice(b"123456789");
+        expected.extend_from_slice(&0xcbf4_3926u32.to_le_bytes());
+        expected.push(0);
+        expected.extend_from_slice(&0xcbf4_3926u32.to_le_bytes());
+        assert_eq!(stream, expected);
+        assert_eq!(read_all(&stream).unwrap(), b"123456789");
+    }
+
+    #[test]
+    fn test_stream_messages() {
+        let mut writer = StreamWriter::new(Vec::new()).with_chunk_len(100);
+        for row in rows(500) {
+            writer.write_message(&row).unwrap();
+        }
+        writer.flush().unwrap();
+        writer.write_message(&Row { id: 1, name: "after flush".into() }).unwrap();
+        let stream = writer.finish().unwrap();
+
+        let decoded: Vec<Row> = DecodeIter::new(StreamReader::new(&stream[..])).collect::<io::Result<_>>().unwrap();
+        assert_eq!(decoded.len(), 501);
+        assert_eq!(decoded[..500], rows(500));
+        assert_eq!(decoded[500].name, "after flush");
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let mut writer = StreamWriter::new(Vec::new()).with_chunk_len(16);
+        writer.write_all(&[7; 40]).unwrap();
+        let stream = writer.finish().unwrap();
+        assert_eq!(read_all(&stream).unwrap(), [7; 40]);
+
+        let mut corrupt = stream.clone();
+        corrupt[5] ^= 1;
+        assert_eq!(read_all(&corrupt).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Reading on after a failed chunk never returns its unverified bytes.
+        let mut writer = StreamWriter::new(Vec::new());
+        writer.write_all(b"hello world").unwrap();
+        let mut corrupt = writer.finish().unwrap();
+        corrupt[3] ^= 0x20;
+        let mut reader = StreamReader::new(&corrupt[..]);
+        let mut out = [0u8; 16];
+        assert_eq!(reader.read(&mut out).unwrap_err().to_string(), "chunk checksum mismatch");
+        assert!(!matches!(reader.read(&mut out), Ok(n) if n > 0));
+
+        // A stream cut between chunks is detected by its missing end.
+        let cut = &stream[..2 * (2 + 16 + 4)];
+        assert_eq!(read_all(cut).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut reader = StreamReader::new(&stream[..]).with_max_chunk_len(8);
+        assert_eq!(reader.read_to_end(&mut Vec::new()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_stream_compression() {
+        let mut writer = StreamWriter::new(Vec::new()).with_chunk_len(4096).with_compression(3);
+        for row in rows(2000) {
+            writer.write_message(&row).unwrap();
+        }
+        let stream = writer.finish().unwrap();
+
+        let mut plain = StreamWriter::new(Vec::new()).with_chunk_len(4096);
+        for row in rows(2000) {
+            plain.write_message(&row).unwrap();
+        }
+        assert!(stream.len() * 4 < plain.finish().unwrap().len());
+
+        let decoded: Vec<Row> = DecodeIter::new(StreamReader::new(&stream[..])).collect::<io::Result<_>>().unwrap();
+        assert_eq!(decoded, rows(2000));
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[test]
+    fn test_key() {
+        for (id, wire_type) in [(0, WireType::Varint), (15, WireType::Fixed8), (300, WireType::Bytes)] {
+            let mut buf = vec![0u8; size_key(id, wire_type)];
+            marshal_key(id, wire_type, &mut buf.as_mut_slice()).unwrap();
+            assert_eq!(unmarshal_key(&mut buf.as_slice()).unwrap(), (id, wire_type));
+        }
+        assert_eq!(size_key(15, WireType::Fixed8), 1);
+        assert_eq!(size_key(16, WireType::Fixed8), 2);
+        assert_eq!(unmarshal_key(&mut &[6u8][..]), Err(Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_tagged_writer_and_reader() {
+        let names = vec!["a".to_string(), "b".to_string()];
+        let names_size = size_slice(&names, |s| size_string(s));
+        let size = size_tagged(
+            2,
+            size_tagged_field(1, Some(WireType::Varint), size_uint(300))
+                + size_tagged_field(2, None, names_size),
+        );
+
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        let mut tagged = TaggedWriter::new(&mut writer, 2).unwrap();
+        tagged.field(1, Some(WireType::Varint), size_uint(300), |w| marshal_uint(300, w)).unwrap();
+        tagged.field(2, None, names_size, |w| marshal_slice(&names, w, |s, w| marshal_string(s, w))).unwrap();
+        tagged.finish().unwrap();
+        assert!(writer.is_empty(), "marshal did not fill the buffer");
+
+        let mut reader = buf.as_slice();
+        let mut tagged = TaggedReader::new(&mut reader).unwrap();
+        assert_eq!(tagged.next_key().unwrap(), Some((1, WireType::Varint)));
+        assert_eq!(tagged.value(WireType::Varint, Some(WireType::Varint), unmarshal_uint).unwrap(), 300);
+        assert_eq!(tagged.next_key().unwrap(), Some((2, WireType::Bytes)));
+        tagged.skip(WireType::Bytes, None, |_| unreachable!()).unwrap();
+        assert_eq!(tagged.next_key().unwrap(), None);
+        assert_eq!(tagged.next_key().unwrap(), None);
+        assert!(reader.is_empty());
+
+        let mut reader = buf.as_slice();
+        let mut tagged = TaggedReader::new(&mut reader).unwrap();
+        tagged.next_key().unwrap();
+        assert_eq!(tagged.value(WireType::Varint, Some(WireType::Fixed64), unmarshal_u64), Err(Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_skip_any() {
+        let buf = [0xac, 0x02, 7, 1, 2, 1, 2, 3, 4, 2, 9, 9, 0xff];
+        let mut reader = &buf[..];
+        skip_any(&mut reader, WireType::Varint).unwrap();
+        skip_any(&mut reader, WireType::Fixed8).unwrap();
+        skip_any(&mut reader, WireType::Fixed16).unwrap();
+        skip_any(&mut reader, WireType::Fixed32).unwrap();
+        skip_any(&mut reader, WireType::Bytes).unwrap();
+        assert_eq!(reader, [0xff]);
+        assert!(matches!(skip_any(&mut reader, WireType::Fixed64), Err(Error::BufferTooSmall { .. })));
+        assert!(matches!(skip_any(&mut &[0x80u8][..], WireType::Varint), Err(Error::BufferTooSmall { .. })));
+    }
+
+    #[test]
+    fn test_skip_unknown_fields() {
+        let size = size_tagged(
+            3,
+            size_tagged_field(1, Some(WireType::Fixed64), 8)
+                + size_tagged_field(2, None, size_string("new"))
+                + size_tagged_field(3, Some(WireType::Varint), size_uint(5)),
+        );
+        let mut buf = vec![0u8; size];
+        let mut writer = buf.as_mut_slice();
+        let mut tagged = TaggedWriter::new(&mut writer, 3).unwrap();
+        tagged.field(1, Some(WireType::Fixed64), 8, |w| marshal_f64(1.5, w)).unwrap();
+        tagged.field(2, None, size_string("new"), |w| marshal_string("new", w)).unwrap();
+        tagged.field(3, Some(WireType::Varint), size_uint(5), |w| marshal_uint(5, w)).unwrap();
+        tagged.finish().unwrap();
+
+        // A reader that only knows field 3 steps over the others.
+        let mut reader = buf.as_slice();
+        let mut tagged = TaggedReader::new(&mut reader).unwrap();
+        let mut known = None;
+        while let Some((id, wire_type)) = tagged.next_key().unwrap() {
+            match id {
+                3 => known = Some(tagged.value(wire_type, Some(WireType::Varint), unmarshal_uint).unwrap()),
+                _ => tagged.skip_unknown(wire_type).unwrap(),
+            }
+        }
+        assert_eq!(known, Some(5));
+        assert!(reader.is_empty());
+    }
+}
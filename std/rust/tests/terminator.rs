@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use benc::*;
+
+    const CUSTOM: Terminator = Terminator::new([0xfe, 0xed, 0xfa, 0xce]);
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Batch {
+            ids: Vec<u8>,
+            nested: Vec<Vec<u8>>,
+        }
+    }
+
+    #[test]
+    fn test_terminator_slice_round_trip() {
+        let values = [1u16, 2, 3];
+        let mut buf = vec![0; size_fixed_slice(&values, 2)];
+        CUSTOM.marshal_slice(&values, &mut buf.as_mut_slice(), |v, w| marshal_u16(*v, w)).unwrap();
+        assert_eq!(&buf[buf.len() - 4..], CUSTOM.bytes());
+
+        let mut reader = buf.as_slice();
+        assert_eq!(CUSTOM.unmarshal_slice(&mut reader, unmarshal_u16).unwrap(), values);
+        assert!(reader.is_empty());
+        let mut reader = buf.as_slice();
+        CUSTOM.skip_slice(&mut reader, |r| unmarshal_u16(r).map(|_| ())).unwrap();
+        assert!(reader.is_empty());
+
+        // The default context reads what the free functions write.
+        let default = Terminator::default();
+        assert_eq!(default, Terminator::DEFAULT);
+        let mut buf = vec![0; size_fixed_slice(&values, 2)];
+        marshal_slice(&values, &mut buf.as_mut_slice(), |v, w| marshal_u16(*v, w)).unwrap();
+        assert_eq!(default.unmarshal_slice(&mut buf.as_slice(), unmarshal_u16).unwrap(), values);
+
+        // Strings borrow from the reader, and zero-sized elements cannot claim a length
+        // beyond the input.
+        let mut buf = vec![0; size_slice(&["ab"], |s| size_string(s))];
+        CUSTOM.marshal_slice(&["ab"], &mut buf.as_mut_slice(), |s, w| marshal_string(s, w)).unwrap();
+        assert_eq!(CUSTOM.unmarshal_slice(&mut buf.as_slice(), unmarshal_string).unwrap(), ["ab"]);
+        let mut forged = vec![0x80, 0x80, 0x04];
+        forged.extend(CUSTOM.bytes());
+        assert_eq!(CUSTOM.unmarshal_slice(&mut forged.as_slice(), <()>::unmarshal), Err(Error::InvalidValue));
+        assert_eq!(CUSTOM.skip_slice(&mut forged.as_slice(), <()>::skip), Err(Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_terminator_map_round_trip() {
+        let map: BTreeMap<u8, String> = [(1, "a".into()), (2, "b".into())].into();
+        let size = size_pairs(map.iter(), |_| size_u8(), |v| size_string(v));
+        let mut buf = vec![0; size];
+        CUSTOM.marshal_pairs(map.iter(), &mut buf.as_mut_slice(), |k, w| marshal_u8(*k, w), |v, w| marshal_string(v, w))
+            .unwrap();
+
+        let mut reader = buf.as_slice();
+        let decoded: BTreeMap<u8, String> =
+            CUSTOM.unmarshal_map_into(&mut reader, unmarshal_u8, |r| unmarshal_string(r).map(str::to_owned)).unwrap();
+        assert_eq!(decoded, map);
+        let mut reader = buf.as_slice();
+        CUSTOM.skip_map(&mut reader, |r| unmarshal_u8(r).map(|_| ()), skip_string).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_terminator_mismatch() {
+        let mut buf = vec![0; size_fixed_slice(&[7u8], 1)];
+        marshal_slice(&[7u8], &mut buf.as_mut_slice(), |v, w| marshal_u8(*v, w)).unwrap();
+        // A default terminator where a custom one is expected points at the configuration.
+        assert_eq!(
+            CUSTOM.unmarshal_slice(&mut buf.as_slice(), unmarshal_u8),
+            Err(Error::TerminatorMismatch { expected: [0xfe, 0xed, 0xfa, 0xce] })
+        );
+
+        let other = Terminator::new([9, 9, 9, 9]);
+        let mut buf = vec![0; size_fixed_slice(&[7u8], 1)];
+        other.marshal_slice(&[7u8], &mut buf.as_mut_slice(), |v, w| marshal_u8(*v, w)).unwrap();
+        assert_eq!(CUSTOM.unmarshal_slice(&mut buf.as_slice(), unmarshal_u8), Err(Error::MissingTerminator));
+        assert_eq!(unmarshal_slice(&mut buf.as_slice(), unmarshal_u8), Err(Error::MissingTerminator));
+    }
+
+    #[test]
+    fn test_retarget_terminators() {
+        let batch = Batch { ids: vec![1, 1, 1, 1], nested: vec![vec![], vec![5]] };
+        let ty = Batch::benc_type().unwrap();
+        let custom = retarget_terminators(&batch.to_vec(), &ty, Terminator::DEFAULT, CUSTOM).unwrap();
+        assert_eq!(custom.len(), batch.size());
+        assert_eq!(&custom[..9], [4, 1, 1, 1, 1, 0xfe, 0xed, 0xfa, 0xce]);
+
+        let mut reader = custom.as_slice();
+        assert_eq!(CUSTOM.unmarshal_slice(&mut reader, unmarshal_u8).unwrap(), batch.ids);
+        let back = retarget_terminators(&custom, &ty, CUSTOM, Terminator::DEFAULT).unwrap();
+        assert_eq!(from_slice::<Batch>(&back).unwrap(), batch);
+
+        assert!(matches!(
+            retarget_terminators(&batch.to_vec(), &ty, CUSTOM, Terminator::DEFAULT),
+            Err(Error::TerminatorMismatch { .. })
+        ));
+    }
+}
@@ -1,7 +1,12 @@
+// The baseline assertions compare against bool literals and zero lengths.
+#![allow(clippy::bool_assert_comparison, clippy::len_zero)]
+
 #[cfg(test)]
 mod tests {
-    use chrono::{DateTime, Utc};
-    use std::collections::HashMap;
+    use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+    use std::collections::{BTreeMap, HashMap};
+    use std::hash::{BuildHasherDefault, DefaultHasher};
+    use std::time::Duration;
     use benc::*;
 
     fn verify_skip(mut bytes: &[u8], skipper: impl Fn(&mut &[u8]) -> Result<()>) {
@@ -121,16 +126,18 @@ mod tests {
 
     #[test]
     fn test_err_buf_too_small() {
-        assert_eq!(unmarshal_bool(&mut &[][..]).err(), Some(Error::BufferTooSmall));
-        assert_eq!(unmarshal_u8(&mut &[][..]).err(), Some(Error::BufferTooSmall));
-        assert_eq!(unmarshal_f32(&mut &[1,2,3][..]).err(), Some(Error::BufferTooSmall));
-        assert_eq!(unmarshal_f64(&mut &[1,2,3,4,5,6,7][..]).err(), Some(Error::BufferTooSmall));
-        assert_eq!(unmarshal_string(&mut &[2,0][..]).err(), Some(Error::BufferTooSmall));
-        assert_eq!(unmarshal_bytes_cropped(&mut &[4,1,2,3][..]).err(), Some(Error::BufferTooSmall));
+        let err = Error::BufferTooSmall { needed: 8, available: 3 };
+        assert_eq!(err.to_string(), "buffer is too small to complete the operation: needed 8 bytes, 3 available");
+        assert_eq!(unmarshal_bool(&mut &[][..]).err(), Some(Error::BufferTooSmall { needed: 1, available: 0 }));
+        assert_eq!(unmarshal_u8(&mut &[][..]).err(), Some(Error::BufferTooSmall { needed: 1, available: 0 }));
+        assert_eq!(unmarshal_f32(&mut &[1,2,3][..]).err(), Some(Error::BufferTooSmall { needed: 4, available: 3 }));
+        assert_eq!(unmarshal_f64(&mut &[1,2,3,4,5,6,7][..]).err(), Some(Error::BufferTooSmall { needed: 8, available: 7 }));
+        assert_eq!(unmarshal_string(&mut &[2,0][..]).err(), Some(Error::BufferTooSmall { needed: 2, available: 1 }));
+        assert_eq!(unmarshal_bytes_cropped(&mut &[4,1,2,3][..]).err(), Some(Error::BufferTooSmall { needed: 4, available: 3 }));
         let mut slice_buf = &[10, 0, 0, 0, 1][..];
-        assert_eq!(unmarshal_slice(&mut slice_buf, unmarshal_u8).err(), Some(Error::BufferTooSmall));
+        assert_eq!(unmarshal_slice(&mut slice_buf, unmarshal_u8).err(), Some(Error::BufferTooSmall { needed: 1, available: 0 }));
         let mut map_buf = &[10, 0, 0, 0, 1][..];
-        assert_eq!(unmarshal_map(&mut map_buf, unmarshal_u8, unmarshal_u8).err(), Some(Error::BufferTooSmall));
+        assert_eq!(unmarshal_map(&mut map_buf, unmarshal_u8, unmarshal_u8).err(), Some(Error::BufferTooSmall { needed: 1, available: 0 }));
     }
 
     #[test]
@@ -150,6 +157,115 @@ mod tests {
         assert!(reader.is_empty());
     }
     
+    #[test]
+    fn test_borrowed_slices() {
+        let strings = vec!["a", "", "ünïcode"];
+        let mut buf = vec![0; size_slice(&strings, |s| size_string(s))];
+        marshal_slice(&strings, &mut buf.as_mut_slice(), |s, w| marshal_string(s, w)).unwrap();
+        let mut reader = buf.as_slice();
+        let decoded = unmarshal_str_slice(&mut reader).unwrap();
+        assert_eq!(decoded, strings);
+        assert!(reader.is_empty());
+        // The elements point into the buffer.
+        assert!(buf.as_ptr_range().contains(&decoded[2].as_ptr()));
+
+        let chunks: Vec<&[u8]> = vec![&[1, 2], &[], &[3]];
+        let mut buf = vec![0; size_slice(&chunks, |b| size_bytes(b))];
+        marshal_slice(&chunks, &mut buf.as_mut_slice(), |b, w| marshal_bytes(b, w)).unwrap();
+        assert_eq!(unmarshal_bytes_slice(&mut buf.as_slice()).unwrap(), chunks);
+
+        let pairs = unmarshal_slice(&mut buf.as_slice(), |r| {
+            let b = unmarshal_bytes_cropped(r)?;
+            Ok::<_, Error>((b.len(), b))
+        })
+        .unwrap();
+        assert_eq!(pairs[0], (2, &[1, 2][..]));
+
+        assert!(matches!(unmarshal_str_slice(&mut &[1, 1, 0xff, 1, 1, 1, 1][..]), Err(Error::InvalidUtf8(_))));
+    }
+
+    #[test]
+    fn test_slice_zero_sized_elements() {
+        // Elements that occupy no bytes decode while the length is backed by the input.
+        let units = vec![(); 3];
+        let buf = units.to_vec();
+        assert_eq!(from_slice::<Vec<()>>(&buf).unwrap(), units);
+
+        // A forged length beyond the input is rejected instead of looping over it.
+        let mut forged = vec![0u8; size_uint(u64::MAX)];
+        marshal_uint(u64::MAX, &mut forged.as_mut_slice()).unwrap();
+        forged.extend_from_slice(&[1, 1, 1, 1]);
+        assert_eq!(from_slice::<Vec<()>>(&forged).err(), Some(Error::InvalidValue));
+        assert_eq!(Vec::<()>::skip(&mut forged.as_slice()).err(), Some(Error::InvalidValue));
+        assert_eq!(index_slice(&mut forged.as_slice(), <()>::skip).err(), Some(Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_index_slice() {
+        let slice = vec!["a", "bcd", "", "efghij"];
+        let size = size_slice(&slice, |s| size_string(s));
+        let mut buf = vec![0; size];
+        marshal_slice(&slice, &mut buf.as_mut_slice(), |s, w| marshal_string(s, w)).unwrap();
+
+        let mut reader = buf.as_slice();
+        let offsets = index_slice(&mut reader, skip_string).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(offsets, vec![1, 3, 7, 8]);
+
+        // Jump straight to any element.
+        for (i, expected) in slice.iter().enumerate().rev() {
+            let mut element = &buf[offsets[i]..];
+            assert_eq!(unmarshal_string(&mut element).unwrap(), *expected);
+        }
+
+        // Offsets are relative to the start of the slice, not of the whole buffer.
+        let mut prefixed = vec![0xAA; 3];
+        prefixed.extend_from_slice(&buf);
+        let mut reader = &prefixed[3..];
+        assert_eq!(index_slice(&mut reader, skip_string).unwrap(), offsets);
+
+        let mut truncated = &buf[..size - 1];
+        assert_eq!(index_slice(&mut truncated, skip_string).err(), Some(Error::BufferTooSmall { needed: 4, available: 3 }));
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum AppError {
+        Benc(Error),
+        Negative(i64),
+    }
+
+    impl From<Error> for AppError {
+        fn from(err: Error) -> Self {
+            AppError::Benc(err)
+        }
+    }
+
+    #[test]
+    fn test_custom_error_type() {
+        let values = [3i64, -4, 5];
+        let mut buf = vec![0; size_slice(&values, |v| size_int(*v))];
+        let result = marshal_slice(&values, &mut buf.as_mut_slice(), |v, w| {
+            if *v < 0 {
+                return Err(AppError::Negative(*v));
+            }
+            Ok(marshal_int(*v, w)?)
+        });
+        assert_eq!(result, Err(AppError::Negative(-4)));
+
+        marshal_slice(&values, &mut buf.as_mut_slice(), |v, w| marshal_int(*v, w)).unwrap();
+        let result: Result<Vec<i64>, AppError> = unmarshal_slice(&mut buf.as_slice(), |r| {
+            let v = unmarshal_int(r)?;
+            if v < 0 { Err(AppError::Negative(v)) } else { Ok(v) }
+        });
+        assert_eq!(result, Err(AppError::Negative(-4)));
+
+        // Errors from the format itself are converted into the custom type.
+        let result = unmarshal_slice(&mut &buf[..3], |r| Ok::<_, AppError>(unmarshal_int(r)?));
+        assert_eq!(result, Err(AppError::Benc(Error::BufferTooSmall { needed: 1, available: 0 })));
+        let result = skip_option(&mut &[][..], |_| Ok::<_, AppError>(()));
+        assert_eq!(result, Err(AppError::Benc(Error::BufferTooSmall { needed: 1, available: 0 })));
+    }
+
     #[test]
     fn test_maps() {
         let mut map = HashMap::new();
@@ -172,6 +288,189 @@ mod tests {
         assert!(reader.is_empty());
     }
 
+    #[test]
+    fn test_skip_fixed_map() {
+        let map: HashMap<u32, f64> = (0..500).map(|i| (i, i as f64 / 2.0)).collect();
+        let size = size_map(&map, |_| size_u32(), |_| size_f64());
+        let mut buf = vec![0; size + size_u8()];
+        let mut writer = buf.as_mut_slice();
+        marshal_map(&map, &mut writer, |k, w| marshal_u32(*k, w), |v, w| marshal_f64(*v, w)).unwrap();
+        marshal_u8(7, &mut writer).unwrap();
+
+        verify_skip(&buf[..size], |r| skip_fixed_map(r, size_u32(), size_f64()));
+        let mut reader = buf.as_slice();
+        skip_fixed_map(&mut reader, size_u32(), size_f64()).unwrap();
+        assert_eq!(unmarshal_u8(&mut reader).unwrap(), 7);
+
+        let result = skip_fixed_map(&mut buf.as_slice(), size_u32(), size_u32());
+        assert_eq!(result, Err(Error::MissingTerminator));
+        let huge = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F];
+        assert_eq!(skip_fixed_map(&mut &huge[..], 4, 4), Err(Error::OutOfRange));
+        assert_eq!(skip_fixed_map(&mut &huge[..], usize::MAX, 1), Err(Error::OutOfRange));
+    }
+
+    #[test]
+    fn test_maps_custom_hasher() {
+        type Hasher = BuildHasherDefault<DefaultHasher>;
+
+        let mut map: HashMap<u32, String, Hasher> = HashMap::default();
+        map.insert(1, "one".to_string());
+        map.insert(2, "two".to_string());
+
+        let size = size_map(&map, |_| size_u32(), |v| size_string(v));
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_map(&map, &mut writer, |k, w| marshal_u32(*k, w), |v, w| marshal_string(v, w)).unwrap();
+        assert!(writer.is_empty());
+
+        let mut reader = buf.as_slice();
+        let ret_map: HashMap<u32, String, Hasher> = unmarshal_map_with_hasher(&mut reader,
+            unmarshal_u32,
+            |r| unmarshal_string(r).map(String::from),
+        ).unwrap();
+        assert_eq!(ret_map, map);
+        assert!(reader.is_empty());
+
+        // The hasher does not affect the wire format.
+        let mut reader = buf.as_slice();
+        let std_map = unmarshal_map(&mut reader, unmarshal_u32, unmarshal_string).unwrap();
+        assert_eq!(std_map.len(), 2);
+        assert_eq!(std_map[&2], "two");
+    }
+
+    #[test]
+    fn test_maps_into() {
+        let mut map = HashMap::new();
+        map.insert("b", 2u8);
+        map.insert("a", 1u8);
+        map.insert("c", 3u8);
+
+        let size = size_map(&map, |k| size_string(k), |_| size_u8());
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_map(&map, &mut writer, |k, w| marshal_string(k, w), |v, w| marshal_u8(*v, w)).unwrap();
+
+        let mut reader = buf.as_slice();
+        let btree: BTreeMap<&str, u8> = unmarshal_map_into(&mut reader, unmarshal_string, unmarshal_u8).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(btree.into_iter().collect::<Vec<_>>(), vec![("a", 1), ("b", 2), ("c", 3)]);
+
+        // Plain vectors keep the wire order.
+        let mut reader = buf.as_slice();
+        let pairs: Vec<(&str, u8)> = unmarshal_map_into(&mut reader, unmarshal_string, unmarshal_u8).unwrap();
+        assert_eq!(pairs.len(), 3);
+        assert!(pairs.iter().all(|(k, v)| map[k] == *v));
+
+        let mut truncated = &buf[..size - 1];
+        let result: Result<BTreeMap<&str, u8>> = unmarshal_map_into(&mut truncated, unmarshal_string, unmarshal_u8);
+        assert_eq!(result.err(), Some(Error::BufferTooSmall { needed: 4, available: 3 }));
+    }
+
+    #[test]
+    fn test_marshal_pairs() {
+        let pairs = [("b".to_string(), 2u8), ("a".to_string(), 1u8)];
+        let size = size_pairs(pairs.iter().map(|(k, v)| (k, v)), |k| size_string(k), |_| size_u8());
+        let mut buf = vec![0; size];
+        marshal_pairs(pairs.iter().map(|(k, v)| (k, v)), &mut buf.as_mut_slice(), |k, w| marshal_string(k, w), |v, w| marshal_u8(*v, w))
+            .unwrap();
+
+        // The bytes are those of the equivalent map, in the order of the pairs.
+        let map: HashMap<String, u8> = pairs.iter().cloned().collect();
+        assert_eq!(size, size_map(&map, |k| size_string(k), |_| size_u8()));
+        let decoded: Vec<(&str, u8)> = unmarshal_map_into(&mut buf.as_slice(), unmarshal_string, unmarshal_u8).unwrap();
+        assert_eq!(decoded, vec![("b", 2), ("a", 1)]);
+
+        marshal_pairs_sorted(pairs.iter().map(|(k, v)| (k, v)), &mut buf.as_mut_slice(), |k, w| marshal_string(k, w), |v, w| {
+            marshal_u8(*v, w)
+        })
+        .unwrap();
+        let decoded: Vec<(&str, u8)> = unmarshal_map_verified(&mut buf.as_slice(), unmarshal_string, unmarshal_u8).unwrap();
+        assert_eq!(decoded, vec![("a", 1), ("b", 2)]);
+
+        let duplicates = [(1u8, 1u8), (1, 2)];
+        let result = marshal_pairs_sorted(duplicates.iter().map(|(k, v)| (k, v)), &mut buf.as_mut_slice(), |k, w| marshal_u8(*k, w), |v, w| {
+            marshal_u8(*v, w)
+        });
+        assert_eq!(result, Err(Error::NonCanonical));
+        let result = marshal_pairs(pairs.iter().map(|(k, v)| (k, v)), &mut &mut buf[..size - 1], |k, w| marshal_string(k, w), |v, w| {
+            marshal_u8(*v, w)
+        });
+        assert!(matches!(result, Err(Error::BufferTooSmall { .. })));
+    }
+
+    #[test]
+    fn test_unmarshal_pairs() {
+        // Float keys are neither `Eq + Hash` nor `Ord`, and repeated keys are kept.
+        let pairs = [(0.5f64, "x"), (f64::NAN, "y"), (0.5, "z")];
+        let mut buf = vec![0; size_pairs(pairs.iter().map(|(k, v)| (k, v)), |_| size_f64(), |v| size_string(v))];
+        marshal_pairs(pairs.iter().map(|(k, v)| (k, v)), &mut buf.as_mut_slice(), |k, w| marshal_f64(*k, w), |v, w| {
+            marshal_string(v, w)
+        })
+        .unwrap();
+
+        let mut reader = buf.as_slice();
+        let decoded = unmarshal_pairs(&mut reader, unmarshal_f64, unmarshal_string).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(decoded.iter().map(|(_, v)| *v).collect::<Vec<_>>(), ["x", "y", "z"]);
+        assert_eq!(decoded[2].0, 0.5);
+        assert!(decoded[1].0.is_nan());
+
+        let result = unmarshal_pairs(&mut &buf[..buf.len() - 1], unmarshal_f64, unmarshal_string);
+        assert!(matches!(result, Err(Error::BufferTooSmall { .. })));
+    }
+
+    #[test]
+    fn test_slice_sorted() {
+        let encode = |items: &[u32]| {
+            let mut buf = vec![0; size_fixed_slice(items, size_u32())];
+            marshal_slice(items, &mut buf.as_mut_slice(), |v, w| marshal_u32(*v, w)).unwrap();
+            buf
+        };
+
+        let buf = encode(&[1, 5, 9]);
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_slice_sorted(&mut reader, unmarshal_u32).unwrap(), vec![1, 5, 9]);
+        assert!(reader.is_empty());
+
+        assert_eq!(unmarshal_slice_sorted(&mut encode(&[]).as_slice(), unmarshal_u32).unwrap(), vec![]);
+        assert_eq!(unmarshal_slice_sorted(&mut encode(&[1, 9, 5]).as_slice(), unmarshal_u32).err(), Some(Error::NonCanonical));
+        assert_eq!(unmarshal_slice_sorted(&mut encode(&[1, 5, 5]).as_slice(), unmarshal_u32).err(), Some(Error::NonCanonical));
+    }
+
+    #[test]
+    fn test_map_verified() {
+        let encode = |entries: &[(&str, u8)]| {
+            let size = size_uint(entries.len() as u64)
+                + entries.iter().map(|(k, _)| size_string(k) + size_u8()).sum::<usize>()
+                + 4;
+            let mut buf = vec![0; size];
+            let mut writer = buf.as_mut_slice();
+            marshal_uint(entries.len() as u64, &mut writer).unwrap();
+            for (k, v) in entries {
+                marshal_string(k, &mut writer).unwrap();
+                marshal_u8(*v, &mut writer).unwrap();
+            }
+            // Terminator
+            writer.copy_from_slice(&[1, 1, 1, 1]);
+            buf
+        };
+
+        let buf = encode(&[("a", 1), ("b", 2), ("c", 3)]);
+        let mut reader = buf.as_slice();
+        let map: BTreeMap<&str, u8> = unmarshal_map_verified(&mut reader, unmarshal_string, unmarshal_u8).unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["c"], 3);
+        assert!(reader.is_empty());
+
+        let buf = encode(&[("a", 1), ("c", 3), ("b", 2)]);
+        let result: Result<Vec<(&str, u8)>> = unmarshal_map_verified(&mut buf.as_slice(), unmarshal_string, unmarshal_u8);
+        assert_eq!(result.err(), Some(Error::NonCanonical));
+
+        let buf = encode(&[("a", 1), ("a", 2)]);
+        let result: Result<HashMap<&str, u8>> = unmarshal_map_verified(&mut buf.as_slice(), unmarshal_string, unmarshal_u8);
+        assert_eq!(result.err(), Some(Error::NonCanonical));
+    }
+
     #[test]
     fn test_string_edge_cases() {
         // Empty string
@@ -197,6 +496,50 @@ mod tests {
         assert!(reader.is_empty());
     }
 
+    #[test]
+    fn test_string_lossy() {
+        let mut buf = vec![0; size_string("valid") + size_bytes(b"bad \xFF byte")];
+        let mut writer = buf.as_mut_slice();
+        marshal_string("valid", &mut writer).unwrap();
+        marshal_bytes(b"bad \xFF byte", &mut writer).unwrap();
+
+        let mut reader = buf.as_slice();
+        let valid = unmarshal_string_lossy(&mut reader).unwrap();
+        assert!(matches!(valid, std::borrow::Cow::Borrowed("valid")));
+        let invalid = unmarshal_string_lossy(&mut reader).unwrap();
+        assert_eq!(invalid, "bad \u{FFFD} byte");
+        assert!(reader.is_empty());
+
+        assert_eq!(unmarshal_string_lossy(&mut &[3, b'a'][..]), Err(Error::BufferTooSmall { needed: 3, available: 1 }));
+    }
+
+    #[test]
+    fn test_bytes_into() {
+        let items: [&[u8]; 3] = [b"first", b"", b"third one"];
+        let size: usize = items.iter().map(|b| size_bytes(b)).sum();
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        for b in items {
+            marshal_bytes(b, &mut writer).unwrap();
+        }
+
+        let mut reader = buf.as_slice();
+        let mut scratch = Vec::with_capacity(64);
+        let ptr = scratch.as_ptr();
+        for b in items {
+            unmarshal_bytes_into(&mut reader, &mut scratch).unwrap();
+            assert_eq!(scratch, b);
+            assert_eq!(scratch.as_ptr(), ptr, "allocation was not reused");
+        }
+        assert!(reader.is_empty());
+
+        // The buffer is untouched on error.
+        let truncated = [5, b'a'];
+        let result = unmarshal_bytes_into(&mut truncated.as_slice(), &mut scratch);
+        assert_eq!(result, Err(Error::BufferTooSmall { needed: 5, available: 1 }));
+        assert_eq!(scratch, b"third one");
+    }
+
     #[test]
     fn test_varint_errors() {
         let overflow_buf = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x02];
@@ -204,8 +547,80 @@ mod tests {
         assert_eq!(unmarshal_uint(&mut &overflow_buf[..]).err(), Some(Error::VarintOverflow));
 
         let too_small_buf = [0x80];
-        assert_eq!(skip_uint(&mut &too_small_buf[..]).err(), Some(Error::BufferTooSmall));
-        assert_eq!(unmarshal_uint(&mut &too_small_buf[..]).err(), Some(Error::BufferTooSmall));
+        assert_eq!(skip_uint(&mut &too_small_buf[..]).err(), Some(Error::BufferTooSmall { needed: 2, available: 1 }));
+        assert_eq!(unmarshal_uint(&mut &too_small_buf[..]).err(), Some(Error::BufferTooSmall { needed: 2, available: 1 }));
+    }
+
+    #[test]
+    fn test_strict_varints() {
+        // Overlong encodings decode normally but are rejected by the strict decoders.
+        let overlong = [0x80, 0x00];
+        assert_eq!(unmarshal_uint(&mut &overlong[..]), Ok(0));
+        let mut reader = &overlong[..];
+        assert_eq!(unmarshal_uint_strict(&mut reader), Err(Error::NonMinimalVarint));
+        assert_eq!(reader, overlong);
+        assert_eq!(unmarshal_int_strict(&mut &[0xac, 0x82, 0x00][..]), Err(Error::NonMinimalVarint));
+        assert_eq!(unmarshal_usize_strict(&mut &[0xff, 0x80, 0x80, 0x00][..]), Err(Error::NonMinimalVarint));
+
+        let mut buf = [0u8; 32];
+        let mut writer = &mut buf[..];
+        for v in [0, 127, 128, 300, u64::MAX] {
+            marshal_uint(v, &mut writer).unwrap();
+        }
+        marshal_int(i64::MIN, &mut writer).unwrap();
+        marshal_isize(-1, &mut writer).unwrap();
+        let mut reader = &buf[..];
+        for v in [0, 127, 128, 300] {
+            assert_eq!(unmarshal_usize_strict(&mut reader), Ok(v));
+        }
+        assert_eq!(unmarshal_uint_strict(&mut reader), Ok(u64::MAX));
+        assert_eq!(unmarshal_int_strict(&mut reader), Ok(i64::MIN));
+        assert_eq!(unmarshal_isize_strict(&mut reader), Ok(-1));
+    }
+
+    #[test]
+    fn test_bulk_varints() {
+        // Runs of small values mixed with values of every width.
+        let mut uints: Vec<u64> = (0..100).collect();
+        uints.extend((0..64).map(|shift| 1u64 << shift));
+        uints.extend((0..20).map(|_| rand::random::<u64>()));
+        uints.extend([u64::MAX, 0, 1, 127, 128]);
+        let ints: Vec<i64> = uints.iter().map(|&v| v as i64).chain([i64::MIN, -1, 63, -64]).collect();
+
+        let mut buf = vec![0; uints.iter().map(|&v| size_uint(v)).sum()];
+        let mut writer = buf.as_mut_slice();
+        for &v in &uints {
+            marshal_uint(v, &mut writer).unwrap();
+        }
+        let mut reader = buf.as_slice();
+        let mut out = vec![0; uints.len()];
+        unmarshal_uints(&mut reader, &mut out).unwrap();
+        assert_eq!(out, uints);
+        assert!(reader.is_empty());
+
+        let mut buf = vec![0; ints.iter().map(|&v| size_int(v)).sum()];
+        let mut writer = buf.as_mut_slice();
+        for &v in &ints {
+            marshal_int(v, &mut writer).unwrap();
+        }
+        let mut reader = buf.as_slice();
+        let mut out = vec![0; ints.len()];
+        unmarshal_ints(&mut reader, &mut out).unwrap();
+        assert_eq!(out, ints);
+        assert!(reader.is_empty());
+
+        // Errors leave the reader unchanged.
+        let truncated = [1, 2, 3, 0x80];
+        let mut reader = &truncated[..];
+        let result = unmarshal_uints(&mut reader, &mut [0; 4]);
+        assert_eq!(result, Err(Error::BufferTooSmall { needed: 2, available: 1 }));
+        assert_eq!(reader.len(), 4);
+
+        let mut overflow = vec![0x80; 9];
+        overflow.extend([0x02, 0, 0, 0]);
+        let mut reader = overflow.as_slice();
+        assert_eq!(unmarshal_ints(&mut reader, &mut [0; 2]), Err(Error::VarintOverflow));
+        assert_eq!(reader.len(), 13);
     }
     
     #[test]
@@ -224,6 +639,46 @@ mod tests {
         assert!(reader.is_empty());
     }
 
+    #[test]
+    fn test_time_offset() {
+        let local = DateTime::parse_from_rfc3339("2022-09-16T23:14:55.123456789+05:30").unwrap();
+        let size = size_time_offset();
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_time_offset(local, &mut writer).unwrap();
+        assert!(writer.is_empty());
+
+        verify_skip(&buf, skip_time_offset);
+
+        // The timestamp prefix is a plain UTC time.
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_time(&mut reader).unwrap(), local.to_utc());
+
+        let mut reader = buf.as_slice();
+        let ret_time = unmarshal_time_offset(&mut reader).unwrap();
+        assert_eq!(ret_time, local);
+        assert_eq!(ret_time.offset(), local.offset());
+        assert_eq!(ret_time.to_rfc3339(), local.to_rfc3339());
+        assert!(reader.is_empty());
+
+        // An offset of more than a day is rejected.
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_i64(0, &mut writer).unwrap();
+        marshal_i32(86_400, &mut writer).unwrap();
+        assert_eq!(unmarshal_time_offset(&mut buf.as_slice()).err(), Some(Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_time_offset_traits() {
+        let local = DateTime::parse_from_rfc3339("2022-09-16T23:14:55.123456789-03:00").unwrap();
+        let buf = local.to_vec();
+        assert_eq!(<DateTime<FixedOffset>>::ENCODED_SIZE, Some(size_time_offset()));
+        assert_eq!(buf.len(), size_time_offset());
+        let ret_time = from_slice::<DateTime<FixedOffset>>(&buf).unwrap();
+        assert_eq!(ret_time.to_rfc3339(), local.to_rfc3339());
+    }
+
     #[test]
     fn test_option_pointer() {
         // Non-nil pointer
@@ -251,7 +706,7 @@ mod tests {
         verify_skip(&buf, |r| skip_option(r, skip_string));
 
         let mut reader = buf.as_slice();
-        let ret_opt: Option<String> = unmarshal_option(&mut reader, |_| unreachable!()).unwrap();
+        let ret_opt: Option<String> = unmarshal_option(&mut reader, |_| -> Result<String> { unreachable!() }).unwrap();
         assert_eq!(ret_opt, None);
         assert!(reader.is_empty());
     }
@@ -265,9 +720,9 @@ mod tests {
         let mut writer = buf.as_mut_slice();
         marshal_slice(&slice, &mut writer, |s, w| marshal_string(s, w)).unwrap();
         let mut truncated_buf = &buf[..size - 1];
-        assert_eq!(skip_slice(&mut truncated_buf, skip_string).err(), Some(Error::BufferTooSmall));
+        assert_eq!(skip_slice(&mut truncated_buf, skip_string).err(), Some(Error::BufferTooSmall { needed: 4, available: 3 }));
         let mut truncated_buf_for_unmarshal = &buf[..size - 1];
-        assert_eq!(unmarshal_slice(&mut truncated_buf_for_unmarshal, |r| unmarshal_string(r).map(ToString::to_string)).err(), Some(Error::BufferTooSmall));
+        assert_eq!(unmarshal_slice(&mut truncated_buf_for_unmarshal, |r| unmarshal_string(r).map(ToString::to_string)).err(), Some(Error::BufferTooSmall { needed: 4, available: 3 }));
 
         // Map
         let mut map = HashMap::new();
@@ -277,13 +732,13 @@ mod tests {
         let mut writer = buf.as_mut_slice();
         marshal_map(&map, &mut writer, |k, w| marshal_string(k, w), |v, w| marshal_string(v, w)).unwrap();
         let mut truncated_buf = &buf[..size - 1];
-        assert_eq!(skip_map(&mut truncated_buf, skip_string, skip_string).err(), Some(Error::BufferTooSmall));
+        assert_eq!(skip_map(&mut truncated_buf, skip_string, skip_string).err(), Some(Error::BufferTooSmall { needed: 4, available: 3 }));
         let mut truncated_buf_for_unmarshal = &buf[..size - 1];
         let unmarshal_result = unmarshal_map(&mut truncated_buf_for_unmarshal, 
             unmarshal_string,
             unmarshal_string
         );
-        assert_eq!(unmarshal_result.err(), Some(Error::BufferTooSmall));
+        assert_eq!(unmarshal_result.err(), Some(Error::BufferTooSmall { needed: 4, available: 3 }));
     }
 
     #[test]
@@ -294,38 +749,62 @@ mod tests {
         assert_eq!(size_fixed_slice(&slice, elem_size), expected);
     }
 
+    #[test]
+    fn test_skip_fixed_slice() {
+        let slice: Vec<u64> = (0..1000).collect();
+        let mut buf = vec![0; size_fixed_slice(&slice, size_u64()) + size_u8()];
+        let mut writer = buf.as_mut_slice();
+        marshal_slice(&slice, &mut writer, |v, w| marshal_u64(*v, w)).unwrap();
+        marshal_u8(42, &mut writer).unwrap();
+
+        let mut reader = buf.as_slice();
+        skip_fixed_slice(&mut reader, size_u64()).unwrap();
+        assert_eq!(unmarshal_u8(&mut reader).unwrap(), 42);
+
+        // A wrong element size lands on the wrong terminator position.
+        let result = skip_fixed_slice(&mut buf.as_slice(), size_u32());
+        assert_eq!(result, Err(Error::MissingTerminator));
+        let result = skip_fixed_slice(&mut &buf[..100], size_u64());
+        assert_eq!(result, Err(Error::BufferTooSmall { needed: 8000, available: 98 }));
+        let huge = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F];
+        assert_eq!(skip_fixed_slice(&mut &huge[..], 8), Err(Error::OutOfRange));
+    }
+
     #[test]
     fn test_marshal_err_buf_too_small() {
         // DEVFIX: Changed fixed-size array references to mutable slices to fix type mismatch errors.
         // The marshalling functions expect `&mut &mut [u8]`, not `&mut &mut [u8; N]`.
         let mut buf_0 = [];
         let mut writer_0 = buf_0.as_mut_slice();
-        assert_eq!(marshal_string("a", &mut writer_0).err(), Some(Error::BufferTooSmall));
+        assert_eq!(marshal_string("a", &mut writer_0).err(), Some(Error::BufferTooSmall { needed: 2, available: 0 }));
+        let mut buf_3 = [0u8; 3];
+        let mut writer_3 = buf_3.as_mut_slice();
+        assert_eq!(marshal_string("hello", &mut writer_3).err(), Some(Error::BufferTooSmall { needed: 6, available: 3 }));
         
         let mut buf_0 = [];
         let mut writer_0 = buf_0.as_mut_slice();
-        assert_eq!(marshal_bytes(&[1], &mut writer_0).err(), Some(Error::BufferTooSmall));
+        assert_eq!(marshal_bytes(&[1], &mut writer_0).err(), Some(Error::BufferTooSmall { needed: 2, available: 0 }));
 
         let mut buf_0 = [];
         let mut writer_0 = buf_0.as_mut_slice();
-        assert_eq!(marshal_u16(1, &mut writer_0).err(), Some(Error::BufferTooSmall));
+        assert_eq!(marshal_u16(1, &mut writer_0).err(), Some(Error::BufferTooSmall { needed: 2, available: 0 }));
         
         let mut buf_2 = [0u8; 2]; // Enough for len, but not terminator+data
         let mut writer_2 = buf_2.as_mut_slice();
         let slice = vec![1u32];
         // DEVFIX: Wrapped marshaller in a closure to satisfy higher-ranked trait bounds.
-        assert_eq!(marshal_slice(&slice, &mut writer_2, |v, w| marshal_u32(*v, w)).err(), Some(Error::BufferTooSmall));
+        assert_eq!(marshal_slice(&slice, &mut writer_2, |v, w| marshal_u32(*v, w)).err(), Some(Error::BufferTooSmall { needed: 4, available: 1 }));
 
         let mut buf_0 = [];
         let mut writer_0 = buf_0.as_mut_slice();
         let mut map = HashMap::new();
         map.insert(1u32, 2u32);
         // DEVFIX: Wrapped marshallers in closures.
-        assert_eq!(marshal_map(&map, &mut writer_0, |k, w| marshal_u32(*k, w), |v, w| marshal_u32(*v, w)).err(), Some(Error::BufferTooSmall));
+        assert_eq!(marshal_map(&map, &mut writer_0, |k, w| marshal_u32(*k, w), |v, w| marshal_u32(*v, w)).err(), Some(Error::BufferTooSmall { needed: 1, available: 0 }));
 
         let mut buf_0 = [];
         let mut writer_0 = buf_0.as_mut_slice();
-        assert_eq!(marshal_option(&Some("a"), &mut writer_0, |v, w| marshal_string(v, w)).err(), Some(Error::BufferTooSmall));
+        assert_eq!(marshal_option(&Some("a"), &mut writer_0, |v, w| marshal_string(v, w)).err(), Some(Error::BufferTooSmall { needed: 1, available: 0 }));
     }
 
     #[test]
@@ -334,6 +813,16 @@ mod tests {
         let invalid_utf8_buf = &[4, 0xC3, 0x28, 0, 0];
         let mut reader = invalid_utf8_buf.as_slice();
         assert!(matches!(unmarshal_string(&mut reader).err(), Some(Error::InvalidUtf8(_))));
+
+        // A long string with an invalid byte near the end still reports its position.
+        let mut long = "ü".repeat(1000).into_bytes();
+        long.push(0xFF);
+        let mut buf = vec![0; size_bytes(&long)];
+        marshal_bytes(&long, &mut buf.as_mut_slice()).unwrap();
+        match unmarshal_string(&mut buf.as_slice()) {
+            Err(Error::InvalidUtf8(err)) => assert_eq!(err.valid_up_to(), 2000),
+            other => panic!("unexpected result: {other:?}"),
+        }
     }
 
     #[test]
@@ -459,4 +948,47 @@ mod tests {
             assert_eq!(unmarshalled_nanos, 0);
         }
     }
+
+    #[test]
+    fn test_durations() {
+        for d in [Duration::ZERO, Duration::from_micros(1500), Duration::from_secs(3600), Duration::from_nanos(u64::MAX)] {
+            let mut buf = vec![0; size_duration(d)];
+            marshal_duration(d, &mut buf.as_mut_slice()).unwrap();
+            let mut reader = buf.as_slice();
+            assert_eq!(unmarshal_duration(&mut reader).unwrap(), d);
+            assert!(reader.is_empty());
+            verify_skip(&buf, skip_duration);
+        }
+        // A millisecond latency is a three-byte varint.
+        assert_eq!(size_duration(Duration::from_millis(1)), 3);
+
+        for d in [TimeDelta::zero(), TimeDelta::milliseconds(-250), TimeDelta::hours(36), TimeDelta::nanoseconds(i64::MIN)] {
+            let mut buf = vec![0; size_time_delta(d)];
+            marshal_time_delta(d, &mut buf.as_mut_slice()).unwrap();
+            let mut reader = buf.as_slice();
+            assert_eq!(unmarshal_time_delta(&mut reader).unwrap(), d);
+            assert!(reader.is_empty());
+            verify_skip(&buf, skip_time_delta);
+        }
+
+        let mut buf = vec![0; 16];
+        assert!(matches!(marshal_duration(Duration::MAX, &mut buf.as_mut_slice()), Err(Error::OutOfRange)));
+        assert!(matches!(marshal_time_delta(TimeDelta::MAX, &mut buf.as_mut_slice()), Err(Error::OutOfRange)));
+    }
+
+    #[test]
+    fn test_duration_collections() {
+        let histogram = vec![Duration::from_micros(120), Duration::from_millis(3), Duration::from_secs(2)];
+        let buf = histogram.to_vec();
+        assert_eq!(buf.len(), size_slice(&histogram, |d| size_duration(*d)));
+        assert_eq!(from_slice::<Vec<Duration>>(&buf).unwrap(), histogram);
+        verify_skip(&buf, |r| skip_slice(r, skip_duration));
+
+        let mut offsets = HashMap::new();
+        offsets.insert("ahead".to_string(), TimeDelta::seconds(5));
+        offsets.insert("behind".to_string(), TimeDelta::seconds(-5));
+        let buf = offsets.to_vec();
+        assert_eq!(from_slice::<HashMap<String, TimeDelta>>(&buf).unwrap(), offsets);
+        verify_skip(&buf, |r| skip_map(r, skip_string, skip_time_delta));
+    }
 }
@@ -0,0 +1,268 @@
+#![cfg(feature = "testing")]
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use benc::testing::{Compatibility, Incompatibility, assert_compatible, assert_golden, check_compatibility};
+    use benc::*;
+
+    benc_struct! {
+        struct Sample {
+            id: u16,
+            name: String,
+        }
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("benc-golden-{}", std::process::id())).join(name)
+    }
+
+    fn samples() -> Vec<Sample> {
+        vec![Sample { id: 1, name: "a".into() }, Sample { id: 258, name: String::new() }]
+    }
+
+    #[test]
+    fn test_golden_create_and_compare() {
+        let path = fixture("create.golden");
+        assert_golden(&path, &samples());
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# Golden benc fixture of "));
+        assert!(text.ends_with("\n01000161\n020100\n"));
+
+        // Comments and blank lines are ignored.
+        fs::write(&path, format!("{text}\n# trailing comment\n")).unwrap();
+        assert_golden(&path, &samples());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "sample 1 marshals differently from byte 1")]
+    fn test_golden_changed() {
+        let path = fixture("changed.golden");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "01000161\n020200\n").unwrap();
+        assert_golden(&path, &samples());
+    }
+
+    #[test]
+    #[should_panic(expected = "the fixture holds 1 samples, but 2 were given")]
+    fn test_golden_count() {
+        let path = fixture("count.golden");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "01000161\n").unwrap();
+        assert_golden(&path, &samples());
+    }
+
+    mod v1 {
+        use benc::*;
+
+        benc_struct! {
+            #[derive(Debug, PartialEq)]
+            pub struct User {
+                pub id: u32,
+                pub name: String,
+                #[benc(default)]
+                pub note: String,
+            }
+        }
+
+        benc_struct! {
+            #[benc(tagged)]
+            #[derive(Debug, PartialEq)]
+            pub struct Account {
+                #[benc(id = 1)]
+                pub name: String,
+            }
+        }
+
+        benc_struct! {
+            #[derive(Debug, PartialEq)]
+            pub struct Flag {
+                pub level: u8,
+            }
+        }
+
+        benc_struct! {
+            #[benc(tagged)]
+            #[derive(Debug, PartialEq)]
+            pub struct Profile {
+                #[benc(id = 1)]
+                pub name: String,
+                #[benc(id = 2, default)]
+                pub nickname: Option<String>,
+            }
+        }
+    }
+
+    mod v2 {
+        use benc::*;
+
+        benc_struct! {
+            #[derive(Debug, PartialEq)]
+            pub struct User {
+                pub id: u32,
+                pub name: String,
+            }
+        }
+
+        benc_struct! {
+            #[derive(Debug, PartialEq)]
+            pub struct UserWithEmail {
+                pub id: u32,
+                pub name: String,
+                #[benc(default)]
+                pub note: String,
+                #[benc(default)]
+                pub email: Option<String>,
+            }
+        }
+
+        benc_struct! {
+            #[benc(tagged)]
+            #[derive(Debug, PartialEq)]
+            pub struct Account {
+                #[benc(id = 1)]
+                pub display_name: String,
+            }
+        }
+
+        benc_struct! {
+            #[derive(Debug, PartialEq)]
+            pub struct Flag {
+                pub level: bool,
+            }
+        }
+
+        benc_struct! {
+            #[benc(tagged)]
+            #[derive(Debug, PartialEq)]
+            pub struct ProfileWithEmail {
+                #[benc(id = 1)]
+                pub name: String,
+                #[benc(id = 2, default)]
+                pub nickname: Option<String>,
+                #[benc(id = 3, default)]
+                pub email: Option<String>,
+            }
+        }
+
+        benc_struct! {
+            #[benc(tagged)]
+            #[derive(Debug, PartialEq)]
+            pub struct ProfileWithoutNickname {
+                #[benc(id = 1)]
+                pub name: String,
+            }
+        }
+
+        benc_struct! {
+            #[benc(tagged)]
+            #[derive(Debug, PartialEq)]
+            pub struct ProfileWithoutName {
+                #[benc(id = 2, default)]
+                pub nickname: Option<String>,
+            }
+        }
+    }
+
+    #[test]
+    fn test_compatible_added_field() {
+        let old = [v1::User { id: 1, name: "ann".into(), note: "x".into() }];
+        let new = [v2::UserWithEmail { id: 2, name: "bob".into(), note: String::new(), email: Some("b@x".into()) }];
+        assert_compatible(Compatibility::Backward, &old, &new);
+
+        // Old decoders do not expect the new trailing field, even when it is empty.
+        let found = check_compatibility(Compatibility::Full, &old, &new);
+        assert_eq!(
+            found,
+            vec![
+                Incompatibility::OldReadsNew { sample: 0, error: Error::TrailingBytes },
+                Incompatibility::RoundTrip { sample: 0, found: Err(Error::TrailingBytes) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compatible_removed_field() {
+        let old = [v1::User { id: 1, name: "ann".into(), note: "x".into() }];
+        let new = [v2::User { id: 2, name: "bob".into() }];
+        assert_compatible(Compatibility::Forward, &old, &new);
+        let found = check_compatibility(Compatibility::Backward, &old, &new);
+        assert_eq!(found, vec![Incompatibility::NewReadsOld { sample: 0, error: Error::TrailingBytes }]);
+    }
+
+    #[test]
+    fn test_compatible_renamed_field() {
+        let old = [v1::Account { name: "ann".into() }];
+        let new = [v2::Account { display_name: "bob".into() }];
+        assert_compatible(Compatibility::Full, &old, &new);
+    }
+
+    #[test]
+    fn test_compatible_tagged_added_field() {
+        let old = [
+            v1::Profile { name: "ann".into(), nickname: None },
+            v1::Profile { name: "bob".into(), nickname: Some("b".into()) },
+        ];
+        let new = [
+            v2::ProfileWithEmail { name: "cid".into(), nickname: None, email: Some("c@x".into()) },
+            v2::ProfileWithEmail { name: "dan".into(), nickname: Some("d".into()), email: None },
+        ];
+        // Old readers skip the new field, new readers default it.
+        assert_compatible(Compatibility::Full, &old, &new);
+        // Going back to the old version drops the new field, so only a round trip
+        // through the old version loses data.
+        assert_compatible(Compatibility::Backward, &new, &old);
+        assert_compatible(Compatibility::Forward, &new, &old);
+        let found = check_compatibility(Compatibility::Full, &new, &old);
+        assert_eq!(
+            found,
+            vec![Incompatibility::RoundTrip {
+                sample: 0,
+                found: Ok(r#"ProfileWithEmail { name: "cid", nickname: None, email: None }"#.into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_compatible_tagged_removed_field() {
+        let old = [v1::Profile { name: "ann".into(), nickname: Some("a".into()) }];
+        let new = [v2::ProfileWithoutNickname { name: "bob".into() }];
+        // The removed field had a default, so both versions read each other's data.
+        assert_compatible(Compatibility::Backward, &old, &new);
+        assert_compatible(Compatibility::Forward, &old, &new);
+        assert_compatible(Compatibility::Full, &new, &old);
+        let found = check_compatibility(Compatibility::Full, &old, &new);
+        assert_eq!(
+            found,
+            vec![Incompatibility::RoundTrip { sample: 0, found: Ok(r#"Profile { name: "ann", nickname: None }"#.into()) }]
+        );
+
+        // Removing a required field leaves old readers without it.
+        let new = [v2::ProfileWithoutName { nickname: None }];
+        assert_compatible(Compatibility::Backward, &old, &new);
+        let found = check_compatibility(Compatibility::Forward, &old, &new);
+        assert_eq!(found, vec![Incompatibility::OldReadsNew { sample: 0, error: Error::InvalidValue }]);
+        let found = check_compatibility(Compatibility::Forward, &new, &old);
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_compatible_round_trip() {
+        // Bools other than 1 read as `false`, so the level is lost.
+        let old = [v1::Flag { level: 1 }, v1::Flag { level: 2 }];
+        let new = [v2::Flag { level: true }];
+        assert!(check_compatibility(Compatibility::Backward, &old, &new).is_empty());
+        let found = check_compatibility(Compatibility::Full, &old, &new);
+        assert_eq!(found, vec![Incompatibility::RoundTrip { sample: 1, found: Ok("Flag { level: 0 }".into()) }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "are not Backward compatible:\n  the new version cannot read old sample 0: unexpected trailing bytes")]
+    fn test_assert_compatible_fails() {
+        let old = [v1::User { id: 1, name: "ann".into(), note: "x".into() }];
+        assert_compatible(Compatibility::Backward, &old, &[v2::User { id: 2, name: "bob".into() }]);
+    }
+}
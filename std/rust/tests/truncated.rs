@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    benc_struct! {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Request {
+            pub id: u32,
+            pub path: String,
+            pub body: Vec<u8>,
+            pub trailer: u8,
+        }
+    }
+
+    fn request() -> Request {
+        Request { id: 7, path: "/upload".into(), body: vec![0xab; 1000], trailer: 1 }
+    }
+
+    #[test]
+    fn test_truncated_snapshot() {
+        let r = request();
+        let schema = schema_of::<Request>().unwrap();
+        let buf = marshal_truncated(&r, 64).unwrap();
+        assert!(buf.len() <= 64);
+        assert_eq!(buf.len(), 1 + 4 + size_string(&r.path));
+
+        let snapshot = unmarshal_truncated(&buf, &schema).unwrap();
+        assert!(snapshot.is_truncated());
+        assert_eq!(snapshot.dropped, vec!["body", "trailer"]);
+        assert_eq!(snapshot.value.field("id"), Some(&Value::U32(7)));
+        assert_eq!(snapshot.value.field("path"), Some(&Value::String("/upload".into())));
+        assert_eq!(snapshot.value.field("body"), None);
+    }
+
+    #[test]
+    fn test_truncated_complete() {
+        let r = request();
+        let schema = schema_of::<Request>().unwrap();
+        let buf = marshal_truncated(&r, usize::MAX).unwrap();
+        assert_eq!(buf[0], 4);
+        assert_eq!(&buf[1..], r.to_vec().as_slice());
+
+        // A budget of exactly the header and the message keeps every field.
+        assert_eq!(marshal_truncated(&r, buf.len()).unwrap(), buf);
+        let snapshot = unmarshal_truncated(&buf, &schema).unwrap();
+        assert!(!snapshot.is_truncated());
+        assert_eq!(snapshot.value, value_from_slice(&r.to_vec(), &schema).unwrap());
+    }
+
+    #[test]
+    fn test_truncated_errors() {
+        let r = request();
+        let schema = schema_of::<Request>().unwrap();
+        assert_eq!(marshal_truncated(&r, 0).err(), Some(Error::BufferTooSmall { needed: 1, available: 0 }));
+        assert_eq!(marshal_truncated(&r, 1).unwrap(), vec![0]);
+        assert!(matches!(marshal_truncated(&7u32, 16), Err(Error::Unsupported(_))));
+
+        assert_eq!(unmarshal_truncated(&[5], &schema).err(), Some(Error::InvalidValue));
+        assert_eq!(unmarshal_truncated(&[0, 1], &schema).err(), Some(Error::TrailingBytes));
+    }
+}
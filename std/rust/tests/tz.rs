@@ -0,0 +1,49 @@
+#![cfg(feature = "chrono-tz")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+    use chrono::{Duration, TimeZone, Timelike, Utc};
+    use chrono_tz::{Europe::Berlin, Tz};
+
+    benc_struct! {
+        #[derive(Debug, PartialEq)]
+        struct Meeting {
+            title: String,
+            start: chrono::DateTime<Tz>,
+        }
+    }
+
+    #[test]
+    fn test_time_tz_round_trip() {
+        let t = Berlin.with_ymd_and_hms(2025, 3, 29, 9, 30, 0).unwrap();
+        let buf = t.to_vec();
+        assert_eq!(buf.len(), size_time_tz(&t));
+        assert_eq!(buf.len(), size_time() + size_string("Europe/Berlin"));
+        // The timestamp is laid out like `marshal_time`.
+        assert_eq!(unmarshal_time(&mut buf.as_slice()).unwrap(), t.with_timezone(&Utc));
+
+        let decoded: chrono::DateTime<Tz> = from_slice(&buf).unwrap();
+        assert_eq!(decoded, t);
+        assert_eq!(decoded.timezone(), Berlin);
+        // The zone's rules survive: a day later crosses into summer time.
+        assert_eq!((decoded + Duration::days(1)).hour(), 10);
+
+        let mut reader = buf.as_slice();
+        skip_time_tz(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        let meeting = Meeting { title: "standup".into(), start: t };
+        assert_eq!(from_slice::<Meeting>(&meeting.to_vec()).unwrap(), meeting);
+    }
+
+    #[test]
+    fn test_unknown_zone() {
+        let mut buf = vec![0; size_time() + size_string("Mars/Olympus")];
+        let mut writer = buf.as_mut_slice();
+        marshal_time(Utc::now(), &mut writer).unwrap();
+        marshal_string("Mars/Olympus", &mut writer).unwrap();
+        assert_eq!(from_slice::<chrono::DateTime<Tz>>(&buf), Err(Error::InvalidValue));
+        assert_eq!(from_slice::<Tz>(&Berlin.to_vec()).unwrap(), Berlin);
+    }
+}
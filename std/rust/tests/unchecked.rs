@@ -0,0 +1,28 @@
+#![cfg(feature = "unsafe-fast")]
+
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[test]
+    fn test_string_unchecked() {
+        let strings = ["", "ascii", "grüße", "日本語"];
+        let size: usize = strings.iter().map(|s| size_string(s)).sum();
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        for s in strings {
+            marshal_string(s, &mut writer).unwrap();
+        }
+
+        let mut reader = buf.as_slice();
+        for s in strings {
+            // SAFETY: the buffer was just produced by `marshal_string`.
+            assert_eq!(unsafe { unmarshal_string_unchecked(&mut reader) }.unwrap(), s);
+        }
+        assert!(reader.is_empty());
+
+        // Length checks still apply.
+        let result = unsafe { unmarshal_string_unchecked(&mut &[4, b'a'][..]) };
+        assert_eq!(result, Err(Error::BufferTooSmall { needed: 4, available: 1 }));
+    }
+}
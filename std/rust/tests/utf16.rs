@@ -0,0 +1,35 @@
+#[cfg(test)]
+mod tests {
+    use benc::*;
+
+    #[test]
+    fn test_utf16() {
+        let s = "C:\\Users\\zoë\\🎵.txt";
+        let size = size_utf16(s);
+        let units: Vec<u16> = s.encode_utf16().collect();
+        assert_eq!(size, 1 + 2 * units.len());
+
+        let mut buf = vec![0; size];
+        let mut writer = buf.as_mut_slice();
+        marshal_utf16(s, &mut writer).unwrap();
+        assert!(writer.is_empty());
+        assert_eq!(&buf[1..3], &[b'C', 0]);
+
+        assert_eq!(unmarshal_utf16(&mut buf.as_slice()).unwrap(), s);
+        assert_eq!(unmarshal_utf16_units(&mut buf.as_slice()).unwrap(), units);
+        let mut reader = buf.as_slice();
+        skip_utf16(&mut reader).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_utf16_unpaired_surrogate() {
+        let units = [0x0041, 0xD800, 0x0042];
+        let mut buf = vec![0; size_utf16_units(&units)];
+        marshal_utf16_units(&units, &mut buf.as_mut_slice()).unwrap();
+
+        assert_eq!(unmarshal_utf16(&mut buf.as_slice()), Err(Error::InvalidValue));
+        assert_eq!(unmarshal_utf16_units(&mut buf.as_slice()).unwrap(), units);
+        assert_eq!(unmarshal_utf16(&mut &buf[..4]), Err(Error::BufferTooSmall { needed: 6, available: 3 }));
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use benc::*;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .field("id", Type::U32)
+            .field("name", Type::String)
+            .field("tags", Type::Slice(Box::new(Type::String)))
+            .field("scores", Type::Map(Box::new(Type::Int), Box::new(Type::F64)))
+            .field("parent", Type::Option(Box::new(Type::Uint)))
+            .field("at", Type::Time)
+            .field("inner", Type::Struct(Schema::new().field("flag", Type::Bool).field("raw", Type::Bytes)))
+    }
+
+    fn value() -> Value {
+        Value::Struct(vec![
+            ("id".into(), Value::U32(7)),
+            ("name".into(), Value::String("seven".into())),
+            ("tags".into(), Value::Slice(vec![Value::String("a".into()), Value::String("b".into())])),
+            ("scores".into(), Value::Map(vec![(Value::Int(-1), Value::F64(0.5))])),
+            ("parent".into(), Value::Option(Some(Box::new(Value::Uint(300))))),
+            ("at".into(), Value::Time(Utc.timestamp_opt(1_700_000_000, 0).unwrap())),
+            ("inner".into(), Value::Struct(vec![("flag".into(), Value::Bool(true)), ("raw".into(), Value::Bytes(vec![1, 2]))])),
+        ])
+    }
+
+    #[test]
+    fn test_value_round_trip() {
+        let v = value();
+        let mut buf = vec![0; size_value(&v)];
+        marshal_value(&v, &mut buf.as_mut_slice()).unwrap();
+        assert_eq!(value_from_slice(&buf, &schema()).unwrap(), v);
+        assert_eq!(v.field("name"), Some(&Value::String("seven".into())));
+        assert_eq!(v.field("missing"), None);
+
+        // The dynamic encoding matches the static one.
+        let mut reader = buf.as_slice();
+        assert_eq!(unmarshal_u32(&mut reader).unwrap(), 7);
+        assert_eq!(unmarshal_string(&mut reader).unwrap(), "seven");
+        assert_eq!(unmarshal_slice(&mut reader, |r| unmarshal_string(r).map(str::to_owned)).unwrap(), ["a", "b"]);
+
+        buf.push(0);
+        assert_eq!(value_from_slice(&buf, &schema()), Err(Error::TrailingBytes));
+        assert!(value_from_slice(&buf[..10], &schema()).is_err());
+    }
+
+    #[test]
+    fn test_decode_fields() {
+        let v = value();
+        let mut buf = vec![0u8; size_value(&v)];
+        marshal_value(&v, &mut buf.as_mut_slice()).unwrap();
+
+        let selectors = [
+            FieldSel::Name("tags"),
+            FieldSel::Path(&["inner", "raw"]),
+            FieldSel::Index(0),
+            FieldSel::Name("inner"),
+            FieldSel::Path(&["inner", "flag"]),
+            FieldSel::Name("id"),
+        ];
+        let mut reader = buf.as_slice();
+        let fields = decode_fields(&mut reader, &schema(), &selectors).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(fields, vec![
+            v.field("tags").unwrap().clone(),
+            Value::Bytes(vec![1, 2]),
+            Value::U32(7),
+            v.field("inner").unwrap().clone(),
+            Value::Bool(true),
+            Value::U32(7),
+        ]);
+
+        // Fields after the last selected one are not read.
+        let mut reader = buf.as_slice();
+        assert_eq!(decode_fields(&mut reader, &schema(), &[FieldSel::Name("name")]).unwrap(), vec![Value::String("seven".into())]);
+        assert_eq!(reader.len(), buf.len() - 4 - size_string("seven"));
+        assert_eq!(decode_fields(&mut &buf[..], &schema(), &[]).unwrap(), vec![]);
+
+        // A nested selection skips the rest of its struct.
+        let nested = Schema::new().field("inner", Type::Struct(Schema::new().field("a", Type::U8).field("b", Type::String))).field("c", Type::U8);
+        let buf = [1, 2, b'h', b'i', 3];
+        let fields = decode_fields(&mut &buf[..], &nested, &[FieldSel::Name("c"), FieldSel::Path(&["inner", "a"])]).unwrap();
+        assert_eq!(fields, vec![Value::U8(3), Value::U8(1)]);
+    }
+
+    #[test]
+    fn test_decode_fields_unknown() {
+        let buf = [0u8; 4];
+        for sel in [FieldSel::Name("missing"), FieldSel::Index(7), FieldSel::Path(&["id", "x"]), FieldSel::Path(&[])] {
+            assert!(matches!(decode_fields(&mut &buf[..], &schema(), &[sel]), Err(Error::Unsupported(_))));
+        }
+    }
+}